@@ -10,9 +10,15 @@ ARCH="$(uname -m)"
 
 case "${OS}" in
   Linux)
+    # Alpine and other musl-based distributions need the static musl build
+    if ls /lib/ld-musl-* >/dev/null 2>&1; then
+      LIBC="musl"
+    else
+      LIBC="gnu"
+    fi
     case "${ARCH}" in
-      x86_64)  TARGET="x86_64-unknown-linux-gnu" ;;
-      aarch64) TARGET="aarch64-unknown-linux-gnu" ;;
+      x86_64)  TARGET="x86_64-unknown-linux-${LIBC}" ;;
+      aarch64) TARGET="aarch64-unknown-linux-${LIBC}" ;;
       *)
         echo "Error: unsupported architecture: ${ARCH}" >&2
         exit 1
@@ -22,8 +28,9 @@ case "${OS}" in
   Darwin)
     case "${ARCH}" in
       arm64)   TARGET="aarch64-apple-darwin" ;;
+      x86_64)  TARGET="x86_64-apple-darwin" ;;
       *)
-        echo "Error: unsupported architecture: ${ARCH} (only Apple Silicon is supported)" >&2
+        echo "Error: unsupported architecture: ${ARCH}" >&2
         exit 1
         ;;
     esac
@@ -1,10 +1,17 @@
 use anyhow::{Context, Result};
 use std::process::Command;
 
+use crate::docker;
+use crate::logging::Logged;
+
 /// Open a URL in the host's default browser.
 /// Uses `open` on macOS and `xdg-open` on Linux.
 /// The URL is passed as an argument (not via shell) to prevent injection.
 pub fn open_url(url: &str) -> Result<()> {
+    if docker::dry_run() {
+        docker::print_dry_run(&format!("open {url}"));
+        return Ok(());
+    }
     let cmd = if cfg!(target_os = "macos") {
         "open"
     } else {
@@ -13,8 +20,20 @@ pub fn open_url(url: &str) -> Result<()> {
 
     Command::new(cmd)
         .arg(url)
+        .logged()
         .spawn()
         .with_context(|| format!("failed to open URL with {cmd}"))?;
 
     Ok(())
 }
+
+/// URL of a forward on the host, with `scheme` `http` or `https`. Wildcard
+/// binds are reached through `localhost`.
+pub fn forward_url(scheme: &str, bind: &str, host_port: u16) -> String {
+    let host = match bind {
+        "" | "0.0.0.0" | "::" | "[::]" => "localhost".to_string(),
+        b if b.contains(':') && !b.starts_with('[') => format!("[{b}]"),
+        b => b.to_string(),
+    };
+    format!("{scheme}://{host}:{host_port}")
+}
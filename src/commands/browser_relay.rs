@@ -3,7 +3,9 @@ use std::fs;
 use std::io::Read;
 use std::process::{Command, Stdio};
 use std::sync::Arc;
+use tracing::{debug, info, warn};
 
+use crate::logging::Logged;
 use crate::process;
 use crate::workspace;
 
@@ -36,7 +38,7 @@ fn run_serve() -> Result<()> {
     let server = tiny_http::Server::http(&addr)
         .map_err(|e| anyhow::anyhow!("failed to bind {addr}: {e}"))?;
 
-    eprintln!("Browser relay listening on {addr}");
+    info!("Browser relay listening on {addr}");
 
     for request in server.incoming_requests() {
         handle_request(request, &token);
@@ -121,11 +123,11 @@ fn handle_open(request: tiny_http::Request, body: &str) {
     // Open browser
     match crate::browser::open_url(&url) {
         Ok(_) => {
-            eprintln!("Opened: {url}");
+            info!("Opened: {url}");
             let _ = request.respond(tiny_http::Response::from_string("OK").with_status_code(200));
         }
         Err(e) => {
-            eprintln!("Failed to open URL: {e}");
+            warn!("Failed to open URL: {e}");
             let _ = request.respond(
                 tiny_http::Response::from_string("Failed to open browser").with_status_code(500),
             );
@@ -183,12 +185,12 @@ fn handle_cmux(request: tiny_http::Request, body: &str) {
     cmd.envs(&env);
     cmd.stdin(Stdio::null());
 
-    let result = cmd.output();
+    let result = cmd.logged().output();
 
     let (stdout_b64, stderr_b64, exit_code) = match result {
         Ok(output) => {
             let exit_code = output.status.code().unwrap_or(-1);
-            eprintln!("cmux {:?} -> exit {exit_code}", args);
+            debug!("cmux {:?} -> exit {exit_code}", args);
             (
                 base64_encode(&output.stdout),
                 base64_encode(&output.stderr),
@@ -196,7 +198,7 @@ fn handle_cmux(request: tiny_http::Request, body: &str) {
             )
         }
         Err(e) => {
-            eprintln!("Failed to execute cmux: {e}");
+            warn!("Failed to execute cmux: {e}");
             let msg = format!("failed to execute cmux: {e}");
             ("".to_string(), base64_encode(msg.as_bytes()), -1)
         }
@@ -260,6 +262,14 @@ impl Drop for RelayGuard {
     }
 }
 
+/// Whether the browser relay process is running.
+pub fn relay_running() -> bool {
+    fs::read_to_string(workspace::relay_pid_file())
+        .ok()
+        .and_then(|contents| contents.trim().parse::<i32>().ok())
+        .is_some_and(process::is_dcw_process)
+}
+
 /// Ensure the browser relay is running. If already running, returns the existing token.
 /// Otherwise, generates a new token, spawns the relay process, and returns the token.
 pub fn ensure_relay_running() -> Result<String> {
@@ -300,6 +310,7 @@ pub fn ensure_relay_running() -> Result<String> {
         .stdin(Stdio::null())
         .stdout(Stdio::null())
         .stderr(Stdio::from(log_file))
+        .logged()
         .spawn()
         .context("failed to spawn browser relay")?;
 
@@ -314,7 +325,7 @@ pub fn ensure_relay_running() -> Result<String> {
         bail!("browser relay exited immediately — port {RELAY_PORT} may be in use");
     }
 
-    println!("Browser relay started (pid {pid}, port {RELAY_PORT}).");
+    info!("Browser relay started (pid {pid}, port {RELAY_PORT}).");
     Ok(token)
 }
 
@@ -325,9 +336,9 @@ pub fn stop_relay() {
 
     if let Ok(contents) = fs::read_to_string(&pid_file) {
         if let Ok(pid) = contents.trim().parse::<i32>() {
-            println!("Stopping browser relay (pid {pid})...");
+            info!("Stopping browser relay (pid {pid})...");
             if !process::kill_dcw_process(pid) {
-                println!("  PID {pid} is stale or not a dcw process, skipping kill.");
+                info!("  PID {pid} is stale or not a dcw process, skipping kill.");
             }
         }
         let _ = fs::remove_file(&pid_file);
@@ -339,6 +350,7 @@ pub fn stop_relay() {
 pub fn any_devcontainers_running() -> Result<bool> {
     let output = Command::new(crate::docker::docker_path())
         .args(["ps", "-q", "--filter", "label=devcontainer.local_folder"])
+        .logged()
         .output()
         .context("failed to query running devcontainers")?;
 
@@ -349,7 +361,7 @@ pub fn any_devcontainers_running() -> Result<bool> {
 /// Encode bytes as base64 (standard alphabet with padding).
 fn base64_encode(input: &[u8]) -> String {
     const CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
-    let mut result = String::with_capacity((input.len() + 2) / 3 * 4);
+    let mut result = String::with_capacity(input.len().div_ceil(3) * 4);
     for chunk in input.chunks(3) {
         let b0 = chunk[0] as u32;
         let b1 = if chunk.len() > 1 { chunk[1] as u32 } else { 0 };
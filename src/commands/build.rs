@@ -0,0 +1,120 @@
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::info;
+
+use crate::config;
+use crate::docker::{self, Execute};
+use crate::workspace;
+
+#[derive(clap::Args)]
+pub struct BuildArgs {
+    /// Build the image without using the Docker build cache
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// Tag the built image with this name and push it to its registry
+    #[arg(long, value_name = "IMAGE")]
+    pub push: Option<String>,
+
+    /// Extra arguments passed to `devcontainer build`
+    #[arg(last = true)]
+    pub extra: Vec<String>,
+}
+
+pub fn run(args: &BuildArgs) -> Result<()> {
+    let workspace_folder = workspace::workspace_folder()?;
+    let workspace_root = PathBuf::from(&workspace_folder);
+    let merged_config = config::resolve_config(&workspace_root)?;
+
+    let cmd_args = build_cmd_args(&workspace_folder, merged_config.as_deref(), args);
+
+    info!("Building devcontainer image...");
+    let status = Command::new("devcontainer")
+        .args(&cmd_args)
+        .execute_status()
+        .context("failed to run devcontainer build — is the devcontainer CLI installed?")?;
+
+    if !status.success() {
+        bail!("devcontainer build exited with status {status}");
+    }
+
+    match &args.push {
+        Some(image) => info!("Built and pushed {image}."),
+        None => info!("Build complete."),
+    }
+    Ok(())
+}
+
+/// Assemble the `devcontainer build` argument list.
+fn build_cmd_args(
+    workspace_folder: &str,
+    config_path: Option<&Path>,
+    args: &BuildArgs,
+) -> Vec<String> {
+    let mut cmd_args = vec![
+        "build".to_string(),
+        "--workspace-folder".to_string(),
+        workspace_folder.to_string(),
+    ];
+
+    if let Some(path) = config_path {
+        cmd_args.push("--config".to_string());
+        cmd_args.push(path.to_string_lossy().to_string());
+    }
+
+    if args.no_cache {
+        cmd_args.push("--no-cache".to_string());
+    }
+
+    if let Some(image) = &args.push {
+        cmd_args.push("--image-name".to_string());
+        cmd_args.push(image.clone());
+        cmd_args.push("--push".to_string());
+    }
+
+    cmd_args.extend(docker::devcontainer_cli_docker_args());
+    cmd_args.extend(args.extra.iter().cloned());
+    cmd_args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(no_cache: bool, push: Option<&str>) -> BuildArgs {
+        BuildArgs {
+            no_cache,
+            push: push.map(|s| s.to_string()),
+            extra: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn build_args_default() {
+        let cmd = build_cmd_args("/ws", None, &args(false, None));
+        assert_eq!(&cmd[..3], &["build", "--workspace-folder", "/ws"]);
+        assert!(!cmd.contains(&"--config".to_string()));
+        assert!(!cmd.contains(&"--push".to_string()));
+    }
+
+    #[test]
+    fn build_args_with_merged_config() {
+        let cmd = build_cmd_args(
+            "/ws",
+            Some(Path::new("/run/dcw/x/devcontainer.json")),
+            &args(false, None),
+        );
+        let i = cmd.iter().position(|a| a == "--config").unwrap();
+        assert_eq!(cmd[i + 1], "/run/dcw/x/devcontainer.json");
+    }
+
+    #[test]
+    fn build_args_no_cache_and_push() {
+        let cmd = build_cmd_args("/ws", None, &args(true, Some("ghcr.io/org/app:dev")));
+        assert!(cmd.contains(&"--no-cache".to_string()));
+        let i = cmd.iter().position(|a| a == "--image-name").unwrap();
+        assert_eq!(cmd[i + 1], "ghcr.io/org/app:dev");
+        assert!(cmd.contains(&"--push".to_string()));
+    }
+}
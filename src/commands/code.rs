@@ -0,0 +1,93 @@
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+use std::process::Command;
+use tracing::info;
+
+use crate::commands::exec;
+use crate::config;
+use crate::docker;
+use crate::logging::Logged;
+use crate::workspace;
+
+#[derive(clap::Args)]
+pub struct CodeArgs {
+    /// Open with VS Code Insiders (`code-insiders`)
+    #[arg(long)]
+    pub insiders: bool,
+
+    /// Print the folder URI instead of launching VS Code
+    #[arg(long)]
+    pub print: bool,
+}
+
+pub fn run(args: &CodeArgs) -> Result<()> {
+    let workspace_folder = workspace::workspace_folder()?;
+    let workspace_root = PathBuf::from(&workspace_folder);
+    let container_id = docker::find_devcontainer(&workspace_folder)?
+        .context("no running devcontainer found (start it with `dcw up`)")?;
+
+    let name = docker::container_name(&container_id)?;
+    let configured = config::load_effective_config(&workspace_root)?
+        .and_then(|c| c["workspaceFolder"].as_str().map(str::to_string));
+    let folder = match configured {
+        Some(folder) => folder,
+        None => match docker::exec_metadata(&container_id, &workspace_folder)?.workspace_mount {
+            Some(mount) => mount,
+            None => exec::container_workspace_folder(&workspace_root)?,
+        },
+    };
+    let uri = attached_container_uri(&name, &folder);
+
+    if args.print {
+        info!("{uri}");
+        return Ok(());
+    }
+
+    let program = if args.insiders {
+        "code-insiders"
+    } else {
+        "code"
+    };
+    info!("Opening {folder} in VS Code...");
+    let status = Command::new(program)
+        .args(["--folder-uri", &uri])
+        .logged()
+        .status()
+        .with_context(|| {
+            format!("failed to run {program} — is VS Code's `{program}` command on PATH?")
+        })?;
+    if !status.success() {
+        std::process::exit(status.code().unwrap_or(1));
+    }
+    Ok(())
+}
+
+/// URI that makes VS Code's Dev Containers extension attach to a running
+/// container and open `folder` in it. The authority is the hex-encoded JSON
+/// naming the container. Attaching (rather than a `dev-container+` URI)
+/// keeps VS Code from recreating a container started from a merged config.
+fn attached_container_uri(container_name: &str, folder: &str) -> String {
+    let name = format!("/{}", container_name.trim_start_matches('/'));
+    let authority = serde_json::json!({ "containerName": name }).to_string();
+    let hex: String = authority.bytes().map(|b| format!("{b:02x}")).collect();
+    format!("vscode-remote://attached-container+{hex}{folder}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attached_container_uri_hex_encodes_name() {
+        // {"containerName":"/c"}
+        let hex = "7b22636f6e7461696e65724e616d65223a222f63227d";
+        assert_eq!(
+            attached_container_uri("/c", "/workspaces/app"),
+            format!("vscode-remote://attached-container+{hex}/workspaces/app")
+        );
+        assert_eq!(
+            attached_container_uri("c", "/workspaces/app"),
+            attached_container_uri("/c", "/workspaces/app")
+        );
+    }
+}
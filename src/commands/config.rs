@@ -0,0 +1,82 @@
+use anyhow::{bail, Result};
+use std::path::PathBuf;
+
+use crate::config::{self, ConfigChange};
+use crate::output::{self, OutputFormat};
+use crate::workspace;
+
+#[derive(clap::Subcommand)]
+pub enum ConfigAction {
+    /// Validate devcontainer.json (merged with devcontainer.local.json)
+    Validate,
+    /// Show what devcontainer.local.json (and the active profile) change
+    Diff,
+}
+
+pub fn run(action: &ConfigAction, format: OutputFormat) -> Result<()> {
+    let workspace_root = PathBuf::from(workspace::workspace_folder()?);
+
+    match action {
+        ConfigAction::Validate => {
+            for warning in config::overlay_warnings(&workspace_root)? {
+                eprintln!("warning: {warning}");
+            }
+            let issues = config::validate_workspace(&workspace_root)?;
+            if issues.is_empty() {
+                println!("Config is valid.");
+                return Ok(());
+            }
+            for issue in &issues {
+                eprintln!("{issue}");
+            }
+            bail!("{} problem(s) found in devcontainer config", issues.len());
+        }
+        ConfigAction::Diff => {
+            let (base, merged) = config::load_base_and_merged(&workspace_root)?;
+            let changes = config::diff_configs(&base, &merged);
+            if format == OutputFormat::Json {
+                return output::print_json(&changes);
+            }
+            if changes.is_empty() {
+                println!("No local changes to devcontainer.json.");
+            }
+            for change in &changes {
+                println!("{}", format_change(change));
+            }
+            Ok(())
+        }
+    }
+}
+
+/// One line per change: `+` added, `~` changed, `-` removed.
+fn format_change(change: &ConfigChange) -> String {
+    match change {
+        ConfigChange::Added { path, new } => format!("+ {path}: {new}"),
+        ConfigChange::Changed { path, old, new } => format!("~ {path}: {old} -> {new}"),
+        ConfigChange::Removed { path, old } => format!("- {path}: {old}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn format_change_marks_each_kind() {
+        let changed = ConfigChange::Changed {
+            path: "forwardPorts".to_string(),
+            old: json!([3000]),
+            new: json!([3000, 9229]),
+        };
+        assert_eq!(
+            format_change(&changed),
+            "~ forwardPorts: [3000] -> [3000,9229]"
+        );
+        let added = ConfigChange::Added {
+            path: "remoteEnv.DEBUG".to_string(),
+            new: json!("1"),
+        };
+        assert_eq!(format_change(&added), "+ remoteEnv.DEBUG: \"1\"");
+    }
+}
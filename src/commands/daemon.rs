@@ -0,0 +1,638 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::ErrorKind;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use tracing::{info, warn};
+
+use crate::commands::port::{self, ForwardRow};
+use crate::commands::watch::{self, WatcherHealth};
+use crate::docker;
+use crate::ipc::{self, send, Reply, Request, WatcherInfo};
+use crate::logging::Logged;
+use crate::output::{self, OutputFormat};
+use crate::workspace;
+
+#[derive(clap::Subcommand)]
+pub enum DaemonAction {
+    /// Start the daemon in the background unless it is running
+    Start,
+    /// Stop the daemon and the watchers it runs
+    Stop,
+    /// Show whether the daemon is running and which workspaces it watches
+    Status,
+    /// Run the daemon in the foreground, e.g. under systemd or launchd
+    Run,
+}
+
+/// How often the daemon accepts requests and checks on its watchers.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// How long a workspace's forwards are answered from the cache. Forwards
+/// added or removed through dcw update it at once; this catches the rest.
+const FORWARDS_TTL: Duration = Duration::from_secs(10);
+
+/// How long `start` waits for a new daemon to listen on its socket.
+const START_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long a stopped watcher gets to remove its forwards.
+const STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The watch loop counts as healthy again after running this long, and the
+/// restart delay starts over.
+const STABLE_RUN: Duration = Duration::from_secs(60);
+
+/// Longest wait before restarting a crashed watch loop.
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(60);
+
+/// What the daemon records in `daemon.json` for each watched workspace.
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedWatch {
+    folder: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    container: Option<String>,
+}
+
+pub fn run(action: &DaemonAction, format: OutputFormat) -> Result<()> {
+    match action {
+        DaemonAction::Start => {
+            if let Some(Reply::Status { pid, .. }) = send(&Request::Status)? {
+                info!("dcw daemon is already running (pid {pid}).");
+                return Ok(());
+            }
+            ensure_running()
+        }
+        DaemonAction::Stop => stop(),
+        DaemonAction::Status => print_status(format),
+        DaemonAction::Run => run_daemon(),
+    }
+}
+
+/// Start the daemon in the background unless one is running.
+pub fn ensure_running() -> Result<()> {
+    if send(&Request::Status)?.is_some() {
+        return Ok(());
+    }
+    let dir = workspace::shared_runtime_dir();
+    fs::create_dir_all(&dir).context("failed to create shared runtime directory")?;
+    // A daemon that was killed leaves its socket behind
+    let _ = fs::remove_file(workspace::daemon_socket());
+
+    let exe = std::env::current_exe().context("failed to get current executable path")?;
+    let log_file = workspace::daemon_log_file();
+    let stderr = fs::File::create(&log_file).context("failed to create daemon log")?;
+    let mut child = Command::new(exe)
+        .args(["daemon", "run", "--log-file"])
+        .arg(&log_file)
+        // Its own process group, so Ctrl+C in this terminal doesn't reach it
+        .process_group(0)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(stderr)
+        .logged()
+        .spawn()
+        .context("failed to spawn dcw daemon")?;
+
+    let deadline = Instant::now() + START_TIMEOUT;
+    while Instant::now() < deadline {
+        if send(&Request::Status)?.is_some() {
+            info!("dcw daemon started (pid {}).", child.id());
+            return Ok(());
+        }
+        if let Some(status) = child.try_wait().context("failed to wait for dcw daemon")? {
+            bail!("dcw daemon exited ({status}), see {}", log_file.display());
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    bail!(
+        "dcw daemon did not start within {}s, see {}",
+        START_TIMEOUT.as_secs(),
+        log_file.display()
+    )
+}
+
+/// Have the daemon watch the ports of the workspace in `folder`, starting
+/// the daemon if needed. Returns the watcher's PID.
+pub fn watch(folder: &str, container: Option<&str>) -> Result<u32> {
+    ensure_running()?;
+    let request = Request::Watch {
+        folder: folder.to_string(),
+        container: container.map(str::to_string),
+    };
+    match send(&request)? {
+        Some(Reply::Watching { pid }) => Ok(pid),
+        _ => bail!("dcw daemon did not start the watcher"),
+    }
+}
+
+/// Stop the watcher of the workspace in `folder`. Returns its PID, or
+/// `None` if it had none or the daemon isn't running.
+pub fn unwatch(folder: &str) -> Result<Option<u32>> {
+    let request = Request::Unwatch {
+        folder: folder.to_string(),
+    };
+    match send(&request)? {
+        Some(Reply::Unwatched { pid }) => Ok(pid),
+        _ => Ok(None),
+    }
+}
+
+/// The daemon's PID and the watchers it runs, or `None` if it isn't
+/// running.
+pub fn status() -> Result<Option<(u32, Vec<WatcherInfo>)>> {
+    Ok(match send(&Request::Status)? {
+        Some(Reply::Status { pid, watchers }) => Some((pid, watchers)),
+        _ => None,
+    })
+}
+
+/// The watchers the daemon runs; empty if it isn't running.
+pub fn watchers() -> Vec<WatcherInfo> {
+    match status() {
+        Ok(Some((_, watchers))) => watchers,
+        _ => Vec::new(),
+    }
+}
+
+/// The watcher of the workspace with ID `ws_id`, if the daemon runs one.
+pub fn watcher(ws_id: &str) -> Option<WatcherInfo> {
+    watchers().into_iter().find(|w| w.ws_id == ws_id)
+}
+
+fn stop() -> Result<()> {
+    let Some(Reply::Status { pid, .. }) = send(&Request::Status)? else {
+        info!("dcw daemon is not running.");
+        return Ok(());
+    };
+    info!("Stopping dcw daemon (pid {pid})...");
+    send(&Request::Shutdown)?;
+    // Watchers get STOP_TIMEOUT each to remove their forwards
+    let deadline = Instant::now() + STOP_TIMEOUT * 2;
+    while workspace::daemon_socket().exists() && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(100));
+    }
+    if workspace::daemon_socket().exists() {
+        warn!("dcw daemon (pid {pid}) did not exit in time.");
+    } else {
+        info!("dcw daemon stopped.");
+    }
+    Ok(())
+}
+
+fn print_status(format: OutputFormat) -> Result<()> {
+    let (pid, watchers) = match status()? {
+        Some((pid, watchers)) => (Some(pid), watchers),
+        None => (None, Vec::new()),
+    };
+    if format == OutputFormat::Json {
+        #[derive(Serialize)]
+        struct DaemonStatus {
+            running: bool,
+            pid: Option<u32>,
+            watchers: Vec<WatcherInfo>,
+        }
+        return output::print_json(&DaemonStatus {
+            running: pid.is_some(),
+            pid,
+            watchers,
+        });
+    }
+
+    let Some(pid) = pid else {
+        println!("dcw daemon is not running.");
+        return Ok(());
+    };
+    println!("dcw daemon is running (pid {pid}).");
+    if watchers.is_empty() {
+        println!("No workspaces watched.");
+        return Ok(());
+    }
+    println!(
+        "{:<30} {:<11} {:<8} {:<8} FOLDER",
+        "WORKSPACE", "STATE", "PID", "UPTIME"
+    );
+    for w in &watchers {
+        println!(
+            "{:<30} {:<11} {:<8} {:<8} {}",
+            w.ws_id,
+            w.state,
+            w.pid.map_or("-".to_string(), |pid| pid.to_string()),
+            w.uptime_secs.map_or("-".to_string(), watch::format_uptime),
+            w.folder
+        );
+    }
+    Ok(())
+}
+
+/// Wait before the given restart in a row: 1s, 2s, 4s, ... up to a minute.
+fn restart_delay(attempt: u32) -> Duration {
+    Duration::from_secs(1u64 << attempt.min(6)).min(MAX_RESTART_DELAY)
+}
+
+/// A workspace's `dcw port watch` child process.
+struct Watcher {
+    folder: String,
+    container: Option<String>,
+    /// `None` while waiting to restart after a crash
+    child: Option<Child>,
+    started: Instant,
+    restart_at: Option<Instant>,
+    /// Restarts in a row, for the backoff
+    attempt: u32,
+    health: WatcherHealth,
+}
+
+impl Watcher {
+    /// Start the watch loop in the workspace folder, logging to its
+    /// runtime directory.
+    fn spawn(&mut self, exe: &PathBuf, ws_id: &str) -> Result<()> {
+        let log_file = workspace::watcher_log_file(ws_id);
+        let stderr = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&log_file)
+            .context("failed to open watcher log")?;
+        let mut cmd = Command::new(exe);
+        cmd.args(["port", "watch", "--log-file"])
+            .arg(&log_file)
+            .current_dir(&self.folder);
+        if let Some(container) = &self.container {
+            cmd.args(["--container", container]);
+        }
+        let child = cmd
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(stderr)
+            .logged()
+            .spawn()
+            .context("failed to spawn port watcher")?;
+        self.child = Some(child);
+        self.started = Instant::now();
+        self.restart_at = None;
+        Ok(())
+    }
+
+    fn info(&self, ws_id: &str) -> WatcherInfo {
+        WatcherInfo {
+            ws_id: ws_id.to_string(),
+            folder: self.folder.clone(),
+            state: match self.child {
+                Some(_) => "running",
+                None => "restarting",
+            }
+            .to_string(),
+            pid: self.child.as_ref().map(Child::id),
+            uptime_secs: self
+                .child
+                .as_ref()
+                .map(|_| self.started.elapsed().as_secs()),
+            health: self.health.clone(),
+        }
+    }
+
+    /// Record a crash and schedule the restart.
+    fn crashed(&mut self, status: &str) -> Duration {
+        if self.started.elapsed() >= STABLE_RUN {
+            self.attempt = 0;
+        }
+        let delay = restart_delay(self.attempt);
+        self.attempt += 1;
+        self.restart_at = Some(Instant::now() + delay);
+        self.health.restarts += 1;
+        self.health.last_restart = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .ok()
+            .map(|d| d.as_secs());
+        self.health.last_exit = Some(status.to_string());
+        delay
+    }
+}
+
+/// Ask a watch loop to stop; it removes its forwards on SIGTERM.
+fn terminate(child: &Child) {
+    unsafe { libc::kill(child.id() as i32, libc::SIGTERM) };
+}
+
+/// Wait for terminated watch loops to exit, up to [`STOP_TIMEOUT`].
+fn reap(children: &mut Vec<Child>) {
+    let deadline = Instant::now() + STOP_TIMEOUT;
+    while !children.is_empty() && Instant::now() < deadline {
+        children.retain_mut(|child| matches!(child.try_wait(), Ok(None)));
+        thread::sleep(Duration::from_millis(100));
+    }
+    for child in children.iter() {
+        warn!("watcher (pid {}) did not exit in time.", child.id());
+    }
+}
+
+/// The daemon's state: one watcher per workspace ID.
+struct Daemon {
+    exe: PathBuf,
+    watchers: BTreeMap<String, Watcher>,
+    /// Watchers asked to stop, reaped once they exit
+    stopping: Vec<Child>,
+    /// Sidecar forwards per workspace ID and when they were listed
+    forwards: HashMap<String, (Instant, Vec<ForwardRow>)>,
+}
+
+impl Daemon {
+    /// Watch `folder`, replacing any watcher it has. Returns the new PID.
+    fn watch(&mut self, folder: String, container: Option<String>) -> Result<u32> {
+        let ws_id = workspace::workspace_id_for(&folder)?;
+        if let Some(child) = self.watchers.remove(&ws_id).and_then(|w| w.child) {
+            terminate(&child);
+            reap(&mut vec![child]);
+        }
+        fs::create_dir_all(workspace::runtime_dir_for(&ws_id))
+            .context("failed to create runtime directory")?;
+        // Each watcher starts a fresh log; stderr catches anything not
+        // logged, such as panics
+        fs::File::create(workspace::watcher_log_file(&ws_id))
+            .context("failed to create watcher log")?;
+        let mut watcher = Watcher {
+            folder,
+            container,
+            child: None,
+            started: Instant::now(),
+            restart_at: None,
+            attempt: 0,
+            health: WatcherHealth::default(),
+        };
+        watcher.spawn(&self.exe, &ws_id)?;
+        let pid = watcher.child.as_ref().map_or(0, Child::id);
+        info!("Watching {} (pid {pid}).", watcher.folder);
+        self.watchers.insert(ws_id, watcher);
+        self.save();
+        Ok(pid)
+    }
+
+    fn unwatch(&mut self, folder: &str) -> Result<Option<u32>> {
+        let ws_id = workspace::workspace_id_for(folder)?;
+        self.forwards.remove(&ws_id);
+        let Some(watcher) = self.watchers.remove(&ws_id) else {
+            return Ok(None);
+        };
+        info!("No longer watching {folder}.");
+        self.save();
+        Ok(watcher.child.map(|child| {
+            terminate(&child);
+            let pid = child.id();
+            self.stopping.push(child);
+            pid
+        }))
+    }
+
+    /// Restart crashed watch loops with backoff, and forget workspaces
+    /// whose watcher exited because the devcontainer stopped.
+    fn reconcile(&mut self) {
+        self.stopping
+            .retain_mut(|child| matches!(child.try_wait(), Ok(None)));
+
+        let mut gone = Vec::new();
+        for (ws_id, watcher) in &mut self.watchers {
+            let Some(child) = &mut watcher.child else {
+                if watcher.restart_at.is_some_and(|at| at <= Instant::now()) {
+                    if let Err(e) = watcher.spawn(&self.exe, ws_id) {
+                        let delay = watcher.crashed(&format!("{e:#}"));
+                        warn!("{e:#}, retrying in {}s...", delay.as_secs());
+                    }
+                }
+                continue;
+            };
+            let status: ExitStatus = match child.try_wait() {
+                Ok(Some(status)) => status,
+                Ok(None) => continue,
+                Err(e) => {
+                    warn!("failed to wait for watcher of {}: {e}", watcher.folder);
+                    continue;
+                }
+            };
+            watcher.child = None;
+            // It removes its forwards on the way out
+            self.forwards.remove(ws_id);
+            if status.success() {
+                info!(
+                    "Watcher of {} exited, its devcontainer stopped.",
+                    watcher.folder
+                );
+                gone.push(ws_id.clone());
+                continue;
+            }
+            // An error may be the Docker hiccup that crashed the loop; keep trying
+            if let Ok(None) = docker::find_devcontainer(&watcher.folder) {
+                info!(
+                    "Devcontainer of {} is gone, no longer restarting its watcher.",
+                    watcher.folder
+                );
+                gone.push(ws_id.clone());
+                continue;
+            }
+            let delay = watcher.crashed(&status.to_string());
+            warn!(
+                "watcher of {} exited ({status}), restarting in {}s...",
+                watcher.folder,
+                delay.as_secs()
+            );
+        }
+        if !gone.is_empty() {
+            self.watchers.retain(|ws_id, _| !gone.contains(ws_id));
+            self.save();
+        }
+    }
+
+    /// The forwards of the workspace in `folder`. Native forwards come
+    /// from its runtime directory each time; sidecars are listed from
+    /// Docker at most every [`FORWARDS_TTL`].
+    fn forwards(&mut self, folder: &str) -> Result<Vec<ForwardRow>> {
+        let ws_id = workspace::workspace_id_for(folder)?;
+        let mut rows = port::native_rows(&workspace::runtime_dir_for(&ws_id));
+        match self.forwards.get(&ws_id) {
+            Some((listed, sidecars)) if listed.elapsed() < FORWARDS_TTL => {
+                rows.extend(sidecars.iter().cloned());
+            }
+            _ => {
+                let sidecars = port::sidecar_rows(&ws_id)?;
+                rows.extend(sidecars.iter().cloned());
+                self.forwards.insert(ws_id, (Instant::now(), sidecars));
+            }
+        }
+        Ok(rows)
+    }
+
+    fn handle(&mut self, request: Request, running: &AtomicBool) -> Result<Reply> {
+        Ok(match request {
+            Request::Watch { folder, container } => Reply::Watching {
+                pid: self.watch(folder, container)?,
+            },
+            Request::Unwatch { folder } => Reply::Unwatched {
+                pid: self.unwatch(&folder)?,
+            },
+            Request::Status => Reply::Status {
+                pid: std::process::id(),
+                watchers: self
+                    .watchers
+                    .iter()
+                    .map(|(ws_id, watcher)| watcher.info(ws_id))
+                    .collect(),
+            },
+            Request::Forwards { folder } => Reply::Forwards {
+                forwards: self.forwards(&folder)?,
+            },
+            Request::Changed { folder } => {
+                self.forwards.remove(&workspace::workspace_id_for(&folder)?);
+                Reply::Ok
+            }
+            Request::Shutdown => {
+                running.store(false, Ordering::SeqCst);
+                Reply::ShuttingDown
+            }
+        })
+    }
+
+    /// Answer one client connection.
+    fn serve(&mut self, stream: UnixStream, running: &AtomicBool) -> Result<()> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(ipc::REPLY_TIMEOUT))?;
+        let Some(line) = ipc::read_message(&stream)? else {
+            return Ok(());
+        };
+        let reply = match serde_json::from_str(&line) {
+            Ok(request) => self.handle(request, running),
+            Err(e) => Err(e.into()),
+        }
+        .unwrap_or_else(|e| Reply::Error {
+            message: format!("{e:#}"),
+        });
+        ipc::write_message(&stream, &reply)
+    }
+
+    /// Record the watched workspaces for the next daemon.
+    fn save(&self) {
+        let saved: Vec<SavedWatch> = self
+            .watchers
+            .values()
+            .map(|w| SavedWatch {
+                folder: w.folder.clone(),
+                container: w.container.clone(),
+            })
+            .collect();
+        let path = workspace::daemon_state_file();
+        if let Err(e) = serde_json::to_string_pretty(&saved)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| Ok(fs::write(&path, json)?))
+        {
+            warn!("failed to write {}: {e}", path.display());
+        }
+    }
+
+    /// Watch again the workspaces a previous daemon watched, if their
+    /// devcontainer is still running.
+    fn resume(&mut self) {
+        let saved: Vec<SavedWatch> = fs::read_to_string(workspace::daemon_state_file())
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        for entry in saved {
+            match docker::find_devcontainer(&entry.folder) {
+                Ok(Some(_)) => {
+                    if let Err(e) = self.watch(entry.folder, entry.container) {
+                        warn!("failed to resume watcher: {e:#}");
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => warn!("failed to check {}: {e}", entry.folder),
+            }
+        }
+        self.save();
+    }
+
+    /// Stop every watcher, letting each remove its forwards.
+    fn shutdown(&mut self) {
+        let mut children = std::mem::take(&mut self.stopping);
+        for watcher in self.watchers.values_mut() {
+            if let Some(child) = watcher.child.take() {
+                terminate(&child);
+                children.push(child);
+            }
+        }
+        reap(&mut children);
+    }
+}
+
+/// Serve the control socket and supervise the watchers until asked to stop.
+fn run_daemon() -> Result<()> {
+    let socket = workspace::daemon_socket();
+    if send(&Request::Status)?.is_some() {
+        bail!("dcw daemon is already running");
+    }
+    fs::create_dir_all(workspace::shared_runtime_dir())
+        .context("failed to create shared runtime directory")?;
+    let _ = fs::remove_file(&socket);
+    let listener = UnixListener::bind(&socket)
+        .with_context(|| format!("failed to listen on {}", socket.display()))?;
+    fs::set_permissions(&socket, fs::Permissions::from_mode(0o600))
+        .context("failed to restrict the control socket")?;
+    listener.set_nonblocking(true)?;
+
+    let running = Arc::new(AtomicBool::new(true));
+    let r = running.clone();
+    ctrlc::set_handler(move || {
+        r.store(false, Ordering::SeqCst);
+    })
+    .context("failed to set Ctrl+C handler")?;
+
+    let mut daemon = Daemon {
+        exe: std::env::current_exe().context("failed to get current executable path")?,
+        watchers: BTreeMap::new(),
+        stopping: Vec::new(),
+        forwards: HashMap::new(),
+    };
+    info!("dcw daemon listening on {}", socket.display());
+    daemon.resume();
+
+    while running.load(Ordering::SeqCst) {
+        loop {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    if let Err(e) = daemon.serve(stream, &running) {
+                        warn!("failed to answer request: {e:#}");
+                    }
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    warn!("failed to accept connection: {e}");
+                    break;
+                }
+            }
+        }
+        daemon.reconcile();
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    info!("Stopping watchers...");
+    daemon.shutdown();
+    let _ = fs::remove_file(&socket);
+    info!("dcw daemon stopped.");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restart_delay_backs_off_to_a_minute() {
+        let delays: Vec<u64> = (0..8).map(|n| restart_delay(n).as_secs()).collect();
+        assert_eq!(delays, [1, 2, 4, 8, 16, 32, 60, 60]);
+        assert_eq!(restart_delay(u32::MAX), MAX_RESTART_DELAY);
+    }
+}
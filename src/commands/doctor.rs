@@ -0,0 +1,291 @@
+use anyhow::{bail, Result};
+use std::fs;
+use std::process::Command;
+
+use crate::commands::{daemon, prune};
+use crate::docker;
+use crate::logging::Logged;
+use crate::workspace;
+
+/// Outcome of a single diagnostic check.
+struct Check {
+    name: &'static str,
+    passed: bool,
+    detail: String,
+    hint: Option<String>,
+}
+
+impl Check {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            passed: true,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self {
+            name,
+            passed: false,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+}
+
+pub fn run() -> Result<()> {
+    let checks = vec![
+        check_docker_daemon(),
+        check_docker_endpoint(),
+        check_devcontainer_cli(),
+        check_sidecar_image(),
+        check_runtime_dir(),
+        check_daemon(),
+        check_orphan_sidecars(),
+        check_host_gateway(),
+    ];
+
+    for check in &checks {
+        println!("{}", format_check(check));
+    }
+
+    let failed = checks.iter().filter(|c| !c.passed).count();
+    if failed > 0 {
+        bail!("{failed} check(s) failed");
+    }
+
+    println!("All checks passed.");
+    Ok(())
+}
+
+fn format_check(check: &Check) -> String {
+    let mark = if check.passed { "[ OK ]" } else { "[FAIL]" };
+    let mut line = format!("{mark} {}: {}", check.name, check.detail);
+    if let Some(hint) = &check.hint {
+        line.push_str(&format!("\n       hint: {hint}"));
+    }
+    line
+}
+
+/// A remote daemon isn't an error, but forwards then end up on that host.
+fn check_docker_endpoint() -> Check {
+    const NAME: &str = "docker endpoint";
+    let endpoint = docker::docker_endpoint();
+    let location = if endpoint.is_remote() {
+        "remote; forwarded ports are published on that host and need an SSH tunnel"
+    } else {
+        "local"
+    };
+    Check::pass(
+        NAME,
+        format!("{} ({}), {location}", endpoint.context, endpoint.host),
+    )
+}
+
+fn check_docker_daemon() -> Check {
+    const NAME: &str = "docker daemon";
+    let docker = docker::docker_path();
+    match Command::new(&docker)
+        .args(["version", "--format", "{{.Server.Version}}"])
+        .logged()
+        .output()
+    {
+        Ok(output) if output.status.success() => Check::pass(
+            NAME,
+            format!(
+                "reachable (server {})",
+                String::from_utf8_lossy(&output.stdout).trim()
+            ),
+        ),
+        Ok(output) => Check::fail(
+            NAME,
+            format!(
+                "not reachable: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+            "start the Docker daemon or check DOCKER_HOST / docker context",
+        ),
+        Err(e) => Check::fail(
+            NAME,
+            format!("failed to run {docker}: {e}"),
+            "install Docker or set DCW_DOCKER_PATH",
+        ),
+    }
+}
+
+fn check_devcontainer_cli() -> Check {
+    const NAME: &str = "devcontainer CLI";
+    match Command::new("devcontainer")
+        .arg("--version")
+        .logged()
+        .output()
+    {
+        Ok(output) if output.status.success() => Check::pass(
+            NAME,
+            format!(
+                "installed (version {})",
+                String::from_utf8_lossy(&output.stdout).trim()
+            ),
+        ),
+        _ => Check::fail(NAME, "not found", "npm install -g @devcontainers/cli"),
+    }
+}
+
+fn check_sidecar_image() -> Check {
+    const NAME: &str = "sidecar image";
+    let image = docker::sidecar_image();
+    let docker = docker::docker_path();
+
+    let present = Command::new(&docker)
+        .args(["image", "inspect", image])
+        .logged()
+        .output()
+        .is_ok_and(|o| o.status.success());
+    if present {
+        return Check::pass(NAME, format!("{image} is available locally"));
+    }
+
+    match Command::new(&docker)
+        .args(["pull", "-q", image])
+        .logged()
+        .output()
+    {
+        Ok(output) if output.status.success() => {
+            Check::pass(NAME, format!("{image} pulled successfully"))
+        }
+        _ => Check::fail(
+            NAME,
+            format!("{image} could not be pulled"),
+            "check network access to the registry",
+        ),
+    }
+}
+
+fn check_runtime_dir() -> Check {
+    const NAME: &str = "runtime directory";
+    let dir = match workspace::runtime_dir() {
+        Ok(d) => d,
+        Err(e) => {
+            return Check::fail(
+                NAME,
+                format!("cannot determine path: {e}"),
+                "run dcw from a workspace directory",
+            )
+        }
+    };
+
+    let probe = dir.join(".doctor");
+    let result = fs::create_dir_all(&dir).and_then(|_| fs::write(&probe, b"ok"));
+    let _ = fs::remove_file(&probe);
+    match result {
+        Ok(()) => Check::pass(NAME, format!("{} is writable", dir.display())),
+        Err(e) => Check::fail(
+            NAME,
+            format!("{} is not writable: {e}", dir.display()),
+            "check permissions or set XDG_RUNTIME_DIR",
+        ),
+    }
+}
+
+fn check_daemon() -> Check {
+    const NAME: &str = "dcw daemon";
+    let socket = workspace::daemon_socket();
+    match daemon::status() {
+        Ok(Some((pid, watchers))) => Check::pass(
+            NAME,
+            format!(
+                "running (pid {pid}), watching {} workspace(s)",
+                watchers.len()
+            ),
+        ),
+        Ok(None) if socket.exists() => Check::fail(
+            NAME,
+            format!("nothing answers on {}", socket.display()),
+            "run `dcw daemon start` to replace the stale socket",
+        ),
+        Ok(None) => Check::pass(NAME, "not running"),
+        Err(e) => Check::fail(
+            NAME,
+            format!("not answering: {e}"),
+            "run `dcw daemon stop`, then `dcw daemon start`",
+        ),
+    }
+}
+
+fn check_orphan_sidecars() -> Check {
+    const NAME: &str = "port-forward sidecars";
+    let orphans = docker::list_devcontainers()
+        .map(|list| {
+            list.into_iter()
+                .filter_map(|c| workspace::workspace_id_for(&c.folder).ok())
+                .collect()
+        })
+        .and_then(|known| prune::find_orphan_sidecars(&known));
+
+    match orphans {
+        Ok(orphans) if orphans.is_empty() => Check::pass(NAME, "no orphaned sidecars"),
+        Ok(orphans) => Check::fail(
+            NAME,
+            format!("orphaned: {}", orphans.join(", ")),
+            "run `dcw prune` to remove them",
+        ),
+        Err(e) => Check::fail(
+            NAME,
+            format!("cannot list sidecars: {}", e.to_string().trim()),
+            "make sure the Docker daemon is reachable",
+        ),
+    }
+}
+
+/// Services on the host are reached as `host.docker.internal`, which Linux
+/// engines only resolve when the container is given the host entry.
+fn check_host_gateway() -> Check {
+    const NAME: &str = "host.docker.internal";
+    let container_id =
+        match workspace::workspace_folder().and_then(|folder| docker::find_devcontainer(&folder)) {
+            Ok(Some(id)) => id,
+            _ => return Check::pass(NAME, "no running devcontainer to check"),
+        };
+
+    // getent is missing from some minimal images; /etc/hosts is where
+    // `--add-host` puts it anyway
+    let script = "getent hosts host.docker.internal || grep -w host.docker.internal /etc/hosts";
+    let output = Command::new(docker::docker_path())
+        .args(["exec", &container_id, "sh", "-c", script])
+        .logged()
+        .output();
+    match output {
+        Ok(output) if output.status.success() => {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let address = stdout.split_whitespace().next().unwrap_or_default();
+            Check::pass(NAME, format!("resolves to {address} in the devcontainer"))
+        }
+        _ => Check::fail(
+            NAME,
+            "does not resolve in the devcontainer",
+            "run `dcw up --host-gateway --rebuild` or set `up.host_gateway` in config.toml",
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_passing_check() {
+        let check = Check::pass("docker daemon", "reachable");
+        assert_eq!(format_check(&check), "[ OK ] docker daemon: reachable");
+    }
+
+    #[test]
+    fn format_failing_check_includes_hint() {
+        let check = Check::fail("devcontainer CLI", "not found", "install it");
+        assert_eq!(
+            format_check(&check),
+            "[FAIL] devcontainer CLI: not found\n       hint: install it"
+        );
+    }
+}
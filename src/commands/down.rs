@@ -1,58 +1,114 @@
-use anyhow::{Context, Result};
-use std::fs;
+use anyhow::Result;
+use std::path::Path;
+use tracing::{info, warn};
 
-use crate::commands::browser_relay;
+use crate::commands::{browser_relay, daemon};
 use crate::docker;
+use crate::hooks::{self, Hook};
+use crate::proxy;
 use crate::workspace;
 
-pub fn run() -> Result<()> {
+#[derive(clap::Args)]
+pub struct DownArgs {
+    /// Also remove the devcontainer, so the next `dcw up` creates a fresh one
+    #[arg(long)]
+    pub remove: bool,
+
+    /// Also delete the container's volumes (requires --remove)
+    #[arg(long, requires = "remove")]
+    pub volumes: bool,
+}
+
+pub fn run(args: &DownArgs) -> Result<()> {
     let workspace_folder = workspace::workspace_folder()?;
     let ws_id = workspace::workspace_id()?;
 
+    hooks::run(Hook::PreDown, Path::new(&workspace_folder))?;
+
+    stop(&workspace_folder, &ws_id)?;
+    if args.remove {
+        remove_devcontainer(&workspace_folder, args.volumes)?;
+    }
+    // Stop browser relay if no other devcontainers are running
+    if !docker::dry_run() && !browser_relay::any_devcontainers_running().unwrap_or(true) {
+        browser_relay::stop_relay();
+    }
+
+    hooks::run(Hook::PostDown, Path::new(&workspace_folder))
+}
+
+/// Stop the port watcher, the port forwards and the devcontainer, keeping
+/// the container. The part of `dcw down` that `dcw stop` does on its own.
+pub fn stop(workspace_folder: &str, ws_id: &str) -> Result<()> {
     // Always stop the watcher regardless of container state
     stop_watcher();
 
     // Always remove port-forwarding sidecars
-    println!("Removing port forwards...");
-    docker::remove_all_port_forwards(&ws_id)?;
+    info!("Removing port forwards...");
+    docker::remove_all_port_forwards(ws_id)?;
+    proxy::stop()?;
 
     // Stop the container if it is still running
-    match docker::find_devcontainer(&workspace_folder)? {
+    match docker::find_devcontainer(workspace_folder)? {
         Some(container_id) => {
-            println!("Stopping container {container_id}...");
-            let output = std::process::Command::new(crate::docker::docker_path())
-                .args(["stop", &container_id])
-                .status()
-                .context("failed to run docker stop")?;
-            if !output.success() {
-                anyhow::bail!("docker stop exited with status {output}");
-            }
-            println!("Devcontainer stopped.");
+            info!("Stopping container {container_id}...");
+            docker::stop_container(&container_id)?;
+            info!("Devcontainer stopped.");
         }
         None => {
-            println!("No running devcontainer found (already stopped).");
+            info!("No running devcontainer found (already stopped).");
         }
     }
-    // Stop browser relay if no other devcontainers are running
-    if !browser_relay::any_devcontainers_running().unwrap_or(true) {
-        browser_relay::stop_relay();
+    Ok(())
+}
+
+/// Remove the stopped devcontainer and, with `volumes`, its volumes. A
+/// Docker Compose project is torn down as a whole.
+fn remove_devcontainer(workspace_folder: &str, volumes: bool) -> Result<()> {
+    let Some(container_id) = docker::find_any_devcontainer(workspace_folder)? else {
+        info!("No devcontainer to remove.");
+        return Ok(());
+    };
+    if let Some(project) = docker::compose_project(&container_id)? {
+        info!("Tearing down compose project {project}...");
+        docker::compose_down(&project, volumes)?;
+        info!("Devcontainer removed.");
+        return Ok(());
     }
 
+    // Look up the volumes while the container still exists
+    let container_volumes = if volumes {
+        docker::container_volumes(&container_id)?
+    } else {
+        Vec::new()
+    };
+    info!("Removing container {container_id}...");
+    docker::remove_container(&container_id)?;
+    for volume in &container_volumes {
+        info!("Removing volume {volume}...");
+        if let Err(e) = docker::remove_volume(volume) {
+            warn!("{e}");
+        }
+    }
+    info!("Devcontainer removed.");
     Ok(())
 }
 
-fn stop_watcher() {
-    let pid_file = match workspace::watcher_pid_file() {
-        Ok(p) => p,
-        Err(_) => return,
+pub fn stop_watcher() {
+    let Ok(folder) = workspace::workspace_folder() else {
+        return;
     };
-    if let Ok(contents) = fs::read_to_string(&pid_file) {
-        if let Ok(pid) = contents.trim().parse::<i32>() {
-            println!("Stopping port watcher (pid {pid})...");
-            if !crate::process::kill_dcw_process(pid) {
-                println!("  PID {pid} is stale or not a dcw process, skipping kill.");
+    if docker::dry_run() {
+        if let Ok(id) = workspace::workspace_id() {
+            if daemon::watcher(&id).is_some() {
+                docker::print_dry_run("stop the port watcher");
             }
         }
-        let _ = fs::remove_file(&pid_file);
+        return;
+    }
+    match daemon::unwatch(&folder) {
+        Ok(Some(pid)) => info!("Stopping port watcher (pid {pid})..."),
+        Ok(None) => {}
+        Err(e) => warn!("failed to stop port watcher: {e}"),
     }
 }
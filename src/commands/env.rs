@@ -0,0 +1,187 @@
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::commands::exec;
+use crate::config;
+use crate::output::{self, OutputFormat};
+use crate::workspace;
+
+#[derive(clap::Args)]
+pub struct EnvArgs {
+    /// Print `export NAME='value'` lines with the container's values, for
+    /// `eval` on the host
+    #[arg(long)]
+    pub export: bool,
+
+    /// Also show variables set in the container but not in the config
+    #[arg(long)]
+    pub all: bool,
+}
+
+/// One environment variable, as configured and as seen in the container.
+#[derive(Debug, PartialEq, Serialize)]
+struct EnvRow {
+    name: String,
+    /// `containerEnv`, `remoteEnv`, or `container` for variables only set
+    /// in the container
+    source: &'static str,
+    /// Value in the merged config, before the container resolves it
+    configured: Option<String>,
+    /// Value in the container; `None` if unset there
+    value: Option<String>,
+}
+
+pub fn run(args: &EnvArgs, format: OutputFormat) -> Result<()> {
+    let workspace_folder = workspace::workspace_folder()?;
+    let config = config::load_effective_config(Path::new(&workspace_folder))?
+        .context("no devcontainer config found")?;
+
+    // Runs as remoteUser with remoteEnv applied, like `dcw exec`
+    let output = exec::output(None, &["cat".to_string(), "/proc/self/environ".to_string()])?;
+    if !output.status.success() {
+        bail!(
+            "failed to read the container's environment: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let actual = parse_environ(&String::from_utf8_lossy(&output.stdout));
+    let rows = env_rows(&config, &actual, args.all);
+
+    if args.export {
+        for row in &rows {
+            if let Some(value) = &row.value {
+                println!("export {}={}", row.name, shell_quote(value));
+            }
+        }
+        return Ok(());
+    }
+    if format == OutputFormat::Json {
+        return output::print_json(&rows);
+    }
+    if rows.is_empty() {
+        println!("No containerEnv or remoteEnv in the config.");
+        return Ok(());
+    }
+    println!("{:<24} {:<13} {:<30} VALUE", "NAME", "SOURCE", "CONFIGURED");
+    for row in &rows {
+        println!(
+            "{:<24} {:<13} {:<30} {}",
+            row.name,
+            row.source,
+            row.configured.as_deref().unwrap_or("-"),
+            row.value.as_deref().unwrap_or("(unset)")
+        );
+    }
+    Ok(())
+}
+
+/// `NAME=value` entries of `/proc/<pid>/environ`, separated by NUL bytes.
+fn parse_environ(environ: &str) -> BTreeMap<String, String> {
+    environ
+        .split('\0')
+        .filter_map(|entry| entry.split_once('='))
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+/// The config's `containerEnv` and `remoteEnv`, with their values in the
+/// container; `remoteEnv` wins for a name in both, as it does in the
+/// container. With `all`, the container's other variables follow.
+fn env_rows(config: &Value, actual: &BTreeMap<String, String>, all: bool) -> Vec<EnvRow> {
+    let mut configured: BTreeMap<&str, (&'static str, Option<String>)> = BTreeMap::new();
+    for source in ["containerEnv", "remoteEnv"] {
+        if let Some(vars) = config[source].as_object() {
+            for (name, value) in vars {
+                // `null` in remoteEnv unsets the variable
+                let value = value.as_str().map(str::to_string);
+                configured.insert(name, (source, value));
+            }
+        }
+    }
+    let mut rows: Vec<EnvRow> = configured
+        .into_iter()
+        .map(|(name, (source, value))| EnvRow {
+            name: name.to_string(),
+            source,
+            configured: value,
+            value: actual.get(name).cloned(),
+        })
+        .collect();
+    if all {
+        let extra: Vec<EnvRow> = actual
+            .iter()
+            .filter(|(name, _)| !rows.iter().any(|row| row.name == **name))
+            .map(|(name, value)| EnvRow {
+                name: name.clone(),
+                source: "container",
+                configured: None,
+                value: Some(value.clone()),
+            })
+            .collect();
+        rows.extend(extra);
+    }
+    rows
+}
+
+/// Quote a value for a POSIX shell.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn env_rows_compare_config_and_container() {
+        let config = json!({
+            "containerEnv": {"NODE_ENV": "development", "API_URL": "http://a"},
+            "remoteEnv": {"API_URL": "${localEnv:API_URL}", "DEBUG": "1", "GONE": null}
+        });
+        let actual = parse_environ("PATH=/usr/bin\0NODE_ENV=development\0API_URL=http://b\0");
+        let rows = env_rows(&config, &actual, false);
+        let summary: Vec<(&str, &str, Option<&str>, Option<&str>)> = rows
+            .iter()
+            .map(|r| {
+                (
+                    r.name.as_str(),
+                    r.source,
+                    r.configured.as_deref(),
+                    r.value.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (
+                    "API_URL",
+                    "remoteEnv",
+                    Some("${localEnv:API_URL}"),
+                    Some("http://b")
+                ),
+                ("DEBUG", "remoteEnv", Some("1"), None),
+                ("GONE", "remoteEnv", None, None),
+                (
+                    "NODE_ENV",
+                    "containerEnv",
+                    Some("development"),
+                    Some("development")
+                ),
+            ]
+        );
+
+        let rows = env_rows(&config, &actual, true);
+        assert_eq!(rows.last().unwrap().name, "PATH");
+        assert_eq!(rows.last().unwrap().source, "container");
+    }
+
+    #[test]
+    fn shell_quote_escapes_single_quotes() {
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+    }
+}
@@ -0,0 +1,226 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+
+use crate::docker;
+use crate::logging::Logged;
+use crate::output::OutputFormat;
+use crate::workspace;
+
+/// Container actions shown without `--all`; exec, attach and the like are
+/// mostly the watcher's own `docker exec` calls.
+const ACTIONS: [&str; 12] = [
+    "create",
+    "start",
+    "restart",
+    "stop",
+    "kill",
+    "die",
+    "oom",
+    "pause",
+    "unpause",
+    "rename",
+    "destroy",
+    "health_status",
+];
+
+#[derive(clap::Args)]
+pub struct EventsArgs {
+    /// Also show past events since this time, e.g. `10m` or
+    /// `2024-01-02T15:04:05` (as for `docker events --since`)
+    #[arg(long, value_name = "TIME")]
+    pub since: Option<String>,
+
+    /// Also show exec, attach and other low-level events
+    #[arg(long)]
+    pub all: bool,
+}
+
+/// One line of `docker events --format '{{json .}}'`.
+#[derive(Debug, Deserialize)]
+struct DockerEvent {
+    #[serde(rename = "Action")]
+    action: String,
+    #[serde(rename = "Actor")]
+    actor: Actor,
+    /// Unix time
+    time: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct Actor {
+    #[serde(rename = "ID")]
+    id: String,
+    /// The container's name, image and labels, plus e.g. `exitCode`
+    #[serde(rename = "Attributes", default)]
+    attributes: HashMap<String, String>,
+}
+
+/// An event as printed.
+#[derive(Debug, PartialEq, Serialize)]
+struct Event {
+    time: i64,
+    /// e.g. `start`, `die` or `health_status`
+    action: String,
+    container: String,
+    /// `devcontainer`, `sidecar`, or the compose service name
+    role: String,
+    /// e.g. `exit code 137` or `unhealthy`
+    detail: Option<String>,
+}
+
+pub fn run(args: &EventsArgs, format: OutputFormat) -> Result<()> {
+    let workspace_folder = workspace::workspace_folder()?;
+    let ws_id = workspace::workspace_id()?;
+
+    // The same filter key given several times matches any of the values
+    let mut filters = vec![
+        "type=container".to_string(),
+        format!("label=devcontainer.local_folder={workspace_folder}"),
+        format!("label=dcw.workspace={ws_id}"),
+    ];
+    let project = match docker::find_any_devcontainer(&workspace_folder)? {
+        Some(container_id) => docker::compose_project(&container_id)?,
+        None => None,
+    };
+    if let Some(project) = project {
+        filters.push(format!("label=com.docker.compose.project={project}"));
+    }
+
+    let mut cmd = Command::new(docker::docker_path());
+    cmd.args(["events", "--format", "{{json .}}"]);
+    for filter in &filters {
+        cmd.args(["--filter", filter]);
+    }
+    if let Some(since) = &args.since {
+        cmd.args(["--since", since]);
+    }
+    let mut child = cmd
+        .stdout(Stdio::piped())
+        .logged()
+        .spawn()
+        .context("failed to run docker events")?;
+
+    let stdout = child
+        .stdout
+        .take()
+        .context("failed to read docker events")?;
+    for line in BufReader::new(stdout).lines() {
+        let line = line.context("failed to read docker events")?;
+        let Some(event) = parse_event(&line, args.all) else {
+            continue;
+        };
+        if format == OutputFormat::Json {
+            println!("{}", serde_json::to_string(&event)?);
+        } else {
+            println!("{}", format_event(&event));
+        }
+    }
+
+    let status = child.wait().context("failed to wait for docker events")?;
+    if !status.success() {
+        bail!("docker events exited with status {status}");
+    }
+    Ok(())
+}
+
+/// Parse one event, skipping low-level ones unless `all` is set.
+fn parse_event(line: &str, all: bool) -> Option<Event> {
+    let event: DockerEvent = serde_json::from_str(line).ok()?;
+    // Health changes come as `health_status: healthy`, execs as
+    // `exec_start: sh -c ...`
+    let (action, argument) = match event.action.split_once(": ") {
+        Some((action, argument)) => (action, Some(argument)),
+        None => (event.action.as_str(), None),
+    };
+    if !all && !ACTIONS.contains(&action) {
+        return None;
+    }
+    let attributes = &event.actor.attributes;
+    let attribute = |key: &str| attributes.get(key).filter(|v| !v.is_empty());
+
+    let role = if attribute("devcontainer.local_folder").is_some() {
+        "devcontainer".to_string()
+    } else if attribute("dcw.workspace").is_some() {
+        "sidecar".to_string()
+    } else {
+        attribute("com.docker.compose.service")
+            .cloned()
+            .unwrap_or_else(|| "container".to_string())
+    };
+    let detail = match action {
+        "die" => attribute("exitCode").map(|code| format!("exit code {code}")),
+        "kill" => attribute("signal").map(|signal| format!("signal {signal}")),
+        _ => argument.map(str::to_string),
+    };
+    Some(Event {
+        time: event.time,
+        action: action.to_string(),
+        container: attribute("name")
+            .cloned()
+            .unwrap_or_else(|| event.actor.id.chars().take(12).collect()),
+        role,
+        detail,
+    })
+}
+
+/// e.g. `14:03:27  die            web-dev (devcontainer): exit code 137`
+fn format_event(event: &Event) -> String {
+    let mut line = format!(
+        "{}  {:<14} {} ({})",
+        format_clock(event.time),
+        event.action,
+        event.container,
+        event.role
+    );
+    if let Some(detail) = &event.detail {
+        line.push_str(&format!(": {detail}"));
+    }
+    line
+}
+
+/// Local time of day of a Unix time, as `HH:MM:SS`.
+fn format_clock(time: i64) -> String {
+    let time = time as libc::time_t;
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&time, &mut tm) }.is_null() {
+        return time.to_string();
+    }
+    format!("{:02}:{:02}:{:02}", tm.tm_hour, tm.tm_min, tm.tm_sec)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_container_events() {
+        let die = r#"{"status":"die","id":"0123456789abcdef","Type":"container","Action":"die","Actor":{"ID":"0123456789abcdef","Attributes":{"devcontainer.local_folder":"/src/app","exitCode":"137","name":"web-dev"}},"scope":"local","time":1700000000,"timeNano":1700000000000000000}"#;
+        assert_eq!(
+            parse_event(die, false),
+            Some(Event {
+                time: 1700000000,
+                action: "die".to_string(),
+                container: "web-dev".to_string(),
+                role: "devcontainer".to_string(),
+                detail: Some("exit code 137".to_string()),
+            })
+        );
+
+        let health = r#"{"Action":"health_status: unhealthy","Actor":{"ID":"fedcba9876543210","Attributes":{"com.docker.compose.service":"db","name":"app-db-1"}},"time":1700000001}"#;
+        let event = parse_event(health, false).unwrap();
+        assert_eq!(event.action, "health_status");
+        assert_eq!(event.role, "db");
+        assert_eq!(event.detail.as_deref(), Some("unhealthy"));
+
+        let exec = r#"{"Action":"exec_start: sh -c cat /proc/net/tcp","Actor":{"ID":"0123456789abcdef","Attributes":{"dcw.workspace":"dev-app-1234abcd","name":"pf-dev-app-c3000"}},"time":1700000002}"#;
+        assert_eq!(parse_event(exec, false), None);
+        let event = parse_event(exec, true).unwrap();
+        assert_eq!(event.role, "sidecar");
+        assert_eq!(event.action, "exec_start");
+
+        assert_eq!(parse_event("not json", true), None);
+    }
+}
@@ -1,44 +1,53 @@
-use anyhow::{Context, Result};
-use std::path::PathBuf;
-use std::process::Command;
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::process::{Command, ExitStatus, Output, Stdio};
+use tracing::warn;
 
 use crate::commands::browser_relay;
 use crate::config;
+use crate::docker;
+use crate::logging::Logged;
 use crate::settings::{RelaySettings, Settings};
 use crate::workspace;
 
 #[derive(clap::Args)]
 pub struct ExecArgs {
+    /// Set an environment variable (`KEY=VALUE`, or `KEY` to pass the host's value)
+    #[arg(short, long = "env", value_name = "KEY=VALUE")]
+    pub env: Vec<String>,
+
+    /// Read environment variables from a file of `KEY=VALUE` lines
+    #[arg(long, value_name = "PATH")]
+    pub env_file: Vec<PathBuf>,
+
+    /// Directory to run the command in; relative paths are resolved against
+    /// the workspace and mapped to its mount in the container
+    #[arg(short, long, value_name = "DIR")]
+    pub workdir: Option<String>,
+
+    /// User to run the command as instead of `remoteUser` (uses `docker exec`)
+    #[arg(short, long)]
+    pub user: Option<String>,
+
+    /// Keep stdin open even if it is not a terminal
+    #[arg(short, long)]
+    pub interactive: bool,
+
+    /// Allocate a TTY even if stdin or stdout is not a terminal
+    #[arg(short, long)]
+    pub tty: bool,
+
     /// Command and arguments to run inside the devcontainer
     #[arg(trailing_var_arg = true, required = true)]
     pub cmd: Vec<String>,
 }
 
 pub fn run(args: &ExecArgs) -> Result<()> {
-    let workspace_folder = workspace::workspace_folder()?;
-    let workspace_root = PathBuf::from(&workspace_folder);
-    let merged_config = config::resolve_config(&workspace_root)?;
-
-    let mut cmd_args = vec![
-        "exec".to_string(),
-        "--workspace-folder".to_string(),
-        workspace_folder,
-    ];
-
-    if let Some(config_path) = &merged_config {
-        cmd_args.push("--config".to_string());
-        cmd_args.push(config_path.to_string_lossy().to_string());
-    }
+    let env = resolve_env(&args.env, &args.env_file)?;
 
     let settings = Settings::get();
-    if settings.docker.path != "docker" {
-        cmd_args.push("--docker-path".to_string());
-        cmd_args.push(settings.docker.path.clone());
-    }
-    if settings.docker.compose_path != "docker-compose" {
-        cmd_args.push("--docker-compose-path".to_string());
-        cmd_args.push(settings.docker.compose_path.clone());
-    }
 
     // Start relay in-process so cmux child processes inherit our process tree
     // (cmux requires callers to be descendants of a cmux terminal).
@@ -48,7 +57,7 @@ pub fn run(args: &ExecArgs) -> Result<()> {
         match browser_relay::start_relay_thread() {
             Ok((token, port, guard)) => Some((token, port, guard)),
             Err(e) => {
-                eprintln!("Warning: failed to start browser relay: {e}");
+                warn!("failed to start browser relay: {e}");
                 None
             }
         }
@@ -56,16 +65,23 @@ pub fn run(args: &ExecArgs) -> Result<()> {
         None
     };
 
-    cmd_args.extend(build_relay_wrapped_cmd(
+    let cmd = build_relay_wrapped_cmd(
         &args.cmd,
         relay.as_ref().map(|(token, port, _)| (token.as_str(), *port)),
         &settings.relay,
-    ));
+    );
 
-    let status = Command::new("devcontainer")
-        .args(&cmd_args)
-        .status()
-        .context("failed to run devcontainer exec — is the devcontainer CLI installed?")?;
+    let options = ExecOptions {
+        user: args.user.as_deref(),
+        env: &env,
+        workdir: args.workdir.as_deref(),
+        interactive: args.interactive || is_tty(libc::STDIN_FILENO),
+        tty: args.tty || (is_tty(libc::STDIN_FILENO) && is_tty(libc::STDOUT_FILENO)),
+    };
+    let (program, cmd_args) = exec_command(&options, &cmd)?;
+    let mut command = Command::new(&program);
+    command.args(&cmd_args);
+    let status = run_with_stdio(command, &options).with_context(|| exec_error(&program))?;
 
     if !status.success() {
         std::process::exit(status.code().unwrap_or(1));
@@ -74,6 +90,291 @@ pub fn run(args: &ExecArgs) -> Result<()> {
     Ok(())
 }
 
+/// Run `command` with stdin set up for the requested mode. Without a TTY, a
+/// terminal stdin is relayed through a pipe: the devcontainer CLI decides
+/// whether to allocate a pseudo-terminal from stdin, and one would turn `\n`
+/// into `\r\n` in piped output.
+fn run_with_stdio(mut command: Command, options: &ExecOptions) -> io::Result<ExitStatus> {
+    if options.tty {
+        return command.logged().status();
+    }
+    if !options.interactive {
+        return command.stdin(Stdio::null()).logged().status();
+    }
+    if !is_tty(libc::STDIN_FILENO) {
+        return command.logged().status();
+    }
+    let mut child = command.stdin(Stdio::piped()).logged().spawn()?;
+    if let Some(mut child_stdin) = child.stdin.take() {
+        std::thread::spawn(move || io::copy(&mut io::stdin(), &mut child_stdin));
+    }
+    child.wait()
+}
+
+/// Run a command inside the devcontainer (as `user`, if given) and capture
+/// its output.
+pub fn output(user: Option<&str>, cmd: &[String]) -> Result<Output> {
+    let options = ExecOptions {
+        user,
+        ..Default::default()
+    };
+    let (program, cmd_args) = exec_command(&options, cmd)?;
+    Command::new(&program)
+        .args(&cmd_args)
+        .logged()
+        .output()
+        .with_context(|| exec_error(&program))
+}
+
+fn exec_error(program: &str) -> String {
+    if program == "devcontainer" {
+        "failed to run devcontainer exec — is the devcontainer CLI installed?".to_string()
+    } else {
+        format!("failed to run {program} exec")
+    }
+}
+
+/// How to run a command inside the devcontainer.
+#[derive(Default)]
+struct ExecOptions<'a> {
+    /// User to run as instead of `remoteUser`
+    user: Option<&'a str>,
+    /// `KEY=VALUE` pairs
+    env: &'a [String],
+    /// `--workdir` as given on the command line
+    workdir: Option<&'a str>,
+    /// Keep stdin attached
+    interactive: bool,
+    /// Allocate a TTY
+    tty: bool,
+}
+
+/// Build the program and arguments running `cmd`. The devcontainer CLI is
+/// used unless a user is given (`devcontainer exec` always runs as
+/// `remoteUser`) or the CLI is not installed; then it is `docker exec`.
+fn exec_command(options: &ExecOptions, cmd: &[String]) -> Result<(String, Vec<String>)> {
+    let workspace_folder = workspace::workspace_folder()?;
+    let workspace_root = PathBuf::from(&workspace_folder);
+    // From a subdirectory of the workspace, run there rather than at the root
+    let cwd = std::env::current_dir().context("failed to get current directory")?;
+    let subdir = (cwd != workspace_root).then(|| cwd.to_string_lossy().to_string());
+    let workdir = options.workdir.or(subdir.as_deref());
+
+    if options.user.is_none() && devcontainer_cli_installed() {
+        let mut cmd_args = base_exec_args()?;
+        for var in options.env {
+            cmd_args.push("--remote-env".to_string());
+            cmd_args.push(var.clone());
+        }
+        match workdir {
+            Some(dir) => {
+                let container_root = container_workspace_folder(&workspace_root)?;
+                let dir = container_workdir(dir, &workspace_root, &container_root)?;
+                cmd_args.extend(with_workdir(&dir, cmd));
+            }
+            None => cmd_args.extend_from_slice(cmd),
+        }
+        return Ok(("devcontainer".to_string(), cmd_args));
+    }
+
+    let container_id =
+        docker::find_devcontainer(&workspace_folder)?.context("no running devcontainer found")?;
+    let metadata = docker::exec_metadata(&container_id, &workspace_folder)?;
+    let user = options.user.or(metadata.remote_user.as_deref());
+    // devcontainer exec starts in the workspace, so docker exec should too
+    let container_root = match metadata.workspace_mount {
+        Some(mount) => mount,
+        None => container_workspace_folder(&workspace_root)?,
+    };
+    let workdir = match workdir {
+        Some(dir) => container_workdir(dir, &workspace_root, &container_root)?,
+        None => container_root,
+    };
+    let cmd_args = docker_exec_args(&container_id, user, &workdir, options, cmd);
+    Ok((docker::docker_path(), cmd_args))
+}
+
+/// Whether the devcontainer CLI is on `PATH`.
+fn devcontainer_cli_installed() -> bool {
+    std::env::var_os("PATH").is_some_and(|paths| {
+        std::env::split_paths(&paths).any(|dir| dir.join("devcontainer").is_file())
+    })
+}
+
+/// Arguments for `docker exec` running `cmd` as `user` (else the
+/// container's default user) in `workdir`.
+fn docker_exec_args(
+    container_id: &str,
+    user: Option<&str>,
+    workdir: &str,
+    options: &ExecOptions,
+    cmd: &[String],
+) -> Vec<String> {
+    let mut args = vec!["exec".to_string()];
+    if options.interactive {
+        args.push("-i".to_string());
+    }
+    if options.tty {
+        args.push("-t".to_string());
+    }
+    if let Some(user) = user {
+        args.push("-u".to_string());
+        args.push(user.to_string());
+    }
+    args.push("-w".to_string());
+    args.push(workdir.to_string());
+    for var in options.env {
+        args.push("-e".to_string());
+        args.push(var.clone());
+    }
+    args.push(container_id.to_string());
+    args.extend_from_slice(cmd);
+    args
+}
+
+/// Whether the file descriptor is a terminal.
+pub fn is_tty(fd: libc::c_int) -> bool {
+    unsafe { libc::isatty(fd) == 1 }
+}
+
+/// Build the `devcontainer exec` arguments shared by every exec invocation:
+/// workspace folder, merged config (if any) and docker path overrides.
+pub fn base_exec_args() -> Result<Vec<String>> {
+    let workspace_folder = workspace::workspace_folder()?;
+    let workspace_root = PathBuf::from(&workspace_folder);
+    let merged_config = config::resolve_config(&workspace_root)?;
+
+    let mut cmd_args = vec![
+        "exec".to_string(),
+        "--workspace-folder".to_string(),
+        workspace_folder,
+    ];
+
+    if let Some(config_path) = &merged_config {
+        cmd_args.push("--config".to_string());
+        cmd_args.push(config_path.to_string_lossy().to_string());
+    }
+
+    cmd_args.extend(docker::devcontainer_cli_docker_args());
+    Ok(cmd_args)
+}
+
+/// Where the workspace is mounted in the container: `workspaceFolder` from
+/// the config, else the devcontainer CLI's default `/workspaces/<basename>`.
+pub fn container_workspace_folder(workspace_root: &Path) -> Result<String> {
+    let configured = config::load_effective_config(workspace_root)?
+        .and_then(|c| c["workspaceFolder"].as_str().map(str::to_string));
+    if let Some(folder) = configured {
+        return Ok(folder);
+    }
+    let basename = workspace_root
+        .file_name()
+        .context("workspace folder has no basename")?
+        .to_string_lossy();
+    Ok(format!("/workspaces/{basename}"))
+}
+
+/// Map `--workdir` to a container path. Relative paths and absolute host
+/// paths inside the workspace are rebased onto `container_root`; other
+/// absolute paths are taken as container paths.
+fn container_workdir(dir: &str, workspace_root: &Path, container_root: &str) -> Result<String> {
+    let path = Path::new(dir);
+    let relative = if path.is_absolute() {
+        match path.strip_prefix(workspace_root) {
+            Ok(rel) => rel,
+            Err(_) => return Ok(dir.to_string()),
+        }
+    } else {
+        path
+    };
+
+    let mut parts: Vec<String> = Vec::new();
+    for component in relative.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().to_string()),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if parts.pop().is_none() {
+                    bail!("--workdir {dir} is outside the workspace");
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => {}
+        }
+    }
+    let root = container_root.trim_end_matches('/');
+    Ok(if parts.is_empty() {
+        root.to_string()
+    } else {
+        format!("{root}/{}", parts.join("/"))
+    })
+}
+
+/// Wrap `cmd` to run in `dir` inside the container.
+fn with_workdir(dir: &str, cmd: &[String]) -> Vec<String> {
+    let mut wrapped = vec![
+        "sh".to_string(),
+        "-c".to_string(),
+        r#"cd "$1" || exit; shift; exec "$@""#.to_string(),
+        "_".to_string(),
+        dir.to_string(),
+    ];
+    wrapped.extend_from_slice(cmd);
+    wrapped
+}
+
+/// Collect `KEY=VALUE` pairs from env files (in order) followed by `-e`
+/// flags, so flags override file entries.
+pub fn resolve_env(env: &[String], env_files: &[PathBuf]) -> Result<Vec<String>> {
+    let mut vars = Vec::new();
+    for path in env_files {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("failed to read env file {}", path.display()))?;
+        let parsed = parse_env_file(&contents)
+            .with_context(|| format!("invalid env file {}", path.display()))?;
+        vars.extend(parsed.into_iter().map(|(k, v)| format!("{k}={v}")));
+    }
+    for var in env {
+        match var.split_once('=') {
+            Some((key, _)) if !key.is_empty() => vars.push(var.clone()),
+            Some(_) => bail!("invalid environment variable {var:?}: expected KEY=VALUE"),
+            // Like `docker run -e KEY`: take the value from the host, skip if unset
+            None => {
+                if let Ok(value) = std::env::var(var) {
+                    vars.push(format!("{var}={value}"));
+                }
+            }
+        }
+    }
+    Ok(vars)
+}
+
+/// Parse a `.env` file: `KEY=VALUE` lines with optional `export ` prefix and
+/// surrounding quotes. Blank lines and `#` comments are skipped.
+fn parse_env_file(contents: &str) -> Result<Vec<(String, String)>> {
+    let mut vars = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((key, value)) = line.split_once('=') else {
+            bail!("line {}: expected KEY=VALUE", i + 1);
+        };
+        let key = key.trim();
+        if key.is_empty() {
+            bail!("line {}: missing variable name", i + 1);
+        }
+        let value = value.trim();
+        let value = ['"', '\'']
+            .iter()
+            .find_map(|q| value.strip_prefix(*q)?.strip_suffix(*q))
+            .unwrap_or(value);
+        vars.push((key.to_string(), value.to_string()));
+    }
+    Ok(vars)
+}
+
 /// Determine the relay hostname based on the Docker runtime in use.
 /// Podman uses `host.containers.internal`, Docker uses `host.docker.internal`.
 fn relay_host() -> &'static str {
@@ -223,3 +524,86 @@ fn build_relay_wrapped_cmd(
     wrapped.extend_from_slice(cmd);
     wrapped
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_env_file_handles_comments_quotes_and_export() {
+        let vars = parse_env_file(
+            "# secrets\n\nAPI_KEY=abc=123\nexport NAME=\"John Doe\"\nEMPTY=\nSINGLE='x y'\n",
+        )
+        .unwrap();
+        assert_eq!(
+            vars,
+            vec![
+                ("API_KEY".to_string(), "abc=123".to_string()),
+                ("NAME".to_string(), "John Doe".to_string()),
+                ("EMPTY".to_string(), String::new()),
+                ("SINGLE".to_string(), "x y".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_env_file_rejects_lines_without_equals() {
+        let err = parse_env_file("A=1\nBROKEN\n").unwrap_err();
+        assert!(err.to_string().contains("line 2"));
+    }
+
+    #[test]
+    fn resolve_env_flags_follow_files() {
+        let path = std::env::temp_dir().join(format!("dcw-exec-env-{}", std::process::id()));
+        fs::write(&path, "A=file\nB=file\n").unwrap();
+        let vars = resolve_env(&["A=flag".to_string()], std::slice::from_ref(&path)).unwrap();
+        let _ = fs::remove_file(&path);
+        assert_eq!(vars, vec!["A=file", "B=file", "A=flag"]);
+    }
+
+    #[test]
+    fn container_workdir_maps_workspace_paths() {
+        let root = Path::new("/home/me/app");
+        let map = |dir| container_workdir(dir, root, "/workspaces/app").unwrap();
+        assert_eq!(map("backend"), "/workspaces/app/backend");
+        assert_eq!(map("./backend/../frontend/"), "/workspaces/app/frontend");
+        assert_eq!(map("."), "/workspaces/app");
+        assert_eq!(map("/home/me/app/backend"), "/workspaces/app/backend");
+        assert_eq!(map("/tmp"), "/tmp");
+        assert!(container_workdir("../other", root, "/workspaces/app").is_err());
+    }
+
+    #[test]
+    fn with_workdir_passes_dir_as_argument() {
+        let cmd = with_workdir("/work space", &["cargo".to_string(), "test".to_string()]);
+        assert_eq!(cmd[4], "/work space");
+        assert_eq!(&cmd[5..], ["cargo", "test"]);
+    }
+
+    #[test]
+    fn docker_exec_args_pass_user_workdir_and_env() {
+        let env = ["A=1".to_string()];
+        let options = ExecOptions {
+            env: &env,
+            interactive: true,
+            tty: true,
+            ..Default::default()
+        };
+        let cmd = ["id".to_string()];
+        let args = docker_exec_args("abc", Some("root"), "/workspaces/app", &options, &cmd);
+        assert_eq!(
+            args.join(" "),
+            "exec -i -t -u root -w /workspaces/app -e A=1 abc id"
+        );
+
+        // Piped output: no TTY, and stdin only when asked for
+        let options = ExecOptions::default();
+        let args = docker_exec_args("abc", None, "/w", &options, &cmd);
+        assert_eq!(args.join(" "), "exec -w /w abc id");
+    }
+
+    #[test]
+    fn resolve_env_rejects_missing_key() {
+        assert!(resolve_env(&["=x".to_string()], &[]).is_err());
+    }
+}
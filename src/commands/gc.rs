@@ -0,0 +1,187 @@
+use anyhow::Result;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use tracing::{info, warn};
+
+use crate::commands::prune;
+use crate::docker;
+use crate::traffic::format_bytes;
+
+#[derive(clap::Args)]
+pub struct GcArgs {
+    /// Also clear the Docker build cache, which other projects share
+    #[arg(long)]
+    pub build_cache: bool,
+
+    /// Also remove dangling devcontainer images, which may come from any
+    /// workspace on the host
+    #[arg(long)]
+    pub all: bool,
+}
+
+pub fn run(args: &GcArgs) -> Result<()> {
+    let in_use = docker::images_in_use()?;
+    let unused = |images: Vec<docker::Image>| -> Vec<docker::Image> {
+        images
+            .into_iter()
+            .filter(|i| !in_use.contains(&i.id))
+            .collect()
+    };
+    // Rebuilds leave the previous image untagged; every devcontainer build
+    // carries this label. Nothing on the image tells which workspace built
+    // it, so these are only removed with --all.
+    let mut builds = unused(docker::list_images(&[
+        "dangling=true",
+        "label=devcontainer.metadata",
+    ])?);
+    if !args.all && !builds.is_empty() {
+        info!(
+            "{} dangling devcontainer images from any workspace take up {}; remove them with `dcw gc --all`.",
+            builds.len(),
+            format_bytes(Some(total_size(&builds)))
+        );
+        builds.clear();
+    }
+    let sidecars = unused(old_sidecar_images()?);
+    let stale_dirs = prune::find_stale_runtime_dirs(&prune::known_workspace_ids()?);
+
+    if builds.is_empty() && sidecars.is_empty() && stale_dirs.is_empty() && !args.build_cache {
+        info!("Nothing to clean up.");
+        return Ok(());
+    }
+
+    let dry_run = docker::dry_run();
+    let verb = if dry_run { "Would remove" } else { "Removing" };
+    let mut reclaimed = 0;
+
+    let images = builds
+        .iter()
+        .map(|i| (i, "dangling devcontainer image"))
+        .chain(sidecars.iter().map(|i| (i, "old sidecar image")));
+    for (image, kind) in images {
+        let name = image.reference.as_deref().unwrap_or(short_id(&image.id));
+        info!("{verb} {kind} {name} ({})", format_bytes(image.size));
+        if dry_run {
+            reclaimed += image.size.unwrap_or(0);
+            continue;
+        }
+        match docker::remove_image(&image.id) {
+            Ok(()) => reclaimed += image.size.unwrap_or(0),
+            Err(e) => warn!("{e}"),
+        }
+    }
+
+    for dir in &stale_dirs {
+        let dir = &dir.path;
+        let size = dir_size(dir);
+        info!(
+            "{verb} stale runtime directory {} ({})",
+            dir.display(),
+            format_bytes(Some(size))
+        );
+        if dry_run {
+            reclaimed += size;
+            continue;
+        }
+        match fs::remove_dir_all(dir) {
+            Ok(()) => reclaimed += size,
+            Err(e) => warn!("failed to remove {}: {e}", dir.display()),
+        }
+    }
+
+    if args.build_cache {
+        info!("Clearing the build cache...");
+        match docker::prune_build_cache() {
+            Ok(size) => reclaimed += size.unwrap_or(0),
+            Err(e) => warn!("{e}"),
+        }
+    }
+
+    if dry_run {
+        info!("Would reclaim {}.", format_bytes(Some(reclaimed)));
+    } else {
+        info!("Reclaimed {}.", format_bytes(Some(reclaimed)));
+    }
+    Ok(())
+}
+
+fn total_size(images: &[docker::Image]) -> u64 {
+    images.iter().filter_map(|i| i.size).sum()
+}
+
+/// Local socat images other than the one sidecars use now: earlier tags,
+/// and the default image once `forward.sidecar_image` points elsewhere.
+fn old_sidecar_images() -> Result<Vec<docker::Image>> {
+    let current = docker::sidecar_image();
+    let repositories: HashSet<&str> = [docker::SIDECAR_IMAGE, current]
+        .into_iter()
+        .map(|r| split_tag(r).0)
+        .collect();
+    let mut images = Vec::new();
+    for repository in repositories {
+        let filter = format!("reference={repository}");
+        images.extend(docker::list_images(&[&filter])?.into_iter().filter(|i| {
+            i.reference
+                .as_deref()
+                .is_some_and(|r| is_old_sidecar(r, current))
+        }));
+    }
+    Ok(images)
+}
+
+fn is_old_sidecar(reference: &str, current: &str) -> bool {
+    split_tag(reference) != split_tag(current)
+}
+
+/// Split an image reference into repository and tag, which defaults to
+/// `latest`.
+fn split_tag(reference: &str) -> (&str, &str) {
+    match reference.rsplit_once(':') {
+        // A colon before the last slash belongs to a registry port
+        Some((repository, tag)) if !tag.contains('/') => (repository, tag),
+        _ => (reference, "latest"),
+    }
+}
+
+/// `sha256:0123456789abcdef...` shortened the way `docker image ls` does.
+fn short_id(id: &str) -> &str {
+    let hex = id.strip_prefix("sha256:").unwrap_or(id);
+    &hex[..hex.len().min(12)]
+}
+
+/// Bytes taken up by the files under `path`.
+fn dir_size(path: &Path) -> u64 {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    fs::read_dir(path)
+        .map(|entries| entries.flatten().map(|e| dir_size(&e.path())).sum())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn old_sidecars_differ_from_the_current_image() {
+        assert!(!is_old_sidecar("alpine/socat:latest", "alpine/socat"));
+        assert!(is_old_sidecar("alpine/socat:1.7.4", "alpine/socat"));
+        assert!(is_old_sidecar(
+            "alpine/socat:latest",
+            "registry.internal:5000/mirror/socat:1.8"
+        ));
+        assert!(!is_old_sidecar(
+            "registry.internal:5000/mirror/socat:1.8",
+            "registry.internal:5000/mirror/socat:1.8"
+        ));
+        assert_eq!(
+            split_tag("registry.internal:5000/socat"),
+            ("registry.internal:5000/socat", "latest")
+        );
+    }
+}
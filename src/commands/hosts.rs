@@ -0,0 +1,103 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use crate::customizations;
+use crate::docker;
+use crate::hostnames;
+use crate::settings::Settings;
+use crate::workspace;
+
+#[derive(clap::Args)]
+pub struct HostsArgs {
+    /// Write the entries to the hosts file (usually needs sudo)
+    #[arg(long, conflicts_with = "remove")]
+    pub sync: bool,
+
+    /// Remove dcw's entries from the hosts file
+    #[arg(long)]
+    pub remove: bool,
+
+    /// Hosts file to update
+    #[arg(long, value_name = "PATH", default_value = "/etc/hosts")]
+    pub file: PathBuf,
+}
+
+pub fn run(args: &HostsArgs) -> Result<()> {
+    let lines = if args.remove {
+        Vec::new()
+    } else {
+        hosts_lines(&running_hostnames()?)
+    };
+    if !args.sync && !args.remove {
+        for line in &lines {
+            println!("{line}");
+        }
+        return Ok(());
+    }
+
+    let contents = fs::read_to_string(&args.file)
+        .with_context(|| format!("failed to read {}", args.file.display()))?;
+    let updated = hostnames::replace_hosts_block(&contents, &lines);
+    if updated == contents {
+        info!("{} is up to date.", args.file.display());
+        return Ok(());
+    }
+    // Written in place: /etc/hosts is often a bind mount that can't be
+    // replaced by renaming
+    fs::write(&args.file, updated).with_context(|| {
+        format!(
+            "failed to write {} — try again with sudo",
+            args.file.display()
+        )
+    })?;
+    if lines.is_empty() {
+        info!("Removed dcw's entries from {}.", args.file.display());
+    } else {
+        info!("Updated {}.", args.file.display());
+    }
+    Ok(())
+}
+
+/// Hostnames of every running devcontainer, and of the compose services
+/// they forward ports from.
+fn running_hostnames() -> Result<Vec<String>> {
+    let mut names = Vec::new();
+    for container in docker::list_devcontainers()? {
+        if container.state != "running" {
+            continue;
+        }
+        let root = Path::new(&container.folder);
+        let settings = customizations::load_or_default(root);
+        let Some(workspace) = hostnames::workspace_hostname(root, &settings) else {
+            continue;
+        };
+        let ws_id = workspace::workspace_id_for(&container.folder)?;
+        for fwd in docker::list_port_forwards(&ws_id)? {
+            let service = (!fwd.service.is_empty()).then_some(fwd.service.as_str());
+            if let Some(name) = service.and_then(|s| {
+                hostnames::forward_hostname(&workspace, Some(s), fwd.protocol, "127.0.0.1", false)
+            }) {
+                names.push(name);
+            }
+        }
+        names.push(workspace);
+    }
+    names.sort();
+    names.dedup();
+    Ok(names)
+}
+
+/// Hosts file lines for `names`; `::1` too when forwards listen there.
+fn hosts_lines(names: &[String]) -> Vec<String> {
+    if names.is_empty() {
+        return Vec::new();
+    }
+    let names = names.join(" ");
+    let mut lines = vec![format!("127.0.0.1 {names}")];
+    if Settings::get().forward.dual_stack {
+        lines.push(format!("::1 {names}"));
+    }
+    lines
+}
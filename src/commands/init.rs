@@ -0,0 +1,379 @@
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use crate::templates::{self, Reference, TemplateInfo, TemplateOption};
+use crate::workspace;
+
+/// Built-in starter templates.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Template {
+    Rust,
+    Node,
+    Python,
+}
+
+impl Template {
+    fn image(self) -> &'static str {
+        match self {
+            Template::Rust => "mcr.microsoft.com/devcontainers/rust:1",
+            Template::Node => "mcr.microsoft.com/devcontainers/javascript-node:22",
+            Template::Python => "mcr.microsoft.com/devcontainers/python:3",
+        }
+    }
+
+    fn extensions(self) -> &'static [&'static str] {
+        match self {
+            Template::Rust => &["rust-lang.rust-analyzer"],
+            Template::Node => &["dbaeumer.vscode-eslint"],
+            Template::Python => &["ms-python.python"],
+        }
+    }
+
+    fn forward_ports(self) -> &'static [u16] {
+        match self {
+            Template::Rust => &[],
+            Template::Node => &[3000],
+            Template::Python => &[8000],
+        }
+    }
+}
+
+#[derive(clap::Args)]
+pub struct InitArgs {
+    /// Template to use (prompted for if omitted)
+    #[arg(long, value_enum)]
+    pub template: Option<Template>,
+
+    /// Use a template from a registry instead: an ID from
+    /// ghcr.io/devcontainers/templates (e.g. `go`) or a full reference
+    #[arg(long, value_name = "ID|REF", conflicts_with = "template")]
+    pub from: Option<String>,
+
+    /// Set an option of the `--from` template instead of being asked,
+    /// e.g. `imageVariant=bookworm`
+    #[arg(long = "option", value_name = "NAME=VALUE", requires = "from")]
+    pub options: Vec<String>,
+
+    /// List the templates in ghcr.io/devcontainers/templates
+    #[arg(long, conflicts_with_all = ["template", "from"])]
+    pub list: bool,
+
+    /// Overwrite existing configuration files
+    #[arg(long)]
+    pub force: bool,
+}
+
+pub fn run(args: &InitArgs) -> Result<()> {
+    if args.list {
+        return list_templates();
+    }
+    let workspace_folder = workspace::workspace_folder()?;
+    let root = PathBuf::from(&workspace_folder);
+    if let Some(reference) = &args.from {
+        return init_from_registry(reference, args, &root);
+    }
+
+    let template = match args.template {
+        Some(t) => t,
+        None => prompt_template()?,
+    };
+
+    let name = root
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "devcontainer".to_string());
+
+    let dc_dir = root.join(".devcontainer");
+    let files = vec![(
+        dc_dir.join("devcontainer.json"),
+        render_devcontainer_json(template, &name).into_bytes(),
+    )];
+    write_scaffold(&dc_dir, files, args.force)
+}
+
+/// Write `files` plus an empty devcontainer.local.json in `dc_dir`.
+fn write_scaffold(dc_dir: &Path, mut files: Vec<(PathBuf, Vec<u8>)>, force: bool) -> Result<()> {
+    files.push((dc_dir.join("devcontainer.local.json"), LOCAL_JSON.into()));
+    write_files(&files, force)?;
+    info!("Add `.devcontainer/devcontainer.local.json` and `.devcontainer/secrets.local.json` to .gitignore to keep local overrides and secrets private.");
+    Ok(())
+}
+
+fn list_templates() -> Result<()> {
+    let mut templates = templates::list_official()?;
+    templates.sort_by(|a, b| a.id.cmp(&b.id));
+    let width = templates
+        .iter()
+        .map(|t| t.id.len())
+        .max()
+        .unwrap_or(0)
+        .max(2);
+    println!("{:<width$}   DESCRIPTION", "ID");
+    for template in &templates {
+        println!("{:<width$}   {}", template.id, template.description);
+    }
+    Ok(())
+}
+
+/// Download a template and write its files with the options filled in.
+fn init_from_registry(reference: &str, args: &InitArgs, root: &Path) -> Result<()> {
+    let reference = Reference::parse(reference)?;
+    let given = parse_options(&args.options)?;
+    info!("Fetching template {reference}...");
+    let template = templates::fetch(&reference)?;
+    let values = option_values(&template.info, given, io::stdin().is_terminal())?;
+
+    let files = template
+        .render(&values)
+        .into_iter()
+        .map(|(path, contents)| (root.join(path), contents))
+        .collect();
+    write_scaffold(&root.join(".devcontainer"), files, args.force)
+}
+
+/// `--option NAME=VALUE` flags by name.
+fn parse_options(options: &[String]) -> Result<HashMap<String, String>> {
+    options
+        .iter()
+        .map(|option| {
+            let (name, value) = option
+                .split_once('=')
+                .with_context(|| format!("expected NAME=VALUE, got {option}"))?;
+            Ok((name.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// A value for every option of the template: as given with `--option`,
+/// else asked for on a terminal, else its default.
+fn option_values(
+    info: &TemplateInfo,
+    mut given: HashMap<String, String>,
+    interactive: bool,
+) -> Result<HashMap<String, String>> {
+    if let Some(unknown) = given.keys().find(|name| !info.options.contains_key(*name)) {
+        bail!("template {} has no option {unknown}", info.id);
+    }
+    let mut values = HashMap::new();
+    for (name, option) in &info.options {
+        let value = match given.remove(name) {
+            Some(value) => value,
+            None if interactive => prompt_option(name, option)?,
+            None => option.default_value(),
+        };
+        option.check(name, &value)?;
+        values.insert(name.clone(), value);
+    }
+    Ok(values)
+}
+
+fn prompt_option(name: &str, option: &TemplateOption) -> Result<String> {
+    let default = option.default_value();
+    if !option.description.is_empty() {
+        println!("{}", option.description);
+    }
+    let choices = option.choices();
+    if choices.is_empty() {
+        print!("{name} [{default}]: ");
+    } else {
+        print!("{name} ({}) [{default}]: ", choices.join("/"));
+    }
+    io::stdout().flush().ok();
+
+    let mut line = String::new();
+    io::stdin()
+        .lock()
+        .read_line(&mut line)
+        .with_context(|| format!("failed to read option {name}"))?;
+    let value = line.trim();
+    Ok(if value.is_empty() {
+        default
+    } else {
+        value.to_string()
+    })
+}
+
+const LOCAL_JSON: &str = "\
+// Local overrides for devcontainer.json (not meant to be committed).
+// This file is deep-merged on top of devcontainer.json by dcw.
+{
+}
+";
+
+/// Write each file, creating parent directories. Without `force`, nothing
+/// is written if any of them already exists.
+fn write_files(files: &[(PathBuf, Vec<u8>)], force: bool) -> Result<()> {
+    if !force {
+        if let Some((path, _)) = files.iter().find(|(path, _)| path.exists()) {
+            bail!(
+                "{} already exists (use --force to overwrite)",
+                path.display()
+            );
+        }
+    }
+    for (path, contents) in files {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create {}", parent.display()))?;
+        }
+        fs::write(path, contents).with_context(|| format!("failed to write {}", path.display()))?;
+        info!("Created {}", path.display());
+    }
+    Ok(())
+}
+
+fn prompt_template() -> Result<Template> {
+    let names: Vec<String> = Template::value_variants()
+        .iter()
+        .filter_map(|t| t.to_possible_value().map(|v| v.get_name().to_string()))
+        .collect();
+
+    print!("Select a template [{}]: ", names.join("/"));
+    io::stdout().flush().ok();
+
+    let mut line = String::new();
+    io::stdin()
+        .lock()
+        .read_line(&mut line)
+        .context("failed to read template choice")?;
+
+    Template::from_str(line.trim(), true)
+        .map_err(|_| anyhow::anyhow!("unknown template: {}", line.trim()))
+}
+
+/// Render a starter devcontainer.json (JSONC) for the given template.
+fn render_devcontainer_json(template: Template, name: &str) -> String {
+    let extensions = template
+        .extensions()
+        .iter()
+        .map(|e| format!("\"{e}\""))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let ports = template
+        .forward_ports()
+        .iter()
+        .map(|p| p.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    format!(
+        r#"// For format details, see https://containers.dev/implementors/json_reference/
+{{
+  "name": {name},
+  "image": "{image}",
+
+  // Ports forwarded to the host by `dcw up`
+  "forwardPorts": [{ports}],
+
+  "customizations": {{
+    "vscode": {{
+      "extensions": [{extensions}]
+    }}
+  }}
+}}
+"#,
+        name = serde_json::Value::String(name.to_string()),
+        image = template.image(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config;
+
+    #[test]
+    fn rendered_templates_are_valid_jsonc() {
+        let dir = std::env::temp_dir().join("dcw-test-init-render");
+        let _ = fs::create_dir_all(&dir);
+        for template in Template::value_variants() {
+            let path = dir.join("devcontainer.json");
+            fs::write(&path, render_devcontainer_json(*template, "my \"app\"")).unwrap();
+            let val = config::read_jsonc(&path).unwrap();
+            assert_eq!(val["name"], "my \"app\"");
+            assert_eq!(val["image"], template.image());
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn node_template_forwards_port_3000() {
+        let dir = std::env::temp_dir().join("dcw-test-init-node");
+        let _ = fs::create_dir_all(&dir);
+        let path = dir.join("devcontainer.json");
+        fs::write(&path, render_devcontainer_json(Template::Node, "app")).unwrap();
+        let val = config::read_jsonc(&path).unwrap();
+        assert_eq!(val["forwardPorts"], serde_json::json!([3000]));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn local_json_is_empty_object() {
+        let dir = std::env::temp_dir().join("dcw-test-init-local");
+        let _ = fs::create_dir_all(&dir);
+        let path = dir.join("devcontainer.local.json");
+        fs::write(&path, LOCAL_JSON).unwrap();
+        let val = config::read_jsonc(&path).unwrap();
+        assert_eq!(val, serde_json::json!({}));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn option_values_fall_back_to_defaults() {
+        let info: TemplateInfo = serde_json::from_str(
+            r#"{"id": "go", "options": {
+                "imageVariant": {"type": "string", "proposals": ["1.22"], "default": "1.22"},
+                "installTools": {"type": "boolean", "default": true}
+            }}"#,
+        )
+        .unwrap();
+        let given = parse_options(&["imageVariant=1.21".to_string()]).unwrap();
+        let values = option_values(&info, given, false).unwrap();
+        assert_eq!(values["imageVariant"], "1.21");
+        assert_eq!(values["installTools"], "true");
+
+        let given = parse_options(&["installTools=maybe".to_string()]).unwrap();
+        assert!(option_values(&info, given, false).is_err());
+        let given = parse_options(&["variant=1.21".to_string()]).unwrap();
+        assert!(option_values(&info, given, false).is_err());
+        assert!(parse_options(&["imageVariant".to_string()]).is_err());
+    }
+
+    #[test]
+    fn write_files_refuses_to_overwrite_without_force() {
+        let dir = std::env::temp_dir().join("dcw-test-init-overwrite");
+        let _ = fs::create_dir_all(&dir);
+        let path = dir.join("devcontainer.json");
+        fs::write(&path, "{}").unwrap();
+        let files = [(path.clone(), b"{\"a\": 1}".to_vec())];
+        assert!(write_files(&files, false).is_err());
+        assert!(write_files(&files, true).is_ok());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{\"a\": 1}");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn existing_local_json_stops_the_whole_scaffold() {
+        let dir = std::env::temp_dir().join("dcw-test-init-local-exists");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("devcontainer.local.json"), "{\"a\": 1}").unwrap();
+        let files = vec![(
+            dir.join("devcontainer.json"),
+            render_devcontainer_json(Template::Rust, "app").into_bytes(),
+        )];
+        let err = write_scaffold(&dir, files, false).unwrap_err();
+        assert!(err.to_string().contains("devcontainer.local.json"));
+        assert!(!dir.join("devcontainer.json").exists());
+        assert_eq!(
+            fs::read_to_string(dir.join("devcontainer.local.json")).unwrap(),
+            "{\"a\": 1}"
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
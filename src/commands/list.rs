@@ -0,0 +1,206 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use tracing::warn;
+
+use crate::commands::daemon;
+use crate::docker;
+use crate::output::{self, OutputFormat};
+use crate::workspace;
+
+/// Aggregated state of one workspace managed by dcw.
+#[derive(Debug, Default, PartialEq, Serialize)]
+struct WorkspaceRow {
+    folder: String,
+    container: String,
+    watcher: String,
+    forwards: usize,
+}
+
+/// State discovered from a `$XDG_RUNTIME_DIR/dcw/<ws_id>/` directory.
+struct RuntimeEntry {
+    ws_id: String,
+    folder: Option<String>,
+    watcher: String,
+}
+
+/// A workspace row as emitted by `--output json`.
+#[derive(Serialize)]
+struct JsonRow<'a> {
+    workspace: &'a str,
+    #[serde(flatten)]
+    row: &'a WorkspaceRow,
+}
+
+pub fn run(format: OutputFormat) -> Result<()> {
+    let watchers = daemon::watchers()
+        .into_iter()
+        .map(|w| (w.ws_id, w.state))
+        .collect();
+    let runtime = scan_runtime_dirs(&workspace::shared_runtime_dir(), &watchers);
+
+    let containers = match docker::list_devcontainers() {
+        Ok(list) => list
+            .into_iter()
+            .filter_map(|c| {
+                Some((
+                    workspace::workspace_id_for(&c.folder).ok()?,
+                    c.folder,
+                    c.state,
+                ))
+            })
+            .collect(),
+        Err(e) => {
+            warn!("failed to list devcontainers: {e}");
+            Vec::new()
+        }
+    };
+
+    let sidecars = match docker::list_all_sidecar_targets() {
+        Ok(list) => list.into_iter().map(|s| s.workspace).collect(),
+        Err(e) => {
+            warn!("failed to list sidecars: {e}");
+            Vec::new()
+        }
+    };
+
+    let rows = merge_rows(runtime, containers, sidecars);
+    if format == OutputFormat::Json {
+        let json: Vec<_> = rows
+            .iter()
+            .map(|(ws_id, row)| JsonRow {
+                workspace: ws_id,
+                row,
+            })
+            .collect();
+        return output::print_json(&json);
+    }
+
+    if rows.is_empty() {
+        println!("No workspaces found.");
+        return Ok(());
+    }
+
+    println!(
+        "{:<32} {:<10} {:<8} {:>8}  FOLDER",
+        "WORKSPACE", "CONTAINER", "WATCHER", "FORWARDS"
+    );
+    for (ws_id, row) in &rows {
+        println!(
+            "{:<32} {:<10} {:<8} {:>8}  {}",
+            ws_id, row.container, row.watcher, row.forwards, row.folder
+        );
+    }
+
+    Ok(())
+}
+
+/// Read every workspace runtime directory under `base`. `watchers` maps
+/// workspace IDs to the state of the watcher `dcw daemon` runs for them.
+fn scan_runtime_dirs(base: &Path, watchers: &HashMap<String, String>) -> Vec<RuntimeEntry> {
+    let Ok(entries) = fs::read_dir(base) else {
+        return Vec::new();
+    };
+
+    entries
+        .flatten()
+        .filter(|e| e.path().is_dir())
+        .map(|e| {
+            let ws_id = e.file_name().to_string_lossy().to_string();
+            RuntimeEntry {
+                folder: fs::read_to_string(e.path().join("workspace_folder"))
+                    .ok()
+                    .map(|s| s.trim().to_string()),
+                watcher: watchers.get(&ws_id).cloned().unwrap_or("-".to_string()),
+                ws_id,
+            }
+        })
+        .collect()
+}
+
+/// Combine runtime dirs, devcontainers `(ws_id, folder, state)` and sidecar
+/// workspace labels into one row per workspace, keyed by workspace ID.
+fn merge_rows(
+    runtime: Vec<RuntimeEntry>,
+    containers: Vec<(String, String, String)>,
+    sidecar_workspaces: Vec<String>,
+) -> BTreeMap<String, WorkspaceRow> {
+    let mut rows: BTreeMap<String, WorkspaceRow> = BTreeMap::new();
+    let new_row = || WorkspaceRow {
+        container: "-".to_string(),
+        watcher: "-".to_string(),
+        ..Default::default()
+    };
+
+    for entry in runtime {
+        let row = rows.entry(entry.ws_id).or_insert_with(new_row);
+        if let Some(folder) = entry.folder {
+            row.folder = folder;
+        }
+        row.watcher = entry.watcher;
+    }
+
+    for (ws_id, folder, state) in containers {
+        let row = rows.entry(ws_id).or_insert_with(new_row);
+        row.folder = folder;
+        row.container = state;
+    }
+
+    for ws_id in sidecar_workspaces {
+        rows.entry(ws_id).or_insert_with(new_row).forwards += 1;
+    }
+
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_rows_combines_sources() {
+        let runtime = vec![RuntimeEntry {
+            ws_id: "dev-api-1234abcd".to_string(),
+            folder: None,
+            watcher: "running".to_string(),
+        }];
+        let containers = vec![(
+            "dev-api-1234abcd".to_string(),
+            "/src/api".to_string(),
+            "running".to_string(),
+        )];
+        let sidecars = vec![
+            "dev-api-1234abcd".to_string(),
+            "dev-api-1234abcd".to_string(),
+        ];
+
+        let rows = merge_rows(runtime, containers, sidecars);
+        assert_eq!(
+            rows["dev-api-1234abcd"],
+            WorkspaceRow {
+                folder: "/src/api".to_string(),
+                container: "running".to_string(),
+                watcher: "running".to_string(),
+                forwards: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn merge_rows_runtime_only_workspace() {
+        let runtime = vec![RuntimeEntry {
+            ws_id: "dev-web-00000000".to_string(),
+            folder: Some("/src/web".to_string()),
+            watcher: "stale".to_string(),
+        }];
+
+        let rows = merge_rows(runtime, Vec::new(), Vec::new());
+        let row = &rows["dev-web-00000000"];
+        assert_eq!(row.folder, "/src/web");
+        assert_eq!(row.container, "-");
+        assert_eq!(row.watcher, "stale");
+        assert_eq!(row.forwards, 0);
+    }
+}
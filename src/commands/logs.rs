@@ -0,0 +1,154 @@
+use anyhow::{bail, Context, Result};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+use tracing::warn;
+
+use crate::docker;
+use crate::logging::Logged;
+use crate::workspace;
+
+#[derive(clap::Args)]
+pub struct LogsArgs {
+    /// Follow log output
+    #[arg(short, long)]
+    pub follow: bool,
+
+    /// Number of lines to show from the end of the logs (or "all")
+    #[arg(long)]
+    pub tail: Option<String>,
+
+    /// Also show logs from port-forward sidecars
+    #[arg(long)]
+    pub sidecars: bool,
+}
+
+pub fn run(args: &LogsArgs) -> Result<()> {
+    let workspace_folder = workspace::workspace_folder()?;
+    let container_id =
+        docker::find_devcontainer(&workspace_folder)?.context("no running devcontainer found")?;
+
+    if !args.sidecars {
+        let status = Command::new(docker::docker_path())
+            .args(build_logs_args(
+                &container_id,
+                args.follow,
+                args.tail.as_deref(),
+            ))
+            .logged()
+            .status()
+            .context("failed to run docker logs")?;
+        if !status.success() {
+            bail!("docker logs exited with status {status}");
+        }
+        return Ok(());
+    }
+
+    let ws_id = workspace::workspace_id()?;
+    let mut targets = vec![("devcontainer".to_string(), container_id)];
+    for fwd in docker::list_port_forwards(&ws_id)? {
+        // The shared sidecar is listed once per forward it serves
+        if !targets.iter().any(|(_, t)| *t == fwd.name) {
+            targets.push((fwd.name.clone(), fwd.name));
+        }
+    }
+
+    let handles: Vec<_> = targets
+        .into_iter()
+        .map(|(label, target)| {
+            let follow = args.follow;
+            let tail = args.tail.clone();
+            thread::spawn(move || stream_prefixed(&label, &target, follow, tail.as_deref()))
+        })
+        .collect();
+
+    for handle in handles {
+        match handle.join() {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => warn!("{e}"),
+            Err(_) => warn!("log streaming thread panicked"),
+        }
+    }
+
+    Ok(())
+}
+
+/// Build the `docker logs` argument list for a container.
+fn build_logs_args(container: &str, follow: bool, tail: Option<&str>) -> Vec<String> {
+    let mut args = vec!["logs".to_string()];
+    if follow {
+        args.push("--follow".to_string());
+    }
+    if let Some(n) = tail {
+        args.push("--tail".to_string());
+        args.push(n.to_string());
+    }
+    args.push(container.to_string());
+    args
+}
+
+/// Run `docker logs` for one container and print each line prefixed with `[label]`.
+/// stdout lines go to stdout and stderr lines go to stderr.
+fn stream_prefixed(label: &str, container: &str, follow: bool, tail: Option<&str>) -> Result<()> {
+    let mut child = Command::new(docker::docker_path())
+        .args(build_logs_args(container, follow, tail))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .logged()
+        .spawn()
+        .with_context(|| format!("failed to run docker logs for {label}"))?;
+
+    let stdout = child.stdout.take().context("missing docker logs stdout")?;
+    let stderr = child.stderr.take().context("missing docker logs stderr")?;
+
+    let err_label = label.to_string();
+    let err_thread = thread::spawn(move || {
+        copy_prefixed(stderr, &err_label, &mut std::io::stderr());
+    });
+    copy_prefixed(stdout, label, &mut std::io::stdout());
+    let _ = err_thread.join();
+
+    let status = child.wait().context("failed to wait for docker logs")?;
+    if !status.success() {
+        bail!("docker logs for {label} exited with status {status}");
+    }
+    Ok(())
+}
+
+fn copy_prefixed<R: Read, W: Write>(reader: R, label: &str, out: &mut W) {
+    for line in BufReader::new(reader).lines() {
+        let Ok(line) = line else { break };
+        let _ = writeln!(out, "{}", prefix_line(label, &line));
+    }
+}
+
+fn prefix_line(label: &str, line: &str) -> String {
+    format!("[{label}] {line}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn logs_args_minimal() {
+        assert_eq!(build_logs_args("abc", false, None), vec!["logs", "abc"]);
+    }
+
+    #[test]
+    fn logs_args_follow_and_tail() {
+        assert_eq!(
+            build_logs_args("abc", true, Some("100")),
+            vec!["logs", "--follow", "--tail", "100", "abc"]
+        );
+    }
+
+    #[test]
+    fn prefix_line_adds_label() {
+        assert_eq!(
+            prefix_line("pf-dev-x-c3000", "hello"),
+            "[pf-dev-x-c3000] hello"
+        );
+    }
+}
@@ -1,7 +1,34 @@
 pub mod browser_relay;
+pub mod build;
+pub mod code;
+pub mod config;
+pub mod daemon;
+pub mod doctor;
 pub mod down;
+pub mod env;
+pub mod events;
 pub mod exec;
+pub mod gc;
+pub mod hosts;
+pub mod init;
+pub mod list;
+pub mod logs;
+pub mod mount;
+pub mod overview;
 pub mod port;
+pub mod prebuild;
+pub mod prune;
+pub mod restart;
+pub mod run;
+pub mod shell;
+pub mod snapshot;
+pub mod ssh;
+pub mod stats;
+pub mod status;
+pub mod stop;
+pub mod ui;
 pub mod up;
 pub mod update;
+pub mod volume;
 pub mod watch;
+pub mod workspaces;
@@ -0,0 +1,304 @@
+use anyhow::{bail, Context, Result};
+use jsonc_parser::cst::CstInputValue;
+use serde::Serialize;
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use crate::config;
+use crate::output::{self, OutputFormat};
+use crate::workspace;
+
+#[derive(clap::Subcommand)]
+pub enum MountAction {
+    /// Bind-mount a host directory or file into the devcontainer, via
+    /// devcontainer.local.json
+    Add {
+        /// Host path; one inside the workspace is stored relative to it
+        source: PathBuf,
+        /// Absolute path in the container
+        target: String,
+        /// Mount read-only
+        #[arg(long)]
+        readonly: bool,
+    },
+    /// Remove a mount from devcontainer.local.json
+    #[command(alias = "rm")]
+    Remove {
+        /// Path of the mount in the container
+        target: String,
+    },
+    /// List the mounts of the config, with devcontainer.local.json merged in
+    #[command(alias = "ls")]
+    List,
+}
+
+/// An entry of `mounts`: a `source=...,target=...,type=...` string or an
+/// object with the same keys.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Mount {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub source: Option<String>,
+    pub target: String,
+    pub readonly: bool,
+}
+
+#[derive(Serialize)]
+struct MountRow {
+    #[serde(flatten)]
+    mount: Mount,
+    /// Set in devcontainer.local.json rather than the config itself
+    local: bool,
+}
+
+pub fn run(action: &MountAction, format: OutputFormat) -> Result<()> {
+    let root = PathBuf::from(workspace::workspace_folder()?);
+    match action {
+        MountAction::Add {
+            source,
+            target,
+            readonly,
+        } => add(&root, source, target, *readonly),
+        MountAction::Remove { target } => remove(&root, target),
+        MountAction::List => list(&root, format),
+    }
+}
+
+fn add(root: &Path, source: &Path, target: &str, readonly: bool) -> Result<()> {
+    let target = normalize_target(target);
+    if !target.starts_with('/') {
+        bail!("the target must be an absolute path in the container");
+    }
+    let source = mount_source(root, source)?;
+    if source.contains(',') || target.contains(',') {
+        bail!("mount paths can't contain commas");
+    }
+
+    let (_, merged) = config::load_base_and_merged(root)?;
+    if merged.get("dockerComposeFile").is_some() {
+        bail!("`mounts` doesn't apply to Docker Compose configs; add a volume to the service in the compose file instead");
+    }
+    if mounts(&merged).iter().any(|m| m.target == target) {
+        bail!("something is already mounted at {target} (see `dcw mount list`)");
+    }
+
+    let mut mount = format!("source={source},target={target},type=bind");
+    if readonly {
+        mount.push_str(",readonly");
+    }
+    config::edit_local_config(root, |object| {
+        // An override's array replaces the config's, so append to it
+        // unless the override already replaces it
+        let key = if object.get("mounts").is_some() {
+            "mounts"
+        } else {
+            "mounts+"
+        };
+        let array = object
+            .array_value_or_create(key)
+            .with_context(|| format!("`{key}` in devcontainer.local.json is not an array"))?;
+        array.ensure_multiline();
+        array.append(CstInputValue::String(mount.clone()));
+        Ok(())
+    })?;
+    info!("Added {mount} to devcontainer.local.json.");
+    info!("Mounts are set when the container is created: run `dcw up --rebuild` to apply it.");
+    Ok(())
+}
+
+fn remove(root: &Path, target: &str) -> Result<()> {
+    let target = normalize_target(target);
+    let (base, _) = config::load_base_and_merged(root)?;
+    config::edit_local_config(root, |object| {
+        let mut removed = false;
+        for key in ["mounts", "mounts+"] {
+            let Some(array) = object.array_value(key) else {
+                continue;
+            };
+            for element in array.elements() {
+                let matches = element
+                    .to_serde_value()
+                    .and_then(|value| parse_mount(&value))
+                    .is_some_and(|m| m.target == target);
+                if matches {
+                    element.remove();
+                    removed = true;
+                }
+            }
+        }
+        if !removed {
+            if mounts(&base).iter().any(|m| m.target == target) {
+                bail!("the mount at {target} is in the devcontainer config, not devcontainer.local.json");
+            }
+            bail!("nothing is mounted at {target} (see `dcw mount list`)");
+        }
+        Ok(())
+    })?;
+    info!("Removed the mount at {target} from devcontainer.local.json.");
+    info!("Mounts are set when the container is created: run `dcw up --rebuild` to apply it.");
+    Ok(())
+}
+
+fn list(root: &Path, format: OutputFormat) -> Result<()> {
+    let (base, merged) = config::load_base_and_merged(root)?;
+    let base = mounts(&base);
+    let rows: Vec<MountRow> = mounts(&merged)
+        .into_iter()
+        .map(|mount| MountRow {
+            local: !base.contains(&mount),
+            mount,
+        })
+        .collect();
+    if format == OutputFormat::Json {
+        return output::print_json(&rows);
+    }
+    if rows.is_empty() {
+        println!("No mounts configured (add one with `dcw mount add`).");
+        return Ok(());
+    }
+    let width = rows
+        .iter()
+        .map(|r| r.mount.target.len())
+        .max()
+        .unwrap_or(0)
+        .max(6);
+    println!(
+        "{:<width$}   {:<6}   {:<4}   {:<6}   SOURCE",
+        "TARGET", "TYPE", "MODE", "FILE"
+    );
+    for row in &rows {
+        println!(
+            "{:<width$}   {:<6}   {:<4}   {:<6}   {}",
+            row.mount.target,
+            row.mount.kind,
+            if row.mount.readonly { "ro" } else { "rw" },
+            if row.local { "local" } else { "config" },
+            row.mount.source.as_deref().unwrap_or("-"),
+        );
+    }
+    Ok(())
+}
+
+/// The `mounts` of a config.
+pub fn mounts(config: &Value) -> Vec<Mount> {
+    config
+        .get("mounts")
+        .and_then(Value::as_array)
+        .map(|mounts| mounts.iter().filter_map(parse_mount).collect())
+        .unwrap_or_default()
+}
+
+fn parse_mount(value: &Value) -> Option<Mount> {
+    let mut mount = Mount {
+        kind: "bind".to_string(),
+        source: None,
+        target: String::new(),
+        readonly: false,
+    };
+    match value {
+        Value::String(spec) => {
+            for part in spec.split(',') {
+                let (key, value) = part.split_once('=').unwrap_or((part, "true"));
+                match key.trim() {
+                    "type" => mount.kind = value.to_string(),
+                    "source" | "src" => mount.source = Some(value.to_string()),
+                    "target" | "destination" | "dst" => mount.target = normalize_target(value),
+                    "readonly" | "ro" => mount.readonly = value != "false",
+                    _ => {}
+                }
+            }
+        }
+        Value::Object(object) => {
+            if let Some(kind) = object.get("type").and_then(Value::as_str) {
+                mount.kind = kind.to_string();
+            }
+            mount.source = object
+                .get("source")
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            mount.target = normalize_target(object.get("target")?.as_str()?);
+        }
+        _ => return None,
+    }
+    (!mount.target.is_empty()).then_some(mount)
+}
+
+fn normalize_target(target: &str) -> String {
+    match target.trim_end_matches('/') {
+        "" if target.starts_with('/') => "/".to_string(),
+        trimmed => trimmed.to_string(),
+    }
+}
+
+/// The source as stored in the config: a path in the workspace relative to
+/// `${localWorkspaceFolder}`, so the override still works if the workspace
+/// moves, else the absolute path.
+fn mount_source(root: &Path, source: &Path) -> Result<String> {
+    let source = match source.strip_prefix("~") {
+        Ok(rest) => dirs::home_dir()
+            .context("failed to determine home directory")?
+            .join(rest),
+        Err(_) => source.to_path_buf(),
+    };
+    let path = fs::canonicalize(&source)
+        .with_context(|| format!("{} does not exist", source.display()))?;
+    let root = fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+    Ok(match path.strip_prefix(&root) {
+        Ok(relative) if relative.as_os_str().is_empty() => "${localWorkspaceFolder}".to_string(),
+        Ok(relative) => format!("${{localWorkspaceFolder}}/{}", relative.display()),
+        Err(_) => path.display().to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parse_mount_strings_and_objects() {
+        assert_eq!(
+            parse_mount(&json!(
+                "source=/home/me/.cache,target=/root/.cache/,type=bind,readonly"
+            )),
+            Some(Mount {
+                kind: "bind".to_string(),
+                source: Some("/home/me/.cache".to_string()),
+                target: "/root/.cache".to_string(),
+                readonly: true,
+            })
+        );
+        assert_eq!(
+            parse_mount(
+                &json!({"source": "node_modules", "target": "/app/node_modules", "type": "volume"})
+            ),
+            Some(Mount {
+                kind: "volume".to_string(),
+                source: Some("node_modules".to_string()),
+                target: "/app/node_modules".to_string(),
+                readonly: false,
+            })
+        );
+        assert_eq!(parse_mount(&json!("type=tmpfs")), None);
+    }
+
+    #[test]
+    fn sources_in_the_workspace_are_relative() {
+        let root = std::env::temp_dir().join("dcw-test-mount-source");
+        let data = root.join("data");
+        fs::create_dir_all(&data).unwrap();
+        assert_eq!(
+            mount_source(&root, &data).unwrap(),
+            "${localWorkspaceFolder}/data"
+        );
+        let outside = fs::canonicalize(std::env::temp_dir()).unwrap();
+        assert_eq!(
+            mount_source(&root, &outside).unwrap(),
+            outside.display().to_string()
+        );
+        assert!(mount_source(&root, &root.join("missing")).is_err());
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,259 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+use crate::commands::daemon;
+use crate::commands::port::{self, ForwardRow};
+use crate::commands::watch::{self, Listener};
+use crate::customizations::{self, WatchCustomizations};
+use crate::docker::{self, Protocol};
+use crate::forward_ports::{self, PortsAttributes};
+use crate::output::{self, OutputFormat};
+
+/// One container port in `port overview`.
+#[derive(Debug, PartialEq, Serialize)]
+struct OverviewRow {
+    port: u16,
+    protocol: Protocol,
+    /// Listed in `forwardPorts`
+    configured: bool,
+    /// `yes`, `loopback` (unreachable through the container IP) or `no`
+    listening: &'static str,
+    /// Host address of the forward, e.g. `127.0.0.1:3000`
+    forward: Option<String>,
+    /// `forwarded`, `ignored` or `missing`
+    status: &'static str,
+    /// Why the port is ignored or missing, or what is wrong with its forward
+    note: Option<String>,
+    label: Option<String>,
+}
+
+/// What decides whether a port gets forwarded.
+struct Sources<'a> {
+    configured: &'a [u16],
+    /// Listening sockets, with whether each is loopback-only
+    listening: &'a HashMap<Listener, bool>,
+    forwards: &'a [ForwardRow],
+    attributes: &'a PortsAttributes,
+    watch: &'a WatchCustomizations,
+    watcher_running: bool,
+}
+
+pub fn run(ws_id: &str, workspace_folder: &str, format: OutputFormat) -> Result<()> {
+    let root = Path::new(workspace_folder);
+    let container_id =
+        docker::find_devcontainer(workspace_folder)?.context("no running devcontainer found")?;
+    let settings = customizations::load_or_default(root);
+    let rows = overview(&Sources {
+        configured: &forward_ports::load_forward_ports(root)?,
+        listening: &watch::scan_listeners(&container_id)?,
+        forwards: &port::collect_forwards(ws_id)?,
+        attributes: &forward_ports::load_ports_attributes(root)?,
+        watch: &settings.watch,
+        watcher_running: daemon::watcher(ws_id).is_some(),
+    });
+
+    if format == OutputFormat::Json {
+        return output::print_json(&rows);
+    }
+    if rows.is_empty() {
+        println!("No configured, listening or forwarded ports.");
+        return Ok(());
+    }
+    println!(
+        "{:<10} {:<10} {:<9} {:<21} {:<9} NOTE",
+        "PORT", "CONFIGURED", "LISTENING", "FORWARD", "STATUS"
+    );
+    for row in &rows {
+        let mut note = row.note.clone().unwrap_or_default();
+        if let Some(label) = &row.label {
+            note = if note.is_empty() {
+                label.clone()
+            } else {
+                format!("{label}: {note}")
+            };
+        }
+        println!(
+            "{:<10} {:<10} {:<9} {:<21} {:<9} {}",
+            format!("{}/{}", row.port, row.protocol.as_str()),
+            if row.configured { "yes" } else { "no" },
+            row.listening,
+            row.forward.as_deref().unwrap_or("-"),
+            row.status,
+            note
+        );
+    }
+    Ok(())
+}
+
+/// Merge configured, listening and forwarded ports into one row each.
+fn overview(sources: &Sources) -> Vec<OverviewRow> {
+    // Forwards from the devcontainer; Unix sockets and compose services
+    // have no container port to compare
+    let forwards: HashMap<Listener, &ForwardRow> = sources
+        .forwards
+        .iter()
+        .filter(|f| f.service.is_none())
+        .filter_map(|f| Some(((f.container_port?, f.protocol), f)))
+        .collect();
+    let ports: BTreeSet<Listener> = sources
+        .configured
+        .iter()
+        .map(|&p| (p, Protocol::Tcp))
+        .chain(sources.listening.keys().copied())
+        .chain(forwards.keys().copied())
+        .collect();
+
+    ports
+        .into_iter()
+        .map(|(port, protocol)| {
+            let configured = protocol == Protocol::Tcp && sources.configured.contains(&port);
+            let loopback_only = sources.listening.get(&(port, protocol)).copied();
+            let forward = forwards.get(&(port, protocol));
+            let (status, note) = classify(
+                sources,
+                port,
+                protocol,
+                configured,
+                loopback_only,
+                forward.is_some(),
+            );
+            OverviewRow {
+                port,
+                protocol,
+                configured,
+                listening: match loopback_only {
+                    Some(false) => "yes",
+                    Some(true) => "loopback",
+                    None => "no",
+                },
+                forward: forward.map(|f| format!("{}:{}", f.bind, f.host_port)),
+                status,
+                note,
+                label: forward
+                    .and_then(|f| f.label.clone())
+                    .or_else(|| sources.attributes.label(port).map(str::to_string)),
+            }
+        })
+        .collect()
+}
+
+/// Status of a port and the reason for it.
+fn classify(
+    sources: &Sources,
+    port: u16,
+    protocol: Protocol,
+    configured: bool,
+    loopback_only: Option<bool>,
+    forwarded: bool,
+) -> (&'static str, Option<String>) {
+    let note = |text: &str| Some(text.to_string());
+    if forwarded {
+        return match loopback_only {
+            None => ("forwarded", note("not listening")),
+            Some(true) => (
+                "forwarded",
+                note("only listening on loopback, unreachable through the forward"),
+            ),
+            Some(false) => ("forwarded", None),
+        };
+    }
+    if sources.attributes.is_ignored(port) {
+        return ("ignored", note("onAutoForward: ignore"));
+    }
+    let Some(loopback_only) = loopback_only else {
+        return ("missing", note("not listening"));
+    };
+    if configured {
+        return ("missing", note("configured but not forwarded"));
+    }
+    if loopback_only && !sources.watch.include_loopback {
+        return (
+            "ignored",
+            note("only listening on loopback (see --include-loopback)"),
+        );
+    }
+    let min_port = sources.watch.min_port.unwrap_or(1024);
+    if port < min_port {
+        return (
+            "ignored",
+            Some(format!("below the watcher's minimum port {min_port}")),
+        );
+    }
+    if sources.watch.exclude.contains(&port) {
+        return ("ignored", note("excluded from watching"));
+    }
+    if protocol == Protocol::Udp && !sources.watch.udp {
+        return ("ignored", note("UDP (see port watch --udp)"));
+    }
+    if !sources.watcher_running {
+        return ("missing", note("watcher not running"));
+    }
+    ("missing", note("not forwarded yet"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::ForwardBackend;
+
+    fn forward(container_port: u16, host_port: u16) -> ForwardRow {
+        ForwardRow {
+            name: String::new(),
+            backend: ForwardBackend::Native,
+            host_port,
+            container_port: Some(container_port),
+            protocol: Protocol::Tcp,
+            bind: "127.0.0.1".to_string(),
+            unix_socket: None,
+            source: None,
+            label: None,
+            service: None,
+            hostname: None,
+            stats: None,
+        }
+    }
+
+    #[test]
+    fn overview_explains_each_port() {
+        let listening = HashMap::from([
+            ((3000, Protocol::Tcp), false),
+            ((5432, Protocol::Tcp), true),
+            ((9229, Protocol::Tcp), false),
+            ((80, Protocol::Tcp), false),
+            ((5353, Protocol::Udp), false),
+        ]);
+        let rows = overview(&Sources {
+            configured: &[3000, 8080],
+            listening: &listening,
+            forwards: &[forward(3000, 3001), forward(4000, 4000)],
+            attributes: &PortsAttributes::default(),
+            watch: &WatchCustomizations::default(),
+            watcher_running: true,
+        });
+        let summary: Vec<(u16, &str, Option<&str>)> = rows
+            .iter()
+            .map(|r| (r.port, r.status, r.note.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (80, "ignored", Some("below the watcher's minimum port 1024")),
+                (3000, "forwarded", None),
+                (4000, "forwarded", Some("not listening")),
+                (5353, "ignored", Some("UDP (see port watch --udp)")),
+                (
+                    5432,
+                    "ignored",
+                    Some("only listening on loopback (see --include-loopback)")
+                ),
+                (8080, "missing", Some("not listening")),
+                (9229, "missing", Some("not forwarded yet")),
+            ]
+        );
+        assert_eq!(rows[1].forward.as_deref(), Some("127.0.0.1:3001"));
+        assert!(rows[1].configured);
+        assert_eq!(rows[4].listening, "loopback");
+    }
+}
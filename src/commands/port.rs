@@ -1,8 +1,22 @@
 use anyhow::{bail, Context, Result};
-use std::collections::HashSet;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::time::Duration;
+use tracing::{debug, info, warn};
 
-use crate::commands::watch;
+use crate::browser;
+use crate::commands::{overview, watch};
+use crate::customizations;
 use crate::docker;
+use crate::forward_ports;
+use crate::forward_state::{self, SavedForward};
+use crate::hostnames;
+use crate::ipc;
+use crate::output::{self, OutputFormat};
+use crate::proxy;
+use crate::settings::{ForwardBackend, Settings};
+use crate::traffic::{self, TrafficStats};
 use crate::workspace;
 
 #[derive(clap::Subcommand)]
@@ -11,11 +25,58 @@ pub enum PortAction {
     Add {
         /// Host port
         host_port: u16,
-        /// Container port
-        container_port: u16,
+        /// Container port (omit if using --unix-socket)
+        #[arg(required_unless_present = "unix_socket")]
+        container_port: Option<u16>,
         /// Run in background (detached)
         #[arg(short, long)]
         detach: bool,
+        /// Forward UDP instead of TCP
+        #[arg(long, conflicts_with = "unix_socket")]
+        udp: bool,
+        /// Forward to a unix socket inside the container (requires socat in the container)
+        #[arg(long, value_name = "PATH", conflicts_with = "container_port")]
+        unix_socket: Option<String>,
+        /// Host address to bind (default: workspace setting or 127.0.0.1)
+        #[arg(long, value_name = "ADDR")]
+        bind: Option<String>,
+        /// Use the next free host port if the requested one is taken
+        #[arg(long, conflicts_with = "unix_socket")]
+        fallback: bool,
+        /// Forwarding backend (default: forward.backend in config.toml)
+        #[arg(long, value_enum)]
+        backend: Option<ForwardBackend>,
+        /// Label shown in `port list` (default: from portsAttributes)
+        #[arg(long)]
+        label: Option<String>,
+        /// Forward from another Docker Compose service instead of the devcontainer
+        #[arg(long, conflicts_with = "unix_socket")]
+        service: Option<String>,
+        /// Log HTTP requests (method, path, status, duration) to the runtime
+        /// directory; uses the native backend
+        #[arg(long, conflicts_with_all = ["unix_socket", "udp", "service"])]
+        log_http: bool,
+        /// Accept HTTPS on the host port and forward plain HTTP to the
+        /// container, with a certificate from mkcert or a self-signed one;
+        /// uses the native backend
+        #[arg(long, conflicts_with_all = ["unix_socket", "udp", "service"])]
+        tls: bool,
+        /// Open the forward in the browser, with the scheme from
+        /// portsAttributes (or https with --tls)
+        #[arg(long, requires = "detach", conflicts_with_all = ["unix_socket", "udp"])]
+        open: bool,
+        /// Limit each direction to this rate, e.g. `1mbit`, `512kbit` or
+        /// `100kb`; uses the native backend
+        #[arg(long, value_name = "RATE", value_parser = traffic::parse_rate, conflicts_with_all = ["unix_socket", "service"])]
+        rate_limit: Option<u64>,
+        /// Serve at most this many connections (UDP: clients) at once and
+        /// turn away the rest; uses the native backend
+        #[arg(long, value_name = "N", conflicts_with_all = ["unix_socket", "service"])]
+        max_connections: Option<u32>,
+        /// Remove the forward once it has carried no traffic for this long,
+        /// e.g. `30m`; uses the native backend
+        #[arg(long, value_name = "DURATION", value_parser = parse_idle_timeout, requires = "detach", conflicts_with_all = ["unix_socket", "service"])]
+        idle_timeout: Option<u64>,
     },
     /// Remove a port forward
     #[command(alias = "rm")]
@@ -25,25 +86,95 @@ pub enum PortAction {
         /// Remove all port forwards
         #[arg(long)]
         all: bool,
+        /// Remove the UDP forward instead of the TCP one
+        #[arg(long)]
+        udp: bool,
+        /// Remove the forward from this Docker Compose service
+        #[arg(long, conflicts_with_all = ["all", "unix"])]
+        service: Option<String>,
+        /// Remove the unix socket forward listening on this host port
+        #[arg(long, conflicts_with = "udp")]
+        unix: bool,
     },
     /// List active port forwards
     #[command(alias = "ls")]
-    List,
+    List {
+        /// Show bytes transferred and open connections per forward
+        #[arg(long)]
+        stats: bool,
+        /// Reset the traffic counters after showing them
+        #[arg(long, requires = "stats")]
+        reset: bool,
+    },
+    /// Recreate forwards saved by `port add` that are not active
+    Restore,
+    /// Show configured, listening and forwarded ports side by side, and why
+    /// a port isn't forwarded
+    Overview,
     /// Watch for new listening ports and auto-forward them
     Watch {
-        /// Polling interval in seconds
-        #[arg(short, long, default_value = "2")]
-        interval: u64,
+        /// Seconds between checks of the container, and between scans with --poll
+        /// (default: customizations.dcw.watch.interval, then watch.interval in config.toml, else 2)
+        #[arg(short, long)]
+        interval: Option<u64>,
         /// Minimum port number to forward
-        #[arg(long, default_value = "1024")]
-        min_port: u16,
+        /// (default: customizations.dcw.watch.minPort, then watch.min_port in config.toml, else 1024)
+        #[arg(long)]
+        min_port: Option<u16>,
         /// Ports to exclude from auto-forwarding
         #[arg(short, long)]
         exclude: Vec<u16>,
+        /// Host address to bind (default: workspace setting or 127.0.0.1)
+        #[arg(long, value_name = "ADDR")]
+        bind: Option<String>,
+        /// Use the next free host port if a detected port is taken on the host
+        #[arg(long)]
+        fallback: bool,
+        /// Forwarding backend (default: forward.backend in config.toml)
+        #[arg(long, value_enum)]
+        backend: Option<ForwardBackend>,
+        /// Keep a forward this long after its port stops listening, e.g. `10s`,
+        /// so servers restarting on file changes keep their forward
+        /// (default: customizations.dcw.watch.removeDelay, then
+        /// watch.remove_delay in config.toml, else 0)
+        #[arg(long, value_name = "DURATION", value_parser = watch::parse_delay)]
+        remove_delay: Option<Duration>,
+        /// Remove forwards that carry no traffic for this long, e.g. `30m`;
+        /// a port is forwarded again once it closes and reopens. Uses the
+        /// native backend
+        #[arg(long, value_name = "DURATION", value_parser = parse_idle_timeout)]
+        idle_timeout: Option<u64>,
+        /// Scan with a `docker exec` per interval instead of streaming changes
+        #[arg(long)]
+        poll: bool,
+        /// Also forward UDP sockets bound inside the container
+        #[arg(long)]
+        udp: bool,
+        /// Also forward ports only listening on a loopback address inside the
+        /// container, which can't be reached through its IP
+        /// (default: customizations.dcw.watch.includeLoopback)
+        #[arg(long)]
+        include_loopback: bool,
+        /// Show a desktop notification when a forward is added or removed
+        /// (default: watch.notify in config.toml)
+        #[arg(long)]
+        notify: bool,
+        /// Render a live-updating table instead of log lines
+        #[arg(long)]
+        live: bool,
+        /// Show whether the background watcher is running and what it forwards
+        #[arg(long, conflicts_with = "stop")]
+        status: bool,
+        /// Stop the background watcher and remove its forwards
+        #[arg(long)]
+        stop: bool,
     },
+    /// Serve native port forwards (started automatically)
+    #[command(hide = true)]
+    Proxy,
 }
 
-pub fn run(action: &PortAction) -> Result<()> {
+pub fn run(action: &PortAction, format: OutputFormat) -> Result<()> {
     let ws_id = workspace::workspace_id()?;
     let workspace_folder = workspace::workspace_folder()?;
 
@@ -52,60 +183,689 @@ pub fn run(action: &PortAction) -> Result<()> {
             host_port,
             container_port,
             detach,
+            udp,
+            unix_socket,
+            bind,
+            fallback,
+            backend,
+            label,
+            service,
+            log_http,
+            tls,
+            open,
+            rate_limit,
+            max_connections,
+            idle_timeout,
         } => {
-            let container_id = docker::find_devcontainer(&workspace_folder)?
+            let mut container_id = docker::find_devcontainer(&workspace_folder)?
                 .context("no running devcontainer found")?;
+            if let Some(service) = service {
+                container_id = docker::find_compose_service(&container_id, service)?;
+            }
             let network = docker::get_container_network(&container_id)?;
+            let settings = customizations::load_or_default(Path::new(&workspace_folder));
+            let bind = bind.clone().or(settings.bind_address);
 
-            println!("Forwarding port {host_port} -> {container_port}...");
-            docker::start_port_forward(
-                &ws_id,
-                &container_id,
-                *host_port,
-                *container_port,
-                &network,
-                *detach,
-                None,
-            )?;
-            println!("Port forward active.");
+            if let Some(path) = unix_socket {
+                info!("Forwarding port {host_port} -> unix:{path}...");
+                let spec = docker::ForwardSpec {
+                    detach: *detach,
+                    bind_address: bind.as_deref(),
+                    ..docker::ForwardSpec::new(*host_port, 0)
+                };
+                docker::start_unix_socket_forward(&ws_id, &container_id, &network, &spec, path)?;
+                ipc::notify_changed(&workspace_folder);
+                if *detach {
+                    save_forward(SavedForward {
+                        host_port: *host_port,
+                        container_port: 0,
+                        protocol: docker::Protocol::Tcp,
+                        backend: ForwardBackend::Sidecar,
+                        unix_socket: Some(path.clone()),
+                        bind: bind.clone(),
+                        label: None,
+                        service: None,
+                        source: None,
+                        log_http: false,
+                        tls: false,
+                        ipv6: false,
+                        rate_limit: None,
+                        max_connections: None,
+                        idle_timeout: None,
+                    });
+                }
+                info!("Port forward active.");
+                return Ok(());
+            }
+
+            let container_port = container_port.context("container port is required")?;
+            let attributes = forward_ports::load_ports_attributes(Path::new(&workspace_folder))?;
+            let protocol = protocol_flag(*udp);
+            let spec = docker::ForwardSpec {
+                protocol,
+                detach: *detach,
+                bind_address: bind.as_deref(),
+                fallback: (*fallback || settings.host_port_fallback)
+                    && attributes.allows_fallback(container_port),
+                label: label.as_deref().or(attributes.label(container_port)),
+                service: service.as_deref(),
+                log_http: *log_http,
+                tls: *tls,
+                ipv6: watch::ipv6_only_listeners(&container_id, &network, *udp)
+                    .contains(&(container_port, protocol)),
+                rate_limit: *rate_limit,
+                max_connections: *max_connections,
+                idle_timeout: *idle_timeout,
+                ..docker::ForwardSpec::new(*host_port, container_port)
+            };
+
+            let target = match service {
+                Some(service) => format!("{service}:{container_port}"),
+                None => container_port.to_string(),
+            };
+            info!(
+                "Forwarding port {host_port} -> {target}/{}...",
+                spec.protocol.as_str()
+            );
+            // Only the native proxy sees the traffic
+            let native_flag = [
+                (*log_http, "--log-http"),
+                (*tls, "--tls"),
+                (rate_limit.is_some(), "--rate-limit"),
+                (max_connections.is_some(), "--max-connections"),
+                (idle_timeout.is_some(), "--idle-timeout"),
+            ]
+            .into_iter()
+            .find_map(|(set, flag)| set.then_some(flag));
+            let backend = match (native_flag, backend) {
+                (Some(flag), Some(b)) if *b != ForwardBackend::Native => {
+                    bail!("{flag} needs --backend native")
+                }
+                (Some(_), _) => ForwardBackend::Native,
+                (None, _) => resolve_backend(*backend),
+            };
+            let host_port = proxy::start_forward(&ws_id, &container_id, &network, &spec, backend)?;
+            ipc::notify_changed(&workspace_folder);
+            if *detach {
+                save_forward(SavedForward {
+                    host_port,
+                    container_port,
+                    protocol: spec.protocol,
+                    backend,
+                    unix_socket: None,
+                    bind: bind.clone(),
+                    label: spec.label.map(str::to_string),
+                    service: service.clone(),
+                    source: None,
+                    log_http: *log_http,
+                    tls: *tls,
+                    ipv6: spec.ipv6,
+                    rate_limit: *rate_limit,
+                    max_connections: *max_connections,
+                    idle_timeout: *idle_timeout,
+                });
+            }
+            info!("Port forward active.");
+            if *open {
+                let scheme = match attributes.get(container_port) {
+                    _ if *tls => "https",
+                    Some(attrs) if service.is_none() => attrs.scheme(),
+                    _ => "http",
+                };
+                let bind = bind.as_deref().unwrap_or(docker::DEFAULT_BIND_ADDRESS);
+                browser::open_url(&browser::forward_url(scheme, bind, host_port))?;
+            }
         }
-        PortAction::Remove { port, all } => {
+        PortAction::Remove {
+            port,
+            all,
+            udp,
+            service,
+            unix,
+        } => {
             if *all {
-                println!("Removing all port forwards...");
+                info!("Removing all port forwards...");
+                proxy::stop()?;
                 docker::remove_all_port_forwards(&ws_id)?;
-                println!("All port forwards removed.");
+                forward_state::forget(|_| true)?;
+                ipc::notify_changed(&workspace_folder);
+                info!("All port forwards removed.");
             } else if let Some(p) = port {
-                println!("Removing port forward for {p}...");
-                docker::remove_port_forward(&ws_id, *p)?;
-                println!("Port forward removed.");
+                info!("Removing port forward for {p}...");
+                let protocol = protocol_flag(*udp);
+                forward_state::forget(|f| match &f.unix_socket {
+                    Some(_) => *unix && f.host_port == *p,
+                    None => {
+                        !*unix
+                            && f.container_port == *p
+                            && f.protocol == protocol
+                            && f.service == *service
+                    }
+                })?;
+                if let Some(service) = service {
+                    docker::remove_service_port_forward(&ws_id, service, *p, protocol)?;
+                } else if *unix {
+                    if let Some(container_id) = docker::find_devcontainer(&workspace_folder)? {
+                        let _ = docker::stop_unix_socket_bridge(&container_id, *p);
+                    }
+                    docker::remove_container(&docker::unix_sidecar_name(&ws_id, *p))?;
+                } else {
+                    proxy::remove_forward(&ws_id, *p, protocol)?;
+                }
+                ipc::notify_changed(&workspace_folder);
+                info!("Port forward removed.");
             } else {
                 bail!("specify a port or --all");
             }
         }
-        PortAction::List => {
-            let forwards = docker::list_port_forwards(&ws_id)?;
-            if forwards.is_empty() {
+        PortAction::List { stats, reset } => {
+            let mut rows = query_forwards(&ws_id, &workspace_folder)?;
+            attach_hostnames(&mut rows, Path::new(&workspace_folder));
+            if *stats {
+                attach_traffic(&mut rows, *reset)?;
+            }
+            if format == OutputFormat::Json {
+                output::print_json(&rows)?;
+            } else if rows.is_empty() {
                 println!("No active port forwards.");
             } else {
-                println!("{:<30} {:>6}   {:>6}", "SIDECAR", "HOST", "CONTAINER");
-                for fwd in &forwards {
-                    println!("{:<30} {:>6}   {:>6}", fwd.name, fwd.host_port, fwd.container_port);
+                print!(
+                    "{:<30} {:>6}   {:>9}   {:<5}   ",
+                    "NAME", "HOST", "CONTAINER", "PROTO"
+                );
+                if *stats {
+                    print!(
+                        "{:>10} {:>10} {:>10} {:>5}   ",
+                        "IN", "OUT", "TOTAL", "CONNS"
+                    );
                 }
+                println!("{:<26} LABEL", "HOSTNAME");
+                for row in &rows {
+                    let (target, proto) = match &row.unix_socket {
+                        Some(path) => (path.clone(), "unix"),
+                        None => {
+                            let port = row
+                                .container_port
+                                .map(|p| p.to_string())
+                                .unwrap_or_default();
+                            let target = match &row.service {
+                                Some(service) => format!("{service}:{port}"),
+                                None => port,
+                            };
+                            (target, row.protocol.as_str())
+                        }
+                    };
+                    let name = if row.backend == ForwardBackend::Native {
+                        "(native)"
+                    } else {
+                        row.name.as_str()
+                    };
+                    print!(
+                        "{:<30} {:>6}   {:>9}   {:<5}   ",
+                        name,
+                        host_display(&row.bind, &row.host_port.to_string()),
+                        target,
+                        proto,
+                    );
+                    if let Some(traffic) = &row.stats {
+                        print!(
+                            "{:>10} {:>10} {:>10} {:>5}   ",
+                            traffic::format_bytes(traffic.bytes_in),
+                            traffic::format_bytes(traffic.bytes_out),
+                            traffic::format_bytes(traffic.bytes_total),
+                            traffic
+                                .connections
+                                .map_or("-".to_string(), |n| n.to_string()),
+                        );
+                    }
+                    println!(
+                        "{:<26} {}",
+                        row.hostname.as_deref().unwrap_or("-"),
+                        row.label.as_deref().unwrap_or("")
+                    );
+                }
+                if *reset {
+                    info!("Traffic counters reset.");
+                }
+            }
+        }
+        PortAction::Restore => {
+            if restore_saved(&ws_id, &workspace_folder)? == 0 {
+                info!("No saved port forwards to restore.");
             }
         }
         PortAction::Watch {
             interval,
             min_port,
             exclude,
+            bind,
+            fallback,
+            backend,
+            remove_delay,
+            idle_timeout,
+            poll,
+            udp,
+            include_loopback,
+            notify,
+            live,
+            status,
+            stop,
         } => {
+            if *status {
+                return watch::print_status(&ws_id, format);
+            }
+            if *stop {
+                return watch::stop(&ws_id);
+            }
+            let settings = customizations::load_or_default(Path::new(&workspace_folder));
+            let backend = match (idle_timeout, backend) {
+                (Some(_), Some(b)) if *b != ForwardBackend::Native => {
+                    bail!("--idle-timeout needs --backend native")
+                }
+                (Some(_), _) => ForwardBackend::Native,
+                (None, _) => resolve_backend(*backend),
+            };
             let config = watch::WatchConfig {
-                interval: *interval,
-                min_port: *min_port,
-                exclude_ports: exclude.iter().copied().collect::<HashSet<u16>>(),
+                interval: interval.or(settings.watch.interval).unwrap_or(2),
+                min_port: min_port.or(settings.watch.min_port).unwrap_or(1024),
+                exclude_ports: exclude
+                    .iter()
+                    .chain(&settings.watch.exclude)
+                    .copied()
+                    .collect::<HashSet<u16>>(),
+                bind_address: bind.clone().or(settings.bind_address),
+                fallback: *fallback || settings.host_port_fallback,
+                backend,
+                remove_delay: remove_delay
+                    .or(settings.watch.remove_delay.map(Duration::from_secs))
+                    .unwrap_or_default(),
+                idle_timeout: *idle_timeout,
+                poll: *poll,
+                udp: *udp || settings.watch.udp,
+                include_loopback: *include_loopback || settings.watch.include_loopback,
+                notify: *notify || Settings::get().watch.notify,
+                live: *live,
+                attributes: forward_ports::load_ports_attributes(Path::new(&workspace_folder))?,
             };
             watch::run_watch(&config)?;
         }
+        PortAction::Overview => overview::run(&ws_id, &workspace_folder, format)?,
+        PortAction::Proxy => proxy::run_daemon()?,
+    }
+
+    Ok(())
+}
+
+/// Record a `port add` forward for `dcw up` and `dcw port restore`.
+fn save_forward(forward: SavedForward) {
+    if let Err(e) = forward_state::record(forward) {
+        warn!("failed to save port forward: {e}");
+    }
+}
+
+/// Recreate the saved forwards whose host port isn't forwarded already.
+/// Returns how many were restored.
+pub fn restore_saved(ws_id: &str, workspace_folder: &str) -> Result<usize> {
+    restore_forwards(ws_id, workspace_folder, forward_state::load()?)
+}
+
+/// Recreate `forwards` whose host port isn't forwarded already. Returns
+/// how many were restored.
+pub fn restore_forwards(
+    ws_id: &str,
+    workspace_folder: &str,
+    forwards: Vec<SavedForward>,
+) -> Result<usize> {
+    let active: HashSet<u16> = collect_forwards(ws_id)?
+        .iter()
+        .map(|f| f.host_port)
+        .collect();
+    let pending: Vec<SavedForward> = forwards
+        .into_iter()
+        .filter(|f| !active.contains(&f.host_port))
+        .collect();
+    if pending.is_empty() {
+        return Ok(0);
+    }
+
+    let container_id =
+        docker::find_devcontainer(workspace_folder)?.context("no running devcontainer found")?;
+    let network = docker::get_container_network(&container_id)?;
+    info!("Restoring port forwards...");
+    let mut restored = 0;
+    for fwd in &pending {
+        match restore_forward(ws_id, &container_id, &network, fwd) {
+            Ok(()) => restored += 1,
+            Err(e) => warn!(
+                "failed to restore forward {} -> {}: {e}",
+                fwd.host_port,
+                saved_target(fwd)
+            ),
+        }
+    }
+    if restored > 0 {
+        ipc::notify_changed(workspace_folder);
+    }
+    Ok(restored)
+}
+
+/// Recreate one saved forward for the devcontainer `container_id`.
+pub fn restore_forward(
+    ws_id: &str,
+    container_id: &str,
+    network: &str,
+    fwd: &SavedForward,
+) -> Result<()> {
+    let spec = docker::ForwardSpec {
+        protocol: fwd.protocol,
+        bind_address: fwd.bind.as_deref(),
+        label: fwd.label.as_deref(),
+        service: fwd.service.as_deref(),
+        source: fwd.source.as_deref(),
+        log_http: fwd.log_http,
+        tls: fwd.tls,
+        ipv6: fwd.ipv6,
+        rate_limit: fwd.rate_limit,
+        max_connections: fwd.max_connections,
+        idle_timeout: fwd.idle_timeout,
+        ..docker::ForwardSpec::new(fwd.host_port, fwd.container_port)
+    };
+    let host_port = match (&fwd.unix_socket, &fwd.service) {
+        (Some(path), _) => {
+            docker::start_unix_socket_forward(ws_id, container_id, network, &spec, path)?;
+            fwd.host_port
+        }
+        (None, Some(service)) => {
+            let id = docker::find_compose_service(container_id, service)?;
+            let network = docker::get_container_network(&id)?;
+            proxy::start_forward(ws_id, &id, &network, &spec, fwd.backend)?
+        }
+        (None, None) => proxy::start_forward(ws_id, container_id, network, &spec, fwd.backend)?,
+    };
+    info!("  Forwarded port {host_port} -> {}", saved_target(fwd));
+    Ok(())
+}
+
+/// What a saved forward points at, for messages.
+fn saved_target(fwd: &SavedForward) -> String {
+    match (&fwd.unix_socket, &fwd.service) {
+        (Some(path), _) => format!("unix:{path}"),
+        (None, Some(service)) => format!("{service}:{}", fwd.container_port),
+        (None, None) => fwd.container_port.to_string(),
+    }
+}
+
+/// Parse `--idle-timeout` into whole seconds.
+fn parse_idle_timeout(value: &str) -> Result<u64, String> {
+    match watch::parse_delay(value)?.as_secs() {
+        0 => Err("idle timeout must be at least 1s".to_string()),
+        secs => Ok(secs),
+    }
+}
+
+/// The forward backend to use: the flag if given, else the configured one.
+fn resolve_backend(flag: Option<ForwardBackend>) -> ForwardBackend {
+    flag.unwrap_or(Settings::get().forward.backend)
+}
+
+fn protocol_flag(udp: bool) -> docker::Protocol {
+    if udp {
+        docker::Protocol::Udp
+    } else {
+        docker::Protocol::Tcp
     }
+}
+
+/// A port forward of the current workspace, whichever backend serves it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ForwardRow {
+    /// Sidecar container name; empty for native forwards.
+    pub name: String,
+    /// `native`, `sidecar` or `shared`
+    pub backend: ForwardBackend,
+    pub host_port: u16,
+    /// Container port; absent for Unix socket forwards.
+    pub container_port: Option<u16>,
+    pub protocol: docker::Protocol,
+    pub bind: String,
+    pub unix_socket: Option<String>,
+    /// Who created the forward (e.g. `watch`); absent for manual forwards.
+    pub source: Option<String>,
+    pub label: Option<String>,
+    /// Compose service forwarded from; absent for the devcontainer.
+    pub service: Option<String>,
+    /// e.g. `myapp.localhost`; only in `port list`, for TCP forwards
+    /// listening on loopback.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    /// Traffic since the last reset; only with `port list --stats`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stats: Option<TrafficStats>,
+}
 
+impl ForwardRow {
+    /// Identifies the forward in the traffic baseline.
+    fn traffic_key(&self) -> String {
+        if self.backend == ForwardBackend::Native {
+            format!(
+                "native:{}/{}",
+                self.container_port.unwrap_or(0),
+                self.protocol.as_str()
+            )
+        } else {
+            format!("{}:{}", self.name, self.host_port)
+        }
+    }
+}
+
+/// Pair each forward with the hostname it can be reached at.
+fn attach_hostnames(rows: &mut [ForwardRow], root: &Path) {
+    let settings = customizations::load_or_default(root);
+    let Some(workspace) = hostnames::workspace_hostname(root, &settings) else {
+        return;
+    };
+    for row in rows {
+        row.hostname = hostnames::forward_hostname(
+            &workspace,
+            row.service.as_deref(),
+            row.protocol,
+            &row.bind,
+            row.unix_socket.is_some(),
+        );
+    }
+}
+
+/// Fill in the traffic of each forward since the last reset, reading each
+/// sidecar once. With `reset`, record the current counters as the new
+/// baseline.
+fn attach_traffic(rows: &mut [ForwardRow], reset: bool) -> Result<()> {
+    let native = proxy::traffic()?;
+    let mut sidecars: HashMap<String, Option<String>> = HashMap::new();
+    let baseline_file = workspace::traffic_baseline_file()?;
+    let baseline = traffic::load_baseline(&baseline_file);
+    let mut counters = HashMap::new();
+
+    for row in rows.iter_mut() {
+        let current = if row.backend == ForwardBackend::Native {
+            row.container_port
+                .and_then(|port| native.get(&(port, row.protocol)).copied())
+        } else {
+            let output = sidecars.entry(row.name.clone()).or_insert_with(|| {
+                traffic::read_sidecar(&row.name)
+                    .map_err(|e| warn!("{e:#}"))
+                    .ok()
+            });
+            output.as_deref().map(|output| {
+                traffic::parse_sidecar(
+                    output,
+                    row.host_port,
+                    row.protocol,
+                    row.backend == ForwardBackend::Shared,
+                )
+            })
+        };
+        let Some(current) = current else {
+            continue;
+        };
+        let key = row.traffic_key();
+        row.stats = Some(match baseline.get(&key) {
+            Some(base) => current.since(base),
+            None => current,
+        });
+        counters.insert(key, current);
+    }
+
+    if reset {
+        traffic::save_baseline(&baseline_file, &counters)?;
+    }
     Ok(())
 }
+
+/// Collect native and sidecar forwards of a workspace, native first.
+pub fn collect_forwards(ws_id: &str) -> Result<Vec<ForwardRow>> {
+    let mut rows = native_rows(&workspace::runtime_dir_for(ws_id));
+    rows.extend(sidecar_rows(ws_id)?);
+    Ok(rows)
+}
+
+/// Forwards of the workspace in `workspace_folder`, from the daemon if one
+/// is running, since it answers without asking Docker each time.
+pub fn query_forwards(ws_id: &str, workspace_folder: &str) -> Result<Vec<ForwardRow>> {
+    match ipc::forwards(workspace_folder) {
+        Ok(Some(rows)) => Ok(rows),
+        Ok(None) => collect_forwards(ws_id),
+        Err(e) => {
+            debug!("failed to list forwards from dcw daemon: {e:#}");
+            collect_forwards(ws_id)
+        }
+    }
+}
+
+/// Native forwards recorded in a workspace's runtime directory.
+pub fn native_rows(runtime_dir: &Path) -> Vec<ForwardRow> {
+    proxy::forwards_in(runtime_dir)
+        .into_iter()
+        .map(|fwd| ForwardRow {
+            name: String::new(),
+            backend: ForwardBackend::Native,
+            host_port: fwd.host_port,
+            container_port: Some(fwd.container_port),
+            protocol: fwd.protocol,
+            bind: fwd.bind,
+            unix_socket: None,
+            source: fwd.source,
+            label: fwd.label,
+            service: None,
+            hostname: None,
+            stats: None,
+        })
+        .collect()
+}
+
+/// Sidecar forwards of a workspace, listed from Docker.
+pub fn sidecar_rows(ws_id: &str) -> Result<Vec<ForwardRow>> {
+    Ok(docker::list_port_forwards(ws_id)?
+        .into_iter()
+        .map(sidecar_row)
+        .collect())
+}
+
+fn sidecar_row(fwd: docker::PortForwardInfo) -> ForwardRow {
+    let unix_socket = (!fwd.unix_socket.is_empty()).then_some(fwd.unix_socket);
+    ForwardRow {
+        backend: if fwd.shared {
+            ForwardBackend::Shared
+        } else {
+            ForwardBackend::Sidecar
+        },
+        host_port: fwd.host_port.parse().unwrap_or(0),
+        container_port: if unix_socket.is_some() {
+            None
+        } else {
+            fwd.container_port.parse().ok()
+        },
+        protocol: fwd.protocol,
+        bind: if fwd.bind.is_empty() {
+            docker::DEFAULT_BIND_ADDRESS.to_string()
+        } else {
+            fwd.bind
+        },
+        unix_socket,
+        source: (!fwd.source.is_empty()).then_some(fwd.source),
+        label: (!fwd.label.is_empty()).then_some(fwd.label),
+        service: (!fwd.service.is_empty()).then_some(fwd.service),
+        name: fwd.name,
+        hostname: None,
+        stats: None,
+    }
+}
+
+/// Format the host side of a forward, showing the bind address only when it
+/// differs from the default.
+fn host_display(bind: &str, host_port: &str) -> String {
+    if bind.is_empty() || bind == docker::DEFAULT_BIND_ADDRESS {
+        host_port.to_string()
+    } else {
+        format!("{bind}:{host_port}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn host_display_hides_default_bind() {
+        assert_eq!(host_display("", "3000"), "3000");
+        assert_eq!(host_display("127.0.0.1", "3000"), "3000");
+        assert_eq!(host_display("0.0.0.0", "3000"), "0.0.0.0:3000");
+    }
+
+    fn sidecar_info() -> docker::PortForwardInfo {
+        docker::PortForwardInfo {
+            name: "dcw-pf-abc-3000".to_string(),
+            host_port: "3000".to_string(),
+            container_port: "3000".to_string(),
+            source: String::new(),
+            protocol: docker::Protocol::Tcp,
+            unix_socket: String::new(),
+            bind: String::new(),
+            shared: false,
+            label: "Web".to_string(),
+            target: "172.17.0.2".to_string(),
+            service: String::new(),
+        }
+    }
+
+    #[test]
+    fn sidecar_row_fills_defaults() {
+        let row = sidecar_row(sidecar_info());
+        assert_eq!(row.backend, ForwardBackend::Sidecar);
+        assert_eq!(row.host_port, 3000);
+        assert_eq!(row.container_port, Some(3000));
+        assert_eq!(row.bind, docker::DEFAULT_BIND_ADDRESS);
+        assert_eq!(row.source, None);
+        assert_eq!(row.label.as_deref(), Some("Web"));
+        assert_eq!(row.service, None);
+    }
+
+    #[test]
+    fn sidecar_row_unix_socket_has_no_container_port() {
+        let mut info = sidecar_info();
+        info.unix_socket = "/var/run/docker.sock".to_string();
+        info.shared = true;
+        let row = sidecar_row(info);
+        assert_eq!(row.backend, ForwardBackend::Shared);
+        assert_eq!(row.container_port, None);
+        assert_eq!(row.unix_socket.as_deref(), Some("/var/run/docker.sock"));
+    }
+
+    #[test]
+    fn forward_row_serializes_to_json() {
+        let json = serde_json::to_value(sidecar_row(sidecar_info())).unwrap();
+        assert_eq!(json["backend"], "sidecar");
+        assert_eq!(json["host_port"], 3000);
+        assert_eq!(json["protocol"], "tcp");
+        assert_eq!(json["unix_socket"], serde_json::Value::Null);
+    }
+}
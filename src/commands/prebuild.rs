@@ -0,0 +1,45 @@
+use anyhow::{bail, Result};
+use std::path::Path;
+use tracing::info;
+
+use crate::commands::build::{self, BuildArgs};
+use crate::customizations;
+use crate::workspace;
+
+#[derive(clap::Args)]
+pub struct PrebuildArgs {
+    /// Image to tag and push (default: customizations.dcw.prebuiltImage)
+    #[arg(long, value_name = "IMAGE")]
+    pub push: Option<String>,
+
+    /// Build the image without using the Docker build cache
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// Extra arguments passed to `devcontainer build`
+    #[arg(last = true)]
+    pub extra: Vec<String>,
+}
+
+/// Build and push the image `dcw up` starts from instead of building the
+/// Dockerfile, once `customizations.dcw.prebuiltImage` names it.
+pub fn run(args: &PrebuildArgs) -> Result<()> {
+    let workspace_folder = workspace::workspace_folder()?;
+    let configured = customizations::load_or_default(Path::new(&workspace_folder)).prebuilt_image;
+    let Some(image) = args.push.clone().or(configured.clone()) else {
+        bail!("no image to push: pass --push IMAGE or set customizations.dcw.prebuiltImage");
+    };
+
+    build::run(&BuildArgs {
+        no_cache: args.no_cache,
+        push: Some(image.clone()),
+        extra: args.extra.clone(),
+    })?;
+
+    if configured.as_deref() != Some(image.as_str()) {
+        info!(
+            "Set customizations.dcw.prebuiltImage to {image:?} in devcontainer.json so `dcw up` starts from it."
+        );
+    }
+    Ok(())
+}
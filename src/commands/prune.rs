@@ -0,0 +1,146 @@
+use anyhow::Result;
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use tracing::{info, warn};
+
+use crate::commands::daemon;
+use crate::docker;
+use crate::lock;
+use crate::workspace;
+
+pub fn run() -> Result<()> {
+    let known = known_workspace_ids()?;
+
+    let orphans = find_orphan_sidecars(&known)?;
+    let stale_dirs = find_stale_runtime_dirs(&known);
+
+    if orphans.is_empty() && stale_dirs.is_empty() {
+        info!("Nothing to prune.");
+        return Ok(());
+    }
+
+    let dry_run = docker::dry_run();
+    let verb = if dry_run { "Would remove" } else { "Removing" };
+
+    for sidecar in &orphans {
+        info!("{verb} orphaned sidecar {sidecar}");
+        if !dry_run {
+            if let Err(e) = docker::remove_container(sidecar) {
+                warn!("{e}");
+            }
+        }
+    }
+
+    for dir in &stale_dirs {
+        let dir = &dir.path;
+        info!("{verb} stale runtime directory {}", dir.display());
+        if !dry_run {
+            if let Err(e) = fs::remove_dir_all(dir) {
+                warn!("failed to remove {}: {e}", dir.display());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Workspace IDs that still have a devcontainer (running or stopped).
+pub fn known_workspace_ids() -> Result<HashSet<String>> {
+    Ok(docker::list_devcontainers()?
+        .into_iter()
+        .filter_map(|c| workspace::workspace_id_for(&c.folder).ok())
+        .collect())
+}
+
+/// Find port-forward sidecars whose target devcontainer no longer exists.
+///
+/// Sidecars carrying a `dcw.container` label are checked against that
+/// container; older sidecars without it are orphaned when their workspace
+/// has no devcontainer at all.
+pub fn find_orphan_sidecars(known_workspaces: &HashSet<String>) -> Result<Vec<String>> {
+    Ok(docker::list_all_sidecar_targets()?
+        .into_iter()
+        .filter(|t| {
+            is_orphan(t, known_workspaces, |id| {
+                docker::container_exists(id).unwrap_or(true)
+            })
+        })
+        .map(|t| t.name)
+        .collect())
+}
+
+fn is_orphan(
+    target: &docker::SidecarTarget,
+    known_workspaces: &HashSet<String>,
+    container_exists: impl Fn(&str) -> bool,
+) -> bool {
+    if target.container.is_empty() {
+        !known_workspaces.contains(&target.workspace)
+    } else {
+        !container_exists(&target.container)
+    }
+}
+
+/// A runtime directory to remove, with its workspace's lock held so no dcw
+/// command starts using it meanwhile.
+pub struct StaleDir {
+    pub path: PathBuf,
+    _lock: Option<lock::WorkspaceLock>,
+}
+
+/// Find workspace runtime directories with no devcontainer and no watcher
+/// run by `dcw daemon`. Directories whose lock another dcw process holds
+/// (e.g. a `dcw up` that hasn't created its container yet) are skipped.
+pub fn find_stale_runtime_dirs(known_workspaces: &HashSet<String>) -> Vec<StaleDir> {
+    let Ok(entries) = fs::read_dir(workspace::shared_runtime_dir()) else {
+        return Vec::new();
+    };
+    let watched: HashSet<String> = daemon::watchers().into_iter().map(|w| w.ws_id).collect();
+
+    entries
+        .flatten()
+        .filter(|e| e.path().is_dir())
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .filter(|ws_id| !known_workspaces.contains(ws_id) && !watched.contains(ws_id))
+        .filter_map(|ws_id| {
+            // A dry run removes nothing, so it needn't hold (or write) locks
+            let lock = if docker::dry_run() {
+                None
+            } else {
+                Some(lock::try_acquire(&ws_id, "prune").ok().flatten()?)
+            };
+            Some(StaleDir {
+                path: workspace::runtime_dir_for(&ws_id),
+                _lock: lock,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(workspace: &str, container: &str) -> docker::SidecarTarget {
+        docker::SidecarTarget {
+            name: "pf-dev-x-c3000".to_string(),
+            workspace: workspace.to_string(),
+            container: container.to_string(),
+        }
+    }
+
+    #[test]
+    fn labeled_sidecar_orphaned_when_container_gone() {
+        let known = HashSet::from(["dev-x".to_string()]);
+        assert!(is_orphan(&target("dev-x", "abc"), &known, |_| false));
+        assert!(!is_orphan(&target("dev-x", "abc"), &known, |_| true));
+    }
+
+    #[test]
+    fn unlabeled_sidecar_falls_back_to_workspace() {
+        let known = HashSet::from(["dev-x".to_string()]);
+        assert!(!is_orphan(&target("dev-x", ""), &known, |_| false));
+        assert!(is_orphan(&target("dev-y", ""), &known, |_| true));
+    }
+}
@@ -0,0 +1,184 @@
+use anyhow::{Context, Result};
+use tracing::{info, warn};
+
+use crate::commands::{browser_relay, down, port, up};
+use crate::docker;
+use crate::forward_state::SavedForward;
+use crate::proxy;
+use crate::workspace;
+
+#[derive(clap::Args)]
+pub struct RestartArgs {
+    /// Watch for new listening ports and auto-forward them after restart
+    #[arg(long, default_value_t = true, action = clap::ArgAction::Set, num_args = 0..=1, default_missing_value = "true")]
+    pub watch: bool,
+
+    /// Extra arguments passed to `devcontainer up`
+    #[arg(last = true)]
+    pub extra: Vec<String>,
+}
+
+pub fn run(args: &RestartArgs) -> Result<()> {
+    restart(&up::UpFlags::default(), false, &args.extra, args.watch)
+}
+
+/// Stop the devcontainer and bring it up again with `flags`, keeping its
+/// port forwards. With `recreate` a new container replaces it.
+pub fn restart(flags: &up::UpFlags, recreate: bool, extra: &[String], watch: bool) -> Result<()> {
+    let workspace_folder = workspace::workspace_folder()?;
+    let ws_id = workspace::workspace_id()?;
+
+    // Remember active forwards before tearing anything down. Watcher-managed
+    // forwards are skipped because the restarted watcher recreates them.
+    let saved = saved_forwards(&docker::list_port_forwards(&ws_id)?);
+    let relay_was_running = browser_relay::relay_running();
+
+    down::stop_watcher();
+
+    // Sidecars point at the old container IP, so they must be recreated
+    docker::remove_all_port_forwards(&ws_id)?;
+
+    if let Some(container_id) = docker::find_devcontainer(&workspace_folder)? {
+        info!("Stopping container {container_id}...");
+        docker::stop_container(&container_id)?;
+    }
+
+    up::devcontainer_up(&workspace_folder, recreate, flags, extra)?;
+    info!("Devcontainer is running.");
+    if let Err(e) = workspace::record_workspace_folder() {
+        warn!("failed to record workspace folder: {e}");
+    }
+
+    // Native forwards survive the restart; they only need the new container IP
+    if !proxy::list_forwards()?.is_empty() {
+        let container_id = docker::find_devcontainer(&workspace_folder)?
+            .context("devcontainer not found after restart")?;
+        let network = docker::get_container_network(&container_id)?;
+        proxy::retarget(&docker::get_container_addresses(&container_id, &network)?)?;
+    }
+
+    if !saved.is_empty() {
+        let container_id = docker::find_devcontainer(&workspace_folder)?
+            .context("devcontainer not found after restart")?;
+        let network = docker::get_container_network(&container_id)?;
+
+        info!("Restoring port forwards...");
+        for fwd in &saved {
+            if let Err(e) = port::restore_forward(&ws_id, &container_id, &network, fwd) {
+                warn!(
+                    "failed to restore forward {} -> {}: {e}",
+                    fwd.host_port, fwd.container_port
+                );
+            }
+        }
+    }
+
+    if watch {
+        up::spawn_watcher()?;
+    }
+
+    if relay_was_running && !docker::dry_run() {
+        match browser_relay::ensure_relay_running() {
+            Ok(_) => info!("Browser relay ready."),
+            Err(e) => warn!("failed to start browser relay: {e}"),
+        }
+    }
+
+    Ok(())
+}
+
+/// Select the forwards that should be recreated after restart.
+fn saved_forwards(forwards: &[docker::PortForwardInfo]) -> Vec<SavedForward> {
+    forwards
+        .iter()
+        .filter(|fwd| fwd.source != "watch")
+        .filter_map(SavedForward::from_sidecar)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::ForwardBackend;
+
+    fn info(host: &str, container: &str, source: &str) -> docker::PortForwardInfo {
+        docker::PortForwardInfo {
+            name: format!("pf-dev-x-c{container}"),
+            host_port: host.to_string(),
+            container_port: container.to_string(),
+            source: source.to_string(),
+            protocol: docker::Protocol::Tcp,
+            unix_socket: String::new(),
+            bind: String::new(),
+            shared: false,
+            label: String::new(),
+            target: String::new(),
+            service: String::new(),
+        }
+    }
+
+    #[test]
+    fn saved_forwards_skips_watch_sidecars() {
+        let forwards = vec![info("3000", "3000", ""), info("8080", "8080", "watch")];
+        assert_eq!(
+            saved_forwards(&forwards),
+            vec![SavedForward {
+                host_port: 3000,
+                container_port: 3000,
+                protocol: docker::Protocol::Tcp,
+                backend: ForwardBackend::Sidecar,
+                unix_socket: None,
+                bind: None,
+                label: None,
+                service: None,
+                source: None,
+                log_http: false,
+                tls: false,
+                ipv6: false,
+                rate_limit: None,
+                max_connections: None,
+                idle_timeout: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn saved_forwards_keeps_distinct_host_port() {
+        let forwards = vec![info("9000", "3000", "")];
+        assert_eq!(
+            saved_forwards(&forwards),
+            vec![SavedForward {
+                host_port: 9000,
+                container_port: 3000,
+                protocol: docker::Protocol::Tcp,
+                backend: ForwardBackend::Sidecar,
+                unix_socket: None,
+                bind: None,
+                label: None,
+                service: None,
+                source: None,
+                log_http: false,
+                tls: false,
+                ipv6: false,
+                rate_limit: None,
+                max_connections: None,
+                idle_timeout: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn saved_forwards_keeps_source() {
+        let forwards = vec![info("3000", "3000", "config")];
+        assert_eq!(
+            saved_forwards(&forwards)[0].source.as_deref(),
+            Some("config")
+        );
+    }
+
+    #[test]
+    fn saved_forwards_skips_unparseable_labels() {
+        let forwards = vec![info("", "3000", "")];
+        assert!(saved_forwards(&forwards).is_empty());
+    }
+}
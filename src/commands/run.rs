@@ -0,0 +1,159 @@
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::commands::exec;
+use crate::docker;
+use crate::logging::Logged;
+use crate::workspace;
+
+/// Environment variable holding the devcontainer's IP in `dcw run` containers.
+const DEVCONTAINER_IP_ENV: &str = "DCW_DEVCONTAINER_IP";
+
+#[derive(clap::Args)]
+pub struct RunArgs {
+    /// Image to run
+    #[arg(long)]
+    pub image: String,
+
+    /// Set an environment variable (`KEY=VALUE`, or `KEY` to pass the host's value)
+    #[arg(short, long = "env", value_name = "KEY=VALUE")]
+    pub env: Vec<String>,
+
+    /// Read environment variables from a file of `KEY=VALUE` lines
+    #[arg(long, value_name = "PATH")]
+    pub env_file: Vec<PathBuf>,
+
+    /// Name for the container (default: chosen by Docker)
+    #[arg(long)]
+    pub name: Option<String>,
+
+    /// Command and arguments to run (default: the image's command)
+    #[arg(trailing_var_arg = true)]
+    pub cmd: Vec<String>,
+}
+
+/// How to start a `dcw run` container.
+struct RunSpec<'a> {
+    ws_id: &'a str,
+    container_id: &'a str,
+    network: &'a str,
+    container_ip: &'a str,
+    name: Option<&'a str>,
+    env: &'a [String],
+    interactive: bool,
+    tty: bool,
+}
+
+pub fn run(args: &RunArgs) -> Result<()> {
+    let env = exec::resolve_env(&args.env, &args.env_file)?;
+    let ws_id = workspace::workspace_id()?;
+    let workspace_folder = workspace::workspace_folder()?;
+    let container_id = docker::find_devcontainer(&workspace_folder)?
+        .context("no running devcontainer found (start it with `dcw up`)")?;
+    let network = docker::get_container_network(&container_id)?;
+    let container_ip = docker::get_container_ip(&container_id, &network)?;
+
+    let stdin_tty = exec::is_tty(libc::STDIN_FILENO);
+    let spec = RunSpec {
+        ws_id: &ws_id,
+        container_id: &container_id,
+        network: &network,
+        container_ip: &container_ip,
+        name: args.name.as_deref(),
+        env: &env,
+        interactive: stdin_tty,
+        tty: stdin_tty && exec::is_tty(libc::STDOUT_FILENO),
+    };
+    let docker = docker::docker_path();
+    let status = Command::new(&docker)
+        .args(run_args(&spec, &args.image, &args.cmd))
+        .logged()
+        .status()
+        .with_context(|| format!("failed to run {docker}"))?;
+    if !status.success() {
+        std::process::exit(status.code().unwrap_or(1));
+    }
+    Ok(())
+}
+
+/// `docker run` arguments for an ephemeral container on the devcontainer's
+/// network, labeled with the workspace like the forwarding sidecars.
+fn run_args(spec: &RunSpec, image: &str, cmd: &[String]) -> Vec<String> {
+    let mut args = vec![
+        "run".to_string(),
+        "--rm".to_string(),
+        "--network".to_string(),
+        spec.network.to_string(),
+        "--label".to_string(),
+        "dcw.role=run".to_string(),
+        "--label".to_string(),
+        format!("dcw.workspace={}", spec.ws_id),
+        "--label".to_string(),
+        format!("dcw.container={}", spec.container_id),
+        "-e".to_string(),
+        format!("{DEVCONTAINER_IP_ENV}={}", spec.container_ip),
+    ];
+    if let Some(name) = spec.name {
+        args.extend(["--name".to_string(), name.to_string()]);
+    }
+    if spec.interactive {
+        args.push("-i".to_string());
+    }
+    if spec.tty {
+        args.push("-t".to_string());
+    }
+    for var in spec.env {
+        args.extend(["-e".to_string(), var.clone()]);
+    }
+    args.push(image.to_string());
+    args.extend_from_slice(cmd);
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run_args_join_devcontainer_network() {
+        let env = vec!["PGPASSWORD=secret".to_string()];
+        let spec = RunSpec {
+            ws_id: "dev-app-12345678",
+            container_id: "abc123",
+            network: "app_default",
+            container_ip: "172.18.0.2",
+            name: Some("psql"),
+            env: &env,
+            interactive: true,
+            tty: false,
+        };
+        let cmd = vec!["psql".to_string(), "-h".to_string(), "db".to_string()];
+        assert_eq!(
+            run_args(&spec, "postgres:16", &cmd),
+            [
+                "run",
+                "--rm",
+                "--network",
+                "app_default",
+                "--label",
+                "dcw.role=run",
+                "--label",
+                "dcw.workspace=dev-app-12345678",
+                "--label",
+                "dcw.container=abc123",
+                "-e",
+                "DCW_DEVCONTAINER_IP=172.18.0.2",
+                "--name",
+                "psql",
+                "-i",
+                "-e",
+                "PGPASSWORD=secret",
+                "postgres:16",
+                "psql",
+                "-h",
+                "db",
+            ]
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use anyhow::{bail, Context, Result};
+
+use crate::commands::exec;
+
+/// Shells tried in order when the remote user's login shell is unusable.
+const FALLBACK_SHELLS: &[&str] = &["zsh", "bash", "sh"];
+
+#[derive(clap::Args)]
+pub struct ShellArgs {
+    /// Shell to launch instead of auto-detecting one
+    #[arg(long)]
+    pub shell: Option<String>,
+
+    /// User to open the shell as instead of `remoteUser`
+    #[arg(short, long)]
+    pub user: Option<String>,
+}
+
+pub fn run(args: &ShellArgs) -> Result<()> {
+    let shell = match &args.shell {
+        Some(s) => s.clone(),
+        None => detect_shell(args.user.as_deref())?,
+    };
+
+    exec::run(&exec::ExecArgs {
+        env: Vec::new(),
+        env_file: Vec::new(),
+        workdir: None,
+        user: args.user.clone(),
+        interactive: false,
+        tty: false,
+        cmd: vec![shell, "-l".to_string()],
+    })
+}
+
+/// Detect the user's shell by running a probe script inside the container.
+fn detect_shell(user: Option<&str>) -> Result<String> {
+    let output = exec::output(
+        user,
+        &["sh".to_string(), "-c".to_string(), probe_script()],
+    )?;
+
+    if !output.status.success() {
+        bail!(
+            "failed to detect container shell: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    choose_shell(&String::from_utf8_lossy(&output.stdout))
+        .context("no usable shell found in the container")
+}
+
+/// Script printing `passwd:<shell>` for the current user's /etc/passwd entry,
+/// followed by `found:<path>` for each fallback shell available on PATH.
+fn probe_script() -> String {
+    let mut script = String::from(
+        r#"_u=$(id -un); awk -F: -v u="$_u" '$1 == u { print "passwd:" $7 }' /etc/passwd 2>/dev/null; "#,
+    );
+    for shell in FALLBACK_SHELLS {
+        script.push_str(&format!(
+            r#"_p=$(command -v {shell}) && echo "found:$_p"; "#
+        ));
+    }
+    script.push_str("true");
+    script
+}
+
+/// Pick a shell from probe output: the passwd entry when it is a real shell,
+/// otherwise the first fallback shell that was found.
+fn choose_shell(probe_output: &str) -> Option<String> {
+    let mut passwd = None;
+    let mut found = Vec::new();
+    for line in probe_output.lines() {
+        let line = line.trim();
+        if let Some(s) = line.strip_prefix("passwd:") {
+            passwd = Some(s.to_string());
+        } else if let Some(s) = line.strip_prefix("found:") {
+            found.push(s.to_string());
+        }
+    }
+
+    match passwd {
+        Some(s) if is_login_shell(&s) => Some(s),
+        _ => found.into_iter().next(),
+    }
+}
+
+/// Returns false for empty entries and placeholders like `nologin` / `false`.
+fn is_login_shell(path: &str) -> bool {
+    !path.is_empty() && !path.ends_with("nologin") && !path.ends_with("/false")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn choose_shell_prefers_passwd_entry() {
+        let out = "passwd:/usr/bin/zsh\nfound:/bin/bash\nfound:/bin/sh\n";
+        assert_eq!(choose_shell(out).as_deref(), Some("/usr/bin/zsh"));
+    }
+
+    #[test]
+    fn choose_shell_skips_nologin() {
+        let out = "passwd:/usr/sbin/nologin\nfound:/bin/bash\nfound:/bin/sh\n";
+        assert_eq!(choose_shell(out).as_deref(), Some("/bin/bash"));
+    }
+
+    #[test]
+    fn choose_shell_alpine_without_passwd_entry() {
+        let out = "found:/bin/sh\n";
+        assert_eq!(choose_shell(out).as_deref(), Some("/bin/sh"));
+    }
+
+    #[test]
+    fn choose_shell_nothing_found() {
+        assert_eq!(choose_shell(""), None);
+    }
+
+    #[test]
+    fn probe_script_checks_all_fallbacks() {
+        let script = probe_script();
+        for shell in FALLBACK_SHELLS {
+            assert!(script.contains(&format!("command -v {shell}")));
+        }
+    }
+}
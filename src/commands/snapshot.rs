@@ -0,0 +1,319 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::info;
+
+use crate::commands::{restart, up, watch};
+use crate::config;
+use crate::docker;
+use crate::output::{self, OutputFormat};
+use crate::traffic::format_bytes;
+use crate::workspace;
+
+#[derive(clap::Subcommand)]
+pub enum SnapshotAction {
+    /// Commit the devcontainer to an image, to go back to with `restore`
+    Create {
+        /// Name of the snapshot (letters, digits, `-` and `_`)
+        name: String,
+    },
+    /// Replace the devcontainer with a new one created from a snapshot
+    Restore {
+        /// Name of the snapshot
+        name: String,
+    },
+    /// List the workspace's snapshots
+    #[command(alias = "ls")]
+    List,
+    /// Delete a snapshot and its image
+    #[command(alias = "rm")]
+    Delete {
+        /// Name of the snapshot
+        name: String,
+    },
+}
+
+/// A snapshot recorded in the workspace's state directory.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub name: String,
+    /// Image the container was committed to
+    pub image: String,
+    /// ID of that image
+    pub image_id: String,
+    /// Name of the container it was taken from
+    pub container: String,
+    /// Unix time it was taken at
+    pub created_at: u64,
+}
+
+#[derive(Serialize)]
+struct SnapshotRow<'a> {
+    #[serde(flatten)]
+    snapshot: &'a Snapshot,
+    /// Size of the image, or `None` if it is gone
+    size: Option<u64>,
+}
+
+pub fn run(action: &SnapshotAction, format: OutputFormat) -> Result<()> {
+    let workspace_folder = workspace::workspace_folder()?;
+    match action {
+        SnapshotAction::Create { name } => create(&workspace_folder, name),
+        SnapshotAction::Restore { name } => restore(name),
+        SnapshotAction::List => list(format),
+        SnapshotAction::Delete { name } => delete(name),
+    }
+}
+
+fn create(workspace_folder: &str, name: &str) -> Result<()> {
+    config::check_name("snapshot", name)?;
+    let path = workspace::snapshots_file()?;
+    let mut snapshots = load_from(&path);
+    if snapshots.iter().any(|s| s.name == name) {
+        bail!(
+            "snapshot '{name}' already exists; delete it first with `dcw snapshot delete {name}`"
+        );
+    }
+    let (_, merged) = config::load_base_and_merged(Path::new(workspace_folder))?;
+    if merged.get("dockerComposeFile").is_some() {
+        bail!("snapshots aren't supported for Docker Compose configs");
+    }
+    let container_id = docker::find_any_devcontainer(workspace_folder)?
+        .context("no devcontainer to snapshot; start one with `dcw up`")?;
+    let container = docker::container_name(&container_id)?;
+
+    let ws_id = workspace::workspace_id()?;
+    let image = format!("{}:{name}", repository(&ws_id));
+    info!("Committing {container} to {image}...");
+    let image_id = docker::commit_container(
+        &container_id,
+        &image,
+        &[("dcw.workspace", &ws_id), ("dcw.snapshot", name)],
+    )?;
+
+    snapshots.push(Snapshot {
+        name: name.to_string(),
+        image,
+        image_id,
+        container,
+        created_at: now(),
+    });
+    if docker::dry_run() {
+        docker::print_dry_run(&format!("update {}", path.display()));
+    } else {
+        save_to(&path, &snapshots)?;
+    }
+    info!("Snapshot {name} created; go back to it with `dcw snapshot restore {name}`.");
+    Ok(())
+}
+
+fn restore(name: &str) -> Result<()> {
+    let snapshot = find(&load_from(&workspace::snapshots_file()?), name)?;
+    let filter = format!("reference={}", snapshot.image);
+    if docker::list_images(&[&filter])?.is_empty() {
+        bail!(
+            "the image {} of snapshot '{name}' no longer exists",
+            snapshot.image
+        );
+    }
+
+    info!("Recreating the devcontainer from snapshot {name}...");
+    let flags = up::UpFlags {
+        snapshot: Some(snapshot.image.clone()),
+        ..Default::default()
+    };
+    restart::restart(&flags, true, &[], true)?;
+    info!("Devcontainer restored from snapshot {name}; `dcw up --rebuild` goes back to the config's image.");
+    Ok(())
+}
+
+fn list(format: OutputFormat) -> Result<()> {
+    let snapshots = load_from(&workspace::snapshots_file()?);
+    let sizes: HashMap<String, Option<u64>> = match snapshots.first() {
+        Some(snapshot) => {
+            let (repository, _) = snapshot.image.rsplit_once(':').unwrap_or_default();
+            let filter = format!("reference={repository}");
+            docker::list_images(&[&filter])?
+                .into_iter()
+                .filter_map(|i| Some((i.reference?, i.size)))
+                .collect()
+        }
+        None => HashMap::new(),
+    };
+    let rows: Vec<SnapshotRow> = snapshots
+        .iter()
+        .map(|snapshot| SnapshotRow {
+            snapshot,
+            size: sizes.get(&snapshot.image).copied().flatten(),
+        })
+        .collect();
+
+    if format == OutputFormat::Json {
+        return output::print_json(&rows);
+    }
+    if rows.is_empty() {
+        println!("No snapshots (create one with `dcw snapshot create <name>`).");
+        return Ok(());
+    }
+    let width = rows
+        .iter()
+        .map(|r| r.snapshot.name.len())
+        .max()
+        .unwrap_or(0)
+        .max(4);
+    println!(
+        "{:<width$}   {:<12}   {:>10}   IMAGE",
+        "NAME", "CREATED", "SIZE"
+    );
+    for row in &rows {
+        let age = now().saturating_sub(row.snapshot.created_at);
+        let size = if sizes.contains_key(&row.snapshot.image) {
+            format_bytes(row.size)
+        } else {
+            "gone".to_string()
+        };
+        println!(
+            "{:<width$}   {:<12}   {:>10}   {}",
+            row.snapshot.name,
+            format!("{} ago", watch::format_uptime(age)),
+            size,
+            row.snapshot.image,
+        );
+    }
+    Ok(())
+}
+
+fn delete(name: &str) -> Result<()> {
+    let path = workspace::snapshots_file()?;
+    delete_from(&path, name, docker::remove_image)?;
+    info!("Snapshot {name} deleted.");
+    Ok(())
+}
+
+/// Remove snapshot `name`'s image with `remove_image`, then its record in
+/// `path`. The record stays if the image can't be removed (e.g. a
+/// devcontainer restored from it still uses it), so the snapshot can still
+/// be found and deleted later.
+fn delete_from(
+    path: &Path,
+    name: &str,
+    remove_image: impl FnOnce(&str) -> Result<()>,
+) -> Result<()> {
+    let mut snapshots = load_from(path);
+    let snapshot = find(&snapshots, name)?;
+    remove_image(&snapshot.image)
+        .with_context(|| format!("failed to delete snapshot '{name}'; it was kept"))?;
+    snapshots.retain(|s| s.name != name);
+    if docker::dry_run() {
+        docker::print_dry_run(&format!("update {}", path.display()));
+        Ok(())
+    } else {
+        save_to(path, &snapshots)
+    }
+}
+
+fn find(snapshots: &[Snapshot], name: &str) -> Result<Snapshot> {
+    if let Some(snapshot) = snapshots.iter().find(|s| s.name == name) {
+        return Ok(snapshot.clone());
+    }
+    let names: Vec<&str> = snapshots.iter().map(|s| s.name.as_str()).collect();
+    if names.is_empty() {
+        bail!("no snapshot named '{name}' (the workspace has none)");
+    }
+    bail!(
+        "no snapshot named '{name}' (available: {})",
+        names.join(", ")
+    );
+}
+
+/// Repository of the workspace's snapshot images. Image names only allow
+/// lowercase letters, digits and a few separators.
+fn repository(ws_id: &str) -> String {
+    let id: String = ws_id
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    format!("dcw-snapshot/{id}")
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn load_from(path: &Path) -> Vec<Snapshot> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_to(path: &Path, snapshots: &[Snapshot]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context("failed to create state directory")?;
+    }
+    fs::write(path, serde_json::to_string_pretty(snapshots)?)
+        .context("failed to write snapshot list")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repository_is_a_valid_image_name() {
+        assert_eq!(
+            repository("dev-My App.v2-1a2b3c4d"),
+            "dcw-snapshot/dev-my-app-v2-1a2b3c4d"
+        );
+    }
+
+    #[test]
+    fn snapshots_round_trip() {
+        let path = std::env::temp_dir().join("dcw-test-snapshots.json");
+        let snapshot = Snapshot {
+            name: "before-upgrade".to_string(),
+            image: "dcw-snapshot/dev-app-1a2b3c4d:before-upgrade".to_string(),
+            image_id: "sha256:abc".to_string(),
+            container: "app_devcontainer".to_string(),
+            created_at: 1_700_000_000,
+        };
+        save_to(&path, std::slice::from_ref(&snapshot)).unwrap();
+        assert_eq!(load_from(&path), vec![snapshot.clone()]);
+        assert_eq!(
+            find(&[snapshot], "before-upgrade").unwrap().image_id,
+            "sha256:abc"
+        );
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn delete_keeps_record_when_image_removal_fails() {
+        let path = std::env::temp_dir().join("dcw-test-snapshots-delete.json");
+        let snapshot = |name: &str| Snapshot {
+            name: name.to_string(),
+            image: format!("dcw-snapshot/dev-app-1a2b3c4d:{name}"),
+            image_id: format!("sha256:{name}"),
+            container: "app_devcontainer".to_string(),
+            created_at: 1_700_000_000,
+        };
+        save_to(&path, &[snapshot("old"), snapshot("new")]).unwrap();
+
+        let err = delete_from(&path, "old", |image| {
+            bail!("failed to remove image {image}: image is in use")
+        })
+        .unwrap_err();
+        assert!(format!("{err:#}").contains("image is in use"));
+        assert_eq!(load_from(&path), vec![snapshot("old"), snapshot("new")]);
+
+        delete_from(&path, "old", |_| Ok(())).unwrap();
+        assert_eq!(load_from(&path), vec![snapshot("new")]);
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,289 @@
+use anyhow::{bail, Context, Result};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::info;
+
+use crate::commands::exec;
+use crate::docker;
+use crate::logging::Logged;
+use crate::workspace;
+
+#[derive(clap::Args)]
+pub struct SshArgs {
+    /// Set up sshd and the Host entry without connecting
+    #[arg(long)]
+    pub setup: bool,
+
+    /// Internal: act as the ProxyCommand for the workspace in FOLDER
+    #[arg(long, value_name = "FOLDER", hide = true)]
+    pub proxy: Option<PathBuf>,
+
+    /// Extra arguments passed to `ssh` (e.g. a remote command)
+    #[arg(last = true)]
+    pub extra: Vec<String>,
+}
+
+/// Installs `openssh-server` with whichever package manager the image has
+/// (if sshd is missing) and generates host keys. Run as root.
+const SETUP_SCRIPT: &str = r#"set -e
+if ! command -v sshd >/dev/null 2>&1 && [ ! -x /usr/sbin/sshd ]; then
+  echo "Installing openssh-server in the container..." >&2
+  if command -v apt-get >/dev/null 2>&1; then
+    apt-get update -qq && DEBIAN_FRONTEND=noninteractive apt-get install -y -qq openssh-server >/dev/null
+  elif command -v apk >/dev/null 2>&1; then
+    apk add --no-cache -q openssh-server
+  elif command -v dnf >/dev/null 2>&1; then
+    dnf install -y -q openssh-server
+  elif command -v yum >/dev/null 2>&1; then
+    yum install -y -q openssh-server
+  else
+    echo "sshd not found and no supported package manager to install openssh-server" >&2
+    exit 1
+  fi
+fi
+ssh-keygen -A >/dev/null
+mkdir -p /run/sshd"#;
+
+/// Adds the public key in `$1` to the current user's authorized_keys.
+const AUTHORIZE_SCRIPT: &str = r#"set -e
+mkdir -p "$HOME/.ssh" && chmod 700 "$HOME/.ssh"
+touch "$HOME/.ssh/authorized_keys" && chmod 600 "$HOME/.ssh/authorized_keys"
+grep -qxF "$1" "$HOME/.ssh/authorized_keys" || echo "$1" >> "$HOME/.ssh/authorized_keys"
+id -un"#;
+
+/// Serves one SSH session on stdin/stdout. No daemon keeps running.
+const PROXY_SCRIPT: &str =
+    r#"mkdir -p /run/sshd; exec "$(command -v sshd || echo /usr/sbin/sshd)" -i"#;
+
+pub fn run(args: &SshArgs) -> Result<()> {
+    if let Some(folder) = &args.proxy {
+        return proxy(folder);
+    }
+
+    let workspace_folder = workspace::workspace_folder()?;
+    let ws_id = workspace::workspace_id()?;
+    if docker::find_devcontainer(&workspace_folder)?.is_none() {
+        bail!("devcontainer is not running (start it with `dcw up`)");
+    }
+
+    let key = ensure_key()?;
+    run_in_container(Some("root"), SETUP_SCRIPT, &[])?;
+    let public_key = fs::read_to_string(key.with_extension("pub"))
+        .context("failed to read dcw SSH public key")?;
+    let user = run_in_container(None, AUTHORIZE_SCRIPT, &[public_key.trim()])?;
+
+    let alias = format!("dcw-{ws_id}");
+    let exe = env::current_exe().context("failed to get current executable path")?;
+    let entry = host_entry(
+        &alias,
+        user.trim(),
+        &key,
+        &exe,
+        Path::new(&workspace_folder),
+    );
+    let config_path = ssh_dir()?.join("config.d").join("dcw");
+    write_host_entry(&config_path, &alias, &entry)?;
+
+    if !includes_dcw_config(&ssh_dir()?.join("config")) {
+        info!(
+            "Hint: add `Include config.d/dcw` to the top of ~/.ssh/config to reach the container as `ssh {alias}` from other tools."
+        );
+    }
+    if args.setup {
+        info!("SSH ready: ssh -F {} {alias}", config_path.display());
+        return Ok(());
+    }
+
+    let status = Command::new("ssh")
+        .arg("-F")
+        .arg(&config_path)
+        .arg(&alias)
+        .args(&args.extra)
+        .logged()
+        .status()
+        .context("failed to run ssh — is the OpenSSH client installed?")?;
+    if !status.success() {
+        std::process::exit(status.code().unwrap_or(1));
+    }
+    Ok(())
+}
+
+/// ProxyCommand: connect stdin/stdout to a one-shot `sshd -i` in the
+/// workspace's container. Looked up on every connection, so the entry keeps
+/// working after the container is recreated.
+fn proxy(folder: &Path) -> Result<()> {
+    let folder = folder.to_string_lossy();
+    let container_id = docker::find_devcontainer(&folder)?
+        .with_context(|| format!("no running devcontainer for {folder}"))?;
+    let status = Command::new(docker::docker_path())
+        .args([
+            "exec",
+            "-i",
+            "-u",
+            "root",
+            &container_id,
+            "sh",
+            "-c",
+            PROXY_SCRIPT,
+        ])
+        .logged()
+        .status()
+        .context("failed to run docker exec")?;
+    if !status.success() {
+        std::process::exit(status.code().unwrap_or(1));
+    }
+    Ok(())
+}
+
+/// Run a script with `sh -c` in the container and return its stdout.
+fn run_in_container(user: Option<&str>, script: &str, args: &[&str]) -> Result<String> {
+    let mut cmd = vec![
+        "sh".to_string(),
+        "-c".to_string(),
+        script.to_string(),
+        "sh".to_string(),
+    ];
+    cmd.extend(args.iter().map(|a| a.to_string()));
+    let output = exec::output(user, &cmd)?;
+    if !output.status.success() {
+        bail!(
+            "failed to set up SSH in the container: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn ssh_dir() -> Result<PathBuf> {
+    Ok(dirs::home_dir()
+        .context("failed to determine home directory")?
+        .join(".ssh"))
+}
+
+/// The key pair dcw uses for container SSH, generated on first use.
+fn ensure_key() -> Result<PathBuf> {
+    let dir = dirs::config_dir()
+        .context("failed to determine config directory")?
+        .join("dcw")
+        .join("ssh");
+    let key = dir.join("id_ed25519");
+    if key.exists() {
+        return Ok(key);
+    }
+    fs::create_dir_all(&dir).context("failed to create dcw SSH directory")?;
+    let status = Command::new("ssh-keygen")
+        .args(["-q", "-t", "ed25519", "-N", "", "-C", "dcw", "-f"])
+        .arg(&key)
+        .logged()
+        .status()
+        .context("failed to run ssh-keygen — is the OpenSSH client installed?")?;
+    if !status.success() {
+        bail!("ssh-keygen failed");
+    }
+    Ok(key)
+}
+
+/// `Host` block for the workspace. Host keys are regenerated with the
+/// container, so they aren't checked.
+fn host_entry(alias: &str, user: &str, key: &Path, exe: &Path, folder: &Path) -> String {
+    // ssh expands %-tokens in ProxyCommand
+    let quote = |p: &Path| format!("\"{}\"", p.display().to_string().replace('%', "%%"));
+    format!(
+        "Host {alias}\n  \
+         HostName {alias}\n  \
+         User {user}\n  \
+         IdentityFile {}\n  \
+         IdentitiesOnly yes\n  \
+         StrictHostKeyChecking no\n  \
+         UserKnownHostsFile /dev/null\n  \
+         LogLevel ERROR\n  \
+         ProxyCommand {} ssh --proxy {}\n",
+        quote(key),
+        quote(exe),
+        quote(folder)
+    )
+}
+
+/// Replace the `Host alias` block in `contents` with `entry`, or append it.
+fn replace_host_entry(contents: &str, alias: &str, entry: &str) -> String {
+    let mut out = String::new();
+    let mut skipping = false;
+    for line in contents.lines() {
+        if let Some(hosts) = line.trim_start().strip_prefix("Host ") {
+            skipping = hosts.split_whitespace().any(|h| h == alias);
+        }
+        if !skipping {
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+    if !out.is_empty() && !out.ends_with("\n\n") {
+        out.push('\n');
+    }
+    out.push_str(entry);
+    out
+}
+
+fn write_host_entry(path: &Path, alias: &str, entry: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context("failed to create ~/.ssh/config.d")?;
+    }
+    let contents = fs::read_to_string(path).unwrap_or_default();
+    fs::write(path, replace_host_entry(&contents, alias, entry))
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Whether ~/.ssh/config includes config.d/dcw (directly or by a glob).
+fn includes_dcw_config(ssh_config: &Path) -> bool {
+    fs::read_to_string(ssh_config).is_ok_and(|contents| {
+        contents.lines().any(|line| {
+            let mut words = line.split_whitespace();
+            words
+                .next()
+                .is_some_and(|w| w.eq_ignore_ascii_case("include"))
+                && words.any(|w| w.contains("config.d/dcw") || w.contains("config.d/*"))
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replace_host_entry_appends_and_replaces() {
+        let first = host_entry(
+            "dcw-a",
+            "node",
+            Path::new("/k"),
+            Path::new("/bin/dcw"),
+            Path::new("/src/a"),
+        );
+        let contents = replace_host_entry("", "dcw-a", &first);
+        assert_eq!(contents, first);
+
+        let other = "Host dcw-b\n  User vscode\n";
+        let contents = replace_host_entry(&format!("{other}\n{first}"), "dcw-a", &first);
+        assert_eq!(contents, format!("{other}\n{first}"));
+
+        let updated = first.replace("User node", "User root");
+        let contents = replace_host_entry(&contents, "dcw-a", &updated);
+        assert_eq!(contents, format!("{other}\n{updated}"));
+    }
+
+    #[test]
+    fn host_entry_quotes_proxy_command() {
+        let entry = host_entry(
+            "dcw-x",
+            "vscode",
+            Path::new("/home/me/.config/dcw/ssh/id_ed25519"),
+            Path::new("/usr/local/bin/dcw"),
+            Path::new("/src/100% done"),
+        );
+        assert!(entry.starts_with("Host dcw-x\n  HostName dcw-x\n  User vscode\n"));
+        assert!(entry
+            .contains("  ProxyCommand \"/usr/local/bin/dcw\" ssh --proxy \"/src/100%% done\"\n"));
+    }
+}
@@ -0,0 +1,170 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+use crate::docker;
+use crate::logging::Logged;
+use crate::output::{self, OutputFormat};
+use crate::workspace;
+
+/// Pause between refreshes with `--watch`; sampling CPU usage already takes
+/// `docker stats` a second or two.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(clap::Args)]
+pub struct StatsArgs {
+    /// Keep refreshing until interrupted
+    #[arg(short, long)]
+    pub watch: bool,
+}
+
+/// One line of `docker stats --format '{{json .}}'`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DockerStats {
+    #[serde(rename = "CPUPerc")]
+    cpu_perc: String,
+    mem_usage: String,
+    mem_perc: String,
+    #[serde(rename = "NetIO")]
+    net_io: String,
+    #[serde(rename = "BlockIO")]
+    block_io: String,
+    #[serde(rename = "PIDs")]
+    pids: String,
+}
+
+/// Resource usage of one container, as `docker stats` formats it.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct StatsRow {
+    pub name: String,
+    /// `devcontainer`, `sidecar` or `shared`
+    pub role: &'static str,
+    pub cpu: String,
+    /// Usage and limit, e.g. `1.2GiB / 7.6GiB`
+    pub memory: String,
+    pub memory_percent: String,
+    /// Received and sent
+    pub net_io: String,
+    /// Read and written
+    pub block_io: String,
+    pub pids: String,
+}
+
+pub fn run(args: &StatsArgs, format: OutputFormat) -> Result<()> {
+    let workspace_folder = workspace::workspace_folder()?;
+    let ws_id = workspace::workspace_id()?;
+
+    loop {
+        let container_id = docker::find_devcontainer(&workspace_folder)?
+            .context("no running devcontainer found")?;
+        let mut targets = vec![(container_id, "devcontainer")];
+        for fwd in docker::list_port_forwards(&ws_id)? {
+            // The shared sidecar is listed once per forward it serves
+            if !targets.iter().any(|(t, _)| *t == fwd.name) {
+                let role = if fwd.shared { "shared" } else { "sidecar" };
+                targets.push((fwd.name, role));
+            }
+        }
+        let rows = collect_stats(&targets)?;
+
+        match (format, args.watch) {
+            // One array per refresh, on its own line
+            (OutputFormat::Json, true) => println!("{}", serde_json::to_string(&rows)?),
+            (OutputFormat::Json, false) => output::print_json(&rows)?,
+            (OutputFormat::Text, true) => {
+                print!("\x1b[H\x1b[2J");
+                println!("dcw stats: {workspace_folder}\n");
+                print_table(&rows);
+            }
+            (OutputFormat::Text, false) => print_table(&rows),
+        }
+        if !args.watch {
+            return Ok(());
+        }
+        thread::sleep(REFRESH_INTERVAL);
+    }
+}
+
+/// Take one sample of each container's usage.
+pub fn collect_stats(targets: &[(String, &'static str)]) -> Result<Vec<StatsRow>> {
+    let output = Command::new(docker::docker_path())
+        .args(["stats", "--no-stream", "--format", "{{json .}}"])
+        .args(targets.iter().map(|(target, _)| target))
+        .logged()
+        .output()
+        .context("failed to run docker stats")?;
+    if !output.status.success() {
+        bail!(
+            "docker stats failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    // Lines come in the order the containers were given
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .zip(targets)
+        .map(|(line, (target, role))| {
+            let stats: DockerStats =
+                serde_json::from_str(line).context("failed to parse docker stats output")?;
+            Ok(stats_row(target, role, stats))
+        })
+        .collect()
+}
+
+fn stats_row(target: &str, role: &'static str, stats: DockerStats) -> StatsRow {
+    StatsRow {
+        name: if role == "devcontainer" {
+            "(devcontainer)".to_string()
+        } else {
+            target.to_string()
+        },
+        role,
+        cpu: stats.cpu_perc,
+        memory: stats.mem_usage,
+        memory_percent: stats.mem_perc,
+        net_io: stats.net_io,
+        block_io: stats.block_io,
+        pids: stats.pids,
+    }
+}
+
+fn print_table(rows: &[StatsRow]) {
+    println!(
+        "{:<30} {:>7}   {:<22} {:>6}   {:<20} {:<20} {:>4}",
+        "NAME", "CPU", "MEM USAGE / LIMIT", "MEM", "NET I/O", "BLOCK I/O", "PIDS"
+    );
+    for row in rows {
+        println!(
+            "{:<30} {:>7}   {:<22} {:>6}   {:<20} {:<20} {:>4}",
+            row.name, row.cpu, row.memory, row.memory_percent, row.net_io, row.block_io, row.pids
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_docker_stats_line() {
+        let line = r#"{"BlockIO":"12.3MB / 4.1MB","CPUPerc":"187.52%","Container":"0123456789ab","ID":"0123456789ab","MemPerc":"41.20%","MemUsage":"3.1GiB / 7.6GiB","Name":"web-dev","NetIO":"1.2MB / 350kB","PIDs":"42"}"#;
+        let stats: DockerStats = serde_json::from_str(line).unwrap();
+        let row = stats_row("0123456789ab", "devcontainer", stats);
+        assert_eq!(
+            row,
+            StatsRow {
+                name: "(devcontainer)".to_string(),
+                role: "devcontainer",
+                cpu: "187.52%".to_string(),
+                memory: "3.1GiB / 7.6GiB".to_string(),
+                memory_percent: "41.20%".to_string(),
+                net_io: "1.2MB / 350kB".to_string(),
+                block_io: "12.3MB / 4.1MB".to_string(),
+                pids: "42".to_string(),
+            }
+        );
+    }
+}
@@ -0,0 +1,119 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::path::Path;
+
+use crate::commands::daemon;
+use crate::commands::port::{self, ForwardRow};
+use crate::commands::watch::WatcherHealth;
+use crate::config;
+use crate::docker;
+use crate::output::{self, OutputFormat};
+use crate::workspace;
+
+/// The Docker endpoint the workspace runs on.
+#[derive(Debug, Serialize)]
+struct DockerStatus {
+    #[serde(flatten)]
+    endpoint: docker::DockerEndpoint,
+    remote: bool,
+}
+
+/// State of the current workspace.
+#[derive(Debug, Serialize)]
+struct Status {
+    workspace: String,
+    folder: String,
+    /// Config file, if not `.devcontainer/devcontainer.json`
+    config: Option<String>,
+    /// Profile selected by `dcw up --profile`, if any
+    profile: Option<String>,
+    docker: DockerStatus,
+    /// Running devcontainer ID, if any
+    container: Option<String>,
+    /// `running`, `restarting` or `stopped`
+    watcher: String,
+    /// Restarts of the watcher after crashes
+    watcher_health: WatcherHealth,
+    forwards: Vec<ForwardRow>,
+}
+
+pub fn run(format: OutputFormat) -> Result<()> {
+    let folder = workspace::workspace_folder()?;
+    let watcher = daemon::watcher(&workspace::workspace_id()?);
+    let status = Status {
+        workspace: workspace::workspace_id()?,
+        config: config::config_file(Path::new(&folder))
+            .ok()
+            .filter(|path| *path != Path::new(&folder).join(".devcontainer/devcontainer.json"))
+            .map(|path| path.display().to_string()),
+        profile: workspace::active_profile(),
+        docker: DockerStatus {
+            endpoint: docker::docker_endpoint().clone(),
+            remote: docker::docker_endpoint().is_remote(),
+        },
+        container: docker::find_devcontainer(&folder)?,
+        watcher: watcher
+            .as_ref()
+            .map_or("stopped".to_string(), |w| w.state.clone()),
+        watcher_health: watcher.map(|w| w.health).unwrap_or_default(),
+        forwards: port::collect_forwards(&workspace::workspace_id()?)?,
+        folder,
+    };
+
+    if format == OutputFormat::Json {
+        return output::print_json(&status);
+    }
+
+    println!("Workspace:  {}", status.workspace);
+    println!("Folder:     {}", status.folder);
+    if let Some(config) = &status.config {
+        println!("Config:     {config}");
+    }
+    if let Some(profile) = &status.profile {
+        println!("Profile:    {profile}");
+    }
+    println!(
+        "Docker:     {} ({}){}",
+        status.docker.endpoint.context,
+        status.docker.endpoint.host,
+        if status.docker.remote {
+            ", remote: forwarded ports are published on that host"
+        } else {
+            ""
+        }
+    );
+    println!(
+        "Container:  {}",
+        status.container.as_deref().unwrap_or("not running")
+    );
+    match status.watcher_health.describe() {
+        Some(restarts) => println!("Watcher:    {} ({restarts})", status.watcher),
+        None => println!("Watcher:    {}", status.watcher),
+    }
+    if status.forwards.is_empty() {
+        println!("Forwards:   none");
+    } else {
+        println!("Forwards:");
+        for fwd in &status.forwards {
+            let target = match (&fwd.unix_socket, fwd.container_port) {
+                (Some(path), _) => path.clone(),
+                (None, Some(p)) => format!("{p}/{}", fwd.protocol.as_str()),
+                (None, None) => String::new(),
+            };
+            let label = fwd
+                .label
+                .as_deref()
+                .map(|l| format!(" ({l})"))
+                .unwrap_or_default();
+            println!(
+                "  {}:{} -> {} [{}]{}",
+                fwd.bind,
+                fwd.host_port,
+                target,
+                fwd.backend.as_str(),
+                label
+            );
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,15 @@
+use anyhow::Result;
+use tracing::info;
+
+use crate::commands::down;
+use crate::workspace;
+
+/// Stop the devcontainer but keep it, along with the browser relay, so the
+/// next `dcw up` resumes it quickly. Unlike `dcw down`, no hooks run.
+pub fn run() -> Result<()> {
+    let workspace_folder = workspace::workspace_folder()?;
+    let ws_id = workspace::workspace_id()?;
+    down::stop(&workspace_folder, &ws_id)?;
+    info!("Resume it with `dcw up`.");
+    Ok(())
+}
@@ -0,0 +1,652 @@
+use anyhow::{Context, Result};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::crossterm::execute;
+use ratatui::crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph, Row, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::browser;
+use crate::commands::port::{self, ForwardRow};
+use crate::commands::{daemon, stats};
+use crate::docker::{self, Protocol};
+use crate::logging::Logged;
+use crate::workspace;
+
+/// How long the dashboard waits for a key before checking for new data.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(clap::Args)]
+pub struct UiArgs {
+    /// Seconds between refreshes
+    #[arg(long, default_value_t = 2, value_name = "SECS")]
+    pub interval: u64,
+}
+
+/// A devcontainer as the dashboard shows it.
+struct WorkspaceView {
+    folder: String,
+    state: String,
+    cpu: Option<String>,
+    memory: Option<String>,
+    /// State of its watcher in `dcw daemon`
+    watcher: Option<String>,
+    forwards: Vec<ForwardRow>,
+}
+
+/// What the background threads send the dashboard.
+enum Message {
+    Refreshed(Result<Vec<WorkspaceView>>),
+    /// A `dcw` command run for a key finished, with the line to show
+    Finished(String),
+}
+
+/// Something the event loop does for a key.
+enum Action {
+    Quit,
+    Refresh,
+    /// Run `dcw <args>` in a workspace folder in the background
+    Run {
+        folder: String,
+        args: Vec<String>,
+        what: String,
+    },
+    /// Follow the logs in the terminal until Ctrl+C
+    Logs(String),
+    Open(String),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Focus {
+    Workspaces,
+    Forwards,
+}
+
+/// Keys waiting for more input.
+enum Prompt {
+    /// Port to forward, as typed so far
+    Add(String),
+    /// Stop the selected devcontainer?
+    Stop,
+}
+
+struct App {
+    workspaces: Vec<WorkspaceView>,
+    workspace_state: TableState,
+    forward_state: TableState,
+    focus: Focus,
+    prompt: Option<Prompt>,
+    status: String,
+}
+
+pub fn run(args: &UiArgs) -> Result<()> {
+    let exe = std::env::current_exe().context("failed to get current executable path")?;
+    // Ctrl+C is a key in raw mode; this only matters while following logs,
+    // where it should stop `dcw logs` and not the dashboard
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let i = interrupted.clone();
+    ctrlc::set_handler(move || {
+        i.store(true, Ordering::SeqCst);
+    })
+    .context("failed to set Ctrl+C handler")?;
+
+    let (tx, rx) = mpsc::channel();
+    let (refresh_tx, refresh_rx) = mpsc::channel();
+    let refresh_sender = tx.clone();
+    let interval = Duration::from_secs(args.interval.max(1));
+    thread::spawn(move || refresh_loop(refresh_sender, refresh_rx, interval));
+
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, &exe, &tx, &rx, &refresh_tx, &interrupted);
+    ratatui::restore();
+    result
+}
+
+/// Collect the dashboard's data every `interval`, or sooner when asked.
+fn refresh_loop(tx: Sender<Message>, refresh: Receiver<()>, interval: Duration) {
+    loop {
+        if tx.send(Message::Refreshed(collect())).is_err() {
+            return;
+        }
+        match refresh.recv_timeout(interval) {
+            Ok(()) | Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+        // Several requests in a row need one refresh
+        while refresh.try_recv().is_ok() {}
+    }
+}
+
+fn event_loop(
+    terminal: &mut DefaultTerminal,
+    exe: &Path,
+    tx: &Sender<Message>,
+    rx: &Receiver<Message>,
+    refresh: &Sender<()>,
+    interrupted: &AtomicBool,
+) -> Result<()> {
+    let mut app = App {
+        workspaces: Vec::new(),
+        workspace_state: TableState::default().with_selected(0),
+        forward_state: TableState::default(),
+        focus: Focus::Workspaces,
+        prompt: None,
+        status: "Loading...".to_string(),
+    };
+    loop {
+        while let Ok(message) = rx.try_recv() {
+            app.receive(message);
+        }
+        terminal.draw(|frame| app.draw(frame))?;
+        if interrupted.swap(false, Ordering::SeqCst) {
+            return Ok(());
+        }
+        if !event::poll(POLL_INTERVAL)? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match app.key(key) {
+            None => {}
+            Some(Action::Quit) => return Ok(()),
+            Some(Action::Refresh) => {
+                app.status = "Refreshing...".to_string();
+                let _ = refresh.send(());
+            }
+            Some(Action::Run { folder, args, what }) => {
+                app.status = format!("{what}...");
+                spawn_dcw(exe, folder, args, what, tx.clone(), refresh.clone());
+            }
+            Some(Action::Logs(folder)) => {
+                follow_logs(terminal, exe, &folder)?;
+                interrupted.store(false, Ordering::SeqCst);
+            }
+            Some(Action::Open(url)) => {
+                app.status = match browser::open_url(&url) {
+                    Ok(()) => format!("Opened {url}"),
+                    Err(e) => format!("Failed to open {url}: {e:#}"),
+                };
+            }
+        }
+    }
+}
+
+/// Run `dcw <args>` in `folder`, report how it went and refresh.
+fn spawn_dcw(
+    exe: &Path,
+    folder: String,
+    args: Vec<String>,
+    what: String,
+    tx: Sender<Message>,
+    refresh: Sender<()>,
+) {
+    let mut cmd = Command::new(exe);
+    cmd.args(&args)
+        .current_dir(&folder)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped());
+    thread::spawn(move || {
+        let line = match cmd.output() {
+            Ok(output) if output.status.success() => format!("{what}: done"),
+            Ok(output) => {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                let error = stderr.lines().last().unwrap_or_default().trim();
+                format!("{what}: {error}")
+            }
+            Err(e) => format!("{what}: failed to run dcw: {e}"),
+        };
+        let _ = tx.send(Message::Finished(line));
+        let _ = refresh.send(());
+    });
+}
+
+/// Leave the dashboard for `dcw logs --follow` until it is interrupted.
+fn follow_logs(terminal: &mut DefaultTerminal, exe: &Path, folder: &str) -> Result<()> {
+    terminal::disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen)?;
+    println!("Following logs of {folder}, press Ctrl+C to return...");
+    let status = Command::new(exe)
+        .args(["logs", "--follow", "--tail", "100"])
+        .current_dir(folder)
+        .logged()
+        .status();
+    if let Err(e) = status {
+        eprintln!("failed to run dcw logs: {e}");
+    }
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    terminal::enable_raw_mode()?;
+    terminal.clear()?;
+    Ok(())
+}
+
+/// Every devcontainer with its usage, watcher and forwards.
+fn collect() -> Result<Vec<WorkspaceView>> {
+    let watchers: HashMap<String, String> = daemon::watchers()
+        .into_iter()
+        .map(|w| (w.ws_id, w.state))
+        .collect();
+    let mut views = Vec::new();
+    let mut targets = Vec::new();
+    for container in docker::list_devcontainers()? {
+        let ws_id = workspace::workspace_id_for(&container.folder)?;
+        let mut view = WorkspaceView {
+            watcher: watchers.get(&ws_id).cloned(),
+            folder: container.folder,
+            state: container.state,
+            cpu: None,
+            memory: None,
+            forwards: Vec::new(),
+        };
+        if view.state == "running" {
+            if let Some(id) = docker::find_devcontainer(&view.folder)? {
+                targets.push((id, "devcontainer"));
+            }
+            view.forwards = port::query_forwards(&ws_id, &view.folder)?;
+        }
+        views.push(view);
+    }
+    views.sort_by(|a, b| a.folder.cmp(&b.folder));
+
+    if !targets.is_empty() {
+        let usage = stats::collect_stats(&targets)?;
+        let running = views.iter_mut().filter(|v| v.state == "running");
+        for (view, row) in running.zip(usage) {
+            view.cpu = Some(row.cpu);
+            view.memory = Some(row.memory);
+        }
+    }
+    Ok(views)
+}
+
+impl App {
+    fn receive(&mut self, message: Message) {
+        match message {
+            Message::Refreshed(Ok(workspaces)) => {
+                if self.status == "Loading..."
+                    || self.status == "Refreshing..."
+                    || self.status.starts_with("Failed to refresh")
+                {
+                    self.status.clear();
+                }
+                self.workspaces = workspaces;
+                let last = self.workspaces.len().saturating_sub(1);
+                if self.workspace_state.selected().is_some_and(|i| i > last) {
+                    self.workspace_state.select(Some(last));
+                }
+                self.clamp_forward();
+            }
+            Message::Refreshed(Err(e)) => self.status = format!("Failed to refresh: {e:#}"),
+            Message::Finished(line) => self.status = line,
+        }
+    }
+
+    fn selected(&self) -> Option<&WorkspaceView> {
+        self.workspaces.get(self.workspace_state.selected()?)
+    }
+
+    fn selected_forward(&self) -> Option<&ForwardRow> {
+        self.selected()?
+            .forwards
+            .get(self.forward_state.selected()?)
+    }
+
+    /// Keep the forward selection within the selected workspace's forwards.
+    fn clamp_forward(&mut self) {
+        let count = self.selected().map_or(0, |w| w.forwards.len());
+        match self.forward_state.selected() {
+            _ if count == 0 => self.forward_state.select(None),
+            None => self.forward_state.select(Some(0)),
+            Some(i) if i >= count => self.forward_state.select(Some(count - 1)),
+            Some(_) => {}
+        }
+    }
+
+    fn key(&mut self, key: KeyEvent) -> Option<Action> {
+        if let Some(prompt) = self.prompt.take() {
+            return self.prompt_key(prompt, key);
+        }
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Some(Action::Quit);
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Some(Action::Quit),
+            KeyCode::Char('r') => return Some(Action::Refresh),
+            KeyCode::Tab | KeyCode::BackTab => {
+                self.focus = match self.focus {
+                    Focus::Workspaces => Focus::Forwards,
+                    Focus::Forwards => Focus::Workspaces,
+                };
+            }
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::Char('a') if self.running() => self.prompt = Some(Prompt::Add(String::new())),
+            KeyCode::Char('s') if self.running() => self.prompt = Some(Prompt::Stop),
+            KeyCode::Char('l') if self.running() => {
+                return self.selected().map(|w| Action::Logs(w.folder.clone()));
+            }
+            KeyCode::Char('d') | KeyCode::Delete => {
+                let (workspace, forward) = (self.selected()?, self.selected_forward()?);
+                return Some(Action::Run {
+                    folder: workspace.folder.clone(),
+                    args: remove_args(forward),
+                    what: format!("Removing forward {}", forward.host_port),
+                });
+            }
+            KeyCode::Char('o') | KeyCode::Enter => {
+                let forward = self.selected_forward()?;
+                match forward_url(forward) {
+                    Some(url) => return Some(Action::Open(url)),
+                    None => self.status = "Only TCP forwards can be opened.".to_string(),
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn prompt_key(&mut self, prompt: Prompt, key: KeyEvent) -> Option<Action> {
+        let folder = self.selected()?.folder.clone();
+        match prompt {
+            Prompt::Add(mut input) => match key.code {
+                KeyCode::Enter => match parse_port_spec(&input) {
+                    Ok((host, container)) => {
+                        return Some(Action::Run {
+                            folder,
+                            args: vec![
+                                "port".to_string(),
+                                "add".to_string(),
+                                host.to_string(),
+                                container.to_string(),
+                                "--detach".to_string(),
+                            ],
+                            what: format!("Forwarding {host} -> {container}"),
+                        });
+                    }
+                    Err(e) => self.status = e,
+                },
+                KeyCode::Esc => {}
+                KeyCode::Backspace => {
+                    input.pop();
+                    self.prompt = Some(Prompt::Add(input));
+                }
+                KeyCode::Char(c) if c.is_ascii_digit() || c == ':' => {
+                    input.push(c);
+                    self.prompt = Some(Prompt::Add(input));
+                }
+                _ => self.prompt = Some(Prompt::Add(input)),
+            },
+            Prompt::Stop => {
+                if key.code == KeyCode::Char('y') {
+                    return Some(Action::Run {
+                        folder,
+                        args: vec!["down".to_string()],
+                        what: "Stopping devcontainer".to_string(),
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    fn running(&self) -> bool {
+        self.selected().is_some_and(|w| w.state == "running")
+    }
+
+    fn move_selection(&mut self, by: isize) {
+        let (state, count) = match self.focus {
+            Focus::Workspaces => (&mut self.workspace_state, self.workspaces.len()),
+            Focus::Forwards => {
+                let count = self
+                    .workspace_state
+                    .selected()
+                    .and_then(|i| self.workspaces.get(i))
+                    .map_or(0, |w| w.forwards.len());
+                (&mut self.forward_state, count)
+            }
+        };
+        if count == 0 {
+            return;
+        }
+        let current = state.selected().unwrap_or(0) as isize;
+        state.select(Some((current + by).clamp(0, count as isize - 1) as usize));
+        if self.focus == Focus::Workspaces {
+            self.forward_state.select(Some(0));
+            self.clamp_forward();
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [top, bottom, status] = Layout::vertical([
+            Constraint::Percentage(45),
+            Constraint::Min(5),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let highlight = Style::default().add_modifier(Modifier::REVERSED);
+        let border = |focus| {
+            if self.focus == focus {
+                Style::default().fg(Color::Cyan)
+            } else {
+                Style::default()
+            }
+        };
+
+        let rows = self.workspaces.iter().map(|w| {
+            Row::new([
+                folder_name(&w.folder),
+                w.state.clone(),
+                w.cpu.clone().unwrap_or("-".to_string()),
+                w.memory.clone().unwrap_or("-".to_string()),
+                w.watcher.clone().unwrap_or("-".to_string()),
+                w.forwards.len().to_string(),
+                w.folder.clone(),
+            ])
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(20),
+                Constraint::Length(10),
+                Constraint::Length(8),
+                Constraint::Length(20),
+                Constraint::Length(10),
+                Constraint::Length(8),
+                Constraint::Min(10),
+            ],
+        )
+        .header(
+            Row::new([
+                "NAME", "STATE", "CPU", "MEMORY", "WATCHER", "FORWARDS", "FOLDER",
+            ])
+            .bold(),
+        )
+        .row_highlight_style(highlight)
+        .block(
+            Block::bordered()
+                .title(" Workspaces ")
+                .border_style(border(Focus::Workspaces)),
+        );
+        frame.render_stateful_widget(table, top, &mut self.workspace_state);
+
+        let forwards = self.selected().map_or(&[][..], |w| &w.forwards[..]);
+        let rows = forwards.iter().map(|f| {
+            Row::new([
+                f.host_port.to_string(),
+                forward_target(f),
+                f.backend.as_str().to_string(),
+                f.source.clone().unwrap_or_default(),
+                f.label.clone().unwrap_or_default(),
+                forward_url(f).unwrap_or_default(),
+            ])
+        });
+        let title = match self.selected() {
+            Some(w) => format!(" Forwards of {} ", folder_name(&w.folder)),
+            None => " Forwards ".to_string(),
+        };
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(6),
+                Constraint::Length(24),
+                Constraint::Length(8),
+                Constraint::Length(8),
+                Constraint::Length(20),
+                Constraint::Min(10),
+            ],
+        )
+        .header(Row::new(["HOST", "TARGET", "BACKEND", "SOURCE", "LABEL", "URL"]).bold())
+        .row_highlight_style(highlight)
+        .block(
+            Block::bordered()
+                .title(title)
+                .border_style(border(Focus::Forwards)),
+        );
+        frame.render_stateful_widget(table, bottom, &mut self.forward_state);
+
+        let line = match &self.prompt {
+            Some(Prompt::Add(input)) => {
+                format!("Forward port (e.g. 3000 or 8080:3000): {input}_")
+            }
+            Some(Prompt::Stop) => "Stop this devcontainer? (y/N)".to_string(),
+            None if self.status.is_empty() => {
+                "q quit  tab switch  a add  d remove  o open  l logs  s stop  r refresh".to_string()
+            }
+            None => self.status.clone(),
+        };
+        frame.render_widget(Paragraph::new(Line::from(line)), status);
+    }
+}
+
+/// The last path component of a workspace folder.
+fn folder_name(folder: &str) -> String {
+    PathBuf::from(folder)
+        .file_name()
+        .map_or(folder.to_string(), |name| {
+            name.to_string_lossy().to_string()
+        })
+}
+
+/// e.g. `3000/tcp`, `db:5432/tcp` or `unix:/run/app.sock`
+fn forward_target(row: &ForwardRow) -> String {
+    if let Some(path) = &row.unix_socket {
+        return format!("unix:{path}");
+    }
+    let port = row.container_port.unwrap_or(0);
+    match &row.service {
+        Some(service) => format!("{service}:{port}/{}", row.protocol.as_str()),
+        None => format!("{port}/{}", row.protocol.as_str()),
+    }
+}
+
+/// Where a browser reaches a TCP forward.
+fn forward_url(row: &ForwardRow) -> Option<String> {
+    (row.protocol == Protocol::Tcp && row.unix_socket.is_none())
+        .then(|| browser::forward_url("http", &row.bind, row.host_port))
+}
+
+/// `dcw port remove` arguments for a forward.
+fn remove_args(row: &ForwardRow) -> Vec<String> {
+    let mut args = vec!["port".to_string(), "remove".to_string()];
+    if row.unix_socket.is_some() {
+        args.extend([row.host_port.to_string(), "--unix".to_string()]);
+        return args;
+    }
+    args.push(row.container_port.unwrap_or(row.host_port).to_string());
+    if row.protocol == Protocol::Udp {
+        args.push("--udp".to_string());
+    }
+    if let Some(service) = &row.service {
+        args.extend(["--service".to_string(), service.clone()]);
+    }
+    args
+}
+
+/// `3000` forwards host port 3000 to container port 3000; `8080:3000`
+/// forwards host port 8080 to container port 3000.
+fn parse_port_spec(input: &str) -> Result<(u16, u16), String> {
+    let parse = |s: &str| {
+        s.parse::<u16>()
+            .ok()
+            .filter(|p| *p > 0)
+            .ok_or(format!("invalid port: {input}"))
+    };
+    match input.split_once(':') {
+        Some((host, container)) => Ok((parse(host)?, parse(container)?)),
+        None => {
+            let port = parse(input)?;
+            Ok((port, port))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::ForwardBackend;
+
+    fn forward(container_port: u16, protocol: Protocol, service: Option<&str>) -> ForwardRow {
+        ForwardRow {
+            name: String::new(),
+            backend: ForwardBackend::Native,
+            host_port: container_port + 1,
+            container_port: Some(container_port),
+            protocol,
+            bind: "127.0.0.1".to_string(),
+            unix_socket: None,
+            source: None,
+            label: None,
+            service: service.map(str::to_string),
+            hostname: None,
+            stats: None,
+        }
+    }
+
+    #[test]
+    fn parse_port_specs() {
+        assert_eq!(parse_port_spec("3000"), Ok((3000, 3000)));
+        assert_eq!(parse_port_spec("8080:3000"), Ok((8080, 3000)));
+        assert!(parse_port_spec("").is_err());
+        assert!(parse_port_spec("0").is_err());
+        assert!(parse_port_spec("8080:").is_err());
+    }
+
+    #[test]
+    fn remove_args_identify_the_forward() {
+        assert_eq!(
+            remove_args(&forward(3000, Protocol::Tcp, None)),
+            ["port", "remove", "3000"]
+        );
+        assert_eq!(
+            remove_args(&forward(5432, Protocol::Udp, Some("db"))),
+            ["port", "remove", "5432", "--udp", "--service", "db"]
+        );
+        let socket = ForwardRow {
+            unix_socket: Some("/run/app.sock".to_string()),
+            container_port: None,
+            ..forward(9000, Protocol::Tcp, None)
+        };
+        assert_eq!(remove_args(&socket), ["port", "remove", "9001", "--unix"]);
+        assert_eq!(forward_url(&socket), None);
+        assert_eq!(
+            forward_url(&forward(3000, Protocol::Tcp, None)).as_deref(),
+            Some("http://127.0.0.1:3001")
+        );
+    }
+}
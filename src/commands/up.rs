@@ -1,12 +1,27 @@
 use anyhow::{bail, Context, Result};
+use serde::Serialize;
 use std::fs;
-use std::path::PathBuf;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
 
-use crate::commands::browser_relay;
+use crate::browser;
+use crate::commands::{browser_relay, daemon, port, watch};
 use crate::config;
-use crate::docker;
+use crate::customizations;
+use crate::display;
+use crate::docker::{self, Execute};
 use crate::forward_ports;
+use crate::forward_state::SavedForward;
+use crate::gpg;
+use crate::hooks::{self, Hook};
+use crate::lifecycle::{self, LifecycleLog, Step};
+use crate::logging::Logged;
+use crate::output::{self, OutputFormat};
+use crate::proxy;
 use crate::settings::Settings;
 use crate::workspace;
 
@@ -17,107 +32,734 @@ pub struct UpArgs {
     pub rebuild: bool,
 
     /// Automatically forward ports from devcontainer.json after start
-    #[arg(long, default_value_t = true, action = clap::ArgAction::Set, num_args = 0..=1, default_missing_value = "true")]
-    pub auto_forward: bool,
+    /// (default: `up.auto_forward` in config.toml, else true)
+    #[arg(long, action = clap::ArgAction::Set, num_args = 0..=1, default_missing_value = "true")]
+    pub auto_forward: Option<bool>,
 
     /// Watch for new listening ports and auto-forward them
     #[arg(long, default_value_t = true, action = clap::ArgAction::Set, num_args = 0..=1, default_missing_value = "true")]
     pub watch: bool,
 
+    /// Use `.devcontainer/<NAME>/devcontainer.json` instead of `.devcontainer/devcontainer.json`
+    #[arg(long, value_name = "NAME")]
+    pub config_name: Option<String>,
+
+    /// Print how long each phase took and save it to timings.json in the
+    /// runtime directory
+    #[arg(long)]
+    pub timings: bool,
+
+    /// Wait until the devcontainer and the other services of its compose
+    /// project report healthy before forwarding ports
+    #[arg(long)]
+    pub wait_healthy: bool,
+
+    /// How long `--wait-healthy` waits, e.g. `90s` or `5m`
+    #[arg(long, value_name = "DURATION", value_parser = watch::parse_delay, default_value = "120s", requires = "wait_healthy")]
+    pub timeout: Duration,
+
+    /// Merge `devcontainer.<PROFILE>.json` on top of devcontainer.json
+    #[arg(long)]
+    pub profile: Option<String>,
+
+    #[command(flatten)]
+    pub flags: UpFlags,
+
     /// Extra arguments passed to `devcontainer up`
     #[arg(last = true)]
     pub extra: Vec<String>,
 }
 
-pub fn run(args: &UpArgs) -> Result<()> {
-    let workspace_folder = workspace::workspace_folder()?;
+/// Image and container options for `devcontainer up`.
+#[derive(clap::Args, Default)]
+pub struct UpFlags {
+    /// Set a Docker build argument (repeatable)
+    #[arg(long, value_name = "KEY=VALUE")]
+    pub build_arg: Vec<String>,
+
+    /// Build the image without using the Docker build cache
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// Build from the Dockerfile even if `customizations.dcw.prebuiltImage`
+    /// is set
+    #[arg(long)]
+    pub no_prebuilt: bool,
+
+    /// Pull newer versions of the base image before building
+    #[arg(long)]
+    pub pull: bool,
+
+    /// GPUs to give the container, as for `docker run --gpus` (e.g. `all`)
+    #[arg(long, value_name = "GPUS")]
+    pub gpus: Option<String>,
+
+    /// JSON file of secrets to set as remoteEnv (default: .devcontainer/secrets.local.json)
+    #[arg(long, value_name = "PATH")]
+    pub secrets_file: Option<PathBuf>,
+
+    /// Forward the host gpg-agent for commit signing (default: `up.gpg_agent`
+    /// in config.toml, else false)
+    #[arg(long, action = clap::ArgAction::Set, num_args = 0..=1, default_missing_value = "true")]
+    pub gpg_agent: Option<bool>,
+
+    /// Share the host's X11/Wayland display for GUI apps
+    #[arg(long)]
+    pub x11: bool,
+
+    /// Map `host.docker.internal` to the host, for Linux engines that don't
+    /// (default: `up.host_gateway` in config.toml, else false)
+    #[arg(long, action = clap::ArgAction::Set, num_args = 0..=1, default_missing_value = "true")]
+    pub host_gateway: Option<bool>,
+
+    /// Snapshot image to create the container from, for `dcw snapshot restore`
+    #[arg(skip)]
+    pub snapshot: Option<String>,
+}
+
+impl UpFlags {
+    fn gpg_agent(&self) -> bool {
+        self.gpg_agent.unwrap_or(Settings::get().up.gpg_agent)
+    }
 
+    fn host_gateway(&self) -> bool {
+        self.host_gateway.unwrap_or(Settings::get().up.host_gateway)
+    }
+}
+
+pub fn run(args: &UpArgs, format: OutputFormat) -> Result<()> {
+    let mut timings = Timings::start();
+    let workspace_folder = workspace::workspace_folder()?;
     let workspace_root = PathBuf::from(&workspace_folder);
+
+    workspace::record_config_path(config::config_flag())?;
+    if let Some(name) = &args.config_name {
+        config::check_config_name(&workspace_root, name)?;
+    }
+    workspace::record_config_name(args.config_name.as_deref())?;
+    if let Some(profile) = &args.profile {
+        config::check_profile(&workspace_root, profile)?;
+    }
+    workspace::record_profile(args.profile.as_deref())?;
+
+    hooks::run(Hook::PreUp, &workspace_root)?;
+
+    let adopted = adopt_legacy_workspace().unwrap_or_else(|e| {
+        warn!("failed to adopt port forwards from the old workspace ID: {e}");
+        Vec::new()
+    });
+
+    // Pull the sidecar image while the container starts
+    docker::prefetch_sidecar_image();
+
+    let phase = Instant::now();
+    let steps = devcontainer_up(&workspace_folder, args.rebuild, &args.flags, &args.extra)?;
+    timings.record("devcontainer up", phase.elapsed());
+    for (name, took) in steps {
+        timings.record_within("devcontainer up", &name, took);
+    }
+    if docker::dry_run() && docker::find_devcontainer(&workspace_folder)?.is_none() {
+        info!("The devcontainer isn't running, so its port forwards can't be worked out in a dry run.");
+        return hooks::run(Hook::PostUp, &workspace_root);
+    }
+
+    info!("Devcontainer is running.");
+    if let Err(e) = workspace::record_workspace_folder() {
+        warn!("failed to record workspace folder: {e}");
+    }
+
+    if args.wait_healthy {
+        let phase = Instant::now();
+        wait_healthy(&workspace_folder, args.timeout)?;
+        timings.record("wait healthy", phase.elapsed());
+    }
+
+    if args.flags.gpg_agent() && docker::dry_run() {
+        docker::print_dry_run("set up GPG agent forwarding in the devcontainer");
+    } else if args.flags.gpg_agent() {
+        match gpg::setup_container() {
+            Ok(()) => info!("GPG agent forwarded."),
+            Err(e) => warn!("failed to set up GPG agent forwarding: {e}"),
+        }
+    }
+
+    if args.auto_forward.unwrap_or(Settings::get().up.auto_forward) {
+        let phase = Instant::now();
+        auto_forward_ports(&workspace_folder)?;
+        timings.record("auto-forward", phase.elapsed());
+    }
+
+    let ws_id = workspace::workspace_id()?;
+    if !adopted.is_empty() {
+        if let Err(e) = port::restore_forwards(&ws_id, &workspace_folder, adopted) {
+            warn!("failed to recreate adopted port forwards: {e}");
+        }
+    }
+
+    // Forwards added with `dcw port add` before the last `dcw down`
+    if let Err(e) = port::restore_saved(&ws_id, &workspace_folder) {
+        warn!("failed to restore saved port forwards: {e}");
+    }
+
+    if args.watch {
+        let phase = Instant::now();
+        spawn_watcher()?;
+        timings.record("watcher spawn", phase.elapsed());
+    }
+
+    // Start browser relay if not already running (non-fatal)
+    if Settings::get().relay.browser.enabled && !docker::dry_run() {
+        match browser_relay::ensure_relay_running() {
+            Ok(_) => info!("Browser relay ready."),
+            Err(e) => warn!("failed to start browser relay: {e}"),
+        }
+    }
+
+    hooks::run(Hook::PostUp, &workspace_root)?;
+
+    if args.timings {
+        timings.finish();
+        if docker::dry_run() {
+            // Nothing ran, so there is nothing to compare with later
+        } else if let Err(e) = timings.save() {
+            warn!("failed to save timings: {e}");
+        }
+        match format {
+            OutputFormat::Json => output::print_json(&timings)?,
+            OutputFormat::Text => timings.print(),
+        }
+    }
+    Ok(())
+}
+
+/// How long each phase of `dcw up` took, for `--timings`.
+#[derive(Serialize)]
+struct Timings {
+    /// Unix time `dcw up` started at
+    started_at: u64,
+    total_seconds: f64,
+    phases: Vec<Phase>,
+    #[serde(skip)]
+    started: Instant,
+}
+
+#[derive(Serialize)]
+struct Phase {
+    name: String,
+    seconds: f64,
+    /// The phase this one is part of, e.g. `devcontainer up` for the image build
+    #[serde(skip_serializing_if = "Option::is_none")]
+    within: Option<&'static str>,
+}
+
+impl Timings {
+    fn start() -> Self {
+        Timings {
+            started_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            total_seconds: 0.0,
+            phases: Vec::new(),
+            started: Instant::now(),
+        }
+    }
+
+    fn record(&mut self, name: &str, took: Duration) {
+        self.phases.push(Phase {
+            name: name.to_string(),
+            seconds: took.as_secs_f64(),
+            within: None,
+        });
+    }
+
+    fn record_within(&mut self, parent: &'static str, name: &str, took: Duration) {
+        self.phases.push(Phase {
+            name: name.to_string(),
+            seconds: took.as_secs_f64(),
+            within: Some(parent),
+        });
+    }
+
+    fn finish(&mut self) {
+        self.total_seconds = self.started.elapsed().as_secs_f64();
+    }
+
+    fn save(&self) -> Result<()> {
+        let path = workspace::timings_file()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context("failed to create runtime directory")?;
+        }
+        fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    fn print(&self) {
+        println!("{:<24} {:>8}", "PHASE", "TIME");
+        for phase in &self.phases {
+            let name = match phase.within {
+                Some(_) => format!("  {}", phase.name),
+                None => phase.name.clone(),
+            };
+            println!("{name:<24} {:>7.1}s", phase.seconds);
+        }
+        println!("{:<24} {:>7.1}s", "total", self.total_seconds);
+    }
+}
+
+/// Take over what an older dcw left under the workspace's legacy ID (see
+/// [`workspace::legacy_workspace_id`]): its watcher and proxy daemon are
+/// stopped, its sidecars removed, and its runtime directory moved to the
+/// current ID. Returns the forwards to recreate under the new ID.
+fn adopt_legacy_workspace() -> Result<Vec<SavedForward>> {
+    let Some(legacy_id) = workspace::legacy_workspace_id()? else {
+        return Ok(Vec::new());
+    };
+    let legacy_dir = workspace::shared_runtime_dir().join(&legacy_id);
+    let sidecars = docker::list_port_forwards(&legacy_id)?;
+    if sidecars.is_empty() && !legacy_dir.exists() {
+        return Ok(Vec::new());
+    }
+    if docker::dry_run() {
+        docker::print_dry_run(&format!(
+            "adopt port forwards from old workspace ID {legacy_id}"
+        ));
+        return Ok(Vec::new());
+    }
+    info!("Adopting port forwards from old workspace ID {legacy_id}...");
+
+    // Watch forwards are recreated by the new watcher
+    let mut adopted: Vec<SavedForward> = sidecars
+        .iter()
+        .filter(|fwd| fwd.source != "watch")
+        .filter_map(SavedForward::from_sidecar)
+        .collect();
+    docker::remove_all_port_forwards(&legacy_id)?;
+
+    stop_watcher_if_running(&legacy_dir.join("watch.pid"));
+    let proxy_pid = legacy_dir.join("proxy.pid");
+    if let Some(pid) = fs::read_to_string(&proxy_pid)
+        .ok()
+        .and_then(|s| s.trim().parse().ok())
+    {
+        crate::process::kill_dcw_process(pid);
+    }
+    let _ = fs::remove_file(proxy_pid);
+    adopted.extend(
+        proxy::forwards_in(&legacy_dir)
+            .iter()
+            .filter(|fwd| fwd.source.as_deref() != Some("watch"))
+            .map(SavedForward::from_native),
+    );
+    let _ = fs::remove_file(legacy_dir.join("proxy.json"));
+
+    let runtime_dir = workspace::runtime_dir()?;
+    if legacy_dir.exists() {
+        if runtime_dir.exists() {
+            let _ = fs::remove_dir_all(&legacy_dir);
+        } else {
+            fs::rename(&legacy_dir, &runtime_dir).context("failed to move runtime directory")?;
+        }
+    }
+    Ok(adopted)
+}
+
+/// Run `devcontainer up` for the workspace, applying the merged config,
+/// `dcw up` flags and docker path settings. Returns how long the image build
+/// and lifecycle commands took, when the CLI's log reports them.
+pub fn devcontainer_up(
+    workspace_folder: &str,
+    rebuild: bool,
+    flags: &UpFlags,
+    extra: &[String],
+) -> Result<Vec<(String, Duration)>> {
+    let workspace_root = PathBuf::from(workspace_folder);
+
+    // Surface config problems early; the devcontainer CLI's errors are often obscure
+    if let Ok(issues) = config::validate_workspace(&workspace_root) {
+        for issue in &issues {
+            warn!("{issue}");
+        }
+    }
+
     let merged_config = config::resolve_config(&workspace_root)?;
 
     let mut cmd_args = vec![
         "up".to_string(),
         "--workspace-folder".to_string(),
-        workspace_folder.clone(),
+        workspace_folder.to_string(),
     ];
 
-    if let Some(config_path) = &merged_config {
+    let mut config_path = merged_config.clone();
+    let build_args = parse_build_args(&flags.build_arg)?;
+    let build = !build_args.is_empty() || flags.pull;
+    let secrets = load_secrets(&workspace_root, flags.secrets_file.as_deref())?;
+    let gpg_socket = if flags.gpg_agent() {
+        Some(gpg::host_socket()?)
+    } else {
+        None
+    };
+    let display = if flags.x11 {
+        Some(display::forwarding()?)
+    } else {
+        None
+    };
+    // Building with other arguments or without cache means building from source
+    let prebuilt = if build || flags.no_cache || flags.no_prebuilt || flags.snapshot.is_some() {
+        None
+    } else {
+        prebuilt_image(&workspace_root)
+    };
+    if build
+        || prebuilt.is_some()
+        || flags.snapshot.is_some()
+        || flags.gpus.is_some()
+        || !secrets.is_empty()
+        || gpg_socket.is_some()
+        || display.is_some()
+        || flags.host_gateway()
+    {
+        let gateway_compose = workspace::runtime_dir()?.join("compose.host-gateway.yml");
+        let mut gateway = false;
+        let mut gateway_service = None;
+        let mut built = false;
+        let mut gpus = false;
+        let mut gpg_mount = false;
+        let mut display_mounts = false;
+        let mut used_prebuilt = false;
+        let mut used_snapshot = false;
+        let path = config::write_override_config(&workspace_root, |config| {
+            built = build && config::apply_build_overrides(config, &build_args, flags.pull);
+            used_prebuilt = prebuilt
+                .as_deref()
+                .is_some_and(|image| config::apply_prebuilt_image(config, image));
+            used_snapshot = flags
+                .snapshot
+                .as_deref()
+                .is_some_and(|image| config::apply_snapshot_image(config, image));
+            gpus = flags
+                .gpus
+                .as_ref()
+                .is_some_and(|g| config::apply_gpus(config, g));
+            let env = config::apply_secrets(config, &secrets);
+            gpg_mount = gpg_socket
+                .as_ref()
+                .is_some_and(|s| config::apply_mount(config, &gpg::mount(s)));
+            if let Some(display) = &display {
+                display_mounts = display
+                    .mounts
+                    .iter()
+                    .all(|m| config::apply_mount(config, m));
+                if display_mounts {
+                    config::apply_container_env(config, &display.env);
+                }
+            }
+            if flags.host_gateway() {
+                gateway = config::apply_host_gateway(config, &gateway_compose);
+                gateway_service = config["service"].as_str().map(str::to_string);
+            }
+            built
+                || used_prebuilt
+                || used_snapshot
+                || gpus
+                || env
+                || gpg_mount
+                || display_mounts
+                || gateway
+        })?;
+        if let Some(service) = gateway_service.filter(|_| gateway) {
+            if docker::dry_run() {
+                docker::print_dry_run(&format!("write {}", gateway_compose.display()));
+            } else {
+                fs::write(&gateway_compose, config::host_gateway_compose(&service))
+                    .context("failed to write compose override")?;
+            }
+        }
+        if let Some(path) = path {
+            // The devcontainer CLI finds the container by these labels;
+            // point them at the config other commands use, not this one
+            let label_config = match &merged_config {
+                Some(path) => path.clone(),
+                None => config::config_file(&workspace_root)?,
+            };
+            cmd_args.extend([
+                "--id-label".to_string(),
+                format!("devcontainer.local_folder={workspace_folder}"),
+                "--id-label".to_string(),
+                format!("devcontainer.config_file={}", label_config.display()),
+            ]);
+            config_path = Some(path);
+        }
+        if !built {
+            if !build_args.is_empty() {
+                warn!("ignoring --build-arg: the config does not build from a Dockerfile");
+            }
+            if flags.pull {
+                pull_image(&workspace_root)?;
+            }
+        }
+        if flags.snapshot.is_some() && !used_snapshot {
+            bail!("snapshots can't be restored for Docker Compose configs");
+        }
+        if let Some(image) = &prebuilt {
+            if used_prebuilt {
+                info!("Using prebuilt image {image}.");
+            } else {
+                warn!("ignoring prebuiltImage: the config does not build from a Dockerfile");
+            }
+        }
+        if flags.gpus.is_some() && !gpus {
+            warn!("ignoring --gpus: set `deploy.resources` in the compose file instead");
+        }
+        if gpg_socket.is_some() && !gpg_mount {
+            warn!("not forwarding the GPG agent: mount the socket in the compose file instead");
+        }
+        if display.is_some() && !display_mounts {
+            warn!("ignoring --x11: mount the display sockets in the compose file instead");
+        }
+    }
+
+    if let Some(config_path) = &config_path {
         cmd_args.push("--config".to_string());
         cmd_args.push(config_path.to_string_lossy().to_string());
     }
 
-    if args.rebuild {
+    if rebuild {
         cmd_args.push("--remove-existing-container".to_string());
     }
 
-    let settings = Settings::get();
-    if settings.docker.path != "docker" {
-        cmd_args.push("--docker-path".to_string());
-        cmd_args.push(settings.docker.path.clone());
+    if flags.no_cache {
+        cmd_args.push("--build-no-cache".to_string());
     }
-    if settings.docker.compose_path != "docker-compose" {
-        cmd_args.push("--docker-compose-path".to_string());
-        cmd_args.push(settings.docker.compose_path.clone());
+
+    cmd_args.extend(docker::devcontainer_cli_docker_args());
+
+    // Follow lifecycle commands in the JSON log, unless the caller picked a format
+    let json_log = !extra.iter().any(|arg| arg.starts_with("--log-format"));
+    if json_log {
+        cmd_args.extend(["--log-format".to_string(), "json".to_string()]);
     }
 
-    cmd_args.extend(args.extra.clone());
+    cmd_args.extend(extra.iter().cloned());
 
-    println!("Starting devcontainer...");
+    info!("Starting devcontainer...");
+    if json_log && !docker::dry_run() {
+        return run_with_lifecycle_log(&cmd_args);
+    }
     let status = Command::new("devcontainer")
         .args(&cmd_args)
-        .status()
+        .execute_status()
         .context("failed to run devcontainer up — is the devcontainer CLI installed?")?;
 
     if !status.success() {
         bail!("devcontainer up exited with status {status}");
     }
 
-    println!("Devcontainer is running.");
+    Ok(Vec::new())
+}
 
-    if args.auto_forward {
-        auto_forward_ports(&workspace_folder)?;
+/// Run `devcontainer up` with `--log-format json`, passing its output through
+/// and marking where each lifecycle command (`postCreateCommand`, ...) starts
+/// and ends. If one fails, the error carries its output.
+fn run_with_lifecycle_log(args: &[String]) -> Result<Vec<(String, Duration)>> {
+    let mut child = Command::new("devcontainer")
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .logged()
+        .spawn()
+        .context("failed to run devcontainer up — is the devcontainer CLI installed?")?;
+    // The result JSON on stdout is small; the log streams on stderr
+    let mut stdout = child
+        .stdout
+        .take()
+        .context("devcontainer up has no stdout")?;
+    let result = thread::spawn(move || {
+        let mut result = String::new();
+        let _ = stdout.read_to_string(&mut result);
+        result
+    });
+    let stderr = child
+        .stderr
+        .take()
+        .context("devcontainer up has no stderr")?;
+
+    let mut log = LifecycleLog::default();
+    for line in BufReader::new(stderr).lines().map_while(|l| l.ok()) {
+        match log.handle(&line) {
+            Some(Step::Output(text)) => {
+                let mut out = std::io::stdout().lock();
+                let _ = out.write_all(text.as_bytes());
+                let _ = out.flush();
+            }
+            Some(Step::HookStarted { hook, command }) => match command {
+                Some(command) => info!("==> {hook}: {command}"),
+                None => info!("==> {hook}"),
+            },
+            Some(Step::HookFinished {
+                hook,
+                success,
+                elapsed,
+            }) => {
+                let elapsed = elapsed.as_secs_f64();
+                if success {
+                    info!("<== {hook} finished in {elapsed:.1}s");
+                } else {
+                    warn!("{hook} failed after {elapsed:.1}s");
+                }
+            }
+            None => {}
+        }
     }
 
-    if args.watch {
-        spawn_watcher()?;
+    let status = child.wait().context("failed to wait for devcontainer up")?;
+    let result = result.join().unwrap_or_default();
+    if status.success() {
+        return Ok(log.timings());
+    }
+    if let Some(failed) = log.failed() {
+        let command = failed
+            .command
+            .map(|c| format!(" `{c}`"))
+            .unwrap_or_default();
+        bail!(
+            "{}{command} failed:\n{}",
+            failed.hook,
+            failed.output.trim_end()
+        );
     }
+    match lifecycle::error_message(&result) {
+        Some(message) => bail!("devcontainer up failed: {message}"),
+        None => bail!("devcontainer up exited with status {status}"),
+    }
+}
 
-    // Start browser relay if not already running (non-fatal)
-    if settings.relay.browser.enabled {
-        match browser_relay::ensure_relay_running() {
-            Ok(_) => println!("Browser relay ready."),
-            Err(e) => eprintln!("Warning: failed to start browser relay: {e}"),
-        }
+/// `customizations.dcw.prebuiltImage`, if it has been pushed yet.
+fn prebuilt_image(workspace_root: &Path) -> Option<String> {
+    let image = customizations::load_or_default(workspace_root).prebuilt_image?;
+    if docker::image_available(&image) {
+        Some(image)
+    } else {
+        info!("Prebuilt image {image} not found, building from the Dockerfile.");
+        None
     }
+}
 
-    Ok(())
+/// Load `--secrets-file`, or `secrets.local.json` next to devcontainer.json if
+/// it exists.
+fn load_secrets(
+    workspace_root: &Path,
+    secrets_file: Option<&Path>,
+) -> Result<Vec<(String, String)>> {
+    let default = config::config_dir(workspace_root)?.join(config::SECRETS_FILE);
+    let path = match secrets_file {
+        Some(path) => path,
+        None if default.exists() => &default,
+        None => return Ok(Vec::new()),
+    };
+    let secrets = config::load_secrets(path)?;
+    info!("Loaded {} secret(s) from {}", secrets.len(), path.display());
+    Ok(secrets)
 }
 
-/// Spawn `dcw port watch` as a detached background process.
-fn spawn_watcher() -> Result<()> {
-    let exe = std::env::current_exe().context("failed to get current executable path")?;
-    let pid_file = workspace::watcher_pid_file()?;
+/// Parse `--build-arg` values of the form `KEY=VALUE`.
+fn parse_build_args(args: &[String]) -> Result<Vec<(String, String)>> {
+    args.iter()
+        .map(|arg| match arg.split_once('=') {
+            Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+            _ => bail!("invalid --build-arg {arg:?}: expected KEY=VALUE"),
+        })
+        .collect()
+}
 
-    // Kill any existing watcher first
-    stop_watcher_if_running(&pid_file);
+/// How often `--wait-healthy` checks the containers.
+const HEALTH_POLL_INTERVAL: Duration = Duration::from_secs(2);
 
-    if let Some(parent) = pid_file.parent() {
-        fs::create_dir_all(parent).context("failed to create runtime directory")?;
+/// `--wait-healthy`: wait until the devcontainer and the other running
+/// services of its compose project are healthy. Containers without a
+/// healthcheck only need to be running.
+fn wait_healthy(workspace_folder: &str, timeout: Duration) -> Result<()> {
+    let container_id = docker::find_devcontainer(workspace_folder)?
+        .context("devcontainer not found after start")?;
+    let mut containers = match docker::compose_project(&container_id)? {
+        Some(project) => docker::compose_containers(&project)?,
+        None => Vec::new(),
+    };
+    if !containers.contains(&container_id) {
+        containers.insert(0, container_id);
     }
 
-    let child = Command::new(exe)
-        .args(["port", "watch"])
-        .stdin(Stdio::null())
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .spawn()
-        .context("failed to spawn port watcher")?;
+    info!("Waiting for containers to become healthy...");
+    let deadline = Instant::now() + timeout;
+    loop {
+        let health = containers
+            .iter()
+            .map(|id| docker::container_health(id))
+            .collect::<Result<Vec<_>>>()?;
+        let pending = unhealthy(&health);
+        if pending.is_empty() {
+            info!("All containers are healthy.");
+            return Ok(());
+        }
+        if Instant::now() >= deadline {
+            bail!(
+                "containers not healthy after {}s: {}",
+                timeout.as_secs(),
+                pending.join(", ")
+            );
+        }
+        thread::sleep(HEALTH_POLL_INTERVAL);
+    }
+}
+
+/// Containers that aren't healthy yet, as e.g. `db (starting)`.
+fn unhealthy(health: &[docker::ContainerHealth]) -> Vec<String> {
+    health
+        .iter()
+        .filter_map(|c| {
+            let problem = if !c.running {
+                "not running"
+            } else if c.health.is_empty() || c.health == "healthy" {
+                return None;
+            } else {
+                &c.health
+            };
+            Some(format!("{} ({problem})", c.name))
+        })
+        .collect()
+}
+
+/// `--pull` for image-based configs: pull the image before starting.
+fn pull_image(workspace_root: &Path) -> Result<()> {
+    let image = config::load_effective_config(workspace_root)?
+        .and_then(|c| c["image"].as_str().map(str::to_string));
+    let Some(image) = image else {
+        warn!("ignoring --pull: the config neither builds nor names an image");
+        return Ok(());
+    };
+    info!("Pulling {image}...");
+    let status = Command::new(docker::docker_path())
+        .args(["pull", &image])
+        .execute_status()
+        .context("failed to run docker pull")?;
+    if !status.success() {
+        bail!("docker pull {image} exited with status {status}");
+    }
+    Ok(())
+}
 
-    let pid = child.id();
-    fs::write(&pid_file, pid.to_string())
-        .context("failed to write watcher PID file")?;
+/// Have `dcw daemon` watch this workspace's ports, starting the daemon if
+/// needed. The daemon restarts the watcher if it crashes.
+pub fn spawn_watcher() -> Result<()> {
+    if docker::dry_run() {
+        docker::print_dry_run("watch ports with dcw daemon");
+        return Ok(());
+    }
+    // Watchers from before the daemon ran detached, with a PID file
+    stop_watcher_if_running(&workspace::runtime_dir()?.join("watch.pid"));
 
-    println!("Port watcher started (pid {pid}).");
+    let pid = daemon::watch(&workspace::workspace_folder()?, docker::container_flag())?;
+    info!("Port watcher started (pid {pid}).");
     Ok(())
 }
 
@@ -125,7 +767,7 @@ fn stop_watcher_if_running(pid_file: &PathBuf) {
     if let Ok(contents) = fs::read_to_string(pid_file) {
         if let Ok(pid) = contents.trim().parse::<i32>() {
             if !crate::process::kill_dcw_process(pid) {
-                eprintln!("Warning: stale PID {pid} in watcher PID file (process not found or not dcw)");
+                warn!("stale PID {pid} in watcher PID file (process not found or not dcw)");
             }
         }
         let _ = fs::remove_file(pid_file);
@@ -135,10 +777,11 @@ fn stop_watcher_if_running(pid_file: &PathBuf) {
 fn auto_forward_ports(workspace_folder: &str) -> Result<()> {
     let ws_id = workspace::workspace_id()?;
     let root = PathBuf::from(workspace_folder);
-    let ports = forward_ports::load_forward_ports(&root)?;
+    let ports = forward_ports::load_port_mappings(&root)?;
+    let service_ports = forward_ports::load_service_ports(&root)?;
 
-    if ports.is_empty() {
-        println!("No forwardPorts configured.");
+    if ports.is_empty() && service_ports.is_empty() {
+        info!("No forwardPorts or appPort configured.");
         return Ok(());
     }
 
@@ -147,14 +790,65 @@ fn auto_forward_ports(workspace_folder: &str) -> Result<()> {
 
     let network = docker::get_container_network(&container_id)?;
 
-    println!("Auto-forwarding ports: {:?}", ports);
-    for port in &ports {
-        if let Err(e) =
-            docker::start_port_forward(&ws_id, &container_id, *port, *port, &network, true, None)
-        {
-            eprintln!("Warning: failed to forward port {port}: {e}");
-        } else {
-            println!("  Forwarded port {port} -> {port}");
+    let settings = customizations::load_or_default(&root);
+    let backend = Settings::get().forward.backend;
+    let attributes = forward_ports::load_ports_attributes(&root)?;
+    let ipv6_only = watch::ipv6_only_listeners(&container_id, &network, false);
+
+    let container_ports: Vec<u16> = ports.iter().map(|m| m.container_port).collect();
+    info!("Auto-forwarding ports: {:?}", container_ports);
+    for mapping in &ports {
+        let port = mapping.container_port;
+        if attributes.is_ignored(port) {
+            info!("  Skipping port {port} (onAutoForward: ignore)");
+            continue;
+        }
+        let spec = docker::ForwardSpec {
+            bind_address: settings.bind_address.as_deref(),
+            fallback: settings.host_port_fallback && attributes.allows_fallback(port),
+            label: attributes.label(port),
+            ipv6: ipv6_only.contains(&(port, docker::Protocol::Tcp)),
+            ..docker::ForwardSpec::new(mapping.host_port, port)
+        };
+        match proxy::start_forward(&ws_id, &container_id, &network, &spec, backend) {
+            Ok(host_port) => {
+                info!("  Forwarded port {host_port} -> {port}");
+                if let Some(attrs) = attributes
+                    .get(port)
+                    .filter(|a| a.on_auto_forward.opens_browser())
+                {
+                    let bind = spec.bind_address.unwrap_or(docker::DEFAULT_BIND_ADDRESS);
+                    let url = browser::forward_url(attrs.scheme(), bind, host_port);
+                    if let Err(e) = browser::open_url(&url) {
+                        warn!("failed to open {url}: {e}");
+                    }
+                }
+            }
+            Err(e) => warn!("failed to forward port {port}: {e}"),
+        }
+    }
+
+    for entry in &service_ports {
+        let name = format!("{}:{}", entry.service, entry.port);
+        let service_id = match docker::find_compose_service(&container_id, &entry.service) {
+            Ok(id) => id,
+            Err(e) => {
+                warn!("failed to forward port {name}: {e}");
+                continue;
+            }
+        };
+        let spec = docker::ForwardSpec {
+            bind_address: settings.bind_address.as_deref(),
+            fallback: settings.host_port_fallback,
+            service: Some(&entry.service),
+            ..docker::ForwardSpec::new(entry.port, entry.port)
+        };
+        let result = docker::get_container_network(&service_id).and_then(|network| {
+            proxy::start_forward(&ws_id, &service_id, &network, &spec, backend)
+        });
+        match result {
+            Ok(host_port) => info!("  Forwarded port {host_port} -> {name}"),
+            Err(e) => warn!("failed to forward port {name}: {e}"),
         }
     }
 
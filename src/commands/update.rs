@@ -1,110 +1,176 @@
 use std::env;
 use std::fs;
+use std::io::{IsTerminal, Read};
 use std::os::unix::fs::PermissionsExt;
-use std::process::Command;
+use std::path::Path;
 
 use anyhow::{bail, Context, Result};
+use flate2::read::GzDecoder;
+use reqwest::blocking::Client;
+use sha2::{Digest, Sha256};
+use tracing::{info, warn, Level};
+
+use crate::version_check;
 
 const REPO: &str = "hisamekms/dcw";
-const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Exit status of `dcw update --check` when a newer version exists.
+const UPDATE_AVAILABLE_EXIT_CODE: i32 = 10;
+
+/// Which releases `dcw update` considers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Channel {
+    /// The latest full release
+    #[default]
+    Stable,
+    /// The newest release, including pre-releases
+    Prerelease,
+}
 
 #[derive(clap::Args)]
 pub struct UpdateArgs {
     /// Install a specific version (e.g. v0.2.0)
-    #[arg(long)]
+    #[arg(long, conflicts_with = "channel")]
     pub version: Option<String>,
 
-    /// Update even if already on the latest version
+    /// Release channel to update from
+    #[arg(long, value_enum, default_value_t = Channel::Stable)]
+    pub channel: Channel,
+
+    /// Only report whether a newer version exists; exits with status 10 if
+    /// so and 0 if up to date
+    #[arg(long, conflicts_with_all = ["version", "force"])]
+    pub check: bool,
+
+    /// Install the latest version even if it is the current one, or older
+    /// (a downgrade)
     #[arg(long)]
     pub force: bool,
+
+    /// Install without checking the release's SHA256SUMS (for old releases
+    /// published without one)
+    #[arg(long)]
+    pub no_verify: bool,
 }
 
 pub fn run(args: &UpdateArgs) -> Result<()> {
     let current = CURRENT_VERSION.trim_start_matches('v');
+    let client = http_client()?;
 
     let tag = match &args.version {
         Some(v) => {
             let v = v.strip_prefix('v').unwrap_or(v);
             format!("v{v}")
         }
-        None => fetch_latest_tag()?,
+        None => {
+            let tag = fetch_latest_tag(&client, args.channel)?;
+            if args.channel == Channel::Stable {
+                let _ = version_check::record_latest(&tag);
+            }
+            tag
+        }
     };
 
     let latest = tag.trim_start_matches('v');
 
-    if latest == current && !args.force {
-        println!("Already up to date (v{current}).");
+    if args.check {
+        if is_newer(latest, current) {
+            info!("Update available: v{current} → {tag} (run `dcw update`).");
+            std::process::exit(UPDATE_AVAILABLE_EXIT_CODE);
+        }
+        info!("Already up to date (v{current}).");
+        return Ok(());
+    }
+
+    // Going back to an older release takes --force or an explicit --version,
+    // e.g. the stable channel's latest after installing a prerelease
+    if !is_newer(latest, current) && !args.force && args.version.is_none() {
+        info!("Already up to date (v{current}).");
         return Ok(());
     }
 
     if latest == current {
-        println!("Reinstalling v{current}...");
+        info!("Reinstalling v{current}...");
+    } else if is_newer(latest, current) {
+        info!("Updating v{current} → {tag}...");
     } else {
-        println!("Updating v{current} → {tag}...");
+        info!("Downgrading v{current} → {tag}...");
     }
 
     let target = detect_target()?;
     let asset = format!("dcw-{tag}-{target}.tar.gz");
-    let url = format!("https://github.com/{REPO}/releases/download/{tag}/{asset}");
+    let release_url = format!("https://github.com/{REPO}/releases/download/{tag}");
 
-    let tmpdir = tempdir()?;
-    let tarball = format!("{tmpdir}/{asset}");
+    let tarball = download(&client, &format!("{release_url}/{asset}"), &asset)?;
 
-    download(&url, &tarball)?;
-
-    let status = Command::new("tar")
-        .args(["xzf", &tarball, "-C", &tmpdir])
-        .status()
-        .context("failed to extract tarball")?;
-    if !status.success() {
-        bail!("tar extraction failed");
+    if args.no_verify {
+        warn!("skipping checksum verification of {asset}");
+    } else {
+        let sums = fetch_text(&client, &format!("{release_url}/SHA256SUMS"))
+            .context("failed to download SHA256SUMS (use --no-verify for releases without one)")?;
+        verify_checksum(&tarball, &asset, &sums)?;
     }
 
-    let new_binary = format!("{tmpdir}/dcw");
+    let new_binary = extract_binary(&tarball)?;
     let current_exe =
         env::current_exe().context("failed to determine current executable path")?;
 
-    // Write to a temporary path then rename to atomically replace the binary.
-    // rename operates on directory entries (not inodes), so it avoids ETXTBSY
-    // errors that occur when overwriting a running executable on Linux.
-    let tmp_dest = current_exe.with_extension("tmp");
-    let replace_result = (|| -> Result<()> {
-        fs::copy(&new_binary, &tmp_dest)
-            .context("failed to write new binary to temporary path")?;
-        fs::set_permissions(&tmp_dest, fs::Permissions::from_mode(0o755))?;
-        fs::rename(&tmp_dest, &current_exe)
-            .context("failed to replace binary — try with appropriate permissions")?;
-        Ok(())
-    })();
-    if replace_result.is_err() {
-        let _ = fs::remove_file(&tmp_dest);
-    }
-    replace_result?;
-
-    let _ = fs::remove_dir_all(&tmpdir);
-
-    println!("Updated to {tag}.");
+    // Write to a temporary file next to the binary then rename it over the
+    // binary. rename operates on directory entries (not inodes), so it
+    // avoids ETXTBSY errors that occur when overwriting a running
+    // executable on Linux.
+    let dir = current_exe.parent().unwrap_or(Path::new("."));
+    let mut tmp = tempfile::Builder::new()
+        .prefix(".dcw-update")
+        .tempfile_in(dir)
+        .context("failed to write new binary to temporary path")?;
+    std::io::Write::write_all(&mut tmp, &new_binary)
+        .context("failed to write new binary to temporary path")?;
+    fs::set_permissions(tmp.path(), fs::Permissions::from_mode(0o755))?;
+    tmp.persist(&current_exe)
+        .context("failed to replace binary — try with appropriate permissions")?;
+
+    info!("Updated to {tag}.");
     Ok(())
 }
 
-fn fetch_latest_tag() -> Result<String> {
-    let output = Command::new("curl")
-        .args([
-            "-fsSL",
-            &format!("https://api.github.com/repos/{REPO}/releases/latest"),
-        ])
-        .output()
-        .context("failed to run curl — is it installed?")?;
+/// HTTP client for GitHub. Proxies are taken from `HTTPS_PROXY` and friends.
+fn http_client() -> Result<Client> {
+    Client::builder()
+        .user_agent(format!("dcw/{CURRENT_VERSION}"))
+        .build()
+        .context("failed to create HTTP client")
+}
 
-    if !output.status.success() {
-        bail!(
-            "failed to fetch latest release: {}",
-            String::from_utf8_lossy(&output.stderr)
-        );
+fn fetch_latest_tag(client: &Client, channel: Channel) -> Result<String> {
+    match channel {
+        Channel::Stable => {
+            let body = fetch_text(
+                client,
+                &format!("https://api.github.com/repos/{REPO}/releases/latest"),
+            )
+            .context("failed to fetch latest release")?;
+            parse_tag_from_response(&body)
+        }
+        Channel::Prerelease => {
+            let body = fetch_text(
+                client,
+                &format!("https://api.github.com/repos/{REPO}/releases?per_page=20"),
+            )
+            .context("failed to fetch releases")?;
+            parse_newest_tag_from_list(&body)
+        }
     }
+}
 
-    let body = String::from_utf8_lossy(&output.stdout);
-    parse_tag_from_response(&body)
+fn fetch_text(client: &Client, url: &str) -> Result<String> {
+    client
+        .get(url)
+        .send()
+        .and_then(|r| r.error_for_status())
+        .and_then(|r| r.text())
+        .with_context(|| format!("request failed: {url}"))
 }
 
 /// Extract `tag_name` from a GitHub API JSON response body.
@@ -118,52 +184,152 @@ fn parse_tag_from_response(body: &str) -> Result<String> {
     Ok(tag)
 }
 
-fn detect_target() -> Result<String> {
-    let os = cmd_output("uname", &["-s"])?;
-    let arch = cmd_output("uname", &["-m"])?;
-    let target = match (os.as_str(), arch.as_str()) {
-        ("Linux", "x86_64") => "x86_64-unknown-linux-gnu",
-        ("Linux", "aarch64") => "aarch64-unknown-linux-gnu",
-        ("Darwin", "arm64") => "aarch64-apple-darwin",
-        _ => bail!("unsupported platform: {os}/{arch}"),
-    };
-    Ok(target.to_string())
+/// The newest published (non-draft) release in a GitHub API release list,
+/// which is ordered newest first.
+fn parse_newest_tag_from_list(body: &str) -> Result<String> {
+    let json: serde_json::Value =
+        serde_json::from_str(body).context("failed to parse GitHub API response as JSON")?;
+    json.as_array()
+        .context("expected a list of releases in GitHub API response")?
+        .iter()
+        .filter(|release| !release["draft"].as_bool().unwrap_or(false))
+        .find_map(|release| release["tag_name"].as_str())
+        .map(str::to_string)
+        .context("no published releases found")
 }
 
-fn download(url: &str, dest: &str) -> Result<()> {
-    let status = Command::new("curl")
-        .args(["-fsSL", url, "-o", dest])
-        .status()
-        .context("failed to run curl")?;
+/// Whether version `candidate` is newer than `current`, by semver
+/// precedence: `1.2.0` > `1.2.0-rc.2` > `1.2.0-rc.1` > `1.1.9`.
+pub fn is_newer(candidate: &str, current: &str) -> bool {
+    version_key(candidate) > version_key(current)
+}
+
+/// Sort key for a `MAJOR.MINOR.PATCH[-PRE]` version. A release sorts after
+/// its pre-releases; pre-release identifiers compare numerically when both
+/// are numbers.
+fn version_key(version: &str) -> (Vec<u64>, bool, Vec<(u64, String)>) {
+    let version = version.trim_start_matches('v');
+    let version = version.split('+').next().unwrap_or(version);
+    let (core, pre) = match version.split_once('-') {
+        Some((core, pre)) => (core, Some(pre)),
+        None => (version, None),
+    };
+    let core = core.split('.').map(|n| n.parse().unwrap_or(0)).collect();
+    let pre: Vec<(u64, String)> = pre
+        .map(|pre| {
+            pre.split('.')
+                .map(|id| match id.parse::<u64>() {
+                    Ok(n) => (n, String::new()),
+                    // Alphanumeric identifiers sort after numeric ones
+                    Err(_) => (u64::MAX, id.to_string()),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    (core, pre.is_empty(), pre)
+}
 
-    if !status.success() {
-        bail!("download failed: {url}");
+/// Check `contents` against the digest listed for `asset` in a SHA256SUMS
+/// file.
+fn verify_checksum(contents: &[u8], asset: &str, sums: &str) -> Result<()> {
+    let expected = expected_checksum(sums, asset)
+        .with_context(|| format!("SHA256SUMS has no entry for {asset}; not installing"))?;
+    let actual = format!("{:x}", Sha256::digest(contents));
+    if actual != expected {
+        bail!("checksum mismatch for {asset} (expected {expected}, got {actual}); not installing");
     }
     Ok(())
 }
 
-fn tempdir() -> Result<String> {
-    let output = Command::new("mktemp")
-        .args(["-d"])
-        .output()
-        .context("failed to create temp directory")?;
+/// The digest listed for `asset` in `sha256sum` output, whose lines are
+/// `<hex>  <name>` (or `<hex> *<name>` in binary mode).
+fn expected_checksum(sums: &str, asset: &str) -> Option<String> {
+    sums.lines().find_map(|line| {
+        let (hash, name) = line.split_once(char::is_whitespace)?;
+        let name = name.trim_start().trim_start_matches('*');
+        (name == asset).then(|| hash.to_ascii_lowercase())
+    })
+}
+
+/// The release target of the running binary. A musl build stays on musl,
+/// so Alpine hosts keep getting binaries that run there.
+fn detect_target() -> Result<&'static str> {
+    target_for(
+        env::consts::OS,
+        env::consts::ARCH,
+        cfg!(target_env = "musl"),
+    )
+}
 
-    if !output.status.success() {
-        bail!("mktemp failed");
+fn target_for(os: &str, arch: &str, musl: bool) -> Result<&'static str> {
+    match (os, arch, musl) {
+        ("linux", "x86_64", false) => Ok("x86_64-unknown-linux-gnu"),
+        ("linux", "x86_64", true) => Ok("x86_64-unknown-linux-musl"),
+        ("linux", "aarch64", false) => Ok("aarch64-unknown-linux-gnu"),
+        ("linux", "aarch64", true) => Ok("aarch64-unknown-linux-musl"),
+        ("macos", "x86_64", _) => Ok("x86_64-apple-darwin"),
+        ("macos", "aarch64", _) => Ok("aarch64-apple-darwin"),
+        _ => bail!("unsupported platform: {os}/{arch}"),
     }
-    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
 }
 
-fn cmd_output(cmd: &str, args: &[&str]) -> Result<String> {
-    let output = Command::new(cmd)
-        .args(args)
-        .output()
-        .with_context(|| format!("failed to run {cmd}"))?;
+/// Download `url` into memory, showing progress on a terminal.
+fn download(client: &Client, url: &str, name: &str) -> Result<Vec<u8>> {
+    let mut response = client
+        .get(url)
+        .send()
+        .and_then(|r| r.error_for_status())
+        .with_context(|| format!("download failed: {url}"))?;
+    let total = response.content_length();
+    let show_progress = tracing::enabled!(Level::INFO) && std::io::stderr().is_terminal();
+
+    let mut body = Vec::with_capacity(total.unwrap_or(0) as usize);
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = response
+            .read(&mut buf)
+            .with_context(|| format!("download failed: {url}"))?;
+        if n == 0 {
+            break;
+        }
+        body.extend_from_slice(&buf[..n]);
+        if show_progress {
+            eprint!("\r{}", progress_line(name, body.len() as u64, total));
+        }
+    }
+    if show_progress {
+        eprintln!();
+    }
+    Ok(body)
+}
 
-    if !output.status.success() {
-        bail!("{cmd} failed: {}", String::from_utf8_lossy(&output.stderr));
+fn progress_line(name: &str, done: u64, total: Option<u64>) -> String {
+    let mib = |bytes: u64| bytes as f64 / (1024.0 * 1024.0);
+    match total {
+        Some(total) if total > 0 => format!(
+            "Downloading {name}: {:.1}/{:.1} MiB ({}%)",
+            mib(done),
+            mib(total),
+            done * 100 / total
+        ),
+        _ => format!("Downloading {name}: {:.1} MiB", mib(done)),
+    }
+}
+
+/// The `dcw` binary from a release tarball.
+fn extract_binary(tarball: &[u8]) -> Result<Vec<u8>> {
+    let mut archive = tar::Archive::new(GzDecoder::new(tarball));
+    for entry in archive.entries().context("failed to read tarball")? {
+        let mut entry = entry.context("failed to read tarball")?;
+        if entry.path()?.file_name().is_some_and(|name| name == "dcw") {
+            let mut binary = Vec::new();
+            entry
+                .read_to_end(&mut binary)
+                .context("failed to extract dcw from tarball")?;
+            return Ok(binary);
+        }
     }
-    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    bail!("tarball does not contain a dcw binary");
 }
 
 #[cfg(test)]
@@ -192,6 +358,110 @@ mod tests {
         assert!(parse_tag_from_response(body).is_err());
     }
 
+    #[test]
+    fn expected_checksum_finds_asset() {
+        let sums = "\
+0123abcd  dcw-v1.0.0-x86_64-unknown-linux-gnu.tar.gz
+4567EF01 *dcw-v1.0.0-aarch64-apple-darwin.tar.gz
+";
+        assert_eq!(
+            expected_checksum(sums, "dcw-v1.0.0-x86_64-unknown-linux-gnu.tar.gz").as_deref(),
+            Some("0123abcd")
+        );
+        assert_eq!(
+            expected_checksum(sums, "dcw-v1.0.0-aarch64-apple-darwin.tar.gz").as_deref(),
+            Some("4567ef01")
+        );
+        assert_eq!(expected_checksum(sums, "dcw-v1.0.0.tar.gz"), None);
+    }
+
+    #[test]
+    fn verify_checksum_rejects_mismatch() {
+        let good = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824  dcw.tar.gz";
+        assert!(verify_checksum(b"hello", "dcw.tar.gz", good).is_ok());
+        let bad = "0000000000000000000000000000000000000000000000000000000000000000  dcw.tar.gz";
+        assert!(verify_checksum(b"hello", "dcw.tar.gz", bad).is_err());
+        assert!(verify_checksum(b"hello", "other.tar.gz", good).is_err());
+    }
+
+    #[test]
+    fn extract_binary_from_release_tarball() {
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            Vec::new(),
+            flate2::Compression::default(),
+        ));
+        let mut header = tar::Header::new_gnu();
+        header.set_size(7);
+        header.set_mode(0o755);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "dcw", &b"\x7fELF..."[..])
+            .unwrap();
+        let tarball = builder.into_inner().unwrap().finish().unwrap();
+
+        assert_eq!(extract_binary(&tarball).unwrap(), b"\x7fELF...");
+        assert!(extract_binary(b"not a tarball").is_err());
+    }
+
+    #[test]
+    fn progress_line_with_and_without_total() {
+        assert_eq!(
+            progress_line("dcw.tar.gz", 1024 * 1024, Some(4 * 1024 * 1024)),
+            "Downloading dcw.tar.gz: 1.0/4.0 MiB (25%)"
+        );
+        assert_eq!(
+            progress_line("dcw.tar.gz", 512 * 1024, None),
+            "Downloading dcw.tar.gz: 0.5 MiB"
+        );
+    }
+
+    #[test]
+    fn parse_newest_tag_skips_drafts() {
+        let body = r#"[
+  {"tag_name": "v0.7.0", "draft": true, "prerelease": false},
+  {"tag_name": "v0.7.0-rc.1", "draft": false, "prerelease": true},
+  {"tag_name": "v0.6.0", "draft": false, "prerelease": false}
+]"#;
+        assert_eq!(parse_newest_tag_from_list(body).unwrap(), "v0.7.0-rc.1");
+        assert!(parse_newest_tag_from_list("[]").is_err());
+        assert!(parse_newest_tag_from_list(r#"{"tag_name": "v1.0.0"}"#).is_err());
+    }
+
+    #[test]
+    fn is_newer_follows_semver_precedence() {
+        assert!(is_newer("0.6.0", "0.5.1"));
+        assert!(is_newer("v0.10.0", "0.9.0"));
+        assert!(!is_newer("0.5.1", "0.5.1"));
+        assert!(!is_newer("0.5.0", "0.5.1"));
+        assert!(is_newer("0.6.0", "0.6.0-rc.1"));
+        assert!(is_newer("0.6.0-rc.1", "0.5.1"));
+        assert!(!is_newer("0.6.0-rc.1", "0.6.0"));
+        assert!(is_newer("0.6.0-rc.10", "0.6.0-rc.2"));
+        assert!(is_newer("0.6.0-rc.1", "0.6.0-beta.3"));
+    }
+
+    #[test]
+    fn target_for_known_platforms() {
+        assert_eq!(
+            target_for("linux", "x86_64", false).unwrap(),
+            "x86_64-unknown-linux-gnu"
+        );
+        assert_eq!(
+            target_for("linux", "aarch64", true).unwrap(),
+            "aarch64-unknown-linux-musl"
+        );
+        assert_eq!(
+            target_for("macos", "x86_64", false).unwrap(),
+            "x86_64-apple-darwin"
+        );
+        assert_eq!(
+            target_for("macos", "aarch64", false).unwrap(),
+            "aarch64-apple-darwin"
+        );
+        assert!(target_for("windows", "x86_64", false).is_err());
+        assert!(target_for("linux", "riscv64", false).is_err());
+    }
+
     #[test]
     fn parse_tag_invalid_json() {
         let body = "not json at all";
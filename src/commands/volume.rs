@@ -0,0 +1,286 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use tracing::{info, warn};
+
+use crate::commands::mount;
+use crate::config;
+use crate::docker;
+use crate::output::{self, OutputFormat};
+use crate::traffic::format_bytes;
+use crate::workspace;
+
+#[derive(clap::Subcommand)]
+pub enum VolumeAction {
+    /// List the volumes of the workspace's devcontainer and compose project
+    /// with their sizes
+    #[command(alias = "ls")]
+    List,
+    /// Remove the workspace's volumes that no container uses any more
+    Prune,
+}
+
+/// How a volume was found to belong to the workspace.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum Owner {
+    /// Mounted by one of its devcontainers
+    Container,
+    /// Part of its Docker Compose project
+    Compose,
+    /// Named in its config's `mounts`, or after its `${devcontainerId}`
+    Config,
+}
+
+impl Owner {
+    fn as_str(self) -> &'static str {
+        match self {
+            Owner::Container => "container",
+            Owner::Compose => "compose",
+            Owner::Config => "config",
+        }
+    }
+}
+
+/// What ties volumes to the workspace. Besides the containers that exist,
+/// the config tells which volumes a removed container used.
+#[derive(Default)]
+struct WorkspaceVolumes {
+    mounted: HashSet<String>,
+    project: Option<String>,
+    configured: HashSet<String>,
+    devcontainer_id: String,
+}
+
+impl WorkspaceVolumes {
+    fn owner(&self, volume: &docker::Volume) -> Option<Owner> {
+        if self.mounted.contains(&volume.name) {
+            return Some(Owner::Container);
+        }
+        if let Some(project) = &self.project {
+            // Only unlabeled volumes go by name: `app_v2_data` starts with
+            // `app_` but may belong to project `app_v2`
+            let ours = match volume.labels.get("com.docker.compose.project") {
+                Some(label) => label == project,
+                None => volume.name.starts_with(&format!("{project}_")),
+            };
+            if ours {
+                return Some(Owner::Compose);
+            }
+        }
+        // Features name their volumes after the id, e.g. docker-in-docker's
+        // dind-var-lib-docker-${devcontainerId}
+        let by_id = !self.devcontainer_id.is_empty() && volume.name.contains(&self.devcontainer_id);
+        (by_id || self.configured.contains(&volume.name)).then_some(Owner::Config)
+    }
+}
+
+#[derive(Serialize)]
+struct VolumeRow {
+    name: String,
+    /// Bytes on disk, if docker reported it
+    size: Option<u64>,
+    in_use: bool,
+    owner: Owner,
+}
+
+pub fn run(action: &VolumeAction, format: OutputFormat) -> Result<()> {
+    let workspace_folder = workspace::workspace_folder()?;
+    let rows = workspace_volume_rows(&workspace_folder)?;
+    match action {
+        VolumeAction::List => list(&rows, format),
+        VolumeAction::Prune => prune(&rows),
+    }
+}
+
+fn list(rows: &[VolumeRow], format: OutputFormat) -> Result<()> {
+    if format == OutputFormat::Json {
+        return output::print_json(rows);
+    }
+    if rows.is_empty() {
+        println!("No volumes found for this workspace.");
+        return Ok(());
+    }
+    let width = rows.iter().map(|r| r.name.len()).max().unwrap_or(0).max(4);
+    println!(
+        "{:<width$}   {:>10}   {:<6}   MATCHED BY",
+        "NAME", "SIZE", "IN USE"
+    );
+    for row in rows {
+        println!(
+            "{:<width$}   {:>10}   {:<6}   {}",
+            row.name,
+            format_bytes(row.size),
+            if row.in_use { "yes" } else { "no" },
+            row.owner.as_str(),
+        );
+    }
+    let unused: Vec<&VolumeRow> = rows.iter().filter(|r| !r.in_use).collect();
+    println!();
+    println!(
+        "{} volumes, {} in total; {} unused ({}, remove with `dcw volume prune`)",
+        rows.len(),
+        format_bytes(Some(total_size(rows.iter()))),
+        unused.len(),
+        format_bytes(Some(total_size(unused.into_iter()))),
+    );
+    Ok(())
+}
+
+fn prune(rows: &[VolumeRow]) -> Result<()> {
+    let unused: Vec<&VolumeRow> = rows.iter().filter(|r| !r.in_use).collect();
+    if unused.is_empty() {
+        info!("No unused volumes for this workspace.");
+        return Ok(());
+    }
+
+    let dry_run = docker::dry_run();
+    let verb = if dry_run { "Would remove" } else { "Removing" };
+    let mut freed = 0;
+    for row in unused {
+        info!("{verb} volume {} ({})", row.name, format_bytes(row.size));
+        if dry_run {
+            continue;
+        }
+        match docker::remove_volume(&row.name) {
+            Ok(()) => freed += row.size.unwrap_or(0),
+            Err(e) => warn!("{e}"),
+        }
+    }
+    if !dry_run {
+        info!("Freed {}.", format_bytes(Some(freed)));
+    }
+    if rows.iter().any(|r| r.in_use) {
+        info!("Volumes still used by a container are kept; `dcw down --remove --volumes` removes the devcontainer with its volumes.");
+    }
+    Ok(())
+}
+
+fn total_size<'a>(rows: impl Iterator<Item = &'a VolumeRow>) -> u64 {
+    rows.filter_map(|r| r.size).sum()
+}
+
+fn workspace_volume_rows(workspace_folder: &str) -> Result<Vec<VolumeRow>> {
+    let workspace = workspace_volumes(workspace_folder)?;
+    let mut volumes: Vec<(docker::Volume, Owner)> = docker::list_volumes()?
+        .into_iter()
+        .filter_map(|v| workspace.owner(&v).map(|owner| (v, owner)))
+        .collect();
+    volumes.sort_by(|a, b| a.0.name.cmp(&b.0.name));
+
+    let sizes = if volumes.is_empty() {
+        HashMap::new()
+    } else {
+        docker::volume_sizes().unwrap_or_else(|e| {
+            warn!("failed to get volume sizes: {e}");
+            HashMap::new()
+        })
+    };
+    Ok(volumes
+        .into_iter()
+        .map(|(volume, owner)| VolumeRow {
+            size: sizes.get(&volume.name).copied(),
+            in_use: volume.in_use,
+            name: volume.name,
+            owner,
+        })
+        .collect())
+}
+
+fn workspace_volumes(workspace_folder: &str) -> Result<WorkspaceVolumes> {
+    let mut workspace = WorkspaceVolumes::default();
+    for id in docker::workspace_container_ids(workspace_folder)? {
+        workspace.mounted.extend(docker::container_volumes(&id)?);
+        if workspace.project.is_none() {
+            workspace.project = docker::compose_project(&id)?;
+        }
+    }
+
+    let root = Path::new(workspace_folder);
+    let Ok(config) = config::load_expanded(root) else {
+        return Ok(workspace);
+    };
+    if workspace.project.is_none() {
+        workspace.project = config::compose_project_name(root, &config);
+    }
+    workspace.configured = mount::mounts(&config)
+        .into_iter()
+        .filter(|m| m.kind == "volume")
+        .filter_map(|m| m.source)
+        .collect();
+    if let Ok(file) = config::labeled_config_file(root) {
+        workspace.devcontainer_id = config::devcontainer_id(workspace_folder, &file);
+    }
+    Ok(workspace)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn volume(name: &str, labels: &[(&str, &str)]) -> docker::Volume {
+        docker::Volume {
+            name: name.to_string(),
+            labels: labels
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            in_use: false,
+        }
+    }
+
+    #[test]
+    fn volumes_are_matched_to_the_workspace() {
+        let workspace = WorkspaceVolumes {
+            mounted: HashSet::from(["4f1c0a".to_string()]),
+            project: Some("app_devcontainer".to_string()),
+            configured: HashSet::from(["app-node_modules".to_string()]),
+            devcontainer_id: "0abc123".to_string(),
+        };
+        let owner = |v: docker::Volume| workspace.owner(&v);
+        assert_eq!(owner(volume("4f1c0a", &[])), Some(Owner::Container));
+        assert_eq!(
+            owner(volume(
+                "pgdata",
+                &[("com.docker.compose.project", "app_devcontainer")]
+            )),
+            Some(Owner::Compose)
+        );
+        assert_eq!(
+            owner(volume("app_devcontainer_cache", &[])),
+            Some(Owner::Compose)
+        );
+        assert_eq!(owner(volume("app-node_modules", &[])), Some(Owner::Config));
+        assert_eq!(
+            owner(volume("dind-var-lib-docker-0abc123", &[])),
+            Some(Owner::Config)
+        );
+        assert_eq!(
+            owner(volume(
+                "other_db",
+                &[("com.docker.compose.project", "other")]
+            )),
+            None
+        );
+    }
+
+    #[test]
+    fn prefix_match_ignores_volumes_of_another_project() {
+        let workspace = WorkspaceVolumes {
+            project: Some("app".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            workspace.owner(&volume(
+                "app_v2_data",
+                &[("com.docker.compose.project", "app_v2")]
+            )),
+            None
+        );
+        assert_eq!(
+            workspace.owner(&volume("app_data", &[])),
+            Some(Owner::Compose)
+        );
+    }
+}
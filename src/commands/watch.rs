@@ -1,26 +1,102 @@
 use anyhow::{Context, Result};
-use std::collections::HashSet;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::process::{Child, Command, Stdio};
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
 use std::sync::Arc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime};
+use tracing::{info, warn};
 
-use crate::docker;
+use crate::browser;
+use crate::commands::daemon;
+use crate::commands::port::{self, ForwardRow};
+use crate::docker::{self, Protocol};
+use crate::forward_ports::{OnAutoForward, PortsAttributes};
+use crate::hooks::{self, Event};
+use crate::ipc;
+use crate::lock;
+use crate::logging::Logged;
+use crate::notify;
+use crate::output::{self, OutputFormat};
+use crate::process;
+use crate::proxy;
+use crate::settings::ForwardBackend;
 use crate::workspace;
 
 pub struct WatchConfig {
     pub interval: u64,
     pub min_port: u16,
     pub exclude_ports: HashSet<u16>,
+    pub bind_address: Option<String>,
+    pub fallback: bool,
+    pub backend: ForwardBackend,
+    /// How long a port must stay closed before its forward is removed
+    pub remove_delay: Duration,
+    /// Seconds without traffic after which the proxy removes a forward
+    pub idle_timeout: Option<u64>,
+    /// Scan `/proc/net/tcp` with one `docker exec` per interval instead of
+    /// streaming changes from a long-running one
+    pub poll: bool,
+    /// Also forward UDP sockets bound inside the container
+    pub udp: bool,
+    /// Also forward sockets bound only to a loopback address
+    pub include_loopback: bool,
+    /// Show desktop notifications for forwards that aren't `silent`
+    pub notify: bool,
+    /// Render a live-updating table instead of printing log lines
+    pub live: bool,
+    pub attributes: PortsAttributes,
+}
+
+/// A listening socket inside the container.
+pub type Listener = (u16, Protocol);
+
+/// What to look for when scanning the container's sockets.
+#[derive(Debug, Clone, Copy)]
+struct ScanOptions {
+    udp: bool,
+    include_loopback: bool,
+}
+
+/// Whether a `/proc/net` `ADDR:PORT` local address is bound to a loopback
+/// address. Such sockets can't be reached through the container's IP, so
+/// forwarding them is pointless. `ADDR` holds the address in 32-bit words
+/// in host byte order, little-endian on the platforms containers run on.
+fn is_loopback_address(local_address: &str) -> bool {
+    let Some((hex, _)) = local_address.rsplit_once(':') else {
+        return false;
+    };
+    let mut bytes = Vec::with_capacity(16);
+    for i in (0..hex.len()).step_by(8) {
+        match hex.get(i..i + 8).map(|word| u32::from_str_radix(word, 16)) {
+            Some(Ok(word)) => bytes.extend(word.to_le_bytes()),
+            _ => return false,
+        }
+    }
+    if let Ok(v4) = <[u8; 4]>::try_from(bytes.as_slice()) {
+        return Ipv4Addr::from(v4).is_loopback();
+    }
+    match <[u8; 16]>::try_from(bytes.as_slice()) {
+        Ok(v6) => {
+            let addr = Ipv6Addr::from(v6);
+            addr.is_loopback() || addr.to_ipv4_mapped().is_some_and(|v4| v4.is_loopback())
+        }
+        Err(_) => false,
+    }
 }
 
 /// Parse `/proc/net/tcp` (or `/proc/net/tcp6`) content and return
-/// the set of ports in LISTEN state (state == 0A).
+/// the set of ports in LISTEN state (state == 0A). Sockets bound to a
+/// loopback address are skipped unless `include_loopback` is set.
 ///
 /// Format (each line after header):
 ///   sl  local_address rem_address   st ...
 /// where local_address is `ADDR:PORT` (hex).
-pub fn parse_proc_net_tcp(content: &str) -> HashSet<u16> {
+pub fn parse_proc_net_tcp(content: &str, include_loopback: bool) -> HashSet<u16> {
     let mut ports = HashSet::new();
     for line in content.lines().skip(1) {
         let fields: Vec<&str> = line.split_whitespace().collect();
@@ -28,11 +104,11 @@ pub fn parse_proc_net_tcp(content: &str) -> HashSet<u16> {
             continue;
         }
         // fields[3] is the state
-        if fields[3] != "0A" {
+        if fields[3] != "0A" || (!include_loopback && is_loopback_address(fields[1])) {
             continue;
         }
         // fields[1] is local_address in format ADDR:PORT (hex)
-        if let Some(port_hex) = fields[1].split(':').last() {
+        if let Some(port_hex) = fields[1].rsplit(':').next() {
             if let Ok(port) = u16::from_str_radix(port_hex, 16) {
                 ports.insert(port);
             }
@@ -41,38 +117,283 @@ pub fn parse_proc_net_tcp(content: &str) -> HashSet<u16> {
     ports
 }
 
-/// Detect listening ports inside a container by reading /proc/net/tcp{,6}.
-fn detect_listening_ports(container_id: &str) -> Result<HashSet<u16>> {
-    let tcp = docker::exec_in_container(container_id, &["cat", "/proc/net/tcp"])
-        .context("failed to read /proc/net/tcp")?;
-    let mut ports = parse_proc_net_tcp(&tcp);
+/// Parse `/proc/net/udp` (or `/proc/net/udp6`) content and return the
+/// ports of bound, unconnected sockets (state == 07 with no remote
+/// address), which is how datagram listeners show up. Loopback sockets are
+/// skipped unless `include_loopback` is set.
+pub fn parse_proc_net_udp(content: &str, include_loopback: bool) -> HashSet<u16> {
+    let mut ports = HashSet::new();
+    for line in content.lines().skip(1) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 4 {
+            continue;
+        }
+        if fields[3] != "07" || !fields[2].ends_with(":0000") {
+            continue;
+        }
+        if !include_loopback && is_loopback_address(fields[1]) {
+            continue;
+        }
+        if let Some(port_hex) = fields[1].rsplit(':').next() {
+            if let Ok(port) = u16::from_str_radix(port_hex, 16) {
+                ports.insert(port);
+            }
+        }
+    }
+    ports
+}
+
+/// Detect listening sockets inside a container by reading /proc/net/tcp{,6}
+/// and, if `udp` is set, /proc/net/udp{,6}.
+fn detect_listening_ports(container_id: &str, scan: ScanOptions) -> Result<HashSet<Listener>> {
+    Ok(read_socket_tables(container_id, scan.udp)?.listeners(scan.include_loopback))
+}
+
+/// Sockets listening inside a container, TCP and UDP, each with whether it
+/// is only bound to a loopback address.
+pub fn scan_listeners(container_id: &str) -> Result<HashMap<Listener, bool>> {
+    let tables = read_socket_tables(container_id, true)?;
+    let reachable = tables.listeners(false);
+    Ok(tables
+        .listeners(true)
+        .into_iter()
+        .map(|l| (l, !reachable.contains(&l)))
+        .collect())
+}
+
+/// Of the container's listeners, those only listening on IPv6, provided the
+/// container has an IPv6 address to reach them on. Such sockets may refuse
+/// connections to the IPv4 address, so their forwards target the IPv6 one.
+/// Returns an empty set if the scan fails.
+pub fn ipv6_only_listeners(container_id: &str, network: &str, udp: bool) -> HashSet<Listener> {
+    if !matches!(
+        docker::get_container_ipv6(container_id, network),
+        Ok(Some(_))
+    ) {
+        return HashSet::new();
+    }
+    read_socket_tables(container_id, udp)
+        .map(|tables| tables.ipv6_only())
+        .unwrap_or_default()
+}
+
+/// Contents of the container's `/proc/net` socket tables; the IPv6 ones may
+/// be missing.
+struct SocketTables {
+    tcp: String,
+    tcp6: Option<String>,
+    udp: Option<String>,
+    udp6: Option<String>,
+}
+
+fn read_socket_tables(container_id: &str, udp: bool) -> Result<SocketTables> {
+    let read = |file: &str| docker::exec_in_container(container_id, &["cat", file]).ok();
+    Ok(SocketTables {
+        tcp: docker::exec_in_container(container_id, &["cat", "/proc/net/tcp"])
+            .context("failed to read /proc/net/tcp")?,
+        tcp6: read("/proc/net/tcp6"),
+        udp: udp.then(|| read("/proc/net/udp")).flatten(),
+        udp6: udp.then(|| read("/proc/net/udp6")).flatten(),
+    })
+}
+
+impl SocketTables {
+    fn listeners(&self, include_loopback: bool) -> HashSet<Listener> {
+        let mut listeners = self.family(false, include_loopback);
+        listeners.extend(self.family(true, include_loopback));
+        listeners
+    }
+
+    /// Listeners in the IPv6 tables but not the IPv4 ones. Loopback sockets
+    /// are left out, as they can't be forwarded through either address.
+    fn ipv6_only(&self) -> HashSet<Listener> {
+        let ipv4 = self.family(false, false);
+        self.family(true, false)
+            .into_iter()
+            .filter(|l| !ipv4.contains(l))
+            .collect()
+    }
 
-    // tcp6 may not exist; ignore errors
-    if let Ok(tcp6) = docker::exec_in_container(container_id, &["cat", "/proc/net/tcp6"]) {
-        ports.extend(parse_proc_net_tcp(&tcp6));
+    /// Listeners in the IPv4 or the IPv6 tables.
+    fn family(&self, ipv6: bool, include_loopback: bool) -> HashSet<Listener> {
+        let (tcp, udp) = if ipv6 {
+            (self.tcp6.as_deref(), self.udp6.as_deref())
+        } else {
+            (Some(self.tcp.as_str()), self.udp.as_deref())
+        };
+        let tcp = tcp
+            .map(|content| parse_proc_net_tcp(content, include_loopback))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|p| (p, Protocol::Tcp));
+        let udp = udp
+            .map(|content| parse_proc_net_udp(content, include_loopback))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|p| (p, Protocol::Udp));
+        tcp.chain(udp).collect()
     }
+}
+
+/// Script run inside the container that prints the listening sockets from
+/// `/proc/net/tcp{,6}` (and `/proc/net/udp{,6}` when `$1` is `udp`) as
+/// `tcp|udp ADDR:PORT` lines followed by an `END` line whenever they change.
+/// The proc files can't be watched with inotify, so it rescans every half
+/// second; that is cheap inside the container, unlike a `docker exec` per
+/// scan. A `.` heartbeat is printed periodically so the script dies of
+/// SIGPIPE soon after the watcher goes away.
+const STREAM_SCRIPT: &str = r#"prev=none; n=0
+while :; do
+  cur=$({
+    for f in /proc/net/tcp /proc/net/tcp6; do
+      [ -r "$f" ] || continue
+      while read -r _ addr _ st _; do [ "$st" = 0A ] && echo "tcp $addr"; done < "$f"
+    done
+    [ "$1" = udp ] && for f in /proc/net/udp /proc/net/udp6; do
+      [ -r "$f" ] || continue
+      while read -r _ addr rem st _; do
+        [ "$st" = 07 ] && [ "${rem##*:}" = 0000 ] && echo "udp $addr"
+      done < "$f"
+    done
+  } | sort -u)
+  if [ "$cur" != "$prev" ]; then printf '%s\nEND\n' "$cur"; prev=$cur; n=0; fi
+  n=$((n + 1)); if [ "$n" -ge 20 ]; then echo .; n=0; fi
+  sleep 0.5 2>/dev/null || sleep 1
+done"#;
 
-    Ok(ports)
+/// Parse `tcp|udp ADDR:PORT` (hex) lines printed by [`STREAM_SCRIPT`],
+/// skipping loopback sockets unless `include_loopback` is set.
+pub fn parse_listen_addresses<S: AsRef<str>>(
+    lines: &[S],
+    include_loopback: bool,
+) -> HashSet<Listener> {
+    lines
+        .iter()
+        .filter_map(|line| {
+            let (proto, addr) = line.as_ref().trim().split_once(' ')?;
+            if !include_loopback && is_loopback_address(addr) {
+                return None;
+            }
+            let port = u16::from_str_radix(addr.rsplit(':').next()?, 16).ok()?;
+            Some((port, Protocol::from_label(proto)))
+        })
+        .collect()
+}
+
+/// Where listening port snapshots come from.
+enum PortSource {
+    /// A long-running `docker exec` printing a snapshot on every change
+    Stream {
+        child: Child,
+        rx: Receiver<HashSet<Listener>>,
+    },
+    /// One `docker exec` per interval
+    Poll { first: bool, scan: ScanOptions },
+}
+
+impl PortSource {
+    fn new(container_id: &str, poll: bool, scan: ScanOptions) -> Self {
+        if poll {
+            return PortSource::Poll { first: true, scan };
+        }
+        match spawn_port_stream(container_id, scan) {
+            Ok((child, rx)) => PortSource::Stream { child, rx },
+            Err(e) => {
+                warn!("failed to stream port changes, polling instead: {e}");
+                PortSource::Poll { first: true, scan }
+            }
+        }
+    }
+
+    /// Wait up to `timeout` for the next snapshot. Returns `None` if the
+    /// listening ports did not change in that time.
+    fn next(
+        &mut self,
+        container_id: &str,
+        timeout: Duration,
+        scan: ScanOptions,
+    ) -> Result<Option<HashSet<Listener>>> {
+        match self {
+            PortSource::Stream { rx, .. } => match rx.recv_timeout(timeout) {
+                Ok(ports) => Ok(Some(ports)),
+                Err(RecvTimeoutError::Timeout) => Ok(None),
+                Err(RecvTimeoutError::Disconnected) => {
+                    warn!("port stream ended, polling instead.");
+                    *self = PortSource::Poll { first: true, scan };
+                    self.next(container_id, timeout, scan)
+                }
+            },
+            PortSource::Poll { first, scan } => {
+                if !std::mem::take(first) {
+                    thread::sleep(timeout);
+                }
+                detect_listening_ports(container_id, *scan).map(Some)
+            }
+        }
+    }
+}
+
+impl Drop for PortSource {
+    fn drop(&mut self) {
+        if let PortSource::Stream { child, .. } = self {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+/// Start [`STREAM_SCRIPT`] in the container and parse its output on a
+/// background thread.
+fn spawn_port_stream(
+    container_id: &str,
+    scan: ScanOptions,
+) -> Result<(Child, Receiver<HashSet<Listener>>)> {
+    let mode = if scan.udp { "udp" } else { "tcp" };
+    let mut child = Command::new(docker::docker_path())
+        .args(["exec", container_id, "sh", "-c", STREAM_SCRIPT, "_", mode])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .logged()
+        .spawn()
+        .context("failed to run docker exec for port stream")?;
+    let stdout = child.stdout.take().context("port stream has no stdout")?;
+
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut block = Vec::new();
+        for line in BufReader::new(stdout).lines().map_while(|l| l.ok()) {
+            match line.trim() {
+                "." => {}
+                "END" => {
+                    if tx
+                        .send(parse_listen_addresses(&block, scan.include_loopback))
+                        .is_err()
+                    {
+                        return;
+                    }
+                    block.clear();
+                }
+                _ => block.push(line),
+            }
+        }
+    });
+    Ok((child, rx))
 }
 
 pub fn run_watch(config: &WatchConfig) -> Result<()> {
     let ws_id = workspace::workspace_id()?;
     let workspace_folder = workspace::workspace_folder()?;
 
-    let container_id = docker::find_devcontainer(&workspace_folder)?
+    let mut container_id = docker::find_devcontainer(&workspace_folder)?
         .context("no running devcontainer found")?;
-    let network = docker::get_container_network(&container_id)?;
-
-    // Clean up any orphaned watch sidecars from a previous watcher instance
-    if let Err(e) = docker::remove_port_forwards_by_source(&ws_id, "watch") {
-        eprintln!("Warning: failed to clean up old watch sidecars: {e}");
-    }
+    let mut network = docker::get_container_network(&container_id)?;
 
-    println!(
+    info!(
         "Watching for listening ports (interval: {}s)...",
         config.interval
     );
-    println!("Press Ctrl+C to stop and clean up.");
+    info!("Press Ctrl+C to stop and clean up.");
 
     let running = Arc::new(AtomicBool::new(true));
     let r = running.clone();
@@ -81,74 +402,811 @@ pub fn run_watch(config: &WatchConfig) -> Result<()> {
     })
     .context("failed to set Ctrl+C handler")?;
 
-    let mut managed: HashSet<u16> = HashSet::new();
+    let mut managed: HashSet<Listener> = HashSet::new();
+    let mut listening: HashSet<Listener> = HashSet::new();
+    // Host address each forward was created on, for the live table
+    let mut hosts: HashMap<Listener, String> = HashMap::new();
+    // Ports opened in the browser, for `openBrowserOnce`
+    let mut opened: HashSet<u16> = HashSet::new();
+    // Ports whose forward went idle, left alone until they close
+    let mut idle: HashSet<Listener> = HashSet::new();
+    // Adopt forwards a previous watcher instance left behind, e.g. before
+    // the supervisor restarted it, so they are tracked and cleaned up
+    match port::collect_forwards(&ws_id) {
+        Ok(forwards) => {
+            hosts = watch_listeners(&forwards);
+            managed.extend(hosts.keys().copied());
+            if !hosts.is_empty() {
+                info!("Adopted {} existing watch forward(s).", hosts.len());
+            }
+        }
+        Err(e) => warn!("failed to list existing watch forwards: {e}"),
+    }
+    let mut report = Reporter::new(config.live);
+    let mut supervisor = Supervisor::default();
+    let mut closed = ClosedPorts::default();
     let interval = Duration::from_secs(config.interval);
+    let scan = ScanOptions {
+        udp: config.udp,
+        include_loopback: config.include_loopback,
+    };
+    let mut source = PortSource::new(&container_id, config.poll, scan);
 
     while running.load(Ordering::SeqCst) {
-        // Check container is still running
+        // Forwards created or removed this round, for the daemon
+        let mut changed = false;
+        // Check container is still running, or has been restarted
         if !docker::is_container_running(&container_id)? {
-            println!("Container stopped, exiting watch.");
-            break;
+            let Some(restarted) = wait_for_container(&workspace_folder, &running) else {
+                report.finish();
+                info!("Container stopped, exiting watch.");
+                break;
+            };
+            report.info(format!(
+                "Container restarted ({restarted}), recreating forwards..."
+            ));
+            // The old forwards target the previous container's IP
+            if let Err(e) = remove_watch_forwards(&ws_id) {
+                report.warn(format!("failed to remove old watch forwards: {e}"));
+            }
+            ipc::notify_changed(&workspace_folder);
+            managed.clear();
+            listening.clear();
+            hosts.clear();
+            idle.clear();
+            closed = ClosedPorts::default();
+            supervisor = Supervisor::default();
+            network = docker::get_container_network(&restarted)?;
+            container_id = restarted;
+            source = PortSource::new(&container_id, config.poll, scan);
+            continue;
+        }
+
+        match source.next(&container_id, interval, scan) {
+            Ok(Some(ports)) => listening = ports,
+            // Unchanged; still reconcile in case the supervisor dropped forwards
+            Ok(None) => {}
+            Err(e) => {
+                report.warn(format!("failed to detect ports: {e}"));
+                continue;
+            }
         }
 
-        let listening = match detect_listening_ports(&container_id) {
-            Ok(ports) => ports,
+        // Leave the round to a dcw command changing the workspace, e.g. `up`
+        // or `port add`, rather than racing it on sidecar names
+        let _lock = match lock::try_acquire(&ws_id, "port watch") {
+            Ok(Some(lock)) => lock,
+            Ok(None) => continue,
             Err(e) => {
-                eprintln!("Warning: failed to detect ports: {e}");
-                thread::sleep(interval);
+                report.warn(format!("{e:#}"));
                 continue;
             }
         };
 
+        match supervisor.check(&ws_id, &container_id, &network, &mut managed, &mut report) {
+            Ok(gone) => {
+                changed |= !gone.is_empty();
+                for (port, protocol) in gone {
+                    let name = listener_name(port, protocol);
+                    hosts.remove(&(port, protocol));
+                    if config.idle_timeout.is_some() {
+                        report.info(format!(
+                            "Forward for port {name} was removed after going idle, forwarding it again once the port reopens."
+                        ));
+                        idle.insert((port, protocol));
+                    } else {
+                        report.info(format!(
+                            "Forward for port {name} disappeared, recreating..."
+                        ));
+                    }
+                }
+            }
+            Err(e) => report.warn(format!("failed to check port forwards: {e}")),
+        }
+
         // Apply filters
-        let eligible: HashSet<u16> = listening
-            .into_iter()
-            .filter(|p| *p >= config.min_port && !config.exclude_ports.contains(p))
+        let eligible: HashSet<Listener> = listening
+            .iter()
+            .copied()
+            .filter(|(p, _)| *p >= config.min_port && !config.exclude_ports.contains(p))
+            .filter(|(p, _)| on_auto_forward(config, *p) != OnAutoForward::Ignore)
             .collect();
+        idle.retain(|l| eligible.contains(l));
 
         // New ports to forward
-        let new_ports: Vec<u16> = eligible.difference(&managed).copied().collect();
-        for port in new_ports {
-            println!("Detected port {port}, creating forward...");
-            match docker::start_port_forward(
-                &ws_id,
-                &container_id,
-                port,
-                port,
-                &network,
-                true,
-                Some("watch"),
-            ) {
-                Ok(()) => {
-                    println!("  Forwarded 127.0.0.1:{port} -> {port}");
-                    managed.insert(port);
+        let new_ports: Vec<Listener> = eligible
+            .difference(&managed)
+            .filter(|l| !idle.contains(l))
+            .copied()
+            .collect();
+        let ipv6_only = if new_ports.is_empty() {
+            HashSet::new()
+        } else {
+            ipv6_only_listeners(&container_id, &network, config.udp)
+        };
+        for (port, protocol) in new_ports {
+            let name = listener_name(port, protocol);
+            let attrs = config.attributes.detected(port);
+            let silent = on_auto_forward(config, port) == OnAutoForward::Silent;
+            if !silent {
+                report.info(format!("Detected port {name}, creating forward..."));
+            }
+            hooks::emit(Event::PortDetected { port, protocol });
+            let spec = docker::ForwardSpec {
+                protocol,
+                source: Some("watch"),
+                bind_address: config.bind_address.as_deref(),
+                fallback: config.fallback && !attrs.is_some_and(|a| a.require_local_port),
+                label: attrs.and_then(|a| a.label.as_deref()),
+                ipv6: ipv6_only.contains(&(port, protocol)),
+                idle_timeout: config.idle_timeout,
+                ..docker::ForwardSpec::new(port, port)
+            };
+            match proxy::start_forward(&ws_id, &container_id, &network, &spec, config.backend) {
+                Ok(host_port) => {
+                    let bind = spec.bind_address.unwrap_or(docker::DEFAULT_BIND_ADDRESS);
+                    hosts.insert((port, protocol), format!("{bind}:{host_port}"));
+                    if !silent {
+                        report.info(format!("  Forwarded {bind}:{host_port} -> {name}"));
+                        if config.notify {
+                            let mut body = forward_address(bind, host_port, protocol);
+                            if let Some(label) = spec.label {
+                                body = format!("{label}: {body}");
+                            }
+                            notify_user(&format!("dcw: port {name} forwarded"), &body);
+                        }
+                    }
+                    let policy = on_auto_forward(config, port);
+                    let once = policy == OnAutoForward::OpenBrowserOnce;
+                    if protocol == Protocol::Tcp
+                        && policy.opens_browser()
+                        && !(once && opened.contains(&port))
+                    {
+                        let scheme = attrs.map_or("http", |a| a.scheme());
+                        let url = browser::forward_url(scheme, bind, host_port);
+                        match browser::open_url(&url) {
+                            Ok(()) => report.info(format!("  Opened {url} in the browser")),
+                            Err(e) => report.warn(format!("failed to open {url}: {e}")),
+                        }
+                        opened.insert(port);
+                    }
+                    managed.insert((port, protocol));
+                    changed = true;
                 }
                 Err(e) => {
-                    eprintln!("  Warning: failed to forward port {port}: {e}");
+                    report.warn(format!("failed to forward port {name}: {e}"));
                 }
             }
         }
 
-        // Ports that disappeared
-        let disappeared: Vec<u16> = managed.difference(&eligible).copied().collect();
-        for port in disappeared {
-            println!("Port {port} no longer listening, removing forward...");
-            if let Err(e) = docker::remove_port_forward(&ws_id, port) {
-                eprintln!("  Warning: failed to remove forward for port {port}: {e}");
+        // Ports that disappeared, once they have stayed closed long enough
+        let disappeared: HashSet<Listener> = managed.difference(&eligible).copied().collect();
+        let changes = closed.update(&disappeared, config.remove_delay, Instant::now());
+        for (port, protocol) in changes.closed {
+            report.info(format!(
+                "Port {} no longer listening, removing forward in {}s unless it returns...",
+                listener_name(port, protocol),
+                config.remove_delay.as_secs_f64()
+            ));
+        }
+        // Forwards removed some other way are simply forgotten
+        for (port, protocol) in changes
+            .reopened
+            .into_iter()
+            .filter(|l| eligible.contains(l))
+        {
+            report.info(format!(
+                "Port {} is listening again, keeping its forward.",
+                listener_name(port, protocol)
+            ));
+        }
+        for (port, protocol) in changes.due {
+            let name = listener_name(port, protocol);
+            report.info(format!(
+                "Port {name} no longer listening, removing forward..."
+            ));
+            if let Err(e) = proxy::remove_forward(&ws_id, port, protocol) {
+                report.warn(format!("failed to remove forward for port {name}: {e}"));
             }
-            managed.remove(&port);
+            if config.notify && on_auto_forward(config, port) != OnAutoForward::Silent {
+                notify_user(
+                    &format!("dcw: port {name} closed"),
+                    &format!("The forward for port {name} was removed."),
+                );
+            }
+            managed.remove(&(port, protocol));
+            hosts.remove(&(port, protocol));
+            changed = true;
+        }
+        if changed {
+            ipc::notify_changed(&workspace_folder);
         }
 
-        thread::sleep(interval);
+        if let Some(view) = &mut report.live {
+            view.update(live_rows(config, &listening, &managed, &hosts));
+            view.render(&workspace_folder);
+        }
     }
+    drop(source);
+    report.finish();
 
-    println!("Cleaning up watcher-managed port forwards...");
-    docker::remove_port_forwards_by_source(&ws_id, "watch")?;
-    println!("Done.");
+    info!("Cleaning up watcher-managed port forwards...");
+    remove_watch_forwards(&ws_id)?;
+    ipc::notify_changed(&workspace_folder);
+    info!("Done.");
 
     Ok(())
 }
 
+/// How long the watcher waits for a stopped devcontainer to come back, e.g.
+/// during `dcw restart`, before exiting.
+const CONTAINER_RESTART_GRACE: Duration = Duration::from_secs(30);
+
+/// Wait for the workspace's devcontainer to be running again, possibly as a
+/// new container. Returns `None` if it doesn't come back in time.
+fn wait_for_container(workspace_folder: &str, running: &AtomicBool) -> Option<String> {
+    let deadline = Instant::now() + CONTAINER_RESTART_GRACE;
+    while running.load(Ordering::SeqCst) && Instant::now() < deadline {
+        // Errors are likely the Docker daemon restarting with the container
+        if let Ok(Some(id)) = docker::find_devcontainer(workspace_folder) {
+            return Some(id);
+        }
+        thread::sleep(Duration::from_secs(1));
+    }
+    None
+}
+
+/// Forwarded ports that stopped listening but are kept for
+/// [`WatchConfig::remove_delay`], in case the server is only restarting.
+#[derive(Default)]
+struct ClosedPorts {
+    since: HashMap<Listener, Instant>,
+}
+
+/// What changed in [`ClosedPorts`] since the last pass.
+#[derive(Debug, Default, PartialEq)]
+struct ClosedChanges {
+    /// Ports that just stopped listening, kept for now
+    closed: Vec<Listener>,
+    /// Ports listening again before their forward was removed
+    reopened: Vec<Listener>,
+    /// Ports closed for the whole delay, whose forward should go
+    due: Vec<Listener>,
+}
+
+impl ClosedPorts {
+    /// Track the forwarded ports that are `gone` this pass.
+    fn update(&mut self, gone: &HashSet<Listener>, delay: Duration, now: Instant) -> ClosedChanges {
+        let mut changes = ClosedChanges::default();
+        self.since.retain(|listener, _| {
+            let keep = gone.contains(listener);
+            if !keep {
+                changes.reopened.push(*listener);
+            }
+            keep
+        });
+        for listener in gone {
+            let since = *self.since.entry(*listener).or_insert_with(|| {
+                if !delay.is_zero() {
+                    changes.closed.push(*listener);
+                }
+                now
+            });
+            if now.duration_since(since) >= delay {
+                self.since.remove(listener);
+                changes.due.push(*listener);
+            }
+        }
+        changes.closed.sort_unstable();
+        changes.reopened.sort_unstable();
+        changes.due.sort_unstable();
+        changes
+    }
+}
+
+/// Parse `--remove-delay`: seconds, optionally with an `ms`, `s` or `m` unit.
+pub fn parse_delay(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let digits = value.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let amount: u64 = digits
+        .parse()
+        .map_err(|_| format!("invalid duration {value:?} (e.g. 10s, 500ms, 1m)"))?;
+    match &value[digits.len()..] {
+        "" | "s" => Ok(Duration::from_secs(amount)),
+        "ms" => Ok(Duration::from_millis(amount)),
+        "m" => Ok(Duration::from_secs(amount * 60)),
+        unit => Err(format!("unknown unit {unit:?} (use ms, s or m)")),
+    }
+}
+
+/// How often forwards are health-checked. Probing a forward opens a
+/// connection to the application behind it, so this is kept well above the
+/// watch interval.
+const CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+/// How many recent events the live table shows below the ports.
+const LIVE_EVENTS: usize = 5;
+
+/// Where watcher activity goes: log lines, or the live table's event list.
+struct Reporter {
+    live: Option<LiveView>,
+}
+
+impl Reporter {
+    fn new(live: bool) -> Self {
+        Self {
+            live: live.then(LiveView::default),
+        }
+    }
+
+    fn info(&mut self, msg: String) {
+        match &mut self.live {
+            Some(view) => view.event(msg),
+            None => info!("{msg}"),
+        }
+    }
+
+    fn warn(&mut self, msg: String) {
+        match &mut self.live {
+            Some(view) => view.event(format!("Warning: {msg}")),
+            None => warn!("{msg}"),
+        }
+    }
+
+    /// Leave the live table on screen and go back to log lines.
+    fn finish(&mut self) {
+        if self.live.take().is_some() {
+            println!();
+        }
+    }
+}
+
+/// One row of the live table.
+#[derive(Debug, Clone, PartialEq)]
+struct LiveRow {
+    status: &'static str,
+    host: String,
+    label: String,
+}
+
+/// State of the table rendered by `--live`.
+#[derive(Default)]
+struct LiveView {
+    rows: BTreeMap<Listener, (LiveRow, Instant)>,
+    events: VecDeque<(Instant, String)>,
+}
+
+impl LiveView {
+    fn event(&mut self, msg: String) {
+        if self.events.len() == LIVE_EVENTS {
+            self.events.pop_front();
+        }
+        self.events
+            .push_back((Instant::now(), msg.trim().to_string()));
+    }
+
+    /// Replace the rows, keeping the change time of rows that are unchanged.
+    fn update(&mut self, rows: BTreeMap<Listener, LiveRow>) {
+        let now = Instant::now();
+        let mut previous = std::mem::take(&mut self.rows);
+        for (listener, row) in rows {
+            let changed = match previous.remove(&listener) {
+                Some((old, at)) if old == row => at,
+                _ => now,
+            };
+            self.rows.insert(listener, (row, changed));
+        }
+    }
+
+    fn render(&self, workspace_folder: &str) {
+        let mut out = String::from("\x1b[H\x1b[2J");
+        out.push_str(&format!("dcw port watch: {workspace_folder}\n"));
+        out.push_str("Press Ctrl+C to stop and clean up.\n\n");
+        if self.rows.is_empty() {
+            out.push_str("No listening ports detected.\n");
+        } else {
+            out.push_str(&format!(
+                "{:<11} {:<10} {:<22} {:<20} CHANGED\n",
+                "PORT", "STATUS", "HOST", "LABEL"
+            ));
+            for ((port, protocol), (row, changed)) in &self.rows {
+                out.push_str(&format!(
+                    "{:<11} {:<10} {:<22} {:<20} {} ago\n",
+                    listener_name(*port, *protocol),
+                    row.status,
+                    row.host,
+                    row.label,
+                    format_uptime(changed.elapsed().as_secs())
+                ));
+            }
+        }
+        if !self.events.is_empty() {
+            out.push_str("\nRecent events:\n");
+            for (at, msg) in &self.events {
+                out.push_str(&format!(
+                    "  {:>8} ago  {msg}\n",
+                    format_uptime(at.elapsed().as_secs())
+                ));
+            }
+        }
+        let mut stdout = std::io::stdout().lock();
+        let _ = stdout.write_all(out.as_bytes());
+        let _ = stdout.flush();
+    }
+}
+
+/// Build the live table: every detected or forwarded port with why it is
+/// (not) forwarded.
+fn live_rows(
+    config: &WatchConfig,
+    listening: &HashSet<Listener>,
+    managed: &HashSet<Listener>,
+    hosts: &HashMap<Listener, String>,
+) -> BTreeMap<Listener, LiveRow> {
+    listening
+        .union(managed)
+        .map(|listener| {
+            let port = listener.0;
+            let status = if managed.contains(listener) {
+                "forwarded"
+            } else if port < config.min_port {
+                "below min"
+            } else if config.exclude_ports.contains(&port) {
+                "excluded"
+            } else if on_auto_forward(config, port) == OnAutoForward::Ignore {
+                "ignored"
+            } else {
+                "failed"
+            };
+            let row = LiveRow {
+                status,
+                host: managed
+                    .contains(listener)
+                    .then(|| hosts.get(listener).cloned())
+                    .flatten()
+                    .unwrap_or_default(),
+                label: config
+                    .attributes
+                    .detected(port)
+                    .and_then(|a| a.label.clone())
+                    .unwrap_or_default(),
+            };
+            (*listener, row)
+        })
+        .collect()
+}
+
+/// Minimum time between two restarts of the same sidecar, so a forward that
+/// stays unhealthy is not recreated on every pass.
+const RESTART_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// Health checks for the workspace's forwards, run on every watch pass.
+#[derive(Default)]
+struct Supervisor {
+    last_check: Option<Instant>,
+    restarted: HashMap<String, Instant>,
+}
+
+impl Supervisor {
+    /// Restart sidecars that point at a stale container IP or no longer
+    /// accept connections, refresh native forwards, and drop watcher-managed
+    /// ports whose forward has disappeared, returning them.
+    fn check(
+        &mut self,
+        ws_id: &str,
+        container_id: &str,
+        network: &str,
+        managed: &mut HashSet<Listener>,
+        report: &mut Reporter,
+    ) -> Result<Vec<Listener>> {
+        if self
+            .last_check
+            .is_some_and(|at| at.elapsed() < CHECK_INTERVAL)
+        {
+            return Ok(Vec::new());
+        }
+        self.last_check = Some(Instant::now());
+
+        let addresses = docker::get_container_addresses(container_id, network)?;
+        if proxy::supervise(&addresses)? {
+            report.info(format!(
+                "Container IP changed, native forwards now target {}.",
+                addresses.ipv4
+            ));
+        }
+
+        let forwards = docker::list_port_forwards(ws_id)?;
+        let mut shared_problem = None;
+        // Forwards from other compose services don't depend on the devcontainer
+        for fwd in forwards.iter().filter(|f| f.service.is_empty()) {
+            let Some(problem) = sidecar_problem(fwd, &addresses) else {
+                continue;
+            };
+            if fwd.shared {
+                shared_problem.get_or_insert(problem);
+            } else if self.may_restart(&fwd.name) {
+                report.info(format!("Sidecar {} {problem}, restarting...", fwd.name));
+                if let Err(e) = restart_sidecar(ws_id, container_id, network, fwd) {
+                    report.warn(format!("failed to restart {}: {e}", fwd.name));
+                }
+            }
+        }
+        let shared_name = docker::shared_sidecar_name(ws_id);
+        if let Some(problem) = shared_problem {
+            if self.may_restart(&shared_name) {
+                report.info(format!("Sidecar {shared_name} {problem}, restarting..."));
+                if let Err(e) = docker::restart_shared_forwards(ws_id, container_id, network) {
+                    report.warn(format!("failed to restart {shared_name}: {e}"));
+                }
+            }
+        }
+
+        // Sidecars run with --rm, so a crashed one is simply gone
+        let mut present: HashSet<Listener> = forwards
+            .iter()
+            .filter(|f| f.source == "watch")
+            .filter_map(|f| Some((f.container_port.parse().ok()?, f.protocol)))
+            .collect();
+        present.extend(
+            proxy::list_forwards()?
+                .iter()
+                .filter(|f| f.source.as_deref() == Some("watch"))
+                .map(|f| (f.container_port, f.protocol)),
+        );
+        let mut gone: Vec<Listener> = managed.difference(&present).copied().collect();
+        gone.sort_unstable();
+        managed.retain(|p| present.contains(p));
+        Ok(gone)
+    }
+
+    fn may_restart(&mut self, name: &str) -> bool {
+        let now = Instant::now();
+        match self.restarted.get(name) {
+            Some(at) if now.duration_since(*at) < RESTART_COOLDOWN => false,
+            _ => {
+                self.restarted.insert(name.to_string(), now);
+                true
+            }
+        }
+    }
+}
+
+/// Display a listener as `3000` for TCP or `5353/udp` for UDP.
+fn listener_name(port: u16, protocol: Protocol) -> String {
+    match protocol {
+        Protocol::Tcp => port.to_string(),
+        Protocol::Udp => format!("{port}/udp"),
+    }
+}
+
+/// Where a forward can be reached from the host: a URL for TCP, an address
+/// for UDP. Wildcard binds are shown as `localhost`.
+fn forward_address(bind: &str, host_port: u16, protocol: Protocol) -> String {
+    let url = browser::forward_url("http", bind, host_port);
+    match protocol {
+        Protocol::Tcp => url,
+        Protocol::Udp => format!("{}/udp", url.trim_start_matches("http://")),
+    }
+}
+
+fn notify_user(title: &str, body: &str) {
+    if let Err(e) = notify::send(title, body) {
+        warn!("failed to show notification: {e}");
+    }
+}
+
+/// Why a sidecar needs restarting, if it does.
+fn sidecar_problem(
+    fwd: &docker::PortForwardInfo,
+    addresses: &docker::ContainerAddresses,
+) -> Option<&'static str> {
+    if !fwd.target.is_empty() && !addresses.contains(&fwd.target) {
+        return Some("targets a stale container IP");
+    }
+    if fwd.protocol == Protocol::Tcp {
+        let port = fwd.host_port.parse().ok()?;
+        if !docker::host_port_reachable(&fwd.bind, port) {
+            return Some("is not accepting connections");
+        }
+    }
+    None
+}
+
+/// Recreate a per-port sidecar with the settings it was created with.
+fn restart_sidecar(
+    ws_id: &str,
+    container_id: &str,
+    network: &str,
+    fwd: &docker::PortForwardInfo,
+) -> Result<()> {
+    let host_port = fwd.host_port.parse().context("invalid host port label")?;
+    let container_port = fwd.container_port.parse().unwrap_or(host_port);
+    let spec = docker::ForwardSpec {
+        protocol: fwd.protocol,
+        detach: true,
+        source: (!fwd.source.is_empty()).then_some(fwd.source.as_str()),
+        bind_address: (!fwd.bind.is_empty()).then_some(fwd.bind.as_str()),
+        label: (!fwd.label.is_empty()).then_some(fwd.label.as_str()),
+        // A stale IPv6 target is replaced by the new IPv6 address
+        ipv6: fwd.target.contains(':'),
+        ..docker::ForwardSpec::new(host_port, container_port)
+    };
+    if fwd.unix_socket.is_empty() {
+        docker::start_port_forward(ws_id, container_id, network, &spec)?;
+    } else {
+        docker::start_unix_socket_forward(ws_id, container_id, network, &spec, &fwd.unix_socket)?;
+    }
+    Ok(())
+}
+
+/// How long `stop` waits for the watcher to clean up its forwards.
+const STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// State of the workspace's background watcher.
+#[derive(Debug, Serialize)]
+struct WatcherStatus {
+    /// `running`, `restarting` or `stopped`
+    state: String,
+    pid: Option<u32>,
+    /// Seconds since the watcher was started
+    uptime_secs: Option<u64>,
+    /// Restarts after the watch loop crashed
+    health: WatcherHealth,
+    /// Forwards created by the watcher
+    forwards: Vec<ForwardRow>,
+}
+
+/// Print whether the watcher is running, for how long, and which forwards
+/// it manages.
+pub fn print_status(ws_id: &str, format: OutputFormat) -> Result<()> {
+    let watcher = daemon::watcher(ws_id);
+    let status = WatcherStatus {
+        state: watcher
+            .as_ref()
+            .map_or("stopped".to_string(), |w| w.state.clone()),
+        pid: watcher.as_ref().and_then(|w| w.pid),
+        uptime_secs: watcher.as_ref().and_then(|w| w.uptime_secs),
+        health: watcher.map(|w| w.health).unwrap_or_default(),
+        forwards: port::collect_forwards(ws_id)?
+            .into_iter()
+            .filter(|f| f.source.as_deref() == Some("watch"))
+            .collect(),
+    };
+
+    if format == OutputFormat::Json {
+        return output::print_json(&status);
+    }
+
+    match (status.state.as_str(), status.pid) {
+        ("running", Some(pid)) => {
+            let uptime = status.uptime_secs.map(format_uptime).unwrap_or_default();
+            println!("Watcher is running (pid {pid}, up {uptime}).");
+        }
+        ("restarting", _) => println!("Watcher crashed and is about to be restarted."),
+        _ => println!("Watcher is not running."),
+    }
+    if let Some(restarts) = status.health.describe() {
+        println!("Watch loop {restarts}.");
+    }
+    if status.forwards.is_empty() {
+        println!("No watcher-managed forwards.");
+    } else {
+        println!("Managed forwards:");
+        for fwd in &status.forwards {
+            let port = fwd.container_port.unwrap_or(fwd.host_port);
+            println!(
+                "  {}:{} -> {}",
+                fwd.bind,
+                fwd.host_port,
+                listener_name(port, fwd.protocol)
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Stop the background watcher, waiting for it to remove its forwards.
+/// Leftover forwards (e.g. from a watcher that crashed) are removed too.
+pub fn stop(ws_id: &str) -> Result<()> {
+    match daemon::unwatch(&workspace::workspace_folder()?)? {
+        Some(pid) => {
+            info!("Stopping port watcher (pid {pid})...");
+            let pid = pid as i32;
+            let deadline = Instant::now() + STOP_TIMEOUT;
+            while process::is_dcw_process(pid) && Instant::now() < deadline {
+                thread::sleep(Duration::from_millis(100));
+            }
+            if process::is_dcw_process(pid) {
+                warn!(
+                    "watcher (pid {pid}) did not exit within {}s.",
+                    STOP_TIMEOUT.as_secs()
+                );
+            }
+        }
+        None => info!("Watcher is not running."),
+    }
+    remove_watch_forwards(ws_id)?;
+    info!("Watcher stopped.");
+    Ok(())
+}
+
+/// Restarts of the watch loop, recorded by `dcw daemon`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WatcherHealth {
+    pub restarts: u32,
+    /// Unix time of the last restart
+    pub last_restart: Option<u64>,
+    /// How the watch loop exited before the last restart, e.g. `exit status: 101`
+    pub last_exit: Option<String>,
+}
+
+impl WatcherHealth {
+    /// e.g. `restarted 2 times, last 3m 12s ago after exit status: 101`
+    pub fn describe(&self) -> Option<String> {
+        if self.restarts == 0 {
+            return None;
+        }
+        let times = if self.restarts == 1 { "time" } else { "times" };
+        let mut text = format!("restarted {} {times}", self.restarts);
+        if let Some(ago) = self.last_restart.and_then(seconds_since) {
+            text.push_str(&format!(", last {} ago", format_uptime(ago)));
+        }
+        if let Some(exit) = &self.last_exit {
+            text.push_str(&format!(" after {exit}"));
+        }
+        Some(text)
+    }
+}
+
+fn seconds_since(unix_time: u64) -> Option<u64> {
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .ok()?;
+    now.as_secs().checked_sub(unix_time)
+}
+
+/// Format an uptime as e.g. `2h 5m`, `3m 12s` or `40s`.
+pub fn format_uptime(secs: u64) -> String {
+    let (h, m, s) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if h > 0 {
+        format!("{h}h {m}m")
+    } else if m > 0 {
+        format!("{m}m {s}s")
+    } else {
+        format!("{s}s")
+    }
+}
+
+/// The `onAutoForward` policy for a detected port, taking
+/// `otherPortsAttributes` into account.
+fn on_auto_forward(config: &WatchConfig, port: u16) -> OnAutoForward {
+    config
+        .attributes
+        .detected(port)
+        .map(|a| a.on_auto_forward)
+        .unwrap_or_default()
+}
+
+/// Remove watcher-managed forwards from every backend.
+/// Forwards the watcher created for the devcontainer, with their host
+/// address.
+fn watch_listeners(forwards: &[ForwardRow]) -> HashMap<Listener, String> {
+    forwards
+        .iter()
+        .filter(|f| f.source.as_deref() == Some("watch") && f.service.is_none())
+        .filter_map(|f| {
+            let listener = (f.container_port?, f.protocol);
+            Some((listener, format!("{}:{}", f.bind, f.host_port)))
+        })
+        .collect()
+}
+
+fn remove_watch_forwards(ws_id: &str) -> Result<()> {
+    docker::remove_port_forwards_by_source(ws_id, "watch")?;
+    docker::remove_shared_forwards(ws_id, |f| f.source.as_deref() == Some("watch"))?;
+    proxy::remove_forwards_by_source("watch")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -162,7 +1220,7 @@ mod tests {
    2: 0100007F:0035 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 12347 1 0000000000000000 100 0 0 10 0
    3: 0100007F:C350 0100007F:0BB8 01 00000000:00000000 00:00000000 00000000     0        0 12348 1 0000000000000000 100 0 0 10 0";
 
-        let ports = parse_proc_net_tcp(content);
+        let ports = parse_proc_net_tcp(content, true);
         // 0x0BB8 = 3000, 0x1F90 = 8080, 0x0035 = 53
         // Line 3 is state 01 (ESTABLISHED), should be excluded
         assert!(ports.contains(&3000));
@@ -170,13 +1228,34 @@ mod tests {
         assert!(ports.contains(&53));
         assert!(!ports.contains(&50000)); // 0xC350 = 50000 but state is 01
         assert_eq!(ports.len(), 3);
+
+        // 53 only listens on 127.0.0.1
+        assert_eq!(
+            parse_proc_net_tcp(content, false),
+            HashSet::from([3000, 8080])
+        );
+    }
+
+    #[test]
+    fn loopback_addresses() {
+        assert!(is_loopback_address("0100007F:0BB8"));
+        assert!(is_loopback_address("0200007F:0BB8"));
+        assert!(!is_loopback_address("00000000:0BB8"));
+        assert!(!is_loopback_address("020012AC:0BB8"));
+        // ::1, ::, and ::ffff:127.0.0.1
+        assert!(is_loopback_address("00000000000000000000000001000000:0BB8"));
+        assert!(!is_loopback_address(
+            "00000000000000000000000000000000:0BB8"
+        ));
+        assert!(is_loopback_address("0000000000000000FFFF00000100007F:0BB8"));
+        assert!(!is_loopback_address("garbage"));
     }
 
     #[test]
     fn parse_tcp_empty() {
         let content = "\
   sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode";
-        let ports = parse_proc_net_tcp(content);
+        let ports = parse_proc_net_tcp(content, false);
         assert!(ports.is_empty());
     }
 
@@ -186,8 +1265,309 @@ mod tests {
   sl  local_address                         remote_address                        st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
    0: 00000000000000000000000000000000:1F90 00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 12345 1 0000000000000000 100 0 0 10 0";
 
-        let ports = parse_proc_net_tcp(content);
+        let ports = parse_proc_net_tcp(content, false);
         assert!(ports.contains(&8080));
         assert_eq!(ports.len(), 1);
     }
+
+    #[test]
+    fn ipv6_only_listeners_in_socket_tables() {
+        let header = "  sl  local_address rem_address   st\n";
+        let tables = SocketTables {
+            // 0.0.0.0:3000
+            tcp: format!("{header}   0: 00000000:0BB8 00000000:0000 0A"),
+            // [::]:3000, [::]:8080, [::1]:9229
+            tcp6: Some(format!(
+                "{header}   0: 00000000000000000000000000000000:0BB8 00000000000000000000000000000000:0000 0A\n\
+                    1: 00000000000000000000000000000000:1F90 00000000000000000000000000000000:0000 0A\n\
+                    2: 00000000000000000000000001000000:2405 00000000000000000000000000000000:0000 0A"
+            )),
+            udp: None,
+            udp6: None,
+        };
+        assert_eq!(tables.ipv6_only(), HashSet::from([(8080, Protocol::Tcp)]));
+        assert_eq!(tables.listeners(true).len(), 3);
+    }
+
+    #[test]
+    fn parse_udp_bound_sockets() {
+        let content = "\
+   sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode ref pointer drops
+ 1225: 0100007F:D471 0100007F:14E9 01 00000000:00000000 00:00000000 00000000     0        0 35205 2 0000000000000000 0
+ 1345: 00000000:14E9 00000000:0000 07 00000000:00000000 00:00000000 00000000     0        0 35204 2 0000000000000000 0";
+
+        let ports = parse_proc_net_udp(content, false);
+        // 0x14E9 = 5353; the connected client socket (state 01) is excluded
+        assert_eq!(ports, HashSet::from([5353]));
+    }
+
+    #[test]
+    fn parse_streamed_listen_addresses() {
+        let lines = [
+            "tcp 00000000:0BB8",
+            "tcp 0100007F:1F90",
+            "tcp 00000000000000000000000000000000:1F90",
+            "udp 00000000:14E9",
+            "",
+        ];
+        let ports = parse_listen_addresses(&lines, true);
+        assert_eq!(
+            ports,
+            HashSet::from([
+                (3000, Protocol::Tcp),
+                (8080, Protocol::Tcp),
+                (5353, Protocol::Udp)
+            ])
+        );
+        // 8080 is also bound to :: and stays
+        assert_eq!(parse_listen_addresses(&lines, false), ports);
+        assert_eq!(
+            parse_listen_addresses(&["tcp 0100007F:1F90"], false),
+            HashSet::new()
+        );
+    }
+
+    #[test]
+    fn forward_address_formats() {
+        assert_eq!(
+            forward_address("127.0.0.1", 3000, Protocol::Tcp),
+            "http://127.0.0.1:3000"
+        );
+        assert_eq!(
+            forward_address("0.0.0.0", 3000, Protocol::Tcp),
+            "http://localhost:3000"
+        );
+        assert_eq!(
+            forward_address("::1", 3000, Protocol::Tcp),
+            "http://[::1]:3000"
+        );
+        assert_eq!(
+            forward_address("127.0.0.1", 5353, Protocol::Udp),
+            "127.0.0.1:5353/udp"
+        );
+    }
+
+    fn test_config() -> WatchConfig {
+        WatchConfig {
+            interval: 2,
+            min_port: 1024,
+            exclude_ports: HashSet::from([5432]),
+            bind_address: None,
+            fallback: false,
+            backend: ForwardBackend::Sidecar,
+            remove_delay: Duration::ZERO,
+            idle_timeout: None,
+            poll: false,
+            udp: false,
+            include_loopback: false,
+            notify: false,
+            live: true,
+            attributes: PortsAttributes::default(),
+        }
+    }
+
+    #[test]
+    fn live_rows_explain_status() {
+        let listening = HashSet::from([
+            (80, Protocol::Tcp),
+            (3000, Protocol::Tcp),
+            (5432, Protocol::Tcp),
+            (8080, Protocol::Tcp),
+        ]);
+        let managed = HashSet::from([(3000, Protocol::Tcp)]);
+        let hosts = HashMap::from([((3000, Protocol::Tcp), "127.0.0.1:3000".to_string())]);
+        let rows = live_rows(&test_config(), &listening, &managed, &hosts);
+        let status = |port| rows[&(port, Protocol::Tcp)].status;
+        assert_eq!(status(80), "below min");
+        assert_eq!(status(3000), "forwarded");
+        assert_eq!(status(5432), "excluded");
+        assert_eq!(status(8080), "failed");
+        assert_eq!(rows[&(3000, Protocol::Tcp)].host, "127.0.0.1:3000");
+        assert_eq!(rows[&(8080, Protocol::Tcp)].host, "");
+    }
+
+    #[test]
+    fn live_view_keeps_change_time_of_unchanged_rows() {
+        let row = |status| LiveRow {
+            status,
+            host: String::new(),
+            label: String::new(),
+        };
+        let mut view = LiveView::default();
+        view.update(BTreeMap::from([
+            ((3000, Protocol::Tcp), row("failed")),
+            ((8080, Protocol::Tcp), row("forwarded")),
+        ]));
+        let first = view.rows[&(8080, Protocol::Tcp)].1;
+        thread::sleep(Duration::from_millis(5));
+        view.update(BTreeMap::from([
+            ((3000, Protocol::Tcp), row("forwarded")),
+            ((8080, Protocol::Tcp), row("forwarded")),
+        ]));
+        assert_eq!(view.rows[&(8080, Protocol::Tcp)].1, first);
+        assert!(view.rows[&(3000, Protocol::Tcp)].1 > first);
+    }
+
+    #[test]
+    fn live_view_keeps_recent_events() {
+        let mut view = LiveView::default();
+        for i in 0..LIVE_EVENTS + 2 {
+            view.event(format!("  event {i}"));
+        }
+        assert_eq!(view.events.len(), LIVE_EVENTS);
+        assert_eq!(
+            view.events.back().unwrap().1,
+            format!("event {}", LIVE_EVENTS + 1)
+        );
+    }
+
+    #[test]
+    fn format_uptime_units() {
+        assert_eq!(format_uptime(40), "40s");
+        assert_eq!(format_uptime(192), "3m 12s");
+        assert_eq!(format_uptime(7500), "2h 5m");
+    }
+
+    #[test]
+    fn listener_name_marks_udp() {
+        assert_eq!(listener_name(3000, Protocol::Tcp), "3000");
+        assert_eq!(listener_name(5353, Protocol::Udp), "5353/udp");
+    }
+
+    fn sidecar(target: &str, protocol: docker::Protocol) -> docker::PortForwardInfo {
+        docker::PortForwardInfo {
+            name: "pf-dev-x-c3000".to_string(),
+            host_port: "3000".to_string(),
+            container_port: "3000".to_string(),
+            source: "watch".to_string(),
+            protocol,
+            unix_socket: String::new(),
+            bind: "127.0.0.1".to_string(),
+            shared: false,
+            label: String::new(),
+            target: target.to_string(),
+            service: String::new(),
+        }
+    }
+
+    fn addresses(ipv4: &str, ipv6: Option<&str>) -> docker::ContainerAddresses {
+        docker::ContainerAddresses {
+            ipv4: ipv4.to_string(),
+            ipv6: ipv6.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn sidecar_problem_detects_stale_target() {
+        let fwd = sidecar("172.17.0.2", Protocol::Tcp);
+        assert_eq!(
+            sidecar_problem(&fwd, &addresses("172.17.0.3", None)),
+            Some("targets a stale container IP")
+        );
+        let fwd = sidecar("fd00::2", Protocol::Udp);
+        assert_eq!(
+            sidecar_problem(&fwd, &addresses("172.17.0.2", Some("fd00::3"))),
+            Some("targets a stale container IP")
+        );
+        assert_eq!(
+            sidecar_problem(&fwd, &addresses("172.17.0.2", Some("fd00::2"))),
+            None
+        );
+    }
+
+    #[test]
+    fn sidecar_problem_skips_probe_for_udp() {
+        let fwd = sidecar("172.17.0.2", Protocol::Udp);
+        assert_eq!(sidecar_problem(&fwd, &addresses("172.17.0.2", None)), None);
+        // Sidecars without a target label are only probed
+        let fwd = sidecar("", Protocol::Udp);
+        assert_eq!(sidecar_problem(&fwd, &addresses("172.17.0.2", None)), None);
+    }
+
+    #[test]
+    fn closed_ports_wait_for_delay() {
+        let delay = Duration::from_secs(10);
+        let start = Instant::now();
+        let web = (3000, Protocol::Tcp);
+        let mut closed = ClosedPorts::default();
+
+        let changes = closed.update(&HashSet::from([web]), delay, start);
+        assert_eq!(changes.closed, [web]);
+        assert!(changes.due.is_empty());
+
+        // Back before the delay: kept
+        let changes = closed.update(&HashSet::new(), delay, start + Duration::from_secs(3));
+        assert_eq!(changes.reopened, [web]);
+
+        // Gone again, now for the whole delay
+        let again = start + Duration::from_secs(5);
+        closed.update(&HashSet::from([web]), delay, again);
+        let changes = closed.update(&HashSet::from([web]), delay, again + Duration::from_secs(9));
+        assert_eq!(changes, ClosedChanges::default());
+        let changes = closed.update(&HashSet::from([web]), delay, again + delay);
+        assert_eq!(changes.due, [web]);
+        assert!(closed.since.is_empty());
+    }
+
+    #[test]
+    fn closed_ports_without_delay_are_due_at_once() {
+        let mut closed = ClosedPorts::default();
+        let web = (3000, Protocol::Tcp);
+        let changes = closed.update(&HashSet::from([web]), Duration::ZERO, Instant::now());
+        assert_eq!(
+            changes,
+            ClosedChanges {
+                due: vec![web],
+                ..ClosedChanges::default()
+            }
+        );
+    }
+
+    #[test]
+    fn parse_delay_units() {
+        assert_eq!(parse_delay("10"), Ok(Duration::from_secs(10)));
+        assert_eq!(parse_delay("10s"), Ok(Duration::from_secs(10)));
+        assert_eq!(parse_delay("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_delay("2m"), Ok(Duration::from_secs(120)));
+        assert!(parse_delay("10h").is_err());
+        assert!(parse_delay("soon").is_err());
+    }
+
+    #[test]
+    fn watch_listeners_from_existing_forwards() {
+        let row = |source: Option<&str>, container_port, host_port| ForwardRow {
+            name: String::new(),
+            backend: ForwardBackend::Native,
+            host_port,
+            container_port: Some(container_port),
+            protocol: Protocol::Tcp,
+            bind: "127.0.0.1".to_string(),
+            unix_socket: None,
+            source: source.map(str::to_string),
+            label: None,
+            service: None,
+            hostname: None,
+            stats: None,
+        };
+        let forwards = [
+            row(Some("watch"), 3000, 3001),
+            row(None, 8080, 8080),
+            ForwardRow {
+                service: Some("db".to_string()),
+                ..row(Some("watch"), 5432, 5432)
+            },
+        ];
+        assert_eq!(
+            watch_listeners(&forwards),
+            HashMap::from([((3000, Protocol::Tcp), "127.0.0.1:3001".to_string())])
+        );
+    }
+
+    #[test]
+    fn supervisor_restart_cooldown() {
+        let mut supervisor = Supervisor::default();
+        assert!(supervisor.may_restart("pf-a"));
+        assert!(!supervisor.may_restart("pf-a"));
+        assert!(supervisor.may_restart("pf-b"));
+    }
 }
@@ -0,0 +1,80 @@
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::path::PathBuf;
+use tracing::info;
+
+use crate::config;
+use crate::output::{self, OutputFormat};
+use crate::registry::Registry;
+use crate::workspace;
+
+#[derive(clap::Subcommand)]
+pub enum WorkspaceAction {
+    /// Register a workspace under a name for `-w`/`--workspace`
+    Add {
+        /// Name to register
+        name: String,
+        /// Workspace folder (default: the current workspace)
+        path: Option<PathBuf>,
+    },
+    /// Unregister a workspace name
+    #[command(alias = "rm")]
+    Remove {
+        /// Name to unregister
+        name: String,
+    },
+    /// List registered workspaces
+    #[command(alias = "ls")]
+    List,
+}
+
+#[derive(Serialize)]
+struct WorkspaceRow {
+    name: String,
+    path: PathBuf,
+}
+
+pub fn run(action: &WorkspaceAction, format: OutputFormat) -> Result<()> {
+    let mut registry = Registry::load()?;
+    match action {
+        WorkspaceAction::Add { name, path } => {
+            config::check_name("workspace", name)?;
+            let path = match path {
+                Some(path) => std::path::absolute(path).context("failed to resolve path")?,
+                None => PathBuf::from(workspace::workspace_folder()?),
+            };
+            if !path.is_dir() {
+                bail!("not a directory: {}", path.display());
+            }
+            info!("Registered workspace '{name}' -> {}", path.display());
+            registry.workspaces.insert(name.clone(), path);
+            registry.save()?;
+        }
+        WorkspaceAction::Remove { name } => {
+            if registry.workspaces.remove(name).is_none() {
+                bail!("unknown workspace '{name}'");
+            }
+            registry.save()?;
+            info!("Unregistered workspace '{name}'.");
+        }
+        WorkspaceAction::List => {
+            let rows: Vec<WorkspaceRow> = registry
+                .workspaces
+                .into_iter()
+                .map(|(name, path)| WorkspaceRow { name, path })
+                .collect();
+            if format == OutputFormat::Json {
+                output::print_json(&rows)?;
+            } else if rows.is_empty() {
+                println!("No workspaces registered (add one with `dcw workspace add`).");
+            } else {
+                let width = rows.iter().map(|r| r.name.len()).max().unwrap_or(0).max(4);
+                println!("{:<width$}   PATH", "NAME");
+                for row in &rows {
+                    println!("{:<width$}   {}", row.name, row.path.display());
+                }
+            }
+        }
+    }
+    Ok(())
+}
@@ -1,10 +1,26 @@
 use anyhow::{Context, Result};
+use jsonc_parser::cst::{CstObject, CstRootNode};
 use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::env;
 use std::fs;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tracing::warn;
 
+use crate::docker;
+use crate::secrets;
 use crate::workspace;
 
+static CONFIG_FLAG: OnceLock<PathBuf> = OnceLock::new();
+
+/// Secrets file in `.devcontainer/` that `dcw up` picks up when no
+/// `--secrets-file` is given. Not meant to be committed.
+pub const SECRETS_FILE: &str = "secrets.local.json";
+
 /// Read a JSONC file (JSON with comments and trailing commas) and parse it.
 pub fn read_jsonc(path: &Path) -> Result<Value> {
     let content =
@@ -18,12 +34,31 @@ pub fn read_jsonc(path: &Path) -> Result<Value> {
 ///
 /// - Objects: keys from overlay are merged recursively; keys only in base are preserved.
 /// - Arrays and scalars: overlay replaces base.
+/// - Array directives: an object of `$append`, `$prepend` and/or `$remove`
+///   arrays edits the base array instead of replacing it, and `"key+": [...]`
+///   is shorthand for `"key": {"$append": [...]}`.
 pub fn deep_merge(base: &mut Value, overlay: Value) {
+    if let Some(directive) = ArrayDirective::parse(&overlay) {
+        directive.apply(base);
+        return;
+    }
     match (base, overlay) {
         (Value::Object(base_map), Value::Object(overlay_map)) => {
             for (key, overlay_val) in overlay_map {
-                let entry = base_map.entry(key).or_insert(Value::Null);
-                deep_merge(entry, overlay_val);
+                match (key.strip_suffix('+'), overlay_val) {
+                    (Some(key), Value::Array(items)) if !key.is_empty() => {
+                        let entry = base_map.entry(key).or_insert(Value::Null);
+                        ArrayDirective {
+                            append: items,
+                            ..Default::default()
+                        }
+                        .apply(entry);
+                    }
+                    (_, overlay_val) => {
+                        let entry = base_map.entry(key).or_insert(Value::Null);
+                        deep_merge(entry, overlay_val);
+                    }
+                }
             }
         }
         (base, overlay) => {
@@ -32,6 +67,46 @@ pub fn deep_merge(base: &mut Value, overlay: Value) {
     }
 }
 
+/// Edits to an array in a config overlay: `{"$append": [...], "$prepend": [...], "$remove": [...]}`.
+#[derive(Debug, Default)]
+struct ArrayDirective {
+    append: Vec<Value>,
+    prepend: Vec<Value>,
+    remove: Vec<Value>,
+}
+
+impl ArrayDirective {
+    /// Parse an overlay value made up only of directive keys with array values.
+    fn parse(overlay: &Value) -> Option<Self> {
+        let map = overlay.as_object().filter(|m| !m.is_empty())?;
+        let mut directive = Self::default();
+        for (key, value) in map {
+            let items = value.as_array()?.clone();
+            match key.as_str() {
+                "$append" => directive.append = items,
+                "$prepend" => directive.prepend = items,
+                "$remove" => directive.remove = items,
+                _ => return None,
+            }
+        }
+        Some(directive)
+    }
+
+    /// Apply to `base`: remove, then prepend and append. A missing or
+    /// non-array base counts as empty.
+    fn apply(self, base: &mut Value) {
+        let mut items = match base.take() {
+            Value::Array(items) => items,
+            _ => Vec::new(),
+        };
+        items.retain(|item| !self.remove.contains(item));
+        let mut merged = self.prepend;
+        merged.append(&mut items);
+        merged.extend(self.append);
+        *base = Value::Array(merged);
+    }
+}
+
 /// Convert a relative path to absolute by joining it with `base`.
 /// If the path is already absolute, return it unchanged.
 fn make_absolute(path_str: &str, base: &Path) -> String {
@@ -113,38 +188,1157 @@ fn resolve_build_paths(config: &mut Value, config_dir: &Path) {
     }
 }
 
-/// Resolve the devcontainer config for the workspace.
+/// The `${devcontainerId}` the devcontainer CLI derives from a container's
+/// id labels: the SHA-256 of the labels as sorted JSON, in base 32, padded
+/// to 52 digits.
+pub fn devcontainer_id(local_folder: &str, config_file: &Path) -> String {
+    let labels = BTreeMap::from([
+        ("devcontainer.config_file", config_file.to_string_lossy()),
+        ("devcontainer.local_folder", local_folder.into()),
+    ]);
+    let json = serde_json::to_string(&labels).unwrap_or_default();
+    let hash = Sha256::digest(json.as_bytes());
+
+    const DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuv";
+    let bits = hash.len() * 8;
+    let bit = |i: usize| (hash[hash.len() - 1 - i / 8] >> (i % 8)) & 1;
+    let mut id: Vec<u8> = (0..bits.div_ceil(5))
+        .map(|d| {
+            let v = (0..5)
+                .filter(|b| d * 5 + b < bits)
+                .fold(0, |v, b| v | bit(d * 5 + b) << b);
+            DIGITS[v as usize]
+        })
+        .collect();
+    id.reverse();
+    String::from_utf8(id).unwrap_or_default()
+}
+
+/// Replace the `${...}` variables in `s` that `lookup` knows; others are
+/// left for the devcontainer CLI.
+fn substitute_str(s: &str, lookup: &dyn Fn(&str) -> Option<String>) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find("${") {
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        let var = &rest[start + 2..start + len];
+        match lookup(var) {
+            Some(value) => out.push_str(&value),
+            None => out.push_str(&rest[start..=start + len]),
+        }
+        rest = &rest[start + len + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn substitute_value(value: &mut Value, lookup: &dyn Fn(&str) -> Option<String>) {
+    match value {
+        Value::String(s) => *s = substitute_str(s, lookup),
+        Value::Array(items) => items.iter_mut().for_each(|v| substitute_value(v, lookup)),
+        Value::Object(map) => map.values_mut().for_each(|v| substitute_value(v, lookup)),
+        _ => {}
+    }
+}
+
+/// Expand `${localEnv:VAR}` (with optional `:default`), `${localWorkspaceFolder}`,
+/// `${containerWorkspaceFolder}` (and their `Basename` forms) and
+/// `${devcontainerId}` in every string of a config.
 ///
-/// If `.devcontainer/devcontainer.local.json` exists, merges it on top of
-/// `devcontainer.json` and writes the result to runtime_dir. Returns the
-/// path to the merged config file.
+/// The devcontainer CLI expands these relative to the config it is given,
+/// which for configs dcw writes is in the runtime directory, so dcw expands
+/// them first.
+pub fn substitute_variables(config: &mut Value, local_folder: &str, devcontainer_id: &str) {
+    let basename = |path: &str| {
+        Path::new(path)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default()
+    };
+    let local_basename = basename(local_folder);
+    let local = |var: &str| -> Option<String> {
+        if let Some(env_var) = var
+            .strip_prefix("localEnv:")
+            .or_else(|| var.strip_prefix("env:"))
+        {
+            let (name, default) = env_var.split_once(':').unwrap_or((env_var, ""));
+            return Some(env::var(name).unwrap_or_else(|_| default.to_string()));
+        }
+        match var {
+            "localWorkspaceFolder" => Some(local_folder.to_string()),
+            "localWorkspaceFolderBasename" => Some(local_basename.clone()),
+            "devcontainerId" => Some(devcontainer_id.to_string()),
+            _ => None,
+        }
+    };
+
+    // workspaceFolder may itself use local variables
+    let container_folder = match config.get("workspaceFolder").and_then(Value::as_str) {
+        Some(folder) => substitute_str(folder, &local),
+        None if config.get("dockerComposeFile").is_some() => "/".to_string(),
+        None => format!("/workspaces/{local_basename}"),
+    };
+    let container_basename = basename(&container_folder);
+    let all = |var: &str| match var {
+        "containerWorkspaceFolder" => Some(container_folder.clone()),
+        "containerWorkspaceFolderBasename" => Some(container_basename.clone()),
+        _ => local(var),
+    };
+    substitute_value(config, &all);
+}
+
+/// Check that `name` is a usable profile or config name: letters, digits,
+/// `-` and `_`.
+pub fn check_name(kind: &str, name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        anyhow::bail!("invalid {kind} name '{name}'");
+    }
+    Ok(())
+}
+
+/// Names of the configs in `.devcontainer/<name>/devcontainer.json`, sorted.
+pub fn config_names(workspace_root: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(workspace_root.join(".devcontainer")) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .flatten()
+        .filter(|e| e.path().join("devcontainer.json").is_file())
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+    names
+}
+
+/// Check that `name` selects a config in `.devcontainer/<name>/`.
+pub fn check_config_name(workspace_root: &Path, name: &str) -> Result<()> {
+    check_name("config", name)?;
+    let path = workspace_root
+        .join(".devcontainer")
+        .join(name)
+        .join("devcontainer.json");
+    if !path.exists() {
+        let names = config_names(workspace_root);
+        let available = if names.is_empty() {
+            "none".to_string()
+        } else {
+            names.join(", ")
+        };
+        anyhow::bail!(
+            "config '{name}' not found: {} does not exist (available: {available})",
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Directory holding the workspace's `devcontainer.json`.
 ///
-/// If the local override does not exist, returns `None` (use default config).
-pub fn resolve_config(workspace_root: &Path) -> Result<Option<PathBuf>> {
+/// `.devcontainer/<name>/` for a named config, else `.devcontainer/` if it
+/// has a `devcontainer.json`, else the only `.devcontainer/*/` that has one.
+/// Errors if there are several to choose from.
+fn config_dir_for(workspace_root: &Path, config_name: Option<&str>) -> Result<PathBuf> {
     let dc_dir = workspace_root.join(".devcontainer");
+    if let Some(name) = config_name {
+        check_config_name(workspace_root, name)?;
+        return Ok(dc_dir.join(name));
+    }
+    if dc_dir.join("devcontainer.json").exists() {
+        return Ok(dc_dir);
+    }
+    match config_names(workspace_root).as_slice() {
+        [] => Ok(dc_dir),
+        [name] => Ok(dc_dir.join(name)),
+        names => anyhow::bail!(
+            "found several devcontainer configs ({}); choose one with `dcw up --config-name`",
+            names.join(", ")
+        ),
+    }
+}
+
+/// Use the config file given with the global `--config` flag, resolved
+/// against the current directory, for this run.
+pub fn set_config_flag(path: &Path) -> Result<()> {
+    let path = std::path::absolute(path).context("failed to resolve --config path")?;
+    if !path.is_file() {
+        anyhow::bail!("config file not found: {}", path.display());
+    }
+    let _ = CONFIG_FLAG.set(path);
+    Ok(())
+}
+
+/// The config file given with the global `--config` flag, if any.
+pub fn config_flag() -> Option<&'static Path> {
+    CONFIG_FLAG.get().map(PathBuf::as_path)
+}
+
+/// The selected config's directory and file: the `--config` flag, else the
+/// file recorded by the last `dcw up --config`, else `devcontainer.json` in
+/// the directory chosen by [`config_dir_for`].
+fn selected_config(workspace_root: &Path) -> Result<(PathBuf, PathBuf)> {
+    let file = config_flag()
+        .map(Path::to_path_buf)
+        .or_else(workspace::active_config_path);
+    if let Some(file) = file {
+        let dir = file.parent().map(Path::to_path_buf).unwrap_or_default();
+        return Ok((dir, file));
+    }
+    let dir = config_dir_for(workspace_root, workspace::active_config_name().as_deref())?;
+    let file = dir.join("devcontainer.json");
+    Ok((dir, file))
+}
+
+/// Directory holding the selected devcontainer config, where its
+/// `devcontainer.local.json`, profiles and secrets live.
+pub fn config_dir(workspace_root: &Path) -> Result<PathBuf> {
+    Ok(selected_config(workspace_root)?.0)
+}
+
+/// Path of the workspace's selected devcontainer config.
+pub fn config_file(workspace_root: &Path) -> Result<PathBuf> {
+    Ok(selected_config(workspace_root)?.1)
+}
+
+/// Config file the selected config's container is labeled with: the merged
+/// config written by [`resolve_config`] if there is anything to merge, else
+/// the config itself.
+pub fn labeled_config_file(workspace_root: &Path) -> Result<PathBuf> {
+    let (dc_dir, main_path) = selected_config(workspace_root)?;
+    if overlay_paths(&dc_dir, workspace::active_profile().as_deref())?.is_empty() {
+        return Ok(main_path);
+    }
+    Ok(workspace::runtime_dir()?.join("devcontainer.json"))
+}
+
+/// Path of the config for a profile: `devcontainer.<profile>.json` next to
+/// `devcontainer.json`.
+pub fn profile_config_path(dc_dir: &Path, profile: &str) -> PathBuf {
+    dc_dir.join(format!("devcontainer.{profile}.json"))
+}
+
+/// Check that `profile` names a profile config in the workspace.
+pub fn check_profile(workspace_root: &Path, profile: &str) -> Result<()> {
+    check_profile_in(&config_dir(workspace_root)?, profile)
+}
+
+fn check_profile_in(dc_dir: &Path, profile: &str) -> Result<()> {
+    check_name("profile", profile)?;
+    if profile == "local" {
+        anyhow::bail!("invalid profile name '{profile}'");
+    }
+    let path = profile_config_path(dc_dir, profile);
+    if !path.exists() {
+        anyhow::bail!(
+            "profile '{profile}' not found: {} does not exist",
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Config files merged on top of `devcontainer.json` in `dc_dir`, in order:
+/// the profile config, then `devcontainer.local.json`.
+fn overlay_paths(dc_dir: &Path, profile: Option<&str>) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    if let Some(profile) = profile {
+        check_profile_in(dc_dir, profile)?;
+        paths.push(profile_config_path(dc_dir, profile));
+    }
     let local_path = dc_dir.join("devcontainer.local.json");
+    if local_path.exists() {
+        paths.push(local_path);
+    }
+    Ok(paths)
+}
 
-    if !local_path.exists() {
-        return Ok(None);
+/// Resolve the devcontainer config for the workspace.
+///
+/// Merges the active profile's `devcontainer.<profile>.json` (see
+/// `dcw up --profile`) and then `devcontainer.local.json`, if present, on
+/// top of the selected config (see [`config_file`]) and writes the result to
+/// runtime_dir. Secret references among the local `remoteEnv` values (see
+/// [`secrets::SecretRef`]) are resolved first. Returns the path to the
+/// merged config file.
+///
+/// If there is nothing to merge, returns the path of the selected config, or
+/// `None` if it is `.devcontainer/devcontainer.json` (use default config).
+pub fn resolve_config(workspace_root: &Path) -> Result<Option<PathBuf>> {
+    let (dc_dir, main_path) = selected_config(workspace_root)?;
+    let overlays = overlay_paths(&dc_dir, workspace::active_profile().as_deref())?;
+
+    if overlays.is_empty() {
+        let default = main_path == workspace_root.join(".devcontainer/devcontainer.json");
+        return Ok((!default).then_some(main_path));
     }
 
-    let main_path = dc_dir.join("devcontainer.json");
     let mut base = read_jsonc(&main_path).context("failed to read devcontainer.json")?;
-    let overlay = read_jsonc(&local_path).context("failed to read devcontainer.local.json")?;
-
-    deep_merge(&mut base, overlay);
+    for path in overlays {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let mut overlay = read_jsonc(&path).with_context(|| format!("failed to read {name}"))?;
+        for issue in unknown_keys(&path, &overlay) {
+            warn!("{issue}");
+        }
+        // Only the uncommitted override may pull in the user's secrets
+        if name == "devcontainer.local.json" {
+            secrets::resolve_remote_env(&mut overlay)
+                .with_context(|| format!("failed to read {name}"))?;
+        }
+        deep_merge(&mut base, overlay);
+    }
     resolve_build_paths(&mut base, &dc_dir);
 
     let runtime = workspace::runtime_dir()?;
     fs::create_dir_all(&runtime).context("failed to create runtime directory")?;
 
     let merged_path = runtime.join("devcontainer.json");
+    let local_folder = workspace_root.to_string_lossy();
+    // The container is labeled with the merged config's path
+    let id = devcontainer_id(&local_folder, &merged_path);
+    substitute_variables(&mut base, &local_folder, &id);
     let json = serde_json::to_string_pretty(&base).context("failed to serialize merged config")?;
-    fs::write(&merged_path, json).context("failed to write merged config")?;
+    write_private(&merged_path, &json).context("failed to write merged config")?;
 
     Ok(Some(merged_path))
 }
 
+/// Load the effective devcontainer config for the workspace as JSON.
+///
+/// If a local override exists, returns the merged config; otherwise reads
+/// devcontainer.json directly. Returns `None` when there is no config at all.
+pub fn load_effective_config(workspace_root: &Path) -> Result<Option<Value>> {
+    let config_path = match resolve_config(workspace_root)? {
+        Some(merged) => merged,
+        None => {
+            let main_path = config_file(workspace_root)?;
+            if !main_path.exists() {
+                return Ok(None);
+            }
+            main_path
+        }
+    };
+
+    read_jsonc(&config_path).map(Some)
+}
+
+/// Add `dcw up --build-arg` / `--pull` to a config's `build.args` and
+/// `build.options`. Returns `false` (leaving the config alone) if it doesn't
+/// build from a Dockerfile.
+pub fn apply_build_overrides(
+    config: &mut Value,
+    build_args: &[(String, String)],
+    pull: bool,
+) -> bool {
+    let Some(obj) = config.as_object_mut() else {
+        return false;
+    };
+    if !builds_from_dockerfile(obj) {
+        return false;
+    }
+
+    let build = obj
+        .entry("build")
+        .or_insert_with(|| Value::Object(Default::default()));
+    let Some(build) = build.as_object_mut() else {
+        return false;
+    };
+    if !build_args.is_empty() {
+        let args = build
+            .entry("args")
+            .or_insert_with(|| Value::Object(Default::default()));
+        if let Some(args) = args.as_object_mut() {
+            for (key, value) in build_args {
+                args.insert(key.clone(), Value::String(value.clone()));
+            }
+        }
+    }
+    if pull {
+        let options = build
+            .entry("options")
+            .or_insert_with(|| Value::Array(Vec::new()));
+        if let Some(options) = options.as_array_mut() {
+            if !options.iter().any(|o| o == "--pull") {
+                options.push(Value::String("--pull".to_string()));
+            }
+        }
+    }
+    true
+}
+
+fn builds_from_dockerfile(obj: &serde_json::Map<String, Value>) -> bool {
+    obj.get("build")
+        .is_some_and(|b| b.get("dockerfile").is_some())
+        || obj.contains_key("dockerFile")
+}
+
+/// Start from the prebuilt `image` instead of building the Dockerfile. The
+/// prebuilt image already contains the features, so they are dropped too.
+/// Returns `false` (leaving the config alone) if it doesn't build from a
+/// Dockerfile.
+pub fn apply_prebuilt_image(config: &mut Value, image: &str) -> bool {
+    let Some(obj) = config.as_object_mut() else {
+        return false;
+    };
+    if !builds_from_dockerfile(obj) {
+        return false;
+    }
+    for key in ["build", "dockerFile", "context", "features"] {
+        obj.remove(key);
+    }
+    obj.insert("image".to_string(), Value::String(image.to_string()));
+    true
+}
+
+/// Start from `image`, committed from an earlier container by `dcw snapshot`,
+/// which already contains what the Dockerfile and features added. Returns
+/// `false` for Docker Compose configs, whose image is set in the compose file.
+pub fn apply_snapshot_image(config: &mut Value, image: &str) -> bool {
+    let Some(obj) = config.as_object_mut() else {
+        return false;
+    };
+    if obj.contains_key("dockerComposeFile") {
+        return false;
+    }
+    for key in ["build", "dockerFile", "context", "features"] {
+        obj.remove(key);
+    }
+    obj.insert("image".to_string(), Value::String(image.to_string()));
+    true
+}
+
+/// Add `dcw up --gpus` to a config's `runArgs`, replacing any `--gpus`
+/// already there. Returns `false` for Docker Compose configs, which don't
+/// use `runArgs`.
+pub fn apply_gpus(config: &mut Value, gpus: &str) -> bool {
+    let Some(obj) = config.as_object_mut() else {
+        return false;
+    };
+    if obj.contains_key("dockerComposeFile") {
+        return false;
+    }
+    let run_args = obj
+        .entry("runArgs")
+        .or_insert_with(|| Value::Array(Vec::new()));
+    let Some(run_args) = run_args.as_array_mut() else {
+        return false;
+    };
+    let mut kept = Vec::new();
+    let mut args = run_args.drain(..);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            Some("--gpus") => {
+                args.next();
+            }
+            Some(a) if a.starts_with("--gpus=") => {}
+            _ => kept.push(arg),
+        }
+    }
+    drop(args);
+    kept.push(Value::String(format!("--gpus={gpus}")));
+    *run_args = kept;
+    true
+}
+
+/// Host entry that points `host.docker.internal` at the host, which Docker
+/// Desktop provides but Linux engines don't.
+pub const HOST_GATEWAY: &str = "host.docker.internal:host-gateway";
+
+/// Map `host.docker.internal` to the host: an `--add-host` in `runArgs`, or
+/// for Docker Compose configs `compose_file` appended to `dockerComposeFile`
+/// (write [`host_gateway_compose`] there). Leaves an existing mapping alone.
+pub fn apply_host_gateway(config: &mut Value, compose_file: &Path) -> bool {
+    let Some(obj) = config.as_object_mut() else {
+        return false;
+    };
+    if let Some(files) = obj.get_mut("dockerComposeFile") {
+        let file = Value::String(compose_file.to_string_lossy().to_string());
+        match files {
+            Value::String(_) => *files = Value::Array(vec![files.take(), file]),
+            Value::Array(list) if !list.contains(&file) => list.push(file),
+            Value::Array(_) => {}
+            _ => return false,
+        }
+        return true;
+    }
+    let run_args = obj
+        .entry("runArgs")
+        .or_insert_with(|| Value::Array(Vec::new()));
+    let Some(run_args) = run_args.as_array_mut() else {
+        return false;
+    };
+    let mapped = run_args.iter().enumerate().any(|(i, arg)| {
+        let host = match arg.as_str() {
+            Some("--add-host") => run_args.get(i + 1).and_then(Value::as_str),
+            Some(a) => a.strip_prefix("--add-host="),
+            None => None,
+        };
+        host.is_some_and(|h| h.starts_with("host.docker.internal:"))
+    });
+    if !mapped {
+        run_args.push(Value::String(format!("--add-host={HOST_GATEWAY}")));
+    }
+    true
+}
+
+/// Compose override adding the host entry to `service`.
+pub fn host_gateway_compose(service: &str) -> String {
+    format!("services:\n  {service}:\n    extra_hosts:\n      - \"{HOST_GATEWAY}\"\n")
+}
+
+/// Add a `mounts` entry (in `source=...,target=...,type=...` form) unless
+/// it is already there. Returns `false` for Docker Compose configs, where
+/// mounts belong in the compose file.
+pub fn apply_mount(config: &mut Value, mount: &str) -> bool {
+    let Some(obj) = config.as_object_mut() else {
+        return false;
+    };
+    if obj.contains_key("dockerComposeFile") {
+        return false;
+    }
+    let mounts = obj
+        .entry("mounts")
+        .or_insert_with(|| Value::Array(Vec::new()));
+    let Some(mounts) = mounts.as_array_mut() else {
+        return false;
+    };
+    if !mounts.iter().any(|m| m.as_str() == Some(mount)) {
+        mounts.push(Value::String(mount.to_string()));
+    }
+    true
+}
+
+/// Read a secrets file: a JSONC object mapping environment variable names
+/// to string, number or boolean values.
+pub fn load_secrets(path: &Path) -> Result<Vec<(String, String)>> {
+    let value = read_jsonc(path)?;
+    let Value::Object(map) = value else {
+        anyhow::bail!("{} must contain a JSON object", path.display());
+    };
+    map.into_iter()
+        .map(|(key, value)| {
+            let value = match value {
+                Value::String(s) => s,
+                Value::Number(n) => n.to_string(),
+                Value::Bool(b) => b.to_string(),
+                _ => anyhow::bail!(
+                    "{}: value of `{key}` must be a string, number or boolean",
+                    path.display()
+                ),
+            };
+            Ok((key, value))
+        })
+        .collect()
+}
+
+/// Add secrets to a config's `remoteEnv`, overriding variables of the same
+/// name. Returns `false` if there are none.
+pub fn apply_secrets(config: &mut Value, secrets: &[(String, String)]) -> bool {
+    merge_env(config, "remoteEnv", secrets)
+}
+
+/// Add variables to a config's `containerEnv`, which unlike `remoteEnv` is
+/// also seen by `docker exec`. Returns `false` if there are none.
+pub fn apply_container_env(config: &mut Value, vars: &[(String, String)]) -> bool {
+    merge_env(config, "containerEnv", vars)
+}
+
+fn merge_env(config: &mut Value, key: &str, vars: &[(String, String)]) -> bool {
+    if vars.is_empty() {
+        return false;
+    }
+    let Some(obj) = config.as_object_mut() else {
+        return false;
+    };
+    let env = obj
+        .entry(key)
+        .or_insert_with(|| Value::Object(Default::default()));
+    if !env.is_object() {
+        *env = Value::Object(Default::default());
+    }
+    if let Some(env) = env.as_object_mut() {
+        for (key, value) in vars {
+            env.insert(key.clone(), Value::String(value.clone()));
+        }
+    }
+    true
+}
+
+/// Write the effective config, changed by `edit`, to the runtime directory
+/// and return its path. Returns `None` if `edit` reports that it changed
+/// nothing. The file is only readable by the user, as it may hold secrets.
+pub fn write_override_config(
+    workspace_root: &Path,
+    edit: impl FnOnce(&mut Value) -> bool,
+) -> Result<Option<PathBuf>> {
+    let (dc_dir, main_path) = selected_config(workspace_root)?;
+    let mut config = match resolve_config(workspace_root)? {
+        // Paths in the merged config are already absolute
+        Some(merged) if merged != main_path => read_jsonc(&merged)?,
+        _ => {
+            let mut config = read_jsonc(&main_path).context("failed to read devcontainer.json")?;
+            resolve_build_paths(&mut config, &dc_dir);
+            // `dcw up` labels the container with devcontainer.json's path
+            let local_folder = workspace_root.to_string_lossy();
+            let id = devcontainer_id(&local_folder, &main_path);
+            substitute_variables(&mut config, &local_folder, &id);
+            config
+        }
+    };
+    if !edit(&mut config) {
+        return Ok(None);
+    }
+
+    let runtime = workspace::runtime_dir()?;
+    let path = runtime.join("devcontainer.up.json");
+    if docker::dry_run() {
+        docker::print_dry_run(&format!("write {}", path.display()));
+        return Ok(Some(path));
+    }
+    fs::create_dir_all(&runtime).context("failed to create runtime directory")?;
+    let json = serde_json::to_string_pretty(&config).context("failed to serialize config")?;
+    write_private(&path, &json).context("failed to write config")?;
+    Ok(Some(path))
+}
+
+/// Write a file only the user can read, as configs may hold secrets.
+fn write_private(path: &Path, contents: &str) -> std::io::Result<()> {
+    // Recreate rather than truncate so an older file's mode doesn't stick
+    let _ = fs::remove_file(path);
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)
+        .and_then(|mut file| file.write_all(contents.as_bytes()))
+}
+
+/// Edit the selected config's `devcontainer.local.json` in place, keeping
+/// its comments and formatting. The file is created if missing, and only
+/// written if `edit` succeeds.
+pub fn edit_local_config<T>(
+    workspace_root: &Path,
+    edit: impl FnOnce(&CstObject) -> Result<T>,
+) -> Result<T> {
+    let path = config_dir(workspace_root)?.join("devcontainer.local.json");
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => "{\n}\n".to_string(),
+        Err(e) => return Err(e).with_context(|| format!("failed to read {}", path.display())),
+    };
+    let root = CstRootNode::parse(&content, &Default::default())
+        .map_err(|e| anyhow::anyhow!("failed to parse {}: {e}", path.display()))?;
+    let object = root
+        .object_value()
+        .with_context(|| format!("{} must contain a JSON object", path.display()))?;
+    let result = edit(&object)?;
+    fs::write(&path, root.to_string())
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(result)
+}
+
+/// A problem found while validating a devcontainer config.
+#[derive(Debug, PartialEq)]
+pub struct ValidationIssue {
+    /// Location of the offending value within the config, e.g. `forwardPorts[1]`.
+    pub path: String,
+    pub message: String,
+    /// Source file and 1-based line where the top-level key was found, if known.
+    pub location: Option<(PathBuf, usize)>,
+}
+
+impl std::fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some((file, line)) = &self.location {
+            write!(f, "{}:{}: ", file.display(), line)?;
+        }
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+fn issue(path: impl Into<String>, message: impl Into<String>) -> ValidationIssue {
+    ValidationIssue {
+        path: path.into(),
+        message: message.into(),
+        location: None,
+    }
+}
+
+/// Check a (merged) devcontainer config against the parts of the
+/// devcontainer.json schema that dcw and the devcontainer CLI rely on.
+pub fn validate_config(config: &Value) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+
+    let Some(obj) = config.as_object() else {
+        issues.push(issue("$", "config must be a JSON object"));
+        return issues;
+    };
+
+    // --- image / build / dockerComposeFile exclusivity ---
+    let has_image = obj.contains_key("image");
+    let has_build = obj.contains_key("build") || obj.contains_key("dockerFile");
+    let has_compose = obj.contains_key("dockerComposeFile");
+    let sources = [has_image, has_build, has_compose]
+        .iter()
+        .filter(|b| **b)
+        .count();
+    if sources == 0 {
+        issues.push(issue(
+            "$",
+            "one of `image`, `build.dockerfile` or `dockerComposeFile` is required",
+        ));
+    } else if sources > 1 {
+        issues.push(issue(
+            "$",
+            "`image`, `build`/`dockerFile` and `dockerComposeFile` are mutually exclusive",
+        ));
+    }
+
+    if let Some(image) = obj.get("image") {
+        if !image.is_string() {
+            issues.push(issue("image", "expected a string"));
+        }
+    }
+
+    if let Some(build) = obj.get("build") {
+        match build.as_object() {
+            Some(b) => {
+                if let Some(df) = b.get("dockerfile") {
+                    if !df.is_string() {
+                        issues.push(issue("build.dockerfile", "expected a string"));
+                    }
+                } else if !obj.contains_key("dockerFile") {
+                    issues.push(issue("build", "missing `dockerfile`"));
+                }
+            }
+            None => issues.push(issue("build", "expected an object")),
+        }
+    }
+
+    if let Some(dcf) = obj.get("dockerComposeFile") {
+        let valid = dcf.is_string()
+            || dcf
+                .as_array()
+                .is_some_and(|a| a.iter().all(|v| v.is_string()));
+        if !valid {
+            issues.push(issue(
+                "dockerComposeFile",
+                "expected a string or an array of strings",
+            ));
+        }
+        if !obj.get("service").is_some_and(|s| s.is_string()) {
+            issues.push(issue(
+                "service",
+                "`service` is required when `dockerComposeFile` is set",
+            ));
+        }
+    }
+
+    // --- forwardPorts ---
+    if let Some(fp) = obj.get("forwardPorts") {
+        match fp.as_array() {
+            Some(arr) => {
+                for (i, entry) in arr.iter().enumerate() {
+                    if !is_valid_forward_port(entry) {
+                        issues.push(issue(
+                            format!("forwardPorts[{i}]"),
+                            format!("invalid port entry {entry}: expected a port number (1-65535) or \"host:port\" string"),
+                        ));
+                    }
+                }
+            }
+            None => issues.push(issue("forwardPorts", "expected an array")),
+        }
+    }
+
+    // --- simple typed fields ---
+    for key in ["remoteUser", "containerUser", "workspaceFolder", "name"] {
+        if obj.get(key).is_some_and(|v| !v.is_string()) {
+            issues.push(issue(key, "expected a string"));
+        }
+    }
+    for key in ["runArgs", "mounts"] {
+        if obj.get(key).is_some_and(|v| !v.is_array()) {
+            issues.push(issue(key, "expected an array"));
+        }
+    }
+    for key in ["containerEnv", "remoteEnv"] {
+        match obj.get(key) {
+            Some(Value::Object(env)) => {
+                for (k, v) in env {
+                    // remoteEnv allows null to unset a variable
+                    let allowed = v.is_string() || (key == "remoteEnv" && v.is_null());
+                    if !allowed {
+                        issues.push(issue(format!("{key}.{k}"), "expected a string"));
+                    }
+                }
+            }
+            Some(_) => issues.push(issue(key, "expected an object")),
+            None => {}
+        }
+    }
+
+    issues
+}
+
+fn is_valid_forward_port(entry: &Value) -> bool {
+    let valid_port = |p: u64| (1..=65535).contains(&p);
+    match entry {
+        Value::Number(n) => n.as_u64().is_some_and(valid_port),
+        Value::String(s) => s
+            .rsplit(':')
+            .next()
+            .and_then(|p| p.parse::<u64>().ok())
+            .is_some_and(valid_port),
+        Value::Object(o) => {
+            o.get("port")
+                .and_then(|p| p.as_u64())
+                .is_some_and(valid_port)
+                && o.get("hostPort")
+                    .is_none_or(|p| p.as_u64().is_some_and(valid_port))
+        }
+        _ => false,
+    }
+}
+
+/// Top-level properties in the devcontainer.json schema, including
+/// deprecated ones.
+const CONFIG_KEYS: &[&str] = &[
+    "$schema",
+    "name",
+    "image",
+    "build",
+    "dockerFile",
+    "context",
+    "dockerComposeFile",
+    "service",
+    "runServices",
+    "workspaceFolder",
+    "workspaceMount",
+    "shutdownAction",
+    "overrideCommand",
+    "appPort",
+    "forwardPorts",
+    "portsAttributes",
+    "otherPortsAttributes",
+    "containerEnv",
+    "remoteEnv",
+    "containerUser",
+    "remoteUser",
+    "updateRemoteUserUID",
+    "userEnvProbe",
+    "mounts",
+    "runArgs",
+    "init",
+    "privileged",
+    "capAdd",
+    "securityOpt",
+    "features",
+    "overrideFeatureInstallOrder",
+    "customizations",
+    "hostRequirements",
+    "secrets",
+    "initializeCommand",
+    "onCreateCommand",
+    "updateContentCommand",
+    "postCreateCommand",
+    "postStartCommand",
+    "postAttachCommand",
+    "waitFor",
+    "settings",
+    "extensions",
+    "devPort",
+];
+
+const BUILD_KEYS: &[&str] = &[
+    "dockerfile",
+    "context",
+    "args",
+    "target",
+    "cacheFrom",
+    "options",
+];
+
+/// `customizations.dcw`, see [`crate::customizations::DcwCustomizations`].
+const DCW_KEYS: &[&str] = &[
+    "bindAddress",
+    "hostPortFallback",
+    "watch",
+    "hooks",
+    "prebuiltImage",
+    "hostname",
+];
+
+const DCW_WATCH_KEYS: &[&str] = &[
+    "interval",
+    "minPort",
+    "exclude",
+    "udp",
+    "includeLoopback",
+    "removeDelay",
+];
+
+const DCW_HOOKS_KEYS: &[&str] = &["preUp", "postUp", "preDown", "postDown"];
+
+/// Keys in an overlay such as `devcontainer.local.json` that neither the
+/// devcontainer.json schema nor dcw knows, e.g. `forwardPort` for
+/// `forwardPorts`. They would be merged in and never read.
+pub fn check_overlay_keys(overlay: &Value) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+    for (path, known) in [
+        ("", CONFIG_KEYS),
+        ("build", BUILD_KEYS),
+        ("customizations.dcw", DCW_KEYS),
+        ("customizations.dcw.watch", DCW_WATCH_KEYS),
+        ("customizations.dcw.hooks", DCW_HOOKS_KEYS),
+    ] {
+        let pointer: String = path.split('.').map(|key| format!("/{key}")).collect();
+        let value = if path.is_empty() {
+            Some(overlay)
+        } else {
+            overlay.pointer(&pointer)
+        };
+        let Some(obj) = value.and_then(Value::as_object) else {
+            continue;
+        };
+        for key in obj.keys() {
+            // `"key+"` appends to an array
+            let key = key.strip_suffix('+').unwrap_or(key);
+            if known.contains(&key) {
+                continue;
+            }
+            let message = match closest_key(key, known) {
+                Some(suggestion) => format!("unknown key (did you mean `{suggestion}`?)"),
+                None => "unknown key".to_string(),
+            };
+            let full_path = if path.is_empty() {
+                key.to_string()
+            } else {
+                format!("{path}.{key}")
+            };
+            issues.push(issue(full_path, message));
+        }
+    }
+    issues
+}
+
+/// [`check_overlay_keys`] for the overlay file at `path`, with the line of
+/// each key.
+fn unknown_keys(path: &Path, overlay: &Value) -> Vec<ValidationIssue> {
+    let content = fs::read_to_string(path).unwrap_or_default();
+    let mut issues = check_overlay_keys(overlay);
+    for issue in &mut issues {
+        let key = issue.path.rsplit('.').next().unwrap_or(&issue.path);
+        issue.location = find_key_line(&content, key)
+            .or_else(|| find_key_line(&content, &format!("{key}+")))
+            .map(|line| (path.to_path_buf(), line));
+    }
+    issues
+}
+
+/// Unknown keys in the active profile's config and `devcontainer.local.json`.
+pub fn overlay_warnings(workspace_root: &Path) -> Result<Vec<ValidationIssue>> {
+    let (dc_dir, _) = selected_config(workspace_root)?;
+    let mut issues = Vec::new();
+    for path in overlay_paths(&dc_dir, workspace::active_profile().as_deref())? {
+        issues.extend(unknown_keys(&path, &read_jsonc(&path)?));
+    }
+    Ok(issues)
+}
+
+/// The known key `key` is most likely a typo of, if any.
+fn closest_key<'a>(key: &str, known: &[&'a str]) -> Option<&'a str> {
+    let key = key.to_lowercase();
+    let max_distance = (key.chars().count() / 4).max(2);
+    known
+        .iter()
+        .map(|candidate| (edit_distance(&key, &candidate.to_lowercase()), *candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Levenshtein distance between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Find the 1-based line on which `"key":` first appears in JSONC source.
+fn find_key_line(content: &str, key: &str) -> Option<usize> {
+    let needle = format!("\"{key}\"");
+    content.lines().position(|line| {
+        line.find(&needle)
+            .is_some_and(|i| line[i + needle.len()..].trim_start().starts_with(':'))
+    })
+    .map(|i| i + 1)
+}
+
+/// How a key differs between the base config and the merged one.
+#[derive(Debug, PartialEq, serde::Serialize)]
+#[serde(rename_all = "lowercase", tag = "change")]
+pub enum ConfigChange {
+    Added {
+        path: String,
+        new: Value,
+    },
+    Changed {
+        path: String,
+        old: Value,
+        new: Value,
+    },
+    Removed {
+        path: String,
+        old: Value,
+    },
+}
+
+/// Structural diff between two configs. Objects are compared key by key;
+/// arrays and scalars are compared as a whole.
+pub fn diff_configs(base: &Value, merged: &Value) -> Vec<ConfigChange> {
+    let mut changes = Vec::new();
+    diff_at("", base, merged, &mut changes);
+    changes
+}
+
+fn diff_at(path: &str, base: &Value, merged: &Value, changes: &mut Vec<ConfigChange>) {
+    let child = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{path}.{key}")
+        }
+    };
+    match (base, merged) {
+        (Value::Object(old), Value::Object(new)) => {
+            for (key, old_val) in old {
+                match new.get(key) {
+                    Some(new_val) => diff_at(&child(key), old_val, new_val, changes),
+                    None => changes.push(ConfigChange::Removed {
+                        path: child(key),
+                        old: old_val.clone(),
+                    }),
+                }
+            }
+            for (key, new_val) in new {
+                if !old.contains_key(key) {
+                    changes.push(ConfigChange::Added {
+                        path: child(key),
+                        new: new_val.clone(),
+                    });
+                }
+            }
+        }
+        (old, new) if old != new => changes.push(ConfigChange::Changed {
+            path: path.to_string(),
+            old: old.clone(),
+            new: new.clone(),
+        }),
+        _ => {}
+    }
+}
+
+/// The selected `devcontainer.json` as written, and with the active profile
+/// and `devcontainer.local.json` merged on top.
+pub fn load_base_and_merged(workspace_root: &Path) -> Result<(Value, Value)> {
+    let (dc_dir, main_path) = selected_config(workspace_root)?;
+    let base = read_jsonc(&main_path)?;
+    let mut merged = base.clone();
+    for path in overlay_paths(&dc_dir, workspace::active_profile().as_deref())? {
+        deep_merge(&mut merged, read_jsonc(&path)?);
+    }
+    Ok((base, merged))
+}
+
+/// The merged config with absolute paths and variables expanded as for
+/// `dcw up`, but without resolving secrets or writing anything.
+pub fn load_expanded(workspace_root: &Path) -> Result<Value> {
+    let (dc_dir, _) = selected_config(workspace_root)?;
+    let (_, mut merged) = load_base_and_merged(workspace_root)?;
+    resolve_build_paths(&mut merged, &dc_dir);
+    let local_folder = workspace_root.to_string_lossy();
+    let id = devcontainer_id(&local_folder, &labeled_config_file(workspace_root)?);
+    substitute_variables(&mut merged, &local_folder, &id);
+    Ok(merged)
+}
+
+/// The Docker Compose project the devcontainer CLI uses for a config with
+/// absolute `dockerComposeFile` paths: `COMPOSE_PROJECT_NAME`, else the
+/// directory of the first compose file, `<workspace>_devcontainer` for
+/// `.devcontainer`. A `name` in the compose file itself is not considered.
+pub fn compose_project_name(workspace_root: &Path, config: &Value) -> Option<String> {
+    let first = match config.get("dockerComposeFile")? {
+        Value::String(file) => file.as_str(),
+        Value::Array(files) => files.first()?.as_str()?,
+        _ => return None,
+    };
+    let basename = |path: &Path| path.file_name().map(|n| n.to_string_lossy().to_string());
+    let name = match env::var("COMPOSE_PROJECT_NAME") {
+        Ok(name) if !name.is_empty() => name,
+        _ => {
+            let dir = Path::new(first).parent()?;
+            if dir == workspace_root.join(".devcontainer") {
+                format!("{}_devcontainer", basename(workspace_root)?)
+            } else {
+                basename(dir)?
+            }
+        }
+    };
+    Some(
+        name.to_lowercase()
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '-')
+            .collect(),
+    )
+}
+
+/// Validate the workspace's devcontainer config (with the active profile and
+/// `devcontainer.local.json` merged in, if present) and attach source line
+/// context to each issue.
+pub fn validate_workspace(workspace_root: &Path) -> Result<Vec<ValidationIssue>> {
+    let (dc_dir, main_path) = selected_config(workspace_root)?;
+
+    let mut merged = read_jsonc(&main_path)?;
+    let mut sources = vec![main_path];
+    for path in overlay_paths(&dc_dir, workspace::active_profile().as_deref())? {
+        deep_merge(&mut merged, read_jsonc(&path)?);
+        // Later sources win, so search the overrides first
+        sources.insert(0, path);
+    }
+
+    let contents: Vec<(PathBuf, String)> = sources
+        .into_iter()
+        .filter_map(|p| fs::read_to_string(&p).ok().map(|c| (p, c)))
+        .collect();
+
+    let mut issues = validate_config(&merged);
+    for issue in &mut issues {
+        let key = issue
+            .path
+            .split(['.', '['])
+            .next()
+            .unwrap_or(&issue.path);
+        issue.location = contents.iter().find_map(|(path, content)| {
+            find_key_line(content, key).map(|line| (path.clone(), line))
+        });
+    }
+    Ok(issues)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -232,6 +1426,130 @@ mod tests {
         assert_eq!(base["a"], "flat");
     }
 
+    #[test]
+    fn deep_merge_array_append_and_prepend() {
+        let mut base = json!({"extensions": ["a", "b"], "runArgs": ["--init"]});
+        let overlay = json!({
+            "extensions": {"$append": ["c"]},
+            "runArgs": {"$prepend": ["--cap-add", "SYS_PTRACE"]}
+        });
+        deep_merge(&mut base, overlay);
+
+        assert_eq!(base["extensions"], json!(["a", "b", "c"]));
+        assert_eq!(base["runArgs"], json!(["--cap-add", "SYS_PTRACE", "--init"]));
+    }
+
+    #[test]
+    fn deep_merge_array_remove() {
+        let mut base = json!({"forwardPorts": [3000, 5432, 8080]});
+        let overlay = json!({"forwardPorts": {"$remove": [5432], "$append": [9229]}});
+        deep_merge(&mut base, overlay);
+
+        assert_eq!(base["forwardPorts"], json!([3000, 8080, 9229]));
+    }
+
+    #[test]
+    fn deep_merge_plus_suffix_appends() {
+        let mut base = json!({"customizations": {"vscode": {"extensions": ["a"]}}});
+        let overlay = json!({"customizations": {"vscode": {"extensions+": ["b"]}}});
+        deep_merge(&mut base, overlay);
+
+        assert_eq!(
+            base,
+            json!({"customizations": {"vscode": {"extensions": ["a", "b"]}}})
+        );
+    }
+
+    #[test]
+    fn deep_merge_directive_on_missing_array() {
+        let mut base = json!({"name": "x"});
+        let overlay = json!({"mounts": {"$append": ["m"]}, "runArgs+": ["--init"]});
+        deep_merge(&mut base, overlay);
+
+        assert_eq!(base["mounts"], json!(["m"]));
+        assert_eq!(base["runArgs"], json!(["--init"]));
+    }
+
+    #[test]
+    fn deep_merge_ignores_non_directive_objects() {
+        // Other keys next to a directive make it a plain object
+        let mut base = json!({"a": [1]});
+        let overlay = json!({"a": {"$append": [2], "other": true}, "b+": "not an array"});
+        deep_merge(&mut base, overlay);
+
+        assert_eq!(base["a"], json!({"$append": [2], "other": true}));
+        assert_eq!(base["b+"], "not an array");
+    }
+
+    #[test]
+    fn check_overlay_keys_suggests_known_keys() {
+        let overlay = json!({
+            "forwardPort": [3000],
+            "runArgs+": ["--init"],
+            "build": {"dockerfile": "Dockerfile", "arg": {}},
+            "customizations": {
+                "vscode": {"extensions": []},
+                "dcw": {"hostname": "shop", "watch": {"minport": 3000}, "colour": "red"}
+            }
+        });
+        let issues: Vec<String> = check_overlay_keys(&overlay)
+            .iter()
+            .map(|i| i.to_string())
+            .collect();
+        assert_eq!(
+            issues,
+            [
+                "forwardPort: unknown key (did you mean `forwardPorts`?)",
+                "build.arg: unknown key (did you mean `args`?)",
+                "customizations.dcw.colour: unknown key",
+                "customizations.dcw.watch.minport: unknown key (did you mean `minPort`?)",
+            ]
+        );
+        assert!(check_overlay_keys(&json!({"forwardPorts+": [8080]})).is_empty());
+    }
+
+    #[test]
+    fn diff_configs_reports_added_changed_and_removed() {
+        let base = json!({
+            "name": "app",
+            "image": "node:20",
+            "forwardPorts": [3000],
+            "customizations": {"vscode": {"extensions": ["a"], "settings": {"x": 1}}}
+        });
+        let merged = json!({
+            "name": "app",
+            "image": "node:22",
+            "forwardPorts": [3000, 9229],
+            "customizations": {"vscode": {"extensions": ["a"]}},
+            "remoteEnv": {"DEBUG": "1"}
+        });
+        let changes = diff_configs(&base, &merged);
+        assert_eq!(
+            changes,
+            vec![
+                ConfigChange::Removed {
+                    path: "customizations.vscode.settings".to_string(),
+                    old: json!({"x": 1}),
+                },
+                ConfigChange::Changed {
+                    path: "forwardPorts".to_string(),
+                    old: json!([3000]),
+                    new: json!([3000, 9229]),
+                },
+                ConfigChange::Changed {
+                    path: "image".to_string(),
+                    old: json!("node:20"),
+                    new: json!("node:22"),
+                },
+                ConfigChange::Added {
+                    path: "remoteEnv".to_string(),
+                    new: json!({"DEBUG": "1"}),
+                },
+            ]
+        );
+        assert!(diff_configs(&base, &base).is_empty());
+    }
+
     #[test]
     fn read_jsonc_strips_line_comments() {
         let dir = std::env::temp_dir().join("dcw-test-config-jsonc-line");
@@ -515,4 +1833,392 @@ mod tests {
         );
         assert_eq!(config["context"], "/workspace/.devcontainer/../other");
     }
+
+    // ---- apply_build_overrides tests ----
+
+    #[test]
+    fn apply_build_overrides_adds_args_and_pull() {
+        let mut config = json!({
+            "build": {"dockerfile": "Dockerfile", "args": {"A": "1", "B": "2"}, "options": ["--network=host"]}
+        });
+        let args = vec![("B".to_string(), "override".to_string()), ("C".to_string(), "3".to_string())];
+        assert!(apply_build_overrides(&mut config, &args, true));
+        assert_eq!(config["build"]["args"], json!({"A": "1", "B": "override", "C": "3"}));
+        assert_eq!(config["build"]["options"], json!(["--network=host", "--pull"]));
+    }
+
+    #[test]
+    fn apply_build_overrides_supports_top_level_dockerfile() {
+        let mut config = json!({"dockerFile": "Dockerfile"});
+        assert!(apply_build_overrides(&mut config, &[], true));
+        assert_eq!(config["build"]["options"], json!(["--pull"]));
+    }
+
+    #[test]
+    fn apply_build_overrides_ignores_image_configs() {
+        let mut config = json!({"image": "alpine"});
+        assert!(!apply_build_overrides(&mut config, &[("A".to_string(), "1".to_string())], false));
+        assert_eq!(config, json!({"image": "alpine"}));
+    }
+
+    #[test]
+    fn apply_prebuilt_image_replaces_build() {
+        let mut config = json!({
+            "build": {"dockerfile": "Dockerfile", "args": {"A": "1"}},
+            "features": {"ghcr.io/devcontainers/features/node:1": {}},
+            "forwardPorts": [3000]
+        });
+        let image = "ghcr.io/org/app-dev:latest";
+        assert!(apply_prebuilt_image(&mut config, image));
+        assert_eq!(config, json!({"image": image, "forwardPorts": [3000]}));
+
+        let mut compose = json!({"dockerComposeFile": "compose.yml", "service": "app"});
+        assert!(!apply_prebuilt_image(&mut compose, image));
+    }
+
+    #[test]
+    fn apply_snapshot_image_replaces_image_and_build() {
+        let image = "dcw-snapshot/dev-app-1234abcd:before-upgrade";
+        let mut config = json!({
+            "image": "mcr.microsoft.com/devcontainers/base:ubuntu",
+            "features": {"ghcr.io/devcontainers/features/node:1": {}},
+            "remoteUser": "vscode"
+        });
+        assert!(apply_snapshot_image(&mut config, image));
+        assert_eq!(config, json!({"image": image, "remoteUser": "vscode"}));
+
+        let mut config = json!({"build": {"dockerfile": "Dockerfile"}});
+        assert!(apply_snapshot_image(&mut config, image));
+        assert_eq!(config, json!({"image": image}));
+
+        let mut compose = json!({"dockerComposeFile": "compose.yml", "service": "app"});
+        assert!(!apply_snapshot_image(&mut compose, image));
+    }
+
+    #[test]
+    fn apply_gpus_replaces_existing_flag() {
+        let mut config = json!({"image": "x", "runArgs": ["--gpus", "1", "--init", "--gpus=2"]});
+        assert!(apply_gpus(&mut config, "all"));
+        assert_eq!(config["runArgs"], json!(["--init", "--gpus=all"]));
+
+        let mut config = json!({"build": {"dockerfile": "Dockerfile"}});
+        assert!(apply_gpus(&mut config, "device=0"));
+        assert_eq!(config["runArgs"], json!(["--gpus=device=0"]));
+    }
+
+    #[test]
+    fn apply_gpus_skips_compose_configs() {
+        let mut config = json!({"dockerComposeFile": "compose.yml", "service": "app"});
+        assert!(!apply_gpus(&mut config, "all"));
+        assert!(config.get("runArgs").is_none());
+    }
+
+    #[test]
+    fn apply_host_gateway_adds_host_once() {
+        let compose_file = Path::new("/run/dcw/compose.host-gateway.yml");
+        let mut config = json!({"image": "x", "runArgs": ["--init"]});
+        assert!(apply_host_gateway(&mut config, compose_file));
+        assert!(apply_host_gateway(&mut config, compose_file));
+        assert_eq!(
+            config["runArgs"],
+            json!(["--init", "--add-host=host.docker.internal:host-gateway"])
+        );
+
+        let mut config =
+            json!({"image": "x", "runArgs": ["--add-host", "host.docker.internal:10.0.0.1"]});
+        assert!(apply_host_gateway(&mut config, compose_file));
+        assert_eq!(config["runArgs"].as_array().unwrap().len(), 2);
+
+        let mut config = json!({"dockerComposeFile": "/w/compose.yml", "service": "app"});
+        assert!(apply_host_gateway(&mut config, compose_file));
+        assert!(apply_host_gateway(&mut config, compose_file));
+        assert_eq!(
+            config["dockerComposeFile"],
+            json!(["/w/compose.yml", "/run/dcw/compose.host-gateway.yml"])
+        );
+        assert!(config.get("runArgs").is_none());
+        assert_eq!(
+            host_gateway_compose("app"),
+            "services:\n  app:\n    extra_hosts:\n      - \"host.docker.internal:host-gateway\"\n"
+        );
+    }
+
+    #[test]
+    fn apply_mount_adds_once() {
+        let mount = "source=/run/a,target=/tmp/a,type=bind";
+        let mut config = json!({"image": "x", "mounts": [{"source": "v", "target": "/v", "type": "volume"}]});
+        assert!(apply_mount(&mut config, mount));
+        assert!(apply_mount(&mut config, mount));
+        assert_eq!(config["mounts"].as_array().unwrap().len(), 2);
+        assert_eq!(config["mounts"][1], mount);
+
+        let mut config = json!({"dockerComposeFile": "compose.yml", "service": "app"});
+        assert!(!apply_mount(&mut config, mount));
+    }
+
+    #[test]
+    fn load_secrets_stringifies_scalars() {
+        let dir = std::env::temp_dir().join(format!("dcw-test-secrets-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("secrets.json");
+        fs::write(
+            &path,
+            "// tokens\n{\"TOKEN\": \"abc\", \"PORT\": 8080, \"DEBUG\": true,}",
+        )
+        .unwrap();
+        let mut secrets = load_secrets(&path).unwrap();
+        secrets.sort();
+        assert_eq!(
+            secrets,
+            vec![
+                ("DEBUG".to_string(), "true".to_string()),
+                ("PORT".to_string(), "8080".to_string()),
+                ("TOKEN".to_string(), "abc".to_string()),
+            ]
+        );
+
+        fs::write(&path, r#"{"NESTED": {"a": 1}}"#).unwrap();
+        assert!(load_secrets(&path).is_err());
+        fs::write(&path, r#"["TOKEN"]"#).unwrap();
+        assert!(load_secrets(&path).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn apply_secrets_merges_into_remote_env() {
+        let mut config = json!({"image": "x", "remoteEnv": {"EDITOR": "vim", "TOKEN": "old"}});
+        let secrets = vec![("TOKEN".to_string(), "new".to_string())];
+        assert!(apply_secrets(&mut config, &secrets));
+        assert_eq!(
+            config["remoteEnv"],
+            json!({"EDITOR": "vim", "TOKEN": "new"})
+        );
+        assert!(!apply_secrets(&mut config, &[]));
+    }
+
+    #[test]
+    fn overlay_paths_merge_profile_before_local() {
+        let root = std::env::temp_dir().join(format!("dcw-test-profile-{}", std::process::id()));
+        let dc = root.join(".devcontainer");
+        fs::create_dir_all(&dc).unwrap();
+        fs::write(dc.join("devcontainer.gpu.json"), "{}").unwrap();
+
+        assert!(overlay_paths(&dc, None).unwrap().is_empty());
+        assert_eq!(
+            overlay_paths(&dc, Some("gpu")).unwrap(),
+            vec![dc.join("devcontainer.gpu.json")]
+        );
+
+        fs::write(dc.join("devcontainer.local.json"), "{}").unwrap();
+        assert_eq!(
+            overlay_paths(&dc, Some("gpu")).unwrap(),
+            vec![
+                dc.join("devcontainer.gpu.json"),
+                dc.join("devcontainer.local.json")
+            ]
+        );
+        assert!(overlay_paths(&dc, Some("minimal")).is_err());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn check_profile_rejects_bad_names() {
+        let root = Path::new("/nonexistent");
+        for name in ["", "local", "../x", "a/b", "a.b"] {
+            let err = check_profile_in(root, name).unwrap_err().to_string();
+            assert!(err.contains("invalid profile name"), "{name}: {err}");
+        }
+        assert!(check_profile_in(root, "gpu")
+            .unwrap_err()
+            .to_string()
+            .contains("not found"));
+    }
+
+    #[test]
+    fn devcontainer_id_matches_cli() {
+        let id = devcontainer_id(
+            "/home/me/proj",
+            Path::new("/home/me/proj/.devcontainer/devcontainer.json"),
+        );
+        assert_eq!(id, "0qbup1i4f2ig7p6igu8b63pn9mil7frd06bh2glbg2t8vn29r9ca");
+    }
+
+    #[test]
+    fn substitute_variables_expands_known_variables() {
+        std::env::set_var("DCW_TEST_SUBST", "from-env");
+        std::env::remove_var("DCW_TEST_SUBST_UNSET");
+        let mut config = json!({
+            "image": "x",
+            "workspaceFolder": "/src/${localWorkspaceFolderBasename}",
+            "mounts": ["source=${localWorkspaceFolder}/.cache,target=${containerWorkspaceFolder}/.cache,type=bind"],
+            "containerEnv": {
+                "A": "${localEnv:DCW_TEST_SUBST}",
+                "B": "${localEnv:DCW_TEST_SUBST_UNSET:fallback}",
+                "C": "${env:DCW_TEST_SUBST_UNSET}",
+                "D": "${containerWorkspaceFolderBasename}-${devcontainerId}",
+                "E": "${containerEnv:PATH} ${unterminated"
+            },
+            "forwardPorts": [3000]
+        });
+        substitute_variables(&mut config, "/home/me/proj", "abc");
+        assert_eq!(config["workspaceFolder"], "/src/proj");
+        assert_eq!(
+            config["mounts"][0],
+            "source=/home/me/proj/.cache,target=/src/proj/.cache,type=bind"
+        );
+        let env = &config["containerEnv"];
+        assert_eq!(env["A"], "from-env");
+        assert_eq!(env["B"], "fallback");
+        assert_eq!(env["C"], "");
+        assert_eq!(env["D"], "proj-abc");
+        assert_eq!(env["E"], "${containerEnv:PATH} ${unterminated");
+        assert_eq!(config["forwardPorts"], json!([3000]));
+    }
+
+    #[test]
+    fn compose_project_name_follows_devcontainer_cli() {
+        let root = Path::new("/home/me/My App");
+        let config = json!({"dockerComposeFile": ["/home/me/My App/.devcontainer/compose.yml"]});
+        assert_eq!(
+            compose_project_name(root, &config).as_deref(),
+            Some("myapp_devcontainer")
+        );
+        let config = json!({"dockerComposeFile": "/home/me/My App/docker/compose.yml"});
+        assert_eq!(compose_project_name(root, &config).as_deref(), Some("docker"));
+        assert_eq!(compose_project_name(root, &json!({"image": "x"})), None);
+    }
+
+    #[test]
+    fn substitute_variables_default_container_folder() {
+        let mut config =
+            json!({"image": "x", "postCreateCommand": "ls ${containerWorkspaceFolder}"});
+        substitute_variables(&mut config, "/home/me/proj", "abc");
+        assert_eq!(config["postCreateCommand"], "ls /workspaces/proj");
+
+        let mut config =
+            json!({"dockerComposeFile": "c.yml", "name": "${containerWorkspaceFolder}"});
+        substitute_variables(&mut config, "/home/me/proj", "abc");
+        assert_eq!(config["name"], "/");
+    }
+
+    #[test]
+    fn set_config_flag_requires_existing_file() {
+        let err = set_config_flag(Path::new("/nonexistent/ops/devcontainer.json")).unwrap_err();
+        assert!(err.to_string().contains("config file not found"));
+        assert!(config_flag().is_none());
+    }
+
+    #[test]
+    fn config_dir_finds_subfolder_configs() {
+        let root = std::env::temp_dir().join(format!("dcw-test-confdir-{}", std::process::id()));
+        let dc = root.join(".devcontainer");
+        fs::create_dir_all(dc.join("python")).unwrap();
+        fs::write(dc.join("python/devcontainer.json"), "{}").unwrap();
+
+        // A single subfolder config is picked up without a name
+        assert_eq!(config_dir_for(&root, None).unwrap(), dc.join("python"));
+
+        fs::create_dir_all(dc.join("node")).unwrap();
+        fs::write(dc.join("node/devcontainer.json"), "{}").unwrap();
+        fs::create_dir_all(dc.join("empty")).unwrap();
+        assert_eq!(config_names(&root), vec!["node", "python"]);
+        let err = config_dir_for(&root, None).unwrap_err().to_string();
+        assert!(err.contains("node, python"), "{err}");
+        assert_eq!(
+            config_dir_for(&root, Some("node")).unwrap(),
+            dc.join("node")
+        );
+        assert!(config_dir_for(&root, Some("empty")).is_err());
+        assert!(config_dir_for(&root, Some("../node")).is_err());
+
+        // The top-level config wins when no name is given
+        fs::write(dc.join("devcontainer.json"), "{}").unwrap();
+        assert_eq!(config_dir_for(&root, None).unwrap(), dc);
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    // ---- validate_config tests ----
+
+    #[test]
+    fn validate_accepts_minimal_image_config() {
+        let config = json!({"image": "mcr.microsoft.com/devcontainers/rust:1", "forwardPorts": [3000, "localhost:8080"]});
+        assert!(validate_config(&config).is_empty());
+    }
+
+    #[test]
+    fn validate_requires_a_container_source() {
+        let issues = validate_config(&json!({"name": "x"}));
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].path, "$");
+    }
+
+    #[test]
+    fn validate_rejects_image_and_build_together() {
+        let config = json!({"image": "alpine", "build": {"dockerfile": "Dockerfile"}});
+        let issues = validate_config(&config);
+        assert!(issues
+            .iter()
+            .any(|i| i.message.contains("mutually exclusive")));
+    }
+
+    #[test]
+    fn validate_flags_bad_forward_ports() {
+        let config = json!({"image": "alpine", "forwardPorts": [3000, 70000, "abc", true]});
+        let paths: Vec<String> = validate_config(&config)
+            .into_iter()
+            .map(|i| i.path)
+            .collect();
+        assert_eq!(paths, vec!["forwardPorts[1]", "forwardPorts[2]", "forwardPorts[3]"]);
+    }
+
+    #[test]
+    fn validate_forward_ports_must_be_array() {
+        let config = json!({"image": "alpine", "forwardPorts": 3000});
+        assert_eq!(validate_config(&config)[0].path, "forwardPorts");
+    }
+
+    #[test]
+    fn validate_compose_requires_service() {
+        let config = json!({"dockerComposeFile": "docker-compose.yml"});
+        let issues = validate_config(&config);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].path, "service");
+    }
+
+    #[test]
+    fn validate_container_env_values_must_be_strings() {
+        let config = json!({"image": "alpine", "containerEnv": {"A": "1", "B": 2}});
+        let issues = validate_config(&config);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].path, "containerEnv.B");
+    }
+
+    #[test]
+    fn find_key_line_locates_top_level_key() {
+        let content = "{\n  // \"forwardPorts\" mentioned in a comment\n  \"image\": \"alpine\",\n  \"forwardPorts\" : [1]\n}";
+        assert_eq!(find_key_line(content, "forwardPorts"), Some(4));
+        assert_eq!(find_key_line(content, "image"), Some(3));
+        assert_eq!(find_key_line(content, "missing"), None);
+    }
+
+    #[test]
+    fn validate_workspace_reports_local_file_location() {
+        let root = std::env::temp_dir().join("dcw-test-config-validate");
+        let dc = root.join(".devcontainer");
+        let _ = fs::create_dir_all(&dc);
+        fs::write(dc.join("devcontainer.json"), "{\n  \"image\": \"alpine\"\n}").unwrap();
+        fs::write(
+            dc.join("devcontainer.local.json"),
+            "{\n  // typo'd port\n  \"forwardPorts\": [\"abc\"]\n}",
+        )
+        .unwrap();
+
+        let issues = validate_workspace(&root).unwrap();
+        assert_eq!(issues.len(), 1);
+        let (file, line) = issues[0].location.clone().unwrap();
+        assert!(file.ends_with("devcontainer.local.json"));
+        assert_eq!(line, 3);
+
+        let _ = fs::remove_dir_all(&root);
+    }
 }
@@ -0,0 +1,225 @@
+use anyhow::Result;
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+use std::path::Path;
+use tracing::warn;
+
+use crate::config;
+use crate::settings::Settings;
+
+/// dcw-specific settings read from `customizations.dcw` in devcontainer.json
+/// (or devcontainer.local.json), shared by everyone working on the workspace.
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct DcwCustomizations {
+    /// Default host address port forwards bind to
+    pub bind_address: Option<String>,
+    /// Pick the next free host port when the requested one is taken
+    pub host_port_fallback: bool,
+    /// Defaults for `dcw port watch`, including the watcher started by `up`
+    pub watch: WatchCustomizations,
+    /// Host commands run around `dcw up` and `dcw down`
+    pub hooks: HooksCustomizations,
+    /// Image pushed by `dcw prebuild`, used by `dcw up` instead of building
+    /// the Dockerfile once it exists
+    pub prebuilt_image: Option<String>,
+    /// Name forwards are shown under as `<hostname>.localhost`, instead of
+    /// the folder name
+    pub hostname: Option<String>,
+}
+
+/// `customizations.dcw.hooks`. Each hook is a command or a list of commands,
+/// run with `sh -c` on the host.
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct HooksCustomizations {
+    #[serde(deserialize_with = "one_or_many")]
+    pub pre_up: Vec<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub post_up: Vec<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub pre_down: Vec<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub post_down: Vec<String>,
+}
+
+/// Accept either a single string or an array of strings.
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(s) => vec![s],
+        OneOrMany::Many(v) => v,
+    })
+}
+
+/// `customizations.dcw.watch`. Command-line flags take precedence.
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct WatchCustomizations {
+    /// Seconds between checks of the container
+    pub interval: Option<u64>,
+    /// Minimum port number to forward
+    pub min_port: Option<u16>,
+    /// Ports never to forward, in addition to `--exclude`
+    pub exclude: Vec<u16>,
+    /// Also forward UDP listeners
+    pub udp: bool,
+    /// Also forward listeners bound only to a loopback address
+    pub include_loopback: bool,
+    /// Seconds a port must stay closed before its forward is removed
+    pub remove_delay: Option<u64>,
+}
+
+/// Extract `customizations.dcw` from a devcontainer config value.
+/// Missing or malformed sections fall back to defaults.
+pub fn from_value(config: &Value) -> DcwCustomizations {
+    config
+        .pointer("/customizations/dcw")
+        .and_then(|v| match DcwCustomizations::deserialize(v) {
+            Ok(c) => Some(c),
+            Err(e) => {
+                warn!("ignoring invalid customizations.dcw: {e}");
+                None
+            }
+        })
+        .unwrap_or_default()
+}
+
+impl DcwCustomizations {
+    /// Fill in what the workspace leaves unset from the user's defaults in
+    /// `~/.config/dcw/config.toml`. Excluded ports are combined.
+    fn with_user_defaults(mut self, settings: &Settings) -> Self {
+        if self.bind_address.is_none() {
+            self.bind_address = settings.forward.bind_address.clone();
+        }
+        let watch = &mut self.watch;
+        watch.interval = watch.interval.or(settings.watch.interval);
+        watch.min_port = watch.min_port.or(settings.watch.min_port);
+        watch.remove_delay = watch.remove_delay.or(settings.watch.remove_delay);
+        for port in &settings.watch.exclude {
+            if !watch.exclude.contains(port) {
+                watch.exclude.push(*port);
+            }
+        }
+        self
+    }
+}
+
+/// Load `customizations.dcw` from the workspace's effective config, on top
+/// of the user's defaults.
+pub fn load(workspace_root: &Path) -> Result<DcwCustomizations> {
+    Ok(config::load_effective_config(workspace_root)?
+        .map(|value| from_value(&value))
+        .unwrap_or_default()
+        .with_user_defaults(Settings::get()))
+}
+
+/// Like [`load`], but warns and returns the user's defaults if the config
+/// can't be read.
+pub fn load_or_default(workspace_root: &Path) -> DcwCustomizations {
+    load(workspace_root).unwrap_or_else(|e| {
+        warn!("failed to read workspace customizations: {e}");
+        DcwCustomizations::default().with_user_defaults(Settings::get())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn missing_section_uses_defaults() {
+        let c = from_value(&json!({"image": "alpine"}));
+        assert_eq!(c, DcwCustomizations::default());
+    }
+
+    #[test]
+    fn parses_bind_address() {
+        let c = from_value(&json!({
+            "customizations": {"dcw": {"bindAddress": "0.0.0.0"}}
+        }));
+        assert_eq!(c.bind_address.as_deref(), Some("0.0.0.0"));
+        assert!(!c.host_port_fallback);
+    }
+
+    #[test]
+    fn parses_host_port_fallback() {
+        let c = from_value(&json!({
+            "customizations": {"dcw": {"hostPortFallback": true}}
+        }));
+        assert!(c.host_port_fallback);
+    }
+
+    #[test]
+    fn parses_watch_settings() {
+        let c = from_value(&json!({
+            "customizations": {"dcw": {"watch": {"interval": 1, "minPort": 3000, "exclude": [5353], "removeDelay": 10}}}
+        }));
+        assert_eq!(
+            c.watch,
+            WatchCustomizations {
+                interval: Some(1),
+                min_port: Some(3000),
+                exclude: vec![5353],
+                udp: false,
+                include_loopback: false,
+                remove_delay: Some(10),
+            }
+        );
+    }
+
+    #[test]
+    fn parses_hooks_as_string_or_array() {
+        let c = from_value(&json!({
+            "customizations": {"dcw": {"hooks": {
+                "preUp": "vpn up",
+                "postDown": ["vpn down", "echo bye"]
+            }}}
+        }));
+        assert_eq!(
+            c.hooks,
+            HooksCustomizations {
+                pre_up: vec!["vpn up".to_string()],
+                post_down: vec!["vpn down".to_string(), "echo bye".to_string()],
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_section_uses_defaults() {
+        let c = from_value(&json!({
+            "customizations": {"dcw": {"bindAddress": 1}}
+        }));
+        assert_eq!(c, DcwCustomizations::default());
+    }
+
+    #[test]
+    fn user_defaults_fill_unset_values() {
+        let mut settings = Settings::default();
+        settings.forward.bind_address = Some("0.0.0.0".to_string());
+        settings.watch.interval = Some(5);
+        settings.watch.exclude = vec![5432, 6379];
+
+        let c = DcwCustomizations::default().with_user_defaults(&settings);
+        assert_eq!(c.bind_address.as_deref(), Some("0.0.0.0"));
+        assert_eq!(c.watch.interval, Some(5));
+
+        let c = from_value(&json!({
+            "customizations": {"dcw": {
+                "bindAddress": "127.0.0.1",
+                "watch": {"interval": 1, "exclude": [6379, 9229]}
+            }}
+        }))
+        .with_user_defaults(&settings);
+        assert_eq!(c.bind_address.as_deref(), Some("127.0.0.1"));
+        assert_eq!(c.watch.interval, Some(1));
+        assert_eq!(c.watch.exclude, vec![6379, 9229, 5432]);
+    }
+}
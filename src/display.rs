@@ -0,0 +1,204 @@
+use anyhow::{bail, Result};
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use tracing::warn;
+
+use crate::logging::Logged;
+use crate::workspace;
+
+/// Where the host's Wayland socket is mounted in the container.
+const CONTAINER_WAYLAND_SOCKET: &str = "/tmp/dcw-wayland";
+
+/// Where the X11 auth cookies are mounted in the container.
+const CONTAINER_XAUTHORITY: &str = "/tmp/dcw-xauthority";
+
+/// Mounts and environment that give the container the host's display.
+#[derive(Debug, Default)]
+pub struct DisplayForward {
+    pub mounts: Vec<String>,
+    pub env: Vec<(String, String)>,
+}
+
+/// Work out how to share the host's X11 and/or Wayland display, from
+/// `DISPLAY`, `WAYLAND_DISPLAY` and `XAUTHORITY`.
+pub fn forwarding() -> Result<DisplayForward> {
+    let mut forward = DisplayForward::default();
+
+    if let Some(socket) = wayland_socket(
+        env::var("WAYLAND_DISPLAY").ok().as_deref(),
+        env::var("XDG_RUNTIME_DIR").ok().as_deref(),
+    ) {
+        if socket.exists() {
+            forward
+                .mounts
+                .push(bind_mount(&socket, CONTAINER_WAYLAND_SOCKET));
+            forward.env.push((
+                "WAYLAND_DISPLAY".to_string(),
+                CONTAINER_WAYLAND_SOCKET.to_string(),
+            ));
+        }
+    }
+
+    if let Ok(value) = env::var("DISPLAY") {
+        match x11_display_number(&value) {
+            Some(number) => {
+                forward
+                    .mounts
+                    .push(bind_mount(Path::new("/tmp/.X11-unix"), "/tmp/.X11-unix"));
+                forward
+                    .env
+                    .push(("DISPLAY".to_string(), format!(":{number}")));
+                if let Some(cookies) = xauthority(&value)? {
+                    forward
+                        .mounts
+                        .push(bind_mount(&cookies, CONTAINER_XAUTHORITY));
+                    forward
+                        .env
+                        .push(("XAUTHORITY".to_string(), CONTAINER_XAUTHORITY.to_string()));
+                }
+            }
+            None if !value.is_empty() => {
+                warn!("not forwarding DISPLAY={value}: only local X11 displays can be shared");
+            }
+            None => {}
+        }
+    }
+
+    if forward.mounts.is_empty() {
+        bail!("no display to forward: neither a local DISPLAY nor WAYLAND_DISPLAY is set");
+    }
+    Ok(forward)
+}
+
+fn bind_mount(source: &Path, target: &str) -> String {
+    format!("source={},target={target},type=bind", source.display())
+}
+
+/// Path of the Wayland socket: `WAYLAND_DISPLAY` itself if absolute, else
+/// relative to `XDG_RUNTIME_DIR`.
+fn wayland_socket(display: Option<&str>, runtime_dir: Option<&str>) -> Option<PathBuf> {
+    let display = display.filter(|d| !d.is_empty())?;
+    if display.starts_with('/') {
+        return Some(PathBuf::from(display));
+    }
+    let runtime_dir = runtime_dir.filter(|d| !d.is_empty())?;
+    Some(Path::new(runtime_dir).join(display))
+}
+
+/// Display number of a local X11 display (`:0`, `:1.0`, `unix:2`), or
+/// `None` for TCP displays such as `localhost:10.0` from SSH forwarding,
+/// whose socket can't be mounted.
+fn x11_display_number(display: &str) -> Option<&str> {
+    let (host, rest) = display.rsplit_once(':')?;
+    if !host.is_empty() && host != "unix" {
+        return None;
+    }
+    let number = rest.split('.').next().unwrap_or(rest);
+    (!number.is_empty() && number.bytes().all(|b| b.is_ascii_digit())).then_some(number)
+}
+
+/// Cookies for the display. The host's entries are tied to its hostname,
+/// which the container doesn't share, so with `xauth` available they are
+/// rewritten to match any host; otherwise the file is mounted as-is.
+fn xauthority(display: &str) -> Result<Option<PathBuf>> {
+    let source = match env::var("XAUTHORITY") {
+        Ok(path) if !path.is_empty() => PathBuf::from(path),
+        _ => match dirs::home_dir() {
+            Some(home) => home.join(".Xauthority"),
+            None => return Ok(None),
+        },
+    };
+    if !source.exists() {
+        return Ok(None);
+    }
+
+    let Ok(list) = Command::new("xauth")
+        .args(["nlist", display])
+        .stderr(Stdio::null())
+        .logged()
+        .output()
+    else {
+        return Ok(Some(source));
+    };
+    let entries: String = String::from_utf8_lossy(&list.stdout)
+        .lines()
+        .filter_map(wildcard_xauth_entry)
+        .collect();
+    if !list.status.success() || entries.is_empty() {
+        return Ok(Some(source));
+    }
+
+    let target = workspace::runtime_dir()?.join("Xauthority");
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let _ = fs::remove_file(&target);
+    let mut merge = Command::new("xauth")
+        .arg("-f")
+        .arg(&target)
+        .args(["nmerge", "-"])
+        .stdin(Stdio::piped())
+        .stderr(Stdio::null())
+        .logged()
+        .spawn()?;
+    if let Some(mut stdin) = merge.stdin.take() {
+        stdin.write_all(entries.as_bytes())?;
+    }
+    if !merge.wait()?.success() {
+        return Ok(Some(source));
+    }
+    Ok(Some(target))
+}
+
+/// Turn an `xauth nlist` line into one valid for any host by setting its
+/// address family to `FamilyWild` (`ffff`).
+fn wildcard_xauth_entry(line: &str) -> Option<String> {
+    let rest = line.get(4..).filter(|rest| !rest.is_empty())?;
+    Some(format!("ffff{rest}\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn x11_display_number_accepts_local_displays() {
+        assert_eq!(x11_display_number(":0"), Some("0"));
+        assert_eq!(x11_display_number(":1.0"), Some("1"));
+        assert_eq!(x11_display_number("unix:2"), Some("2"));
+        assert_eq!(x11_display_number("localhost:10.0"), None);
+        assert_eq!(x11_display_number(""), None);
+        assert_eq!(x11_display_number(":"), None);
+    }
+
+    #[test]
+    fn wayland_socket_resolves_relative_names() {
+        assert_eq!(
+            wayland_socket(Some("wayland-0"), Some("/run/user/1000")),
+            Some(PathBuf::from("/run/user/1000/wayland-0"))
+        );
+        assert_eq!(
+            wayland_socket(Some("/tmp/wl"), None),
+            Some(PathBuf::from("/tmp/wl"))
+        );
+        assert_eq!(wayland_socket(Some("wayland-0"), None), None);
+        assert_eq!(wayland_socket(None, Some("/run/user/1000")), None);
+    }
+
+    #[test]
+    fn wildcard_xauth_entry_replaces_family() {
+        assert_eq!(
+            wildcard_xauth_entry(
+                "0100 0004 686f7374 0001 30 0012 4d49542d4d414749432d434f4f4b49452d31 0010 abcd"
+            ),
+            Some(
+                "ffff 0004 686f7374 0001 30 0012 4d49542d4d414749432d434f4f4b49452d31 0010 abcd\n"
+                    .to_string()
+            )
+        );
+        assert_eq!(wildcard_xauth_entry(""), None);
+    }
+}
@@ -1,8 +1,79 @@
 use anyhow::{bail, Context, Result};
-use std::process::Command;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream, UdpSocket};
+use std::os::unix::process::ExitStatusExt;
+use std::process::{Command, ExitStatus, Output, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tracing::info;
 
+use crate::config;
+use crate::engine::{self, ContainerSummary};
+use crate::logging::{self, Logged};
 use crate::settings::Settings;
 
+/// Image used for socat port-forwarding sidecars unless configured
+/// otherwise (see [`sidecar_image`]).
+pub const SIDECAR_IMAGE: &str = "alpine/socat";
+
+/// Host address forwards bind to unless configured otherwise.
+pub const DEFAULT_BIND_ADDRESS: &str = "127.0.0.1";
+
+/// How many consecutive host ports to try when falling back from a taken one.
+const HOST_PORT_FALLBACK_ATTEMPTS: u16 = 100;
+
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+
+/// Turn on `--dry-run`: commands that change containers, forwards or
+/// processes are printed instead of run.
+pub fn set_dry_run() {
+    DRY_RUN.store(true, Ordering::Relaxed);
+}
+
+pub fn dry_run() -> bool {
+    DRY_RUN.load(Ordering::Relaxed)
+}
+
+/// Print a step a dry run skips.
+pub fn print_dry_run(step: &str) {
+    println!("[dry-run] {step}");
+}
+
+/// Runs the external commands that change something, so `--dry-run` can
+/// print them instead. Commands that only look things up run either way.
+pub trait Execute {
+    /// Like [`Command::output`]; a dry run succeeds without output.
+    fn execute(&mut self) -> io::Result<Output>;
+    /// Like [`Command::status`]; a dry run succeeds.
+    fn execute_status(&mut self) -> io::Result<ExitStatus>;
+}
+
+impl Execute for Command {
+    fn execute(&mut self) -> io::Result<Output> {
+        if dry_run() {
+            print_dry_run(&logging::command_line(self));
+            return Ok(Output {
+                status: ExitStatus::from_raw(0),
+                stdout: Vec::new(),
+                stderr: Vec::new(),
+            });
+        }
+        self.logged().output()
+    }
+
+    fn execute_status(&mut self) -> io::Result<ExitStatus> {
+        if dry_run() {
+            print_dry_run(&logging::command_line(self));
+            return Ok(ExitStatus::from_raw(0));
+        }
+        self.logged().status()
+    }
+}
+
 /// Return the docker executable path.
 /// Resolved via Settings (env var > config.toml > default).
 pub fn docker_path() -> String {
@@ -15,6 +86,114 @@ pub fn docker_compose_path() -> String {
     Settings::get().docker.compose_path.clone()
 }
 
+/// The Docker daemon the CLI talks to: `DOCKER_HOST` if set, else the
+/// current docker context (which honors `DOCKER_CONTEXT`).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DockerEndpoint {
+    /// Context name, or `DOCKER_HOST` when the variable overrides contexts
+    pub context: String,
+    /// Daemon address, e.g. `unix:///var/run/docker.sock` or `ssh://me@box`
+    pub host: String,
+}
+
+impl DockerEndpoint {
+    /// Whether the daemon runs on another machine, in which case published
+    /// ports and container IPs are only reachable there.
+    pub fn is_remote(&self) -> bool {
+        let Some((scheme, rest)) = self.host.split_once("://") else {
+            return false;
+        };
+        match scheme {
+            "unix" | "npipe" | "fd" => false,
+            "ssh" => true,
+            _ => {
+                let authority = rest.split('/').next().unwrap_or("");
+                let host = match authority.rsplit_once(':') {
+                    Some((h, p)) if !p.contains(']') => h,
+                    _ => authority,
+                };
+                !matches!(
+                    host.trim_start_matches('[').trim_end_matches(']'),
+                    "" | "localhost" | "127.0.0.1" | "::1" | "0.0.0.0"
+                )
+            }
+        }
+    }
+
+    /// How to reach a port published on a remote daemon's loopback from
+    /// this machine.
+    pub fn tunnel_hint(&self, host_port: u16) -> String {
+        if self.host.starts_with("ssh://") {
+            format!(
+                "tunnel it with: ssh -N -L {host_port}:127.0.0.1:{host_port} {}",
+                self.host
+            )
+        } else {
+            "tunnel it over SSH, or bind with --bind 0.0.0.0 to expose it on that host's network"
+                .to_string()
+        }
+    }
+}
+
+static ENDPOINT: OnceLock<DockerEndpoint> = OnceLock::new();
+
+/// The Docker endpoint in use, resolved once per process.
+pub fn docker_endpoint() -> &'static DockerEndpoint {
+    ENDPOINT.get_or_init(resolve_docker_endpoint)
+}
+
+/// Resolve the Docker endpoint. Falls back to the default local socket when
+/// the CLI has no context support (e.g. podman).
+fn resolve_docker_endpoint() -> DockerEndpoint {
+    if let Ok(host) = std::env::var("DOCKER_HOST") {
+        if !host.is_empty() {
+            return DockerEndpoint {
+                context: "DOCKER_HOST".to_string(),
+                host,
+            };
+        }
+    }
+    Command::new(docker_path())
+        .args([
+            "context",
+            "inspect",
+            "--format",
+            "{{.Name}}\t{{.Endpoints.docker.Host}}",
+        ])
+        .logged()
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .and_then(|o| {
+            let stdout = String::from_utf8_lossy(&o.stdout);
+            let (name, host) = stdout.trim().split_once('\t')?;
+            Some(DockerEndpoint {
+                context: name.to_string(),
+                host: host.to_string(),
+            })
+        })
+        .unwrap_or_else(|| DockerEndpoint {
+            context: "default".to_string(),
+            host: "unix:///var/run/docker.sock".to_string(),
+        })
+}
+
+/// Return `--docker-path` / `--docker-compose-path` arguments for the
+/// devcontainer CLI when the configured executables differ from the defaults.
+pub fn devcontainer_cli_docker_args() -> Vec<String> {
+    let settings = Settings::get();
+    let mut args = Vec::new();
+    if settings.docker.path != "docker" {
+        args.push("--docker-path".to_string());
+        args.push(settings.docker.path.clone());
+    }
+    if settings.docker.compose_path != "docker-compose" {
+        args.push("--docker-compose-path".to_string());
+        args.push(settings.docker.compose_path.clone());
+    }
+    args
+}
+
 /// Execute a command inside a running container and return stdout.
 pub fn exec_in_container(container_id: &str, cmd: &[&str]) -> Result<String> {
     let mut args = vec!["exec", container_id];
@@ -22,6 +201,7 @@ pub fn exec_in_container(container_id: &str, cmd: &[&str]) -> Result<String> {
 
     let output = Command::new(docker_path())
         .args(&args)
+        .logged()
         .output()
         .context("failed to run docker exec")?;
 
@@ -35,10 +215,31 @@ pub fn exec_in_container(container_id: &str, cmd: &[&str]) -> Result<String> {
     Ok(String::from_utf8_lossy(&output.stdout).to_string())
 }
 
+/// Inspect a container through the Engine API. `None` means the API is
+/// unavailable and the caller should fall back to the CLI.
+fn api_inspect(container_id: &str) -> Option<Option<Value>> {
+    engine::get()?.inspect_container(container_id).ok()
+}
+
+/// List containers with the given labels through the Engine API. `None`
+/// means the API is unavailable and the caller should fall back to the CLI.
+fn api_containers(all: bool, labels: &[String]) -> Option<Vec<ContainerSummary>> {
+    engine::get()?.list_containers(all, labels).ok()
+}
+
+/// Shorten a container ID the way `docker ps -q` does.
+fn short_id(id: &str) -> String {
+    id.chars().take(12).collect()
+}
+
 /// Check if a container is still running.
 pub fn is_container_running(container_id: &str) -> Result<bool> {
+    if let Some(container) = api_inspect(container_id) {
+        return Ok(container.is_some_and(|c| c["State"]["Running"] == true));
+    }
     let output = Command::new(docker_path())
         .args(["inspect", "-f", "{{.State.Running}}", container_id])
+        .logged()
         .output()
         .context("failed to run docker inspect")?;
 
@@ -46,16 +247,110 @@ pub fn is_container_running(container_id: &str) -> Result<bool> {
         && String::from_utf8_lossy(&output.stdout).trim() == "true")
 }
 
+/// Check if a container exists (running or stopped).
+pub fn container_exists(container_id: &str) -> Result<bool> {
+    if let Some(container) = api_inspect(container_id) {
+        return Ok(container.is_some());
+    }
+    let output = Command::new(docker_path())
+        .args(["inspect", "-f", "{{.Id}}", container_id])
+        .logged()
+        .output()
+        .context("failed to run docker inspect")?;
+
+    Ok(output.status.success())
+}
+
+/// Name of a container, with the leading `/` as Docker reports it.
+pub fn container_name(container_id: &str) -> Result<String> {
+    if let Some(container) = api_inspect(container_id) {
+        let container = container.with_context(|| format!("no such container: {container_id}"))?;
+        return Ok(container["Name"].as_str().unwrap_or_default().to_string());
+    }
+    let output = Command::new(docker_path())
+        .args(["inspect", "-f", "{{.Name}}", container_id])
+        .logged()
+        .output()
+        .context("failed to run docker inspect")?;
+    if !output.status.success() {
+        bail!("no such container: {container_id}");
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// IDs of all the workspace folder's devcontainers, running or stopped.
+pub fn workspace_container_ids(workspace_folder: &str) -> Result<Vec<String>> {
+    Ok(workspace_devcontainers(workspace_folder, true)?
+        .into_iter()
+        .map(|c| c.id)
+        .collect())
+}
+
 /// Find a running devcontainer for the given workspace folder.
 /// Returns the container ID if found.
 pub fn find_devcontainer(workspace_folder: &str) -> Result<Option<String>> {
+    find_devcontainer_in(workspace_folder, false)
+}
+
+/// Find the devcontainer for the given workspace folder, running or stopped.
+pub fn find_any_devcontainer(workspace_folder: &str) -> Result<Option<String>> {
+    find_devcontainer_in(workspace_folder, true)
+}
+
+static CONTAINER_FLAG: OnceLock<String> = OnceLock::new();
+
+/// Use the devcontainer given with the global `--container` flag (a name or
+/// ID prefix) when the workspace has several.
+pub fn set_container_flag(container: &str) {
+    let _ = CONTAINER_FLAG.set(container.to_string());
+}
+
+/// The container given with `--container`, or picked interactively earlier
+/// in this run.
+pub fn container_flag() -> Option<&'static str> {
+    CONTAINER_FLAG.get().map(String::as_str)
+}
+
+/// A container labeled with the workspace folder.
+#[derive(Debug, Clone, PartialEq)]
+struct Devcontainer {
+    id: String,
+    name: String,
+    /// The `devcontainer.config_file` label
+    config_file: String,
+}
+
+fn find_devcontainer_in(workspace_folder: &str, all: bool) -> Result<Option<String>> {
+    let candidates = workspace_devcontainers(workspace_folder, all)?;
+    let config_file = config::labeled_config_file(std::path::Path::new(workspace_folder)).ok();
+    match choose_devcontainer(&candidates, container_flag(), config_file.as_deref()) {
+        Ok(chosen) => Ok(chosen.map(|c| c.id.clone())),
+        Err(several) => pick_devcontainer(&several).map(Some),
+    }
+}
+
+fn workspace_devcontainers(workspace_folder: &str, all: bool) -> Result<Vec<Devcontainer>> {
+    let label = format!("devcontainer.local_folder={workspace_folder}");
+    if let Some(containers) = api_containers(all, std::slice::from_ref(&label)) {
+        return Ok(containers
+            .iter()
+            .map(|c| Devcontainer {
+                id: short_id(&c.id),
+                name: c.name().to_string(),
+                config_file: c.label("devcontainer.config_file").to_string(),
+            })
+            .collect());
+    }
     let output = Command::new(docker_path())
+        .args(["ps"])
+        .args(all.then_some("-a"))
         .args([
-            "ps",
-            "-q",
             "--filter",
-            &format!("label=devcontainer.local_folder={workspace_folder}"),
+            &format!("label={label}"),
+            "--format",
+            "{{.ID}}\t{{.Names}}\t{{.Label \"devcontainer.config_file\"}}",
         ])
+        .logged()
         .output()
         .context("failed to run docker ps")?;
 
@@ -66,14 +361,291 @@ pub fn find_devcontainer(workspace_folder: &str) -> Result<Option<String>> {
         );
     }
 
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            Some(Devcontainer {
+                id: fields.next().filter(|id| !id.is_empty())?.to_string(),
+                name: fields.next().unwrap_or_default().to_string(),
+                config_file: fields.next().unwrap_or_default().to_string(),
+            })
+        })
+        .collect())
+}
+
+/// The workspace's devcontainer among `candidates`: the one named by
+/// `--container`, else the only one, else the one created from the
+/// selected config. `Err` holds the candidates if that leaves several.
+fn choose_devcontainer<'a>(
+    candidates: &'a [Devcontainer],
+    flag: Option<&str>,
+    config_file: Option<&std::path::Path>,
+) -> std::result::Result<Option<&'a Devcontainer>, Vec<&'a Devcontainer>> {
+    if let Some(flag) = flag {
+        return Ok(candidates
+            .iter()
+            .find(|c| c.name == flag || c.id.starts_with(flag)));
+    }
+    if candidates.len() <= 1 {
+        return Ok(candidates.first());
+    }
+    let from_config: Vec<&Devcontainer> = candidates
+        .iter()
+        .filter(|c| config_file.is_some_and(|f| std::path::Path::new(&c.config_file) == f))
+        .collect();
+    match from_config[..] {
+        [chosen] => Ok(Some(chosen)),
+        _ => Err(candidates.iter().collect()),
+    }
+}
+
+/// Ask which devcontainer to use, or fail with the choices when not on a
+/// terminal. The answer is kept for the rest of the run.
+fn pick_devcontainer(candidates: &[&Devcontainer]) -> Result<String> {
+    use std::io::{BufRead, IsTerminal, Write};
+
+    let names: Vec<&str> = candidates.iter().map(|c| c.name.as_str()).collect();
+    if !std::io::stdin().is_terminal() || !std::io::stderr().is_terminal() {
+        bail!(
+            "found several devcontainers for this workspace ({}); choose one with `--container <name>`",
+            names.join(", ")
+        );
+    }
+    eprintln!("Several devcontainers belong to this workspace:");
+    for (i, c) in candidates.iter().enumerate() {
+        eprintln!("  {}) {} ({})", i + 1, c.name, c.config_file);
+    }
+    eprint!("Select a container [1-{}]: ", candidates.len());
+    std::io::stderr().flush().ok();
+
+    let mut line = String::new();
+    std::io::stdin()
+        .lock()
+        .read_line(&mut line)
+        .context("failed to read container choice")?;
+    let chosen = line
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| candidates.get(n.checked_sub(1)?))
+        .with_context(|| format!("invalid choice: {}", line.trim()))?;
+    set_container_flag(&chosen.name);
+    Ok(chosen.id.clone())
+}
+
+/// Docker Compose project a container belongs to, if any.
+pub fn compose_project(container_id: &str) -> Result<Option<String>> {
+    const LABEL: &str = "com.docker.compose.project";
+    if let Some(container) = api_inspect(container_id) {
+        let container = container.with_context(|| format!("no such container: {container_id}"))?;
+        return Ok(container["Config"]["Labels"][LABEL]
+            .as_str()
+            .filter(|p| !p.is_empty())
+            .map(str::to_string));
+    }
+    let output = Command::new(docker_path())
+        .args([
+            "inspect",
+            "-f",
+            &format!("{{{{index .Config.Labels \"{LABEL}\"}}}}"),
+            container_id,
+        ])
+        .logged()
+        .output()
+        .context("failed to run docker inspect")?;
+
+    if !output.status.success() {
+        bail!(
+            "docker inspect failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    let project = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Ok((!project.is_empty()).then_some(project))
+}
+
+/// Find the running container of a Docker Compose `service` in the same
+/// project as the devcontainer.
+pub fn find_compose_service(container_id: &str, service: &str) -> Result<String> {
+    let project = compose_project(container_id)?.with_context(|| {
+        format!("cannot forward from service {service}: the devcontainer is not part of a Docker Compose project")
+    })?;
+    let labels = [
+        format!("com.docker.compose.project={project}"),
+        format!("com.docker.compose.service={service}"),
+    ];
+    let id = match api_containers(false, &labels) {
+        Some(containers) => containers.first().map(|c| short_id(&c.id)),
+        None => {
+            let output = Command::new(docker_path())
+                .args([
+                    "ps",
+                    "-q",
+                    "--filter",
+                    &format!("label={}", labels[0]),
+                    "--filter",
+                    &format!("label={}", labels[1]),
+                ])
+                .logged()
+                .output()
+                .context("failed to run docker ps")?;
+            if !output.status.success() {
+                bail!(
+                    "docker ps failed: {}",
+                    String::from_utf8_lossy(&output.stderr)
+                );
+            }
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .next()
+                .map(str::to_string)
+        }
+    };
+    id.with_context(|| format!("no running container for service {service} in compose project {project}"))
+}
+
+/// Running containers of a Docker Compose project.
+pub fn compose_containers(project: &str) -> Result<Vec<String>> {
+    let label = format!("com.docker.compose.project={project}");
+    if let Some(containers) = api_containers(false, std::slice::from_ref(&label)) {
+        return Ok(containers.iter().map(|c| short_id(&c.id)).collect());
+    }
+    let output = Command::new(docker_path())
+        .args(["ps", "-q", "--filter", &format!("label={label}")])
+        .logged()
+        .output()
+        .context("failed to run docker ps")?;
+    if !output.status.success() {
+        bail!(
+            "docker ps failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::to_string)
+        .collect())
+}
+
+/// State of a container and its healthcheck.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContainerHealth {
+    /// Container name, without the leading `/`
+    pub name: String,
+    pub running: bool,
+    /// `starting`, `healthy` or `unhealthy`; empty without a healthcheck
+    pub health: String,
+}
+
+/// Whether a container is running and what its healthcheck reports.
+pub fn container_health(container_id: &str) -> Result<ContainerHealth> {
+    if let Some(container) = api_inspect(container_id) {
+        let container = container.with_context(|| format!("no such container: {container_id}"))?;
+        return Ok(ContainerHealth {
+            name: container["Name"]
+                .as_str()
+                .unwrap_or_default()
+                .trim_start_matches('/')
+                .to_string(),
+            running: container["State"]["Running"] == true,
+            health: container["State"]["Health"]["Status"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+        });
+    }
+    let output = Command::new(docker_path())
+        .args([
+            "inspect",
+            "-f",
+            "{{.Name}}\t{{.State.Running}}\t{{if .State.Health}}{{.State.Health.Status}}{{end}}",
+            container_id,
+        ])
+        .logged()
+        .output()
+        .context("failed to run docker inspect")?;
+    if !output.status.success() {
+        bail!("no such container: {container_id}");
+    }
     let stdout = String::from_utf8_lossy(&output.stdout);
-    let id = stdout.trim();
-    if id.is_empty() {
-        Ok(None)
-    } else {
-        // Take the first container if multiple are returned
-        Ok(Some(id.lines().next().unwrap().to_string()))
+    let mut fields = stdout.trim_end_matches('\n').split('\t');
+    Ok(ContainerHealth {
+        name: fields
+            .next()
+            .unwrap_or_default()
+            .trim_start_matches('/')
+            .to_string(),
+        running: fields.next() == Some("true"),
+        health: fields.next().unwrap_or_default().to_string(),
+    })
+}
+
+/// Stop a running container.
+pub fn stop_container(container_id: &str) -> Result<()> {
+    let status = Command::new(docker_path())
+        .args(["stop", container_id])
+        .execute_status()
+        .context("failed to run docker stop")?;
+
+    if !status.success() {
+        bail!("docker stop exited with status {status}");
+    }
+
+    Ok(())
+}
+
+/// A devcontainer known to the docker daemon (running or stopped).
+pub struct DevcontainerInfo {
+    pub folder: String,
+    pub state: String,
+}
+
+/// List all devcontainers (from any workspace), including stopped ones.
+pub fn list_devcontainers() -> Result<Vec<DevcontainerInfo>> {
+    if let Some(containers) = api_containers(true, &["devcontainer.local_folder".to_string()]) {
+        return Ok(containers
+            .iter()
+            .map(|c| DevcontainerInfo {
+                folder: c.label("devcontainer.local_folder").to_string(),
+                state: c.state.clone(),
+            })
+            .collect());
+    }
+    let output = Command::new(docker_path())
+        .args([
+            "ps",
+            "-a",
+            "--filter",
+            "label=devcontainer.local_folder",
+            "--format",
+            "{{.Label \"devcontainer.local_folder\"}}\t{{.State}}",
+        ])
+        .logged()
+        .output()
+        .context("failed to list devcontainers")?;
+
+    if !output.status.success() {
+        bail!(
+            "docker ps failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
     }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout
+        .trim()
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let parts: Vec<&str> = line.split('\t').collect();
+            DevcontainerInfo {
+                folder: parts.first().unwrap_or(&"").to_string(),
+                state: parts.get(1).unwrap_or(&"").to_string(),
+            }
+        })
+        .collect())
 }
 
 /// Build the Go template string for listing network names.
@@ -88,8 +660,87 @@ fn network_ip_template(network: &str) -> String {
     )
 }
 
+fn network_ipv6_template(network: &str) -> String {
+    format!("{{{{(index .NetworkSettings.Networks \"{network}\").GlobalIPv6Address}}}}")
+}
+
+/// What `docker exec` needs to run a command the way `devcontainer exec`
+/// would.
+#[derive(Debug, Default, PartialEq)]
+pub struct ExecMetadata {
+    /// `remoteUser` (else `containerUser`) from the `devcontainer.metadata` label
+    pub remote_user: Option<String>,
+    /// Where the workspace folder is mounted in the container
+    pub workspace_mount: Option<String>,
+}
+
+/// Read the user and workspace mount of a devcontainer from its labels and
+/// mounts.
+pub fn exec_metadata(container_id: &str, workspace_folder: &str) -> Result<ExecMetadata> {
+    let container = match api_inspect(container_id) {
+        Some(container) => container.with_context(|| format!("no such container: {container_id}"))?,
+        None => {
+            let output = Command::new(docker_path())
+                .args(["inspect", container_id])
+                .logged()
+                .output()
+                .context("failed to run docker inspect")?;
+            if !output.status.success() {
+                bail!(
+                    "docker inspect failed: {}",
+                    String::from_utf8_lossy(&output.stderr)
+                );
+            }
+            let mut containers: Vec<Value> = serde_json::from_slice(&output.stdout)
+                .context("unexpected docker inspect output")?;
+            containers
+                .pop()
+                .with_context(|| format!("no such container: {container_id}"))?
+        }
+    };
+    Ok(parse_exec_metadata(&container, workspace_folder))
+}
+
+fn parse_exec_metadata(container: &Value, workspace_folder: &str) -> ExecMetadata {
+    // The label holds the merged metadata of features and config; later
+    // entries win, as in the devcontainer CLI
+    let metadata: Vec<Value> = container["Config"]["Labels"]["devcontainer.metadata"]
+        .as_str()
+        .and_then(|label| serde_json::from_str(label).ok())
+        .unwrap_or_default();
+    let last = |key: &str| {
+        metadata
+            .iter()
+            .rev()
+            .find_map(|entry| entry[key].as_str().map(str::to_string))
+    };
+    let workspace_mount = container["Mounts"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|m| m["Source"].as_str() == Some(workspace_folder))
+        .and_then(|m| m["Destination"].as_str().map(str::to_string));
+    ExecMetadata {
+        remote_user: last("remoteUser").or_else(|| last("containerUser")),
+        workspace_mount,
+    }
+}
+
 /// Get the network name for a container.
 pub fn get_container_network(container_id: &str) -> Result<String> {
+    if let Some(container) = api_inspect(container_id) {
+        let container = container.with_context(|| format!("no such container: {container_id}"))?;
+        // Sorted like the Go template's map iteration in the CLI path
+        let mut networks: Vec<&String> = container["NetworkSettings"]["Networks"]
+            .as_object()
+            .map(|n| n.keys().collect())
+            .unwrap_or_default();
+        networks.sort();
+        return networks
+            .first()
+            .map(|n| n.to_string())
+            .with_context(|| format!("container {container_id} has no networks"));
+    }
     let output = Command::new(docker_path())
         .args([
             "inspect",
@@ -97,137 +748,1213 @@ pub fn get_container_network(container_id: &str) -> Result<String> {
             network_list_template(),
             container_id,
         ])
+        .logged()
         .output()
         .context("failed to run docker inspect")?;
 
     if !output.status.success() {
         bail!(
-            "docker inspect failed: {}",
+            "docker inspect failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    let network = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if network.is_empty() {
+        bail!("container {container_id} has no networks");
+    }
+
+    // If multiple networks, take the first one
+    Ok(network.split('\n').next().unwrap().to_string())
+}
+
+/// Get the IP address of a container on a given network.
+/// The default `bridge` network doesn't support container name/ID DNS resolution,
+/// so we need the actual IP for socat to connect to.
+pub fn get_container_ip(container_id: &str, network: &str) -> Result<String> {
+    if let Some(container) = api_inspect(container_id) {
+        let container = container.with_context(|| format!("no such container: {container_id}"))?;
+        return container["NetworkSettings"]["Networks"][network]["IPAddress"]
+            .as_str()
+            .filter(|ip| !ip.is_empty())
+            .map(str::to_string)
+            .with_context(|| format!("container {container_id} has no IP on network {network}"));
+    }
+    let template = network_ip_template(network);
+    let output = Command::new(docker_path())
+        .args(["inspect", "-f", &template, container_id])
+        .logged()
+        .output()
+        .context("failed to run docker inspect for IP")?;
+
+    if !output.status.success() {
+        bail!(
+            "docker inspect failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    let ip = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if ip.is_empty() {
+        bail!("container {container_id} has no IP on network {network}");
+    }
+
+    Ok(ip)
+}
+
+/// Get the IPv6 address of a container on a given network, if the network
+/// has IPv6 enabled.
+pub fn get_container_ipv6(container_id: &str, network: &str) -> Result<Option<String>> {
+    if let Some(container) = api_inspect(container_id) {
+        let container = container.with_context(|| format!("no such container: {container_id}"))?;
+        return Ok(
+            container["NetworkSettings"]["Networks"][network]["GlobalIPv6Address"]
+                .as_str()
+                .filter(|ip| !ip.is_empty())
+                .map(str::to_string),
+        );
+    }
+    let output = Command::new(docker_path())
+        .args([
+            "inspect",
+            "-f",
+            &network_ipv6_template(network),
+            container_id,
+        ])
+        .logged()
+        .output()
+        .context("failed to run docker inspect for IPv6 address")?;
+    if !output.status.success() {
+        bail!(
+            "docker inspect failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    let ip = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Ok((!ip.is_empty()).then_some(ip))
+}
+
+/// A container's addresses on one network.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContainerAddresses {
+    pub ipv4: String,
+    /// Only on networks with IPv6 enabled
+    pub ipv6: Option<String>,
+}
+
+impl ContainerAddresses {
+    /// The address a forward connects to: the IPv6 one for forwards to
+    /// IPv6-only listeners, if there is one.
+    pub fn target(&self, ipv6: bool) -> &str {
+        match &self.ipv6 {
+            Some(addr) if ipv6 => addr,
+            _ => &self.ipv4,
+        }
+    }
+
+    /// Whether `target` is still one of the container's addresses.
+    pub fn contains(&self, target: &str) -> bool {
+        self.ipv4 == target || self.ipv6.as_deref() == Some(target)
+    }
+}
+
+pub fn get_container_addresses(container_id: &str, network: &str) -> Result<ContainerAddresses> {
+    Ok(ContainerAddresses {
+        ipv4: get_container_ip(container_id, network)?,
+        ipv6: get_container_ipv6(container_id, network)?,
+    })
+}
+
+/// Transport protocol of a port forward.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    #[default]
+    Tcp,
+    Udp,
+}
+
+impl Protocol {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Protocol::Tcp => "tcp",
+            Protocol::Udp => "udp",
+        }
+    }
+
+    /// Parse a `dcw.protocol` label value. Sidecars created before the label
+    /// existed have no value and are TCP.
+    pub fn from_label(label: &str) -> Protocol {
+        if label.eq_ignore_ascii_case("udp") {
+            Protocol::Udp
+        } else {
+            Protocol::Tcp
+        }
+    }
+}
+
+/// Options for a single port-forwarding sidecar.
+pub struct ForwardSpec<'a> {
+    pub host_port: u16,
+    pub container_port: u16,
+    pub protocol: Protocol,
+    /// Run the sidecar in the background
+    pub detach: bool,
+    /// Value of the `dcw.source` label (e.g. `watch`), if any
+    pub source: Option<&'a str>,
+    /// Unix socket inside the container this forward is bridged to, if any
+    pub unix_socket: Option<&'a str>,
+    /// Host address to bind; defaults to [`DEFAULT_BIND_ADDRESS`]
+    pub bind_address: Option<&'a str>,
+    /// Human-readable label (from `portsAttributes`), if any
+    pub label: Option<&'a str>,
+    /// If the host port is taken, use the next free one instead of failing
+    pub fallback: bool,
+    /// Docker Compose service the container port belongs to, if not the
+    /// devcontainer itself
+    pub service: Option<&'a str>,
+    /// Log HTTP requests passing through (native backend only)
+    pub log_http: bool,
+    /// Terminate TLS on the host side (native backend only)
+    pub tls: bool,
+    /// Connect to the container's IPv6 address, for a port that only
+    /// listens on IPv6
+    pub ipv6: bool,
+    /// Bytes per second in each direction (native backend only)
+    pub rate_limit: Option<u64>,
+    /// Connections, or UDP clients, served at once (native backend only)
+    pub max_connections: Option<u32>,
+    /// Seconds without traffic after which the forward is removed (native
+    /// backend only)
+    pub idle_timeout: Option<u64>,
+}
+
+impl ForwardSpec<'_> {
+    /// A detached TCP forward with no source label.
+    pub fn new(host_port: u16, container_port: u16) -> Self {
+        Self {
+            host_port,
+            container_port,
+            protocol: Protocol::Tcp,
+            detach: true,
+            source: None,
+            unix_socket: None,
+            bind_address: None,
+            fallback: false,
+            label: None,
+            service: None,
+            log_http: false,
+            tls: false,
+            ipv6: false,
+            rate_limit: None,
+            max_connections: None,
+            idle_timeout: None,
+        }
+    }
+}
+
+/// Name of the sidecar forwarding `port`.
+/// TCP: `pf-<ws_id>-c<port>`, UDP: `pf-<ws_id>-c<port>-udp`.
+pub fn sidecar_name(ws_id: &str, port: u16, protocol: Protocol) -> String {
+    match protocol {
+        Protocol::Tcp => format!("pf-{ws_id}-c{port}"),
+        Protocol::Udp => format!("pf-{ws_id}-c{port}-udp"),
+    }
+}
+
+/// Name of the sidecar forwarding `port` of a compose service:
+/// `pf-<ws_id>-<service>-c<port>`, with `-udp` appended for UDP.
+pub fn service_sidecar_name(ws_id: &str, service: &str, port: u16, protocol: Protocol) -> String {
+    sidecar_name(&format!("{ws_id}-{service}"), port, protocol)
+}
+
+/// Name of the sidecar for a unix socket forward listening on `host_port`:
+/// `pf-<ws_id>-u<host_port>`.
+pub fn unix_sidecar_name(ws_id: &str, host_port: u16) -> String {
+    format!("pf-{ws_id}-u{host_port}")
+}
+
+/// Host addresses a forward bound to `bind` listens on: `bind` itself, plus
+/// `::1` for the IPv4 loopback when `forward.dual_stack` is set, so clients
+/// resolving `localhost` to either address reach it.
+pub fn bind_addresses(bind: &str) -> Vec<&str> {
+    let bind = bind.trim_start_matches('[').trim_end_matches(']');
+    if bind == DEFAULT_BIND_ADDRESS && Settings::get().forward.dual_stack {
+        vec![bind, "::1"]
+    } else {
+        vec![bind]
+    }
+}
+
+/// Whether `port` can currently be bound on the host at `bind`.
+pub fn host_port_available(bind: &str, port: u16, protocol: Protocol) -> bool {
+    bind_addresses(bind).into_iter().all(|bind| match protocol {
+        Protocol::Tcp => TcpListener::bind((bind, port)).is_ok(),
+        Protocol::Udp => UdpSocket::bind((bind, port)).is_ok(),
+    })
+}
+
+/// Find the first free host port at or after `start`, trying up to
+/// [`HOST_PORT_FALLBACK_ATTEMPTS`] ports.
+pub fn find_free_host_port(bind: &str, start: u16, protocol: Protocol) -> Option<u16> {
+    (start..=u16::MAX)
+        .take(HOST_PORT_FALLBACK_ATTEMPTS as usize)
+        .find(|p| host_port_available(bind, *p, protocol))
+}
+
+/// Whether something accepts TCP connections on a published host port.
+/// Wildcard binds are probed through loopback.
+pub fn host_port_reachable(bind: &str, port: u16) -> bool {
+    let host = match bind {
+        "" | "0.0.0.0" => "127.0.0.1",
+        "::" | "[::]" => "::1",
+        other => other.trim_start_matches('[').trim_end_matches(']'),
+    };
+    let Ok(ip) = host.parse::<IpAddr>() else {
+        return false;
+    };
+    TcpStream::connect_timeout(&SocketAddr::new(ip, port), Duration::from_millis(500)).is_ok()
+}
+
+/// Build the `docker run -p` values publishing the forward on the host, one
+/// per [`bind_addresses`].
+fn publish_args(spec: &ForwardSpec) -> Vec<String> {
+    let host_port = spec.host_port;
+    bind_addresses(spec.bind_address.unwrap_or(DEFAULT_BIND_ADDRESS))
+        .into_iter()
+        .map(|bind| {
+            // IPv6 addresses must be bracketed to separate them from the ports
+            let bind = if bind.contains(':') {
+                format!("[{bind}]")
+            } else {
+                bind.to_string()
+            };
+            match spec.protocol {
+                Protocol::Tcp => format!("{bind}:{host_port}:{host_port}"),
+                Protocol::Udp => format!("{bind}:{host_port}:{host_port}/udp"),
+            }
+        })
+        .collect()
+}
+
+/// Image for socat sidecars: `forward.sidecar_image` in config.toml or
+/// `DCW_SIDECAR_IMAGE`, e.g. a mirror in an air-gapped registry.
+pub fn sidecar_image() -> &'static str {
+    Settings::get()
+        .forward
+        .sidecar_image
+        .as_deref()
+        .unwrap_or(SIDECAR_IMAGE)
+}
+
+/// Whether `image` is present locally or can be found in its registry.
+pub fn image_available(image: &str) -> bool {
+    let docker = docker_path();
+    ["image", "manifest"].iter().any(|kind| {
+        Command::new(&docker)
+            .args([kind, "inspect", image])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .logged()
+            .status()
+            .is_ok_and(|s| s.success())
+    })
+}
+
+/// Start pulling the sidecar image in the background unless it is present,
+/// so the first sidecar forward doesn't wait for the pull.
+pub fn prefetch_sidecar_image() {
+    if dry_run() {
+        return;
+    }
+    let image = sidecar_image();
+    let docker = docker_path();
+    let present = Command::new(&docker)
+        .args(["image", "inspect", image])
+        .logged()
+        .output()
+        .is_ok_and(|o| o.status.success());
+    if present {
+        return;
+    }
+    info!("Pulling {image} for port forwards in the background...");
+    let _ = Command::new(&docker)
+        .args(["pull", "-q", image])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .logged()
+        .spawn();
+}
+
+/// Build the socat listen/connect addresses for a forward. An IPv6
+/// `container_ip` is connected to with `TCP6:` or `UDP6:`.
+fn socat_args(spec: &ForwardSpec, container_ip: &str) -> [String; 2] {
+    let host_port = spec.host_port;
+    let container_port = spec.container_port;
+    let (family, target) = if container_ip.contains(':') {
+        ("6", format!("[{container_ip}]"))
+    } else {
+        ("", container_ip.to_string())
+    };
+    match spec.protocol {
+        Protocol::Tcp => [
+            format!("TCP-LISTEN:{host_port},fork,reuseaddr"),
+            format!("TCP{family}:{target}:{container_port}"),
+        ],
+        Protocol::Udp => [
+            format!("UDP-LISTEN:{host_port},fork,reuseaddr"),
+            format!("UDP{family}:{target}:{container_port}"),
+        ],
+    }
+}
+
+/// Start a socat port-forwarding sidecar container.
+/// Returns the host port actually used, which differs from the requested
+/// one only when `spec.fallback` is set and that port was taken.
+///
+/// Sidecar naming: see [`sidecar_name`].
+/// Idempotent: removes existing sidecar first.
+pub fn start_port_forward(
+    ws_id: &str,
+    container_id: &str,
+    network: &str,
+    spec: &ForwardSpec,
+) -> Result<u16> {
+    let container_port = spec.container_port;
+    let sidecar_name = match (spec.unix_socket, spec.service) {
+        (Some(_), _) => unix_sidecar_name(ws_id, spec.host_port),
+        (None, Some(service)) => service_sidecar_name(ws_id, service, container_port, spec.protocol),
+        (None, None) => sidecar_name(ws_id, container_port, spec.protocol),
+    };
+
+    // Remove existing sidecar if present (ignore errors)
+    let _ = Command::new(docker_path())
+        .args(["rm", "-f", &sidecar_name])
+        .execute();
+
+    // Check only after removing our own sidecar, which may hold the port
+    let host_port = if spec.fallback {
+        let bind = spec.bind_address.unwrap_or(DEFAULT_BIND_ADDRESS);
+        let port = find_free_host_port(bind, spec.host_port, spec.protocol)
+            .with_context(|| format!("no free host port found from {}", spec.host_port))?;
+        if port != spec.host_port {
+            info!(
+                "Host port {} is in use, using {port} instead.",
+                spec.host_port
+            );
+        }
+        port
+    } else {
+        spec.host_port
+    };
+    let spec = &ForwardSpec { host_port, ..*spec };
+    let addresses = get_container_addresses(container_id, network)?;
+    let container_ip = addresses.target(spec.ipv6);
+
+    let mut args = vec![
+        "run".to_string(),
+        "--rm".to_string(),
+        "--name".to_string(),
+        sidecar_name.clone(),
+        "--network".to_string(),
+        network.to_string(),
+        "--label".to_string(),
+        "dcw.role=port-forward".to_string(),
+        "--label".to_string(),
+        format!("dcw.workspace={ws_id}"),
+        "--label".to_string(),
+        format!("dcw.port={container_port}"),
+        "--label".to_string(),
+        format!("dcw.host_port={host_port}"),
+        "--label".to_string(),
+        format!("dcw.container={container_id}"),
+        "--label".to_string(),
+        format!("dcw.protocol={}", spec.protocol.as_str()),
+        "--label".to_string(),
+        format!("dcw.target={container_ip}"),
+    ];
+
+    if let Some(src) = spec.source {
+        args.extend([
+            "--label".to_string(),
+            format!("dcw.source={src}"),
+        ]);
+    }
+
+    if let Some(label) = spec.label {
+        args.extend(["--label".to_string(), format!("dcw.label={label}")]);
+    }
+
+    if let Some(service) = spec.service {
+        args.extend(["--label".to_string(), format!("dcw.service={service}")]);
+    }
+
+    if let Some(path) = spec.unix_socket {
+        args.extend([
+            "--label".to_string(),
+            format!("dcw.unix_socket={path}"),
+        ]);
+    }
+
+    args.extend([
+        "--label".to_string(),
+        format!(
+            "dcw.bind={}",
+            spec.bind_address.unwrap_or(DEFAULT_BIND_ADDRESS)
+        ),
+    ]);
+    for publish in publish_args(spec) {
+        args.extend(["-p".to_string(), publish]);
+    }
+
+    if spec.detach {
+        args.push("-d".to_string());
+    }
+
+    args.push(sidecar_image().to_string());
+    args.extend(socat_args(spec, container_ip));
+
+    let output = Command::new(docker_path())
+        .args(&args)
+        .execute()
+        .context("failed to run docker run for port forward")?;
+
+    if !output.status.success() {
+        bail!(
+            "failed to start port forward sidecar {sidecar_name}: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    Ok(host_port)
+}
+
+/// Container-side TCP port used to bridge a unix socket forward that
+/// listens on `host_port`. Chosen from the dynamic port range to stay clear
+/// of ports applications typically use.
+pub fn unix_bridge_port(host_port: u16) -> u16 {
+    49152 + host_port % 16384
+}
+
+fn unix_bridge_pid_file(host_port: u16) -> String {
+    format!("/tmp/dcw-unix-bridge-{host_port}.pid")
+}
+
+/// Forward a host TCP port to a unix socket inside the container.
+/// The container port and protocol of `spec` are ignored.
+///
+/// A socat bridge is started inside the container (via `docker exec`) that
+/// exposes the socket on [`unix_bridge_port`], and a regular TCP sidecar
+/// forwards the host port to that bridge. Requires `socat` in the container.
+pub fn start_unix_socket_forward(
+    ws_id: &str,
+    container_id: &str,
+    network: &str,
+    spec: &ForwardSpec,
+    socket_path: &str,
+) -> Result<u16> {
+    let host_port = spec.host_port;
+    exec_in_container(container_id, &["sh", "-c", "command -v socat"])
+        .context("socat is required inside the devcontainer to forward unix sockets")?;
+
+    let _ = stop_unix_socket_bridge(container_id, host_port);
+
+    let bridge_port = unix_bridge_port(host_port);
+    let pid_file = unix_bridge_pid_file(host_port);
+    let script = format!(
+        r#"echo $$ > {pid_file}; exec socat TCP-LISTEN:{bridge_port},fork,reuseaddr "UNIX-CONNECT:$1""#
+    );
+    let output = Command::new(docker_path())
+        .args(["exec", "-d", container_id, "sh", "-c", &script, "_", socket_path])
+        .execute()
+        .context("failed to run docker exec for unix socket bridge")?;
+
+    if !output.status.success() {
+        bail!(
+            "failed to start unix socket bridge: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    let spec = ForwardSpec {
+        container_port: bridge_port,
+        protocol: Protocol::Tcp,
+        unix_socket: Some(socket_path),
+        // The bridge port and sidecar name are derived from the host port
+        fallback: false,
+        ..*spec
+    };
+    start_port_forward(ws_id, container_id, network, &spec)
+}
+
+/// Stop the in-container socat bridge for a unix socket forward.
+pub fn stop_unix_socket_bridge(container_id: &str, host_port: u16) -> Result<()> {
+    let pid_file = unix_bridge_pid_file(host_port);
+    let script = format!(r#"[ -f {pid_file} ] && kill "$(cat {pid_file})"; rm -f {pid_file}"#);
+    let output = Command::new(docker_path())
+        .args(["exec", container_id, "sh", "-c", &script])
+        .execute()
+        .context("failed to run docker exec")?;
+    if !output.status.success() {
+        bail!(
+            "docker exec failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(())
+}
+
+/// Name of the workspace's shared sidecar: `pf-<ws_id>-shared`.
+pub fn shared_sidecar_name(ws_id: &str) -> String {
+    format!("pf-{ws_id}-shared")
+}
+
+/// One forward served by the shared sidecar.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SharedForward {
+    pub protocol: Protocol,
+    pub bind: String,
+    pub host_port: u16,
+    pub container_port: u16,
+    pub source: Option<String>,
+    pub label: Option<String>,
+    /// Connects to the container's IPv6 address
+    pub ipv6: bool,
+}
+
+impl SharedForward {
+    /// Encode as `proto/bind/host/container[/source[/label]]` for the
+    /// `dcw.forwards` label, with `6` appended to `proto` for IPv6 targets.
+    /// The label is escaped since it is free text.
+    fn encode(&self) -> String {
+        let mut s = format!(
+            "{}{}/{}/{}/{}",
+            self.protocol.as_str(),
+            if self.ipv6 { "6" } else { "" },
+            self.bind,
+            self.host_port,
+            self.container_port
+        );
+        if self.source.is_some() || self.label.is_some() {
+            s.push('/');
+            s.push_str(self.source.as_deref().unwrap_or(""));
+        }
+        if let Some(label) = &self.label {
+            s.push('/');
+            s.push_str(&escape_label(label));
+        }
+        s
+    }
+
+    fn decode(s: &str) -> Option<Self> {
+        let mut parts = s.split('/');
+        let protocol = parts.next()?;
+        let ipv6 = protocol.ends_with('6');
+        Some(Self {
+            protocol: Protocol::from_label(protocol.trim_end_matches('6')),
+            bind: parts.next()?.to_string(),
+            host_port: parts.next()?.parse().ok()?,
+            container_port: parts.next()?.parse().ok()?,
+            source: parts.next().filter(|s| !s.is_empty()).map(str::to_string),
+            label: parts.next().map(unescape_label),
+            ipv6,
+        })
+    }
+
+    fn spec(&self) -> ForwardSpec<'_> {
+        ForwardSpec {
+            protocol: self.protocol,
+            bind_address: Some(&self.bind),
+            ipv6: self.ipv6,
+            ..ForwardSpec::new(self.host_port, self.container_port)
+        }
+    }
+}
+
+/// Percent-escape the characters used as separators in `dcw.forwards`.
+fn escape_label(label: &str) -> String {
+    label
+        .replace('%', "%25")
+        .replace('/', "%2F")
+        .replace(',', "%2C")
+}
+
+fn unescape_label(label: &str) -> String {
+    label
+        .replace("%2C", ",")
+        .replace("%2F", "/")
+        .replace("%25", "%")
+}
+
+fn encode_shared_forwards(forwards: &[SharedForward]) -> String {
+    forwards
+        .iter()
+        .map(SharedForward::encode)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn decode_shared_forwards(label: &str) -> Vec<SharedForward> {
+    label.split(',').filter_map(SharedForward::decode).collect()
+}
+
+/// A label of the workspace's shared sidecar, if it exists.
+fn api_shared_sidecar_label(ws_id: &str, key: &str) -> Option<Option<String>> {
+    let container = api_inspect(&shared_sidecar_name(ws_id))?;
+    Some(container.and_then(|c| c["Config"]["Labels"][key].as_str().map(str::to_string)))
+}
+
+/// Forwards currently served by the workspace's shared sidecar.
+pub fn list_shared_forwards(ws_id: &str) -> Vec<SharedForward> {
+    if let Some(label) = api_shared_sidecar_label(ws_id, "dcw.forwards") {
+        return label
+            .map(|l| decode_shared_forwards(&l))
+            .unwrap_or_default();
+    }
+    Command::new(docker_path())
+        .args([
+            "inspect",
+            "-f",
+            r#"{{index .Config.Labels "dcw.forwards"}}"#,
+            &shared_sidecar_name(ws_id),
+        ])
+        .logged()
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| decode_shared_forwards(String::from_utf8_lossy(&o.stdout).trim()))
+        .unwrap_or_default()
+}
+
+/// Shell script running one socat per forward inside the shared sidecar.
+fn shared_sidecar_script(forwards: &[SharedForward], addresses: &ContainerAddresses) -> String {
+    let mut script = String::new();
+    for fwd in forwards {
+        let [listen, connect] = socat_args(&fwd.spec(), addresses.target(fwd.ipv6));
+        script.push_str(&format!("socat {listen} {connect} & "));
+    }
+    script.push_str("wait");
+    script
+}
+
+/// (Re)create the shared sidecar serving `forwards`, or remove it if empty.
+/// Published ports can't be changed on a running container, so every
+/// change recreates it.
+fn apply_shared_forwards(
+    ws_id: &str,
+    container_id: &str,
+    network: &str,
+    forwards: &[SharedForward],
+) -> Result<()> {
+    let name = shared_sidecar_name(ws_id);
+    let _ = Command::new(docker_path())
+        .args(["rm", "-f", &name])
+        .execute();
+    if forwards.is_empty() {
+        return Ok(());
+    }
+
+    let addresses = get_container_addresses(container_id, network)?;
+    let mut args = vec![
+        "run".to_string(),
+        "--rm".to_string(),
+        "-d".to_string(),
+        "--name".to_string(),
+        name.clone(),
+        "--network".to_string(),
+        network.to_string(),
+        "--label".to_string(),
+        "dcw.role=port-forward".to_string(),
+        "--label".to_string(),
+        format!("dcw.workspace={ws_id}"),
+        "--label".to_string(),
+        format!("dcw.container={container_id}"),
+        "--label".to_string(),
+        format!("dcw.target={}", addresses.ipv4),
+        "--label".to_string(),
+        format!("dcw.forwards={}", encode_shared_forwards(forwards)),
+    ];
+    for fwd in forwards {
+        for publish in publish_args(&fwd.spec()) {
+            args.extend(["-p".to_string(), publish]);
+        }
+    }
+    args.extend([
+        "--entrypoint".to_string(),
+        "sh".to_string(),
+        sidecar_image().to_string(),
+        "-c".to_string(),
+        shared_sidecar_script(forwards, &addresses),
+    ]);
+
+    let output = Command::new(docker_path())
+        .args(&args)
+        .execute()
+        .context("failed to run docker run for shared sidecar")?;
+
+    if !output.status.success() {
+        bail!(
+            "failed to start shared sidecar {name}: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    Ok(())
+}
+
+/// Add a forward to the workspace's shared sidecar, replacing any forward
+/// for the same container port and protocol. Returns the host port used.
+/// The shared sidecar always runs detached.
+pub fn start_shared_forward(
+    ws_id: &str,
+    container_id: &str,
+    network: &str,
+    spec: &ForwardSpec,
+) -> Result<u16> {
+    let mut forwards = list_shared_forwards(ws_id);
+    // Ports the shared sidecar holds now are released when it is recreated
+    let held: Vec<u16> = forwards
+        .iter()
+        .filter(|f| f.protocol == spec.protocol)
+        .map(|f| f.host_port)
+        .collect();
+    forwards.retain(|f| (f.container_port, f.protocol) != (spec.container_port, spec.protocol));
+
+    let bind = spec.bind_address.unwrap_or(DEFAULT_BIND_ADDRESS);
+    let mut host_port = spec.host_port;
+    if spec.fallback {
+        let in_use = |p: u16| {
+            forwards
+                .iter()
+                .any(|f| f.protocol == spec.protocol && f.host_port == p)
+        };
+        host_port = (spec.host_port..=u16::MAX)
+            .take(HOST_PORT_FALLBACK_ATTEMPTS as usize)
+            .find(|p| {
+                !in_use(*p) && (held.contains(p) || host_port_available(bind, *p, spec.protocol))
+            })
+            .with_context(|| format!("no free host port found from {}", spec.host_port))?;
+        if host_port != spec.host_port {
+            info!(
+                "Host port {} is in use, using {host_port} instead.",
+                spec.host_port
+            );
+        }
+    }
+
+    forwards.push(SharedForward {
+        protocol: spec.protocol,
+        bind: bind.to_string(),
+        host_port,
+        container_port: spec.container_port,
+        source: spec.source.map(str::to_string),
+        label: spec.label.map(str::to_string),
+        ipv6: spec.ipv6,
+    });
+    apply_shared_forwards(ws_id, container_id, network, &forwards)?;
+    Ok(host_port)
+}
+
+/// Recreate the shared sidecar with its current forwards, e.g. to point it
+/// at a new container IP.
+pub fn restart_shared_forwards(ws_id: &str, container_id: &str, network: &str) -> Result<()> {
+    let forwards = list_shared_forwards(ws_id);
+    if forwards.is_empty() {
+        return Ok(());
+    }
+    apply_shared_forwards(ws_id, container_id, network, &forwards)
+}
+
+/// Remove shared-sidecar forwards matching `pred`. Returns whether any were removed.
+pub fn remove_shared_forwards(
+    ws_id: &str,
+    pred: impl Fn(&SharedForward) -> bool,
+) -> Result<bool> {
+    let mut forwards = list_shared_forwards(ws_id);
+    let before = forwards.len();
+    forwards.retain(|f| !pred(f));
+    if forwards.len() == before {
+        return Ok(false);
+    }
+    let target = find_shared_target(ws_id)?;
+    match target {
+        Some(container_id) if !forwards.is_empty() => {
+            let network = get_container_network(&container_id)?;
+            apply_shared_forwards(ws_id, &container_id, &network, &forwards)?;
+        }
+        _ => remove_container(&shared_sidecar_name(ws_id))?,
+    }
+    Ok(true)
+}
+
+/// The devcontainer the shared sidecar forwards to, if it is still running.
+fn find_shared_target(ws_id: &str) -> Result<Option<String>> {
+    if let Some(id) = api_shared_sidecar_label(ws_id, "dcw.container") {
+        return match id.filter(|id| !id.is_empty()) {
+            Some(id) if is_container_running(&id)? => Ok(Some(id)),
+            _ => Ok(None),
+        };
+    }
+    let output = Command::new(docker_path())
+        .args([
+            "inspect",
+            "-f",
+            r#"{{index .Config.Labels "dcw.container"}}"#,
+            &shared_sidecar_name(ws_id),
+        ])
+        .logged()
+        .output()
+        .context("failed to inspect shared sidecar")?;
+    let id = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if !output.status.success() || id.is_empty() || !is_container_running(&id)? {
+        return Ok(None);
+    }
+    Ok(Some(id))
+}
+
+/// Remove a specific port-forwarding sidecar.
+pub fn remove_port_forward(ws_id: &str, port: u16, protocol: Protocol) -> Result<()> {
+    let sidecar_name = sidecar_name(ws_id, port, protocol);
+    let output = Command::new(docker_path())
+        .args(["rm", "-f", &sidecar_name])
+        .execute()
+        .context("failed to run docker rm")?;
+
+    if !output.status.success() {
+        bail!(
+            "failed to remove sidecar {sidecar_name}: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    Ok(())
+}
+
+/// Remove the sidecar forwarding `port` of a compose service.
+pub fn remove_service_port_forward(
+    ws_id: &str,
+    service: &str,
+    port: u16,
+    protocol: Protocol,
+) -> Result<()> {
+    remove_container(&service_sidecar_name(ws_id, service, port, protocol))
+}
+
+/// Names of the volumes mounted into a container, named and anonymous.
+pub fn container_volumes(container_id: &str) -> Result<Vec<String>> {
+    let output = Command::new(docker_path())
+        .args([
+            "inspect",
+            "-f",
+            "{{range .Mounts}}{{if eq .Type \"volume\"}}{{.Name}} {{end}}{{end}}",
+            container_id,
+        ])
+        .logged()
+        .output()
+        .context("failed to run docker inspect")?;
+    if !output.status.success() {
+        bail!(
+            "docker inspect failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .map(str::to_string)
+        .collect())
+}
+
+/// Remove a volume. Fails if another container still uses it.
+pub fn remove_volume(name: &str) -> Result<()> {
+    let output = Command::new(docker_path())
+        .args(["volume", "rm", name])
+        .execute()
+        .context("failed to run docker volume rm")?;
+    if !output.status.success() {
+        bail!(
+            "failed to remove volume {name}: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// A docker volume.
+#[derive(Debug, PartialEq)]
+pub struct Volume {
+    pub name: String,
+    pub labels: HashMap<String, String>,
+    /// Mounted by some container, running or stopped
+    pub in_use: bool,
+}
+
+/// All volumes, with whether a container still uses them.
+pub fn list_volumes() -> Result<Vec<Volume>> {
+    let volumes = docker_volume_ls(&["--format", "{{.Name}}\t{{.Labels}}"])?;
+    let dangling: HashSet<String> = docker_volume_ls(&["-q", "--filter", "dangling=true"])?
+        .lines()
+        .map(str::to_string)
+        .collect();
+    Ok(volumes
+        .lines()
+        .filter_map(|line| {
+            let (name, labels) = line.split_once('\t').unwrap_or((line, ""));
+            (!name.is_empty()).then(|| Volume {
+                name: name.to_string(),
+                labels: parse_labels(labels),
+                in_use: !dangling.contains(name),
+            })
+        })
+        .collect())
+}
+
+fn docker_volume_ls(args: &[&str]) -> Result<String> {
+    let output = Command::new(docker_path())
+        .args(["volume", "ls"])
+        .args(args)
+        .logged()
+        .output()
+        .context("failed to run docker volume ls")?;
+    if !output.status.success() {
+        bail!(
+            "docker volume ls failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Parse labels as `docker ... --format '{{.Labels}}'` prints them:
+/// `key=value` pairs separated by commas.
+fn parse_labels(labels: &str) -> HashMap<String, String> {
+    labels
+        .split(',')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (key.to_string(), value.to_string())
+        })
+        .collect()
+}
+
+/// Disk usage of each volume in bytes, from `docker system df -v`. This
+/// walks every volume, so it can take a while.
+pub fn volume_sizes() -> Result<HashMap<String, u64>> {
+    let output = Command::new(docker_path())
+        .args(["system", "df", "-v", "--format", "json"])
+        .logged()
+        .output()
+        .context("failed to run docker system df")?;
+    if !output.status.success() {
+        bail!(
+            "docker system df failed: {}",
             String::from_utf8_lossy(&output.stderr)
         );
     }
+    let usage: Value = serde_json::from_slice(&output.stdout)
+        .context("unexpected output from docker system df")?;
+    Ok(usage["Volumes"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|v| {
+            let name = v["Name"].as_str()?;
+            let size = parse_size(v["Size"].as_str()?)?;
+            Some((name.to_string(), size))
+        })
+        .collect())
+}
 
-    let network = String::from_utf8_lossy(&output.stdout).trim().to_string();
-    if network.is_empty() {
-        bail!("container {container_id} has no networks");
+/// Parse a size as docker prints it, e.g. `1.23GB` or `512kB` (powers of
+/// 1000).
+fn parse_size(size: &str) -> Option<u64> {
+    let size = size.trim();
+    let number = size.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let factor: f64 = match &size[number.len()..] {
+        "B" => 1.0,
+        "kB" | "KB" => 1e3,
+        "MB" => 1e6,
+        "GB" => 1e9,
+        "TB" => 1e12,
+        "PB" => 1e15,
+        _ => return None,
+    };
+    let number: f64 = number.trim().parse().ok()?;
+    Some((number * factor).round() as u64)
+}
+
+/// An image as listed by `docker image ls`.
+#[derive(Debug, PartialEq)]
+pub struct Image {
+    /// Full ID, `sha256:...`
+    pub id: String,
+    /// `repository:tag`, or `None` for a dangling image
+    pub reference: Option<String>,
+    pub size: Option<u64>,
+}
+
+/// Images matching the `docker image ls` filters, e.g. `dangling=true`.
+pub fn list_images(filters: &[&str]) -> Result<Vec<Image>> {
+    let mut cmd = Command::new(docker_path());
+    cmd.args(["image", "ls", "--no-trunc"]);
+    for filter in filters {
+        cmd.args(["--filter", filter]);
+    }
+    let output = cmd
+        .args(["--format", "{{.ID}}\t{{.Repository}}:{{.Tag}}\t{{.Size}}"])
+        .logged()
+        .output()
+        .context("failed to run docker image ls")?;
+    if !output.status.success() {
+        bail!(
+            "docker image ls failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
     }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(parse_image_line)
+        .collect())
+}
 
-    // If multiple networks, take the first one
-    Ok(network.split('\n').next().unwrap().to_string())
+fn parse_image_line(line: &str) -> Option<Image> {
+    let mut fields = line.split('\t');
+    let id = fields.next().filter(|id| !id.is_empty())?;
+    let reference = fields.next().unwrap_or_default();
+    Some(Image {
+        id: id.to_string(),
+        reference: (!reference.contains("<none>")).then(|| reference.to_string()),
+        size: fields.next().and_then(parse_size),
+    })
 }
 
-/// Get the IP address of a container on a given network.
-/// The default `bridge` network doesn't support container name/ID DNS resolution,
-/// so we need the actual IP for socat to connect to.
-pub fn get_container_ip(container_id: &str, network: &str) -> Result<String> {
-    let template = network_ip_template(network);
+/// IDs of the images containers (running or stopped) are created from,
+/// which docker won't remove.
+pub fn images_in_use() -> Result<HashSet<String>> {
     let output = Command::new(docker_path())
-        .args(["inspect", "-f", &template, container_id])
+        .args(["ps", "-a", "--no-trunc", "--format", "{{.ID}}"])
+        .logged()
         .output()
-        .context("failed to run docker inspect for IP")?;
-
+        .context("failed to run docker ps")?;
     if !output.status.success() {
         bail!(
-            "docker inspect failed: {}",
+            "docker ps failed: {}",
             String::from_utf8_lossy(&output.stderr)
         );
     }
-
-    let ip = String::from_utf8_lossy(&output.stdout).trim().to_string();
-    if ip.is_empty() {
-        bail!("container {container_id} has no IP on network {network}");
+    let ids = String::from_utf8_lossy(&output.stdout);
+    let ids: Vec<&str> = ids.lines().filter(|id| !id.is_empty()).collect();
+    if ids.is_empty() {
+        return Ok(HashSet::new());
     }
-
-    Ok(ip)
+    let output = Command::new(docker_path())
+        .args(["inspect", "-f", "{{.Image}}"])
+        .args(&ids)
+        .logged()
+        .output()
+        .context("failed to run docker inspect")?;
+    // Containers removed in the meantime fail the command but still print
+    // the others
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::to_string)
+        .collect())
 }
 
-/// Start a socat port-forwarding sidecar container.
-///
-/// Sidecar naming: `pf-<ws_id>-c<container_port>`
-/// Idempotent: removes existing sidecar first.
-pub fn start_port_forward(
-    ws_id: &str,
+/// Commit a container's filesystem to the image `reference`, adding
+/// `labels`. Returns the new image's ID (empty in a dry run).
+pub fn commit_container(
     container_id: &str,
-    host_port: u16,
-    container_port: u16,
-    network: &str,
-    detach: bool,
-    source: Option<&str>,
-) -> Result<()> {
-    let sidecar_name = format!("pf-{ws_id}-c{container_port}");
-
-    // Remove existing sidecar if present (ignore errors)
-    let _ = Command::new(docker_path())
-        .args(["rm", "-f", &sidecar_name])
-        .output();
-
-    let mut args = vec![
-        "run".to_string(),
-        "--rm".to_string(),
-        "--name".to_string(),
-        sidecar_name.clone(),
-        "--network".to_string(),
-        network.to_string(),
-        "--label".to_string(),
-        "dcw.role=port-forward".to_string(),
-        "--label".to_string(),
-        format!("dcw.workspace={ws_id}"),
-        "--label".to_string(),
-        format!("dcw.port={container_port}"),
-        "--label".to_string(),
-        format!("dcw.host_port={host_port}"),
-    ];
-
-    if let Some(src) = source {
-        args.extend([
-            "--label".to_string(),
-            format!("dcw.source={src}"),
-        ]);
+    reference: &str,
+    labels: &[(&str, &str)],
+) -> Result<String> {
+    let mut cmd = Command::new(docker_path());
+    cmd.arg("commit");
+    for (key, value) in labels {
+        cmd.args(["--change", &format!("LABEL {key}={value:?}")]);
     }
-
-    args.extend([
-        "-p".to_string(),
-        format!("127.0.0.1:{host_port}:{host_port}"),
-    ]);
-
-    if detach {
-        args.push("-d".to_string());
+    let output = cmd
+        .args([container_id, reference])
+        .execute()
+        .context("failed to run docker commit")?;
+    if !output.status.success() {
+        bail!(
+            "failed to commit container {container_id}: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
     }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
 
-    let container_ip = get_container_ip(container_id, network)?;
+/// Remove an image by ID or reference. An image that is already gone
+/// counts as removed.
+pub fn remove_image(image: &str) -> Result<()> {
+    let output = Command::new(docker_path())
+        .args(["image", "rm", image])
+        .execute()
+        .context("failed to run docker image rm")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if is_no_such_image(&stderr) {
+            return Ok(());
+        }
+        bail!("failed to remove image {image}: {}", stderr.trim());
+    }
+    Ok(())
+}
 
-    args.extend([
-        "alpine/socat".to_string(),
-        format!("TCP-LISTEN:{host_port},fork,reuseaddr"),
-        format!("TCP:{container_ip}:{container_port}"),
-    ]);
+fn is_no_such_image(stderr: &str) -> bool {
+    stderr.to_lowercase().contains("no such image")
+}
 
+/// Remove the build cache with `docker builder prune`. Returns the space
+/// it reports reclaimed.
+pub fn prune_build_cache() -> Result<Option<u64>> {
     let output = Command::new(docker_path())
-        .args(&args)
-        .output()
-        .context("failed to run docker run for port forward")?;
-
+        .args(["builder", "prune", "--force"])
+        .execute()
+        .context("failed to run docker builder prune")?;
     if !output.status.success() {
         bail!(
-            "failed to start port forward sidecar {sidecar_name}: {}",
-            String::from_utf8_lossy(&output.stderr)
+            "docker builder prune failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
         );
     }
+    // Ends with e.g. `Total:\t1.2GB` (or `Total reclaimed space: 1.2GB`)
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.strip_prefix("Total"))
+        .filter_map(|rest| rest.rsplit([':', '\t', ' ']).next())
+        .find_map(parse_size))
+}
 
+/// Tear down a Docker Compose project: its containers and networks, and
+/// with `volumes` also its volumes.
+pub fn compose_down(project: &str, volumes: bool) -> Result<()> {
+    let status = Command::new(docker_compose_path())
+        .args(["-p", project, "down", "--remove-orphans"])
+        .args(volumes.then_some("--volumes"))
+        .execute_status()
+        .context("failed to run docker-compose down")?;
+    if !status.success() {
+        bail!("docker-compose down exited with status {status}");
+    }
     Ok(())
 }
 
-/// Remove a specific port-forwarding sidecar.
-pub fn remove_port_forward(ws_id: &str, port: u16) -> Result<()> {
-    let sidecar_name = format!("pf-{ws_id}-c{port}");
+/// Force-remove a container by name or ID.
+pub fn remove_container(name: &str) -> Result<()> {
     let output = Command::new(docker_path())
-        .args(["rm", "-f", &sidecar_name])
-        .output()
+        .args(["rm", "-f", name])
+        .execute()
         .context("failed to run docker rm")?;
 
     if !output.status.success() {
         bail!(
-            "failed to remove sidecar {sidecar_name}: {}",
+            "failed to remove container {name}: {}",
             String::from_utf8_lossy(&output.stderr)
         );
     }
@@ -246,13 +1973,14 @@ pub fn remove_all_port_forwards(ws_id: &str) -> Result<()> {
             "--filter",
             &format!("label=dcw.workspace={ws_id}"),
         ])
+        .logged()
         .output()
         .context("failed to list port-forward sidecars")?;
 
     let stdout = String::from_utf8_lossy(&output.stdout);
     for id in stdout.trim().lines() {
         if !id.is_empty() {
-            let _ = Command::new(docker_path()).args(["rm", "-f", id]).output();
+            let _ = Command::new(docker_path()).args(["rm", "-f", id]).execute();
         }
     }
 
@@ -272,28 +2000,112 @@ pub fn remove_port_forwards_by_source(ws_id: &str, source: &str) -> Result<()> {
             "--filter",
             &format!("label=dcw.source={source}"),
         ])
+        .logged()
         .output()
         .context("failed to list port-forward sidecars by source")?;
 
     let stdout = String::from_utf8_lossy(&output.stdout);
     for id in stdout.trim().lines() {
         if !id.is_empty() {
-            let _ = Command::new(docker_path()).args(["rm", "-f", id]).output();
+            let _ = Command::new(docker_path()).args(["rm", "-f", id]).execute();
         }
     }
 
     Ok(())
 }
 
+/// A port-forwarding sidecar together with the devcontainer it targets.
+pub struct SidecarTarget {
+    pub name: String,
+    pub workspace: String,
+    /// Target devcontainer ID; empty for sidecars created before the label existed.
+    pub container: String,
+}
+
+/// List port-forwarding sidecars across all workspaces with their target container.
+pub fn list_all_sidecar_targets() -> Result<Vec<SidecarTarget>> {
+    if let Some(sidecars) = api_containers(false, &["dcw.role=port-forward".to_string()]) {
+        return Ok(sidecars
+            .iter()
+            .map(|c| SidecarTarget {
+                name: c.name().to_string(),
+                workspace: c.label("dcw.workspace").to_string(),
+                container: c.label("dcw.container").to_string(),
+            })
+            .collect());
+    }
+    let output = Command::new(docker_path())
+        .args([
+            "ps",
+            "--filter",
+            "label=dcw.role=port-forward",
+            "--format",
+            "{{.Names}}\t{{.Label \"dcw.workspace\"}}\t{{.Label \"dcw.container\"}}",
+        ])
+        .logged()
+        .output()
+        .context("failed to list port-forward sidecars")?;
+
+    if !output.status.success() {
+        bail!(
+            "docker ps failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout
+        .trim()
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let parts: Vec<&str> = line.split('\t').collect();
+            SidecarTarget {
+                name: parts.first().unwrap_or(&"").to_string(),
+                workspace: parts.get(1).unwrap_or(&"").to_string(),
+                container: parts.get(2).unwrap_or(&"").to_string(),
+            }
+        })
+        .collect())
+}
+
 /// Info about an active port forward.
 pub struct PortForwardInfo {
     pub name: String,
     pub host_port: String,
     pub container_port: String,
+    pub source: String,
+    pub protocol: Protocol,
+    /// Unix socket path for socket forwards; empty for port forwards.
+    pub unix_socket: String,
+    /// Host bind address; empty for sidecars created before the label existed.
+    pub bind: String,
+    /// Served by the workspace's shared sidecar
+    pub shared: bool,
+    /// Label from `portsAttributes`; empty if none.
+    pub label: String,
+    /// Container IP the sidecar connects to; empty for sidecars created
+    /// before the label existed.
+    pub target: String,
+    /// Compose service forwarded from; empty for the devcontainer.
+    pub service: String,
 }
 
 /// List active port-forwarding sidecars for a workspace.
 pub fn list_port_forwards(ws_id: &str) -> Result<Vec<PortForwardInfo>> {
+    let filters = [
+        "dcw.role=port-forward".to_string(),
+        format!("dcw.workspace={ws_id}"),
+    ];
+    if let Some(sidecars) = api_containers(false, &filters) {
+        let rows = sidecars.iter().map(|c| {
+            std::iter::once(c.name())
+                .chain(SIDECAR_LABELS.iter().map(|key| c.label(key)))
+                .collect()
+        });
+        return Ok(port_forward_infos(rows));
+    }
+
     let output = Command::new(docker_path())
         .args([
             "ps",
@@ -302,8 +2114,9 @@ pub fn list_port_forwards(ws_id: &str) -> Result<Vec<PortForwardInfo>> {
             "--filter",
             &format!("label=dcw.workspace={ws_id}"),
             "--format",
-            "{{.Names}}\t{{.Label \"dcw.host_port\"}}\t{{.Label \"dcw.port\"}}",
+            "{{.Names}}\t{{.Label \"dcw.host_port\"}}\t{{.Label \"dcw.port\"}}\t{{.Label \"dcw.source\"}}\t{{.Label \"dcw.protocol\"}}\t{{.Label \"dcw.unix_socket\"}}\t{{.Label \"dcw.bind\"}}\t{{.Label \"dcw.forwards\"}}\t{{.Label \"dcw.label\"}}\t{{.Label \"dcw.target\"}}\t{{.Label \"dcw.service\"}}",
         ])
+        .logged()
         .output()
         .context("failed to list port-forward sidecars")?;
 
@@ -315,27 +2128,123 @@ pub fn list_port_forwards(ws_id: &str) -> Result<Vec<PortForwardInfo>> {
     }
 
     let stdout = String::from_utf8_lossy(&output.stdout);
-    let forwards = stdout
+    let rows = stdout
         .trim()
         .lines()
         .filter(|line| !line.is_empty())
-        .map(|line| {
-            let parts: Vec<&str> = line.split('\t').collect();
-            PortForwardInfo {
-                name: parts.first().unwrap_or(&"").to_string(),
-                host_port: parts.get(1).unwrap_or(&"").to_string(),
-                container_port: parts.get(2).unwrap_or(&"").to_string(),
-            }
-        })
-        .collect();
+        .map(|line| line.split('\t').collect());
+    Ok(port_forward_infos(rows))
+}
 
-    Ok(forwards)
+/// Sidecar labels read by [`list_port_forwards`], in the order of the
+/// fields following the container name.
+const SIDECAR_LABELS: [&str; 10] = [
+    "dcw.host_port",
+    "dcw.port",
+    "dcw.source",
+    "dcw.protocol",
+    "dcw.unix_socket",
+    "dcw.bind",
+    "dcw.forwards",
+    "dcw.label",
+    "dcw.target",
+    "dcw.service",
+];
+
+/// Build forward infos from rows of a sidecar name followed by its
+/// [`SIDECAR_LABELS`]. The shared sidecar expands to one info per forward.
+fn port_forward_infos<'a>(rows: impl Iterator<Item = Vec<&'a str>>) -> Vec<PortForwardInfo> {
+    let mut forwards = Vec::new();
+    for parts in rows {
+        let name = parts.first().unwrap_or(&"").to_string();
+        let shared = parts.get(7).unwrap_or(&"");
+        let target = parts.get(9).unwrap_or(&"").to_string();
+        if !shared.is_empty() {
+            forwards.extend(decode_shared_forwards(shared).into_iter().map(|f| {
+                PortForwardInfo {
+                    name: name.clone(),
+                    host_port: f.host_port.to_string(),
+                    container_port: f.container_port.to_string(),
+                    source: f.source.unwrap_or_default(),
+                    protocol: f.protocol,
+                    unix_socket: String::new(),
+                    bind: f.bind,
+                    shared: true,
+                    label: f.label.unwrap_or_default(),
+                    target: target.clone(),
+                    service: String::new(),
+                }
+            }));
+            continue;
+        }
+        forwards.push(PortForwardInfo {
+            name,
+            host_port: parts.get(1).unwrap_or(&"").to_string(),
+            container_port: parts.get(2).unwrap_or(&"").to_string(),
+            source: parts.get(3).unwrap_or(&"").to_string(),
+            protocol: Protocol::from_label(parts.get(4).unwrap_or(&"")),
+            unix_socket: parts.get(5).unwrap_or(&"").to_string(),
+            bind: parts.get(6).unwrap_or(&"").to_string(),
+            shared: false,
+            label: parts.get(8).unwrap_or(&"").to_string(),
+            target,
+            service: parts.get(10).unwrap_or(&"").to_string(),
+        });
+    }
+    forwards
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn detects_missing_image() {
+        assert!(is_no_such_image(
+            "Error response from daemon: No such image: dcw-snapshot/app:old"
+        ));
+        assert!(!is_no_such_image(
+            "Error response from daemon: conflict: unable to remove repository reference \"dcw-snapshot/app:old\" (must force) - container 1a2b3c is using its referenced image 4d5e6f"
+        ));
+    }
+
+    #[test]
+    fn choose_devcontainer_among_several() {
+        let container = |id: &str, name: &str, config: &str| Devcontainer {
+            id: id.to_string(),
+            name: name.to_string(),
+            config_file: format!("/src/app/.devcontainer/{config}/devcontainer.json"),
+        };
+        let candidates = [
+            container("aaa111", "api-dev", "api"),
+            container("bbb222", "web-dev", "web"),
+        ];
+        let web_config = std::path::Path::new("/src/app/.devcontainer/web/devcontainer.json");
+
+        assert_eq!(
+            choose_devcontainer(&candidates[..1], None, None),
+            Ok(Some(&candidates[0]))
+        );
+        assert_eq!(choose_devcontainer(&[], None, None), Ok(None));
+        assert_eq!(
+            choose_devcontainer(&candidates, Some("api-dev"), Some(web_config)),
+            Ok(Some(&candidates[0]))
+        );
+        assert_eq!(
+            choose_devcontainer(&candidates, Some("bbb"), None),
+            Ok(Some(&candidates[1]))
+        );
+        assert_eq!(choose_devcontainer(&candidates, Some("db"), None), Ok(None));
+        assert_eq!(
+            choose_devcontainer(&candidates, None, Some(web_config)),
+            Ok(Some(&candidates[1]))
+        );
+        assert_eq!(
+            choose_devcontainer(&candidates, None, None),
+            Err(vec![&candidates[0], &candidates[1]])
+        );
+    }
+
     #[test]
     fn network_list_template_contains_newline_separator() {
         let tmpl = network_list_template();
@@ -352,6 +2261,303 @@ mod tests {
         assert!(tmpl.contains(".IPAddress"));
     }
 
+    #[test]
+    fn parse_labels_and_sizes() {
+        assert_eq!(
+            parse_labels("com.docker.compose.project=app_devcontainer,com.docker.compose.volume=db"),
+            HashMap::from([
+                ("com.docker.compose.project".to_string(), "app_devcontainer".to_string()),
+                ("com.docker.compose.volume".to_string(), "db".to_string()),
+            ])
+        );
+        assert!(parse_labels("").is_empty());
+        assert_eq!(parse_size("0B"), Some(0));
+        assert_eq!(parse_size("512kB"), Some(512_000));
+        assert_eq!(parse_size("1.5GB"), Some(1_500_000_000));
+        assert_eq!(parse_size("N/A"), None);
+    }
+
+    #[test]
+    fn parse_image_lines() {
+        assert_eq!(
+            parse_image_line("sha256:abc\talpine/socat:1.7\t12.5MB"),
+            Some(Image {
+                id: "sha256:abc".to_string(),
+                reference: Some("alpine/socat:1.7".to_string()),
+                size: Some(12_500_000),
+            })
+        );
+        assert_eq!(
+            parse_image_line("sha256:def\t<none>:<none>\t1.2GB").map(|i| i.reference),
+            Some(None)
+        );
+    }
+
+    #[test]
+    fn sidecar_name_distinguishes_protocols() {
+        assert_eq!(sidecar_name("dev-x", 53, Protocol::Tcp), "pf-dev-x-c53");
+        assert_eq!(sidecar_name("dev-x", 53, Protocol::Udp), "pf-dev-x-c53-udp");
+    }
+
+    #[test]
+    fn parse_exec_metadata_reads_user_and_mount() {
+        let container = serde_json::json!({
+            "Config": {"Labels": {"devcontainer.metadata":
+                r#"[{"containerUser":"node"},{"remoteUser":"vscode"},{"remoteUser":"dev"}]"#}},
+            "Mounts": [
+                {"Source": "/var/lib/docker/volumes/x", "Destination": "/cache"},
+                {"Source": "/home/me/app", "Destination": "/workspaces/app"}
+            ]
+        });
+        assert_eq!(
+            parse_exec_metadata(&container, "/home/me/app"),
+            ExecMetadata {
+                remote_user: Some("dev".to_string()),
+                workspace_mount: Some("/workspaces/app".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_exec_metadata_falls_back_to_container_user() {
+        let container = serde_json::json!({
+            "Config": {"Labels": {"devcontainer.metadata": r#"[{"containerUser":"node"}]"#}}
+        });
+        assert_eq!(
+            parse_exec_metadata(&container, "/home/me/app"),
+            ExecMetadata {
+                remote_user: Some("node".to_string()),
+                workspace_mount: None,
+            }
+        );
+    }
+
+    #[test]
+    fn service_sidecar_name_includes_service() {
+        assert_eq!(
+            service_sidecar_name("dev-x", "db", 5432, Protocol::Tcp),
+            "pf-dev-x-db-c5432"
+        );
+        assert_eq!(
+            service_sidecar_name("dev-x", "dns", 53, Protocol::Udp),
+            "pf-dev-x-dns-c53-udp"
+        );
+    }
+
+    #[test]
+    fn unix_bridge_port_in_dynamic_range() {
+        for port in [1, 5432, 49152, 65535] {
+            assert!(unix_bridge_port(port) >= 49152);
+        }
+        assert_eq!(unix_bridge_port(5432), 49152 + 5432);
+    }
+
+    #[test]
+    fn port_forward_infos_expand_shared_sidecar() {
+        let rows = vec![
+            vec![
+                "pf-dev-x-c3000", "3000", "3000", "watch", "tcp", "", "127.0.0.1", "", "Web",
+                "172.17.0.2",
+            ],
+            vec![
+                "pf-dev-x-shared", "", "", "", "", "", "", "tcp/127.0.0.1/8080/80,udp/0.0.0.0/53/53",
+                "", "172.17.0.2",
+            ],
+        ];
+        let infos = port_forward_infos(rows.into_iter());
+        assert_eq!(infos.len(), 3);
+        assert_eq!(infos[0].source, "watch");
+        assert_eq!(infos[0].label, "Web");
+        assert!(!infos[0].shared);
+        assert!(infos[1].shared && infos[2].shared);
+        assert_eq!(infos[2].protocol, Protocol::Udp);
+        assert_eq!(infos[2].target, "172.17.0.2");
+    }
+
+    fn endpoint(host: &str) -> DockerEndpoint {
+        DockerEndpoint {
+            context: "test".to_string(),
+            host: host.to_string(),
+        }
+    }
+
+    #[test]
+    fn docker_endpoint_locality() {
+        assert!(!endpoint("unix:///var/run/docker.sock").is_remote());
+        assert!(!endpoint("npipe:////./pipe/docker_engine").is_remote());
+        assert!(!endpoint("tcp://127.0.0.1:2375").is_remote());
+        assert!(!endpoint("tcp://localhost:2376").is_remote());
+        assert!(!endpoint("tcp://[::1]:2375").is_remote());
+        assert!(endpoint("tcp://10.0.0.5:2376").is_remote());
+        assert!(endpoint("ssh://me@remote-box").is_remote());
+    }
+
+    #[test]
+    fn docker_endpoint_tunnel_hint() {
+        assert_eq!(
+            endpoint("ssh://me@remote-box").tunnel_hint(3000),
+            "tunnel it with: ssh -N -L 3000:127.0.0.1:3000 ssh://me@remote-box"
+        );
+        assert!(endpoint("tcp://10.0.0.5:2376")
+            .tunnel_hint(3000)
+            .contains("--bind 0.0.0.0"));
+    }
+
+    #[test]
+    fn host_port_reachable_probes_listener() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        assert!(host_port_reachable("127.0.0.1", port));
+        assert!(host_port_reachable("0.0.0.0", port));
+        drop(listener);
+        assert!(!host_port_reachable("127.0.0.1", port));
+        assert!(!host_port_reachable("not-an-ip", port));
+    }
+
+    #[test]
+    fn publish_arg_defaults_to_loopback() {
+        assert_eq!(
+            publish_args(&ForwardSpec::new(8080, 3000)),
+            ["127.0.0.1:8080:8080"]
+        );
+    }
+
+    #[test]
+    fn publish_arg_custom_bind_and_udp() {
+        let spec = ForwardSpec {
+            bind_address: Some("0.0.0.0"),
+            protocol: Protocol::Udp,
+            ..ForwardSpec::new(5353, 53)
+        };
+        assert_eq!(publish_args(&spec), ["0.0.0.0:5353:5353/udp"]);
+    }
+
+    #[test]
+    fn publish_arg_brackets_ipv6() {
+        let spec = ForwardSpec {
+            bind_address: Some("::1"),
+            ..ForwardSpec::new(8080, 8080)
+        };
+        assert_eq!(publish_args(&spec), ["[::1]:8080:8080"]);
+    }
+
+    #[test]
+    fn shared_forwards_round_trip() {
+        let forwards = vec![
+            SharedForward {
+                protocol: Protocol::Tcp,
+                bind: "127.0.0.1".to_string(),
+                host_port: 3000,
+                container_port: 3000,
+                source: None,
+                label: None,
+                ipv6: false,
+            },
+            SharedForward {
+                protocol: Protocol::Udp,
+                bind: "::".to_string(),
+                host_port: 5353,
+                container_port: 53,
+                source: Some("watch".to_string()),
+                label: None,
+                ipv6: true,
+            },
+            SharedForward {
+                protocol: Protocol::Tcp,
+                bind: "127.0.0.1".to_string(),
+                host_port: 8080,
+                container_port: 8080,
+                source: None,
+                label: Some("API, v2/beta 100%".to_string()),
+                ipv6: false,
+            },
+        ];
+        let label = encode_shared_forwards(&forwards);
+        assert_eq!(
+            label,
+            "tcp/127.0.0.1/3000/3000,udp6/::/5353/53/watch,tcp/127.0.0.1/8080/8080//API%2C v2%2Fbeta 100%25"
+        );
+        assert_eq!(decode_shared_forwards(&label), forwards);
+        assert!(decode_shared_forwards("").is_empty());
+    }
+
+    #[test]
+    fn shared_sidecar_script_runs_one_socat_per_forward() {
+        let forwards = decode_shared_forwards(
+            "tcp/127.0.0.1/3000/3000,udp/127.0.0.1/53/53,tcp6/127.0.0.1/8080/8080",
+        );
+        let addresses = ContainerAddresses {
+            ipv4: "172.17.0.2".to_string(),
+            ipv6: Some("fd00::2".to_string()),
+        };
+        assert_eq!(
+            shared_sidecar_script(&forwards, &addresses),
+            "socat TCP-LISTEN:3000,fork,reuseaddr TCP:172.17.0.2:3000 & \
+             socat UDP-LISTEN:53,fork,reuseaddr UDP:172.17.0.2:53 & \
+             socat TCP-LISTEN:8080,fork,reuseaddr TCP6:[fd00::2]:8080 & wait"
+        );
+    }
+
+    #[test]
+    fn find_free_host_port_skips_taken_port() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let taken = listener.local_addr().unwrap().port();
+        assert!(!host_port_available("127.0.0.1", taken, Protocol::Tcp));
+        let port = find_free_host_port("127.0.0.1", taken, Protocol::Tcp).unwrap();
+        assert!(port > taken);
+    }
+
+    #[test]
+    fn protocol_from_label_defaults_to_tcp() {
+        assert_eq!(Protocol::from_label(""), Protocol::Tcp);
+        assert_eq!(Protocol::from_label("tcp"), Protocol::Tcp);
+        assert_eq!(Protocol::from_label("udp"), Protocol::Udp);
+    }
+
+    #[test]
+    fn socat_args_tcp() {
+        let spec = ForwardSpec::new(8080, 3000);
+        assert_eq!(
+            socat_args(&spec, "172.17.0.2"),
+            ["TCP-LISTEN:8080,fork,reuseaddr", "TCP:172.17.0.2:3000"]
+        );
+    }
+
+    #[test]
+    fn socat_args_ipv6_target() {
+        let spec = ForwardSpec::new(3000, 3000);
+        assert_eq!(
+            socat_args(&spec, "fd00::2"),
+            ["TCP-LISTEN:3000,fork,reuseaddr", "TCP6:[fd00::2]:3000"]
+        );
+    }
+
+    #[test]
+    fn container_addresses_fall_back_to_ipv4() {
+        let mut addresses = ContainerAddresses {
+            ipv4: "172.17.0.2".to_string(),
+            ipv6: None,
+        };
+        assert_eq!(addresses.target(true), "172.17.0.2");
+        addresses.ipv6 = Some("fd00::2".to_string());
+        assert_eq!(addresses.target(true), "fd00::2");
+        assert_eq!(addresses.target(false), "172.17.0.2");
+        assert!(addresses.contains("fd00::2"));
+        assert!(!addresses.contains("172.17.0.3"));
+    }
+
+    #[test]
+    fn socat_args_udp() {
+        let spec = ForwardSpec {
+            protocol: Protocol::Udp,
+            ..ForwardSpec::new(5353, 53)
+        };
+        assert_eq!(
+            socat_args(&spec, "172.17.0.2"),
+            ["UDP-LISTEN:5353,fork,reuseaddr", "UDP:172.17.0.2:53"]
+        );
+    }
+
     #[test]
     fn network_ip_template_handles_special_chars_in_name() {
         let tmpl = network_ip_template("my-project_default");
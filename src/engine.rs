@@ -0,0 +1,234 @@
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::docker;
+use crate::settings::Settings;
+
+/// How long to wait for the daemon before giving up on a request.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Minimal synchronous client for the Docker Engine API over its unix
+/// socket. Used for the inspect/list calls dcw makes on every watch pass,
+/// which would otherwise spawn a `docker` process each. Everything else (and
+/// everything when the socket is unavailable) goes through the CLI.
+pub struct Engine {
+    socket: PathBuf,
+}
+
+/// A container as returned by `GET /containers/json`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ContainerSummary {
+    pub id: String,
+    #[serde(default)]
+    pub names: Vec<String>,
+    #[serde(default)]
+    pub state: String,
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+}
+
+impl ContainerSummary {
+    /// Container name without the leading `/`.
+    pub fn name(&self) -> &str {
+        self.names
+            .first()
+            .map(|n| n.trim_start_matches('/'))
+            .unwrap_or("")
+    }
+
+    /// Value of a label, or `""` if missing (like `{{.Label "..."}}`).
+    pub fn label(&self, key: &str) -> &str {
+        self.labels.get(key).map(String::as_str).unwrap_or("")
+    }
+}
+
+static ENGINE: OnceLock<Option<Engine>> = OnceLock::new();
+
+/// The Engine API client, if the API is enabled and the daemon answers on a
+/// local unix socket.
+pub fn get() -> Option<&'static Engine> {
+    ENGINE.get_or_init(connect).as_ref()
+}
+
+fn connect() -> Option<Engine> {
+    let settings = &Settings::get().docker;
+    // A custom executable (e.g. podman) may not talk to the socket the
+    // docker context points at
+    let is_docker = Path::new(&settings.path)
+        .file_name()
+        .is_some_and(|name| name == "docker");
+    if !settings.api || !is_docker {
+        return None;
+    }
+    let socket = docker::docker_endpoint().host.strip_prefix("unix://")?;
+    let engine = Engine {
+        socket: PathBuf::from(socket),
+    };
+    engine
+        .request("/_ping")
+        .ok()
+        .filter(|(status, _)| *status == 200)?;
+    Some(engine)
+}
+
+impl Engine {
+    /// `GET /containers/{id}/json`, or `None` if there is no such container.
+    pub fn inspect_container(&self, id: &str) -> Result<Option<Value>> {
+        self.get_json(&format!("/containers/{}/json", encode_query(id)))
+    }
+
+    /// `GET /containers/json` filtered by labels (`key` or `key=value`).
+    pub fn list_containers(&self, all: bool, labels: &[String]) -> Result<Vec<ContainerSummary>> {
+        let filters = serde_json::json!({ "label": labels }).to_string();
+        let path = format!(
+            "/containers/json?all={all}&filters={}",
+            encode_query(&filters)
+        );
+        let value = self.get_json(&path)?.unwrap_or(Value::Array(Vec::new()));
+        serde_json::from_value(value).context("unexpected container list from Docker API")
+    }
+
+    /// GET a JSON resource. Returns `None` on 404.
+    fn get_json(&self, path: &str) -> Result<Option<Value>> {
+        let (status, body) = self.request(path)?;
+        if status == 404 {
+            return Ok(None);
+        }
+        let value: Value = serde_json::from_slice(&body)
+            .with_context(|| format!("invalid JSON from Docker API for {path}"))?;
+        if !(200..300).contains(&status) {
+            let message = value["message"].as_str().unwrap_or("unknown error");
+            bail!("Docker API returned {status} for {path}: {message}");
+        }
+        Ok(Some(value))
+    }
+
+    /// Send a GET request and return the status code and body. HTTP/1.0 makes
+    /// the daemon close the connection after the body, so no chunked
+    /// decoding is needed.
+    fn request(&self, path: &str) -> Result<(u16, Vec<u8>)> {
+        let mut stream = UnixStream::connect(&self.socket)
+            .with_context(|| format!("failed to connect to {}", self.socket.display()))?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        write!(stream, "GET {path} HTTP/1.0\r\nHost: docker\r\n\r\n")?;
+        let mut response = Vec::new();
+        stream
+            .read_to_end(&mut response)
+            .context("failed to read Docker API response")?;
+        let (status, body) = parse_response(&response)?;
+        Ok((status, body.to_vec()))
+    }
+}
+
+/// Split an HTTP response into its status code and body.
+fn parse_response(response: &[u8]) -> Result<(u16, &[u8])> {
+    let header_end = response
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .context("malformed HTTP response from Docker API")?;
+    let head = String::from_utf8_lossy(&response[..header_end]);
+    let status = head
+        .lines()
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse().ok())
+        .context("malformed HTTP status line from Docker API")?;
+    Ok((status, &response[header_end + 4..]))
+}
+
+/// Percent-encode everything but unreserved characters.
+fn encode_query(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || b"-_.~".contains(&b) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{b:02X}"));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_response_splits_status_and_body() {
+        let raw = b"HTTP/1.0 200 OK\r\nContent-Type: application/json\r\n\r\n{\"a\":1}";
+        let (status, body) = parse_response(raw).unwrap();
+        assert_eq!(status, 200);
+        assert_eq!(body, b"{\"a\":1}");
+    }
+
+    #[test]
+    fn parse_response_rejects_garbage() {
+        assert!(parse_response(b"not http").is_err());
+    }
+
+    #[test]
+    fn encode_query_escapes_reserved() {
+        assert_eq!(
+            encode_query(r#"{"label":["a=b"]}"#),
+            "%7B%22label%22%3A%5B%22a%3Db%22%5D%7D"
+        );
+        assert_eq!(encode_query("abc-1_2.3~"), "abc-1_2.3~");
+    }
+
+    #[test]
+    fn get_json_over_unix_socket() {
+        use std::io::BufRead;
+        use std::os::unix::net::UnixListener;
+
+        let socket =
+            std::env::temp_dir().join(format!("dcw-engine-test-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&socket);
+        let listener = UnixListener::bind(&socket).unwrap();
+        let server = std::thread::spawn(move || {
+            for reply in [
+                "HTTP/1.0 200 OK\r\n\r\n{\"State\":{\"Running\":true}}",
+                "HTTP/1.0 404 Not Found\r\n\r\n{\"message\":\"No such container\"}",
+            ] {
+                let (mut conn, _) = listener.accept().unwrap();
+                let mut request_line = String::new();
+                std::io::BufReader::new(&conn)
+                    .read_line(&mut request_line)
+                    .unwrap();
+                assert!(request_line.starts_with("GET /containers/"));
+                conn.write_all(reply.as_bytes()).unwrap();
+            }
+        });
+
+        let engine = Engine {
+            socket: socket.clone(),
+        };
+        let found = engine.inspect_container("abc").unwrap().unwrap();
+        assert_eq!(found["State"]["Running"], true);
+        assert!(engine.inspect_container("missing").unwrap().is_none());
+        server.join().unwrap();
+        let _ = std::fs::remove_file(&socket);
+    }
+
+    #[test]
+    fn container_summary_name_and_labels() {
+        let c: ContainerSummary = serde_json::from_value(serde_json::json!({
+            "Id": "abc",
+            "Names": ["/pf-dev-x-c3000"],
+            "State": "running",
+            "Labels": {"dcw.port": "3000"}
+        }))
+        .unwrap();
+        assert_eq!(c.name(), "pf-dev-x-c3000");
+        assert_eq!(c.label("dcw.port"), "3000");
+        assert_eq!(c.label("dcw.source"), "");
+    }
+}
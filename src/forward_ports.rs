@@ -1,53 +1,375 @@
 use anyhow::Result;
+use serde::Deserialize;
 use serde_json::Value;
 use std::path::Path;
+use tracing::warn;
 
 use crate::config;
 
-/// Parse `forwardPorts` from a JSON value, supporting multiple formats:
+/// A `forwardPorts` entry on another Docker Compose service, such as
+/// `"db:5432"`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServicePort {
+    pub service: String,
+    pub port: u16,
+}
+
+/// A container port forwarded to a host port, which may differ as in
+/// `"8080:3000"`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PortMapping {
+    pub host_port: u16,
+    pub container_port: u16,
+}
+
+impl PortMapping {
+    fn same(port: u16) -> Self {
+        Self {
+            host_port: port,
+            container_port: port,
+        }
+    }
+}
+
+/// Split a `forwardPorts` string into the compose service it names (`None`
+/// for the devcontainer itself) and the port. `"8080:3000"` forwards
+/// container port 3000 to host port 8080; `"localhost:3000"` and `"3000"`
+/// keep the port.
+fn parse_port_string(s: &str) -> Option<(Option<&str>, PortMapping)> {
+    let (host, port) = match s.rsplit_once(':') {
+        Some((host, port)) => (host, port),
+        None => ("", s),
+    };
+    let port = port.parse().ok()?;
+    if let Ok(host_port) = host.parse() {
+        return Some((
+            None,
+            PortMapping {
+                host_port,
+                container_port: port,
+            },
+        ));
+    }
+    let service = match host {
+        "" | "localhost" | "127.0.0.1" => None,
+        service => Some(service),
+    };
+    Some((service, PortMapping::same(port)))
+}
+
+/// Parse a `docker run -p` style `appPort` string: `"3000"`,
+/// `"8000:3000"` or `"127.0.0.1:8000:3000"`, optionally ending in `/tcp`.
+fn parse_app_port_string(s: &str) -> Option<PortMapping> {
+    let s = s.strip_suffix("/tcp").unwrap_or(s);
+    let mut parts = s.rsplit(':');
+    let container_port = parts.next()?.parse().ok()?;
+    // An empty host port (`"127.0.0.1::3000"`) lets Docker choose one
+    let host_port = match parts.next() {
+        Some("") | None => container_port,
+        Some(host) => host.parse().ok()?,
+    };
+    Some(PortMapping {
+        host_port,
+        container_port,
+    })
+}
+
+/// Parse `appPort`: a number, a string, or an array of either. Malformed
+/// entries and UDP ports are skipped.
+fn parse_app_ports(value: &Value) -> Vec<PortMapping> {
+    let entries = match value.get("appPort") {
+        Some(Value::Array(arr)) => arr.iter().collect(),
+        Some(entry) => vec![entry],
+        None => Vec::new(),
+    };
+
+    entries
+        .into_iter()
+        .filter_map(|entry| match entry {
+            Value::Number(n) => Some(PortMapping::same(u16::try_from(n.as_u64()?).ok()?)),
+            Value::String(s) => parse_app_port_string(s),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Parse the devcontainer's ports from `forwardPorts`, supporting multiple
+/// formats:
 /// - Numbers: `3000`
-/// - Strings: `"3000"`, `"localhost:3000"`
-/// - Objects: `{"port": 3000}`
+/// - Strings: `"3000"`, `"localhost:3000"`, `"8080:3000"` (host:container)
+/// - Objects: `{"port": 3000}`, `{"port": 3000, "hostPort": 8080}`
+///
+/// The ports of `appPort` follow, unless already listed. Entries naming
+/// another compose service are returned by
+/// [`parse_service_ports_from_value`] instead.
+pub fn parse_port_mappings_from_value(value: &Value) -> Vec<PortMapping> {
+    let forward_ports = value
+        .get("forwardPorts")
+        .and_then(|v| v.as_array())
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let port = |v: Option<&Value>| {
+        v.and_then(|v| v.as_u64())
+            .and_then(|p| u16::try_from(p).ok())
+    };
+
+    let mut mappings: Vec<PortMapping> = forward_ports
+        .iter()
+        .filter_map(|entry| match entry {
+            Value::Number(_) => port(Some(entry)).map(PortMapping::same),
+            Value::String(s) => match parse_port_string(s)? {
+                (None, mapping) => Some(mapping),
+                (Some(_), _) => None,
+            },
+            Value::Object(obj) => {
+                let container_port = port(obj.get("port"))?;
+                let host_port = match obj.get("hostPort") {
+                    Some(v) => port(Some(v))?,
+                    None => container_port,
+                };
+                Some(PortMapping {
+                    host_port,
+                    container_port,
+                })
+            }
+            _ => None,
+        })
+        .collect();
+    for app_port in parse_app_ports(value) {
+        if !mappings
+            .iter()
+            .any(|m| m.container_port == app_port.container_port)
+        {
+            mappings.push(app_port);
+        }
+    }
+    mappings
+}
+
+/// The container ports of [`parse_port_mappings_from_value`].
 pub fn parse_forward_ports_from_value(value: &Value) -> Vec<u16> {
+    parse_port_mappings_from_value(value)
+        .into_iter()
+        .map(|m| m.container_port)
+        .collect()
+}
+
+/// Parse the `"service:port"` entries of `forwardPorts`.
+pub fn parse_service_ports_from_value(value: &Value) -> Vec<ServicePort> {
     let Some(arr) = value.get("forwardPorts").and_then(|v| v.as_array()) else {
         return Vec::new();
     };
 
     arr.iter()
-        .filter_map(|entry| match entry {
-            Value::Number(n) => n.as_u64().and_then(|p| u16::try_from(p).ok()),
-            Value::String(s) => {
-                // Handle "localhost:3000" or just "3000"
-                let port_str = s.rsplit(':').next().unwrap_or(s);
-                port_str.parse::<u16>().ok()
-            }
-            Value::Object(obj) => obj
-                .get("port")
-                .and_then(|v| v.as_u64())
-                .and_then(|p| u16::try_from(p).ok()),
-            _ => None,
+        .filter_map(|entry| match parse_port_string(entry.as_str()?)? {
+            (Some(service), mapping) => Some(ServicePort {
+                service: service.to_string(),
+                port: mapping.container_port,
+            }),
+            (None, _) => None,
         })
         .collect()
 }
 
+/// What to do when a port is auto-forwarded (`portsAttributes.*.onAutoForward`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OnAutoForward {
+    #[default]
+    Notify,
+    OpenBrowser,
+    OpenBrowserOnce,
+    OpenPreview,
+    Silent,
+    Ignore,
+}
+
+impl OnAutoForward {
+    /// Whether to open the browser once the port is forwarded. Without an
+    /// editor there is no preview, and every run counts as a new session
+    /// for `openBrowserOnce`, except in the watcher.
+    pub fn opens_browser(self) -> bool {
+        matches!(self, Self::OpenBrowser | Self::OpenBrowserOnce)
+    }
+}
+
+/// Attributes for a port from `portsAttributes` in devcontainer.json.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PortAttributes {
+    pub label: Option<String>,
+    pub on_auto_forward: OnAutoForward,
+    /// Application protocol (`http` or `https`)
+    pub protocol: Option<String>,
+    /// Fail rather than fall back to another host port
+    pub require_local_port: bool,
+    /// Parsed for compatibility; dcw never elevates privileges
+    pub elevate_if_needed: bool,
+}
+
+impl PortAttributes {
+    /// URL scheme of the port's application, `http` unless `protocol` says
+    /// otherwise.
+    pub fn scheme(&self) -> &str {
+        match self.protocol.as_deref() {
+            Some("https") => "https",
+            _ => "http",
+        }
+    }
+}
+
+/// Which ports a `portsAttributes` key applies to.
+#[derive(Debug, Clone, PartialEq)]
+enum PortPattern {
+    Single(u16),
+    Range(u16, u16),
+}
+
+impl PortPattern {
+    /// Parse `"3000"` or `"40000-55000"`. Other keys (such as process name
+    /// regexes) are not supported and yield `None`.
+    fn parse(key: &str) -> Option<Self> {
+        match key.split_once('-') {
+            Some((lo, hi)) => Some(Self::Range(lo.trim().parse().ok()?, hi.trim().parse().ok()?)),
+            None => key.trim().parse().ok().map(Self::Single),
+        }
+    }
+
+    fn matches(&self, port: u16) -> bool {
+        match *self {
+            Self::Single(p) => p == port,
+            Self::Range(lo, hi) => (lo..=hi).contains(&port),
+        }
+    }
+}
+
+/// Parsed `portsAttributes`, in declaration order, together with
+/// `otherPortsAttributes` and the ports listed in `forwardPorts`.
+#[derive(Debug, Clone, Default)]
+pub struct PortsAttributes {
+    entries: Vec<(PortPattern, PortAttributes)>,
+    other: Option<PortAttributes>,
+    forward_ports: Vec<u16>,
+}
+
+impl PortsAttributes {
+    /// Attributes for `port`. A single-port key wins over a range; among
+    /// ranges, the first declared match wins.
+    pub fn get(&self, port: u16) -> Option<&PortAttributes> {
+        self.entries
+            .iter()
+            .find(|(pattern, _)| *pattern == PortPattern::Single(port))
+            .or_else(|| self.entries.iter().find(|(pattern, _)| pattern.matches(port)))
+            .map(|(_, attrs)| attrs)
+    }
+
+    /// Attributes for a port found by the watcher. Ports that are neither
+    /// in `forwardPorts` nor matched by `portsAttributes` get
+    /// `otherPortsAttributes`.
+    pub fn detected(&self, port: u16) -> Option<&PortAttributes> {
+        self.get(port).or_else(|| {
+            if self.forward_ports.contains(&port) {
+                None
+            } else {
+                self.other.as_ref()
+            }
+        })
+    }
+
+    /// Whether `port` is marked `onAutoForward: ignore`.
+    pub fn is_ignored(&self, port: u16) -> bool {
+        self.get(port)
+            .is_some_and(|a| a.on_auto_forward == OnAutoForward::Ignore)
+    }
+
+    /// The label for `port`, if any.
+    pub fn label(&self, port: u16) -> Option<&str> {
+        self.get(port).and_then(|a| a.label.as_deref())
+    }
+
+    /// Whether `port` may fall back to another host port.
+    pub fn allows_fallback(&self, port: u16) -> bool {
+        !self.get(port).is_some_and(|a| a.require_local_port)
+    }
+}
+
+/// Parse `portsAttributes` and `otherPortsAttributes` from a JSON value.
+/// Unsupported keys and malformed entries are skipped with a warning.
+pub fn parse_ports_attributes_from_value(value: &Value) -> PortsAttributes {
+    let other = value
+        .get("otherPortsAttributes")
+        .and_then(|v| match PortAttributes::deserialize(v) {
+            Ok(attrs) => Some(attrs),
+            Err(e) => {
+                warn!("ignoring invalid otherPortsAttributes: {e}");
+                None
+            }
+        });
+    let forward_ports = parse_forward_ports_from_value(value);
+
+    let Some(obj) = value.get("portsAttributes").and_then(|v| v.as_object()) else {
+        return PortsAttributes {
+            other,
+            forward_ports,
+            ..Default::default()
+        };
+    };
+
+    let entries = obj
+        .iter()
+        .filter_map(|(key, attrs)| {
+            let Some(pattern) = PortPattern::parse(key) else {
+                warn!("ignoring unsupported portsAttributes key {key:?}");
+                return None;
+            };
+            match PortAttributes::deserialize(attrs) {
+                Ok(attrs) => Some((pattern, attrs)),
+                Err(e) => {
+                    warn!("ignoring invalid portsAttributes.{key}: {e}");
+                    None
+                }
+            }
+        })
+        .collect();
+    PortsAttributes {
+        entries,
+        other,
+        forward_ports,
+    }
+}
+
+/// Load `portsAttributes` from the resolved devcontainer config.
+pub fn load_ports_attributes(workspace_root: &Path) -> Result<PortsAttributes> {
+    Ok(config::load_effective_config(workspace_root)?
+        .map(|value| parse_ports_attributes_from_value(&value))
+        .unwrap_or_default())
+}
+
 /// Load forward ports from the resolved devcontainer config.
 ///
-/// If a local override exists, uses the merged config; otherwise reads
-/// devcontainer.json directly.
+/// If a local override exists, uses the merged config; otherwise reads the
+/// selected devcontainer.json (which may be in a `.devcontainer/` subfolder)
+/// directly.
 pub fn load_forward_ports(workspace_root: &Path) -> Result<Vec<u16>> {
-    let config_path = match config::resolve_config(workspace_root)? {
-        Some(merged) => merged,
-        None => {
-            let main_path = workspace_root.join(".devcontainer/devcontainer.json");
-            if !main_path.exists() {
-                return Ok(Vec::new());
-            }
-            main_path
-        }
-    };
+    Ok(config::load_effective_config(workspace_root)?
+        .map(|value| parse_forward_ports_from_value(&value))
+        .unwrap_or_default())
+}
+
+/// Load the devcontainer's ports with their host ports from the resolved
+/// devcontainer config.
+pub fn load_port_mappings(workspace_root: &Path) -> Result<Vec<PortMapping>> {
+    Ok(config::load_effective_config(workspace_root)?
+        .map(|value| parse_port_mappings_from_value(&value))
+        .unwrap_or_default())
+}
 
-    let value = config::read_jsonc(&config_path)?;
-    Ok(parse_forward_ports_from_value(&value))
+/// Load the `"service:port"` entries of `forwardPorts` from the resolved
+/// devcontainer config.
+pub fn load_service_ports(workspace_root: &Path) -> Result<Vec<ServicePort>> {
+    Ok(config::load_effective_config(workspace_root)?
+        .map(|value| parse_service_ports_from_value(&value))
+        .unwrap_or_default())
 }
 
 #[cfg(test)]
@@ -85,6 +407,81 @@ mod tests {
         assert_eq!(parse_forward_ports_from_value(&val), Vec::<u16>::new());
     }
 
+    #[test]
+    fn parse_host_container_ports() {
+        let val = json!({"forwardPorts": ["8080:3000", {"port": 9229, "hostPort": 9230}, {"port": 5000, "hostPort": 70000}]});
+        let mapping = |host_port, container_port| PortMapping {
+            host_port,
+            container_port,
+        };
+        assert_eq!(
+            parse_port_mappings_from_value(&val),
+            vec![mapping(8080, 3000), mapping(9230, 9229)]
+        );
+        assert_eq!(parse_forward_ports_from_value(&val), vec![3000, 9229]);
+        assert!(parse_service_ports_from_value(&val).is_empty());
+    }
+
+    #[test]
+    fn parse_app_ports() {
+        let val = json!({"appPort": [3000, "8000:3000", "127.0.0.1:9000:9229", "127.0.0.1::4000", "5353/udp", "x"]});
+        let mapping = |host_port, container_port| PortMapping {
+            host_port,
+            container_port,
+        };
+        assert_eq!(
+            super::parse_app_ports(&val),
+            vec![
+                mapping(3000, 3000),
+                mapping(8000, 3000),
+                mapping(9000, 9229),
+                mapping(4000, 4000),
+            ]
+        );
+        assert_eq!(
+            super::parse_app_ports(&json!({"appPort": "8080"})),
+            vec![mapping(8080, 8080)]
+        );
+        assert_eq!(
+            super::parse_app_ports(&json!({"appPort": 8080})),
+            vec![mapping(8080, 8080)]
+        );
+    }
+
+    #[test]
+    fn forward_ports_include_app_ports() {
+        let val = json!({"forwardPorts": [3000], "appPort": ["8000:3000", "8001:9229"]});
+        assert_eq!(
+            parse_port_mappings_from_value(&val),
+            vec![
+                PortMapping::same(3000),
+                PortMapping {
+                    host_port: 8001,
+                    container_port: 9229
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_service_ports() {
+        let val = json!({"forwardPorts": [3000, "db:5432", "localhost:8080", "redis:6379", "db:x"]});
+        assert_eq!(parse_forward_ports_from_value(&val), vec![3000, 8080]);
+        assert_eq!(
+            parse_service_ports_from_value(&val),
+            vec![
+                ServicePort {
+                    service: "db".to_string(),
+                    port: 5432
+                },
+                ServicePort {
+                    service: "redis".to_string(),
+                    port: 6379
+                },
+            ]
+        );
+    }
+
     #[test]
     fn parse_out_of_range_number_port_skipped() {
         let val = json!({"forwardPorts": [3000, 70000, 8080]});
@@ -96,4 +493,63 @@ mod tests {
         let val = json!({"forwardPorts": [{"port": 3000}, {"port": 100000}]});
         assert_eq!(parse_forward_ports_from_value(&val), vec![3000]);
     }
+
+    #[test]
+    fn parse_ports_attributes() {
+        let val = json!({"portsAttributes": {
+            "3000": {"label": "Web", "onAutoForward": "openBrowser", "protocol": "https"},
+            "5432": {"onAutoForward": "ignore", "requireLocalPort": true}
+        }});
+        let attrs = parse_ports_attributes_from_value(&val);
+        let web = attrs.get(3000).unwrap();
+        assert_eq!(web.label.as_deref(), Some("Web"));
+        assert_eq!(web.on_auto_forward, OnAutoForward::OpenBrowser);
+        assert_eq!(web.protocol.as_deref(), Some("https"));
+        assert_eq!(web.scheme(), "https");
+        assert!(web.on_auto_forward.opens_browser());
+        assert!(attrs.is_ignored(5432));
+        assert!(!attrs.allows_fallback(5432));
+        assert!(attrs.allows_fallback(3000));
+        assert!(attrs.get(8080).is_none());
+    }
+
+    #[test]
+    fn single_port_attributes_win_over_range() {
+        let val = json!({"portsAttributes": {
+            "3000-3999": {"label": "Range"},
+            "3001": {"label": "Exact"}
+        }});
+        let attrs = parse_ports_attributes_from_value(&val);
+        assert_eq!(attrs.label(3001), Some("Exact"));
+        assert_eq!(attrs.label(3500), Some("Range"));
+        assert_eq!(attrs.label(4000), None);
+    }
+
+    #[test]
+    fn other_ports_attributes_apply_to_unlisted_ports() {
+        let val = json!({
+            "forwardPorts": [3000],
+            "portsAttributes": {"8080": {"label": "API"}},
+            "otherPortsAttributes": {"onAutoForward": "ignore"}
+        });
+        let attrs = parse_ports_attributes_from_value(&val);
+        assert!(attrs.detected(3000).is_none());
+        assert_eq!(attrs.detected(8080).unwrap().label.as_deref(), Some("API"));
+        assert_eq!(
+            attrs.detected(9000).unwrap().on_auto_forward,
+            OnAutoForward::Ignore
+        );
+        // otherPortsAttributes only concerns detected ports
+        assert!(!attrs.is_ignored(9000));
+    }
+
+    #[test]
+    fn unsupported_ports_attributes_keys_skipped() {
+        let val = json!({"portsAttributes": {
+            "node .*": {"label": "Node"},
+            "8080": {"onAutoForward": "bogus"}
+        }});
+        let attrs = parse_ports_attributes_from_value(&val);
+        assert!(attrs.get(8080).is_none());
+    }
 }
@@ -0,0 +1,230 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::docker::{self, PortForwardInfo, Protocol};
+use crate::proxy::ProxyForward;
+use crate::settings::ForwardBackend;
+use crate::workspace;
+
+/// A port forward to re-establish when the container comes back up. Forwards
+/// made with `dcw port add` are kept in the workspace's `forwards.json` so
+/// they outlive `dcw down`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedForward {
+    pub host_port: u16,
+    /// Container port; 0 for Unix socket forwards.
+    #[serde(default)]
+    pub container_port: u16,
+    #[serde(default)]
+    pub protocol: Protocol,
+    pub backend: ForwardBackend,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unix_socket: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bind: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service: Option<String>,
+    /// Value of the `dcw.source` label the forward was made with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub log_http: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub tls: bool,
+    /// Connects to the container's IPv6 address
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub ipv6: bool,
+    /// Bytes per second in each direction
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<u64>,
+    /// Connections, or UDP clients, served at once
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_connections: Option<u32>,
+    /// Seconds without traffic after which the forward is removed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_timeout: Option<u64>,
+}
+
+impl SavedForward {
+    /// The forward a sidecar serves, or `None` if its labels are unreadable.
+    pub fn from_sidecar(fwd: &PortForwardInfo) -> Option<SavedForward> {
+        Some(SavedForward {
+            host_port: fwd.host_port.parse().ok()?,
+            container_port: fwd.container_port.parse().ok()?,
+            protocol: fwd.protocol,
+            backend: if fwd.shared {
+                ForwardBackend::Shared
+            } else {
+                ForwardBackend::Sidecar
+            },
+            unix_socket: (!fwd.unix_socket.is_empty()).then(|| fwd.unix_socket.clone()),
+            bind: (!fwd.bind.is_empty()).then(|| fwd.bind.clone()),
+            label: (!fwd.label.is_empty()).then(|| fwd.label.clone()),
+            service: (!fwd.service.is_empty()).then(|| fwd.service.clone()),
+            source: (!fwd.source.is_empty()).then(|| fwd.source.clone()),
+            log_http: false,
+            tls: false,
+            ipv6: fwd.target.contains(':'),
+            rate_limit: None,
+            max_connections: None,
+            idle_timeout: None,
+        })
+    }
+
+    /// The forward a native proxy entry serves.
+    pub fn from_native(fwd: &ProxyForward) -> SavedForward {
+        SavedForward {
+            host_port: fwd.host_port,
+            container_port: fwd.container_port,
+            protocol: fwd.protocol,
+            backend: ForwardBackend::Native,
+            unix_socket: None,
+            bind: Some(fwd.bind.clone()),
+            label: fwd.label.clone(),
+            service: None,
+            source: fwd.source.clone(),
+            log_http: fwd.log_http,
+            tls: fwd.tls,
+            ipv6: fwd.ipv6,
+            rate_limit: fwd.rate_limit,
+            max_connections: fwd.max_connections,
+            idle_timeout: fwd.idle_timeout,
+        }
+    }
+
+    /// Whether this forward and `other` occupy the same slot, the way
+    /// `dcw port remove` addresses them: Unix socket forwards by host port,
+    /// others by container port, protocol and service.
+    pub fn same_forward(&self, other: &SavedForward) -> bool {
+        match (&self.unix_socket, &other.unix_socket) {
+            (Some(_), Some(_)) => self.host_port == other.host_port,
+            (None, None) => {
+                self.container_port == other.container_port
+                    && self.protocol == other.protocol
+                    && self.service == other.service
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Forwards recorded for the current workspace.
+pub fn load() -> Result<Vec<SavedForward>> {
+    Ok(load_from(&workspace::forwards_file()?))
+}
+
+/// Record a forward, replacing any earlier one in the same slot.
+pub fn record(forward: SavedForward) -> Result<()> {
+    update(|forwards| {
+        forwards.retain(|f| !f.same_forward(&forward));
+        forwards.push(forward);
+    })
+}
+
+/// Forget the recorded forwards matching `pred`.
+pub fn forget(pred: impl Fn(&SavedForward) -> bool) -> Result<()> {
+    update(|forwards| forwards.retain(|f| !pred(f)))
+}
+
+fn update(edit: impl FnOnce(&mut Vec<SavedForward>)) -> Result<()> {
+    let path = workspace::forwards_file()?;
+    let mut forwards = load_from(&path);
+    let before = forwards.clone();
+    edit(&mut forwards);
+    if forwards == before {
+        return Ok(());
+    }
+    if docker::dry_run() {
+        docker::print_dry_run(&format!("update {}", path.display()));
+        return Ok(());
+    }
+    save_to(&path, &forwards)
+}
+
+fn load_from(path: &Path) -> Vec<SavedForward> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_to(path: &Path, forwards: &[SavedForward]) -> Result<()> {
+    if forwards.is_empty() {
+        let _ = fs::remove_file(path);
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context("failed to create runtime directory")?;
+    }
+    fs::write(path, serde_json::to_string_pretty(forwards)?)
+        .context("failed to write saved port forwards")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn forward(host_port: u16, container_port: u16) -> SavedForward {
+        SavedForward {
+            host_port,
+            container_port,
+            protocol: Protocol::Tcp,
+            backend: ForwardBackend::Sidecar,
+            unix_socket: None,
+            bind: None,
+            label: None,
+            service: None,
+            source: None,
+            log_http: false,
+            tls: false,
+            ipv6: false,
+            rate_limit: None,
+            max_connections: None,
+            idle_timeout: None,
+        }
+    }
+
+    #[test]
+    fn same_forward_matches_remove_semantics() {
+        assert!(forward(9000, 3000).same_forward(&forward(3000, 3000)));
+        assert!(!forward(3000, 3000).same_forward(&forward(3001, 3001)));
+
+        let udp = SavedForward {
+            protocol: Protocol::Udp,
+            ..forward(3000, 3000)
+        };
+        assert!(!udp.same_forward(&forward(3000, 3000)));
+
+        let unix = SavedForward {
+            unix_socket: Some("/var/run/docker.sock".to_string()),
+            ..forward(2375, 0)
+        };
+        assert!(unix.same_forward(&unix.clone()));
+        assert!(!unix.same_forward(&forward(2375, 0)));
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let dir = std::env::temp_dir().join(format!("dcw-test-forwards-{}", std::process::id()));
+        let path = dir.join("forwards.json");
+        let forwards = vec![
+            forward(3000, 3000),
+            SavedForward {
+                label: Some("web".to_string()),
+                service: Some("db".to_string()),
+                ..forward(5432, 5432)
+            },
+        ];
+        save_to(&path, &forwards).unwrap();
+        assert_eq!(load_from(&path), forwards);
+
+        save_to(&path, &[]).unwrap();
+        assert!(!path.exists());
+        assert!(load_from(&path).is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
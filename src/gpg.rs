@@ -0,0 +1,139 @@
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::commands::exec;
+use crate::logging::Logged;
+
+/// Where the host's gpg-agent socket is mounted in the container.
+const CONTAINER_SOCKET: &str = "/tmp/dcw-gpg-agent.sock";
+
+/// Points the remote user's gpg at the mounted socket and keeps it from
+/// starting an agent of its own. `$1` is the mounted socket and `$2` the
+/// armored public keys to import.
+const SETUP_SCRIPT: &str = r#"set -e
+command -v gpg >/dev/null 2>&1 || { echo "gpg is not installed in the container" >&2; exit 1; }
+mkdir -p "$HOME/.gnupg" && chmod 700 "$HOME/.gnupg"
+grep -qx no-autostart "$HOME/.gnupg/gpg.conf" 2>/dev/null || echo no-autostart >> "$HOME/.gnupg/gpg.conf"
+gpgconf --kill gpg-agent 2>/dev/null || true
+socket="$(gpgconf --list-dirs agent-socket)"
+mkdir -p "$(dirname "$socket")"
+ln -sf "$1" "$socket"
+[ -z "$2" ] || printf '%s\n' "$2" | gpg --batch --quiet --import"#;
+
+/// The host's restricted ("extra") gpg-agent socket, which is meant for
+/// forwarding: it can sign and decrypt but not manage keys.
+pub fn host_socket() -> Result<PathBuf> {
+    let output = Command::new("gpgconf")
+        .args(["--list-dirs", "agent-extra-socket"])
+        .logged()
+        .output()
+        .context("failed to run gpgconf — is GnuPG installed on the host?")?;
+    let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if !output.status.success() || path.is_empty() {
+        bail!("gpgconf could not locate the gpg-agent extra socket");
+    }
+    // Starts the agent if needed so the socket exists to be mounted
+    let _ = Command::new("gpg-connect-agent")
+        .arg("/bye")
+        .logged()
+        .output();
+    Ok(PathBuf::from(path))
+}
+
+/// `mounts` entry binding the host socket into the container.
+pub fn mount(host_socket: &Path) -> String {
+    format!(
+        "source={},target={CONTAINER_SOCKET},type=bind",
+        host_socket.display()
+    )
+}
+
+/// Wire the mounted socket up for the remote user and import the host's
+/// public keys for the secret keys the agent holds, so `git commit -S` works.
+pub fn setup_container() -> Result<()> {
+    let keys = export_public_keys()?;
+    let cmd = [
+        "sh".to_string(),
+        "-c".to_string(),
+        SETUP_SCRIPT.to_string(),
+        "sh".to_string(),
+        CONTAINER_SOCKET.to_string(),
+        keys,
+    ];
+    let output = exec::output(None, &cmd)?;
+    if !output.status.success() {
+        bail!(
+            "failed to set up gpg in the container: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// Armored public keys of every secret key on the host.
+fn export_public_keys() -> Result<String> {
+    let output = Command::new("gpg")
+        .args(["--list-secret-keys", "--with-colons"])
+        .logged()
+        .output()
+        .context("failed to run gpg")?;
+    let fingerprints = secret_key_fingerprints(&String::from_utf8_lossy(&output.stdout));
+    if fingerprints.is_empty() {
+        return Ok(String::new());
+    }
+    let output = Command::new("gpg")
+        .args(["--armor", "--export"])
+        .args(&fingerprints)
+        .logged()
+        .output()
+        .context("failed to run gpg --export")?;
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Fingerprints of primary keys in `gpg --list-secret-keys --with-colons`.
+fn secret_key_fingerprints(listing: &str) -> Vec<String> {
+    let mut fingerprints = Vec::new();
+    let mut after_sec = false;
+    for line in listing.lines() {
+        let fields: Vec<&str> = line.split(':').collect();
+        match fields[0] {
+            "sec" => after_sec = true,
+            "fpr" if after_sec => {
+                if let Some(fpr) = fields.get(9).filter(|f| !f.is_empty()) {
+                    fingerprints.push(fpr.to_string());
+                }
+                after_sec = false;
+            }
+            _ => {}
+        }
+    }
+    fingerprints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secret_key_fingerprints_skips_subkeys() {
+        let listing = "\
+sec:u:255:22:AAAA:1700000000:::u:::scESC:::+:::ed25519:::0:
+fpr:::::::::1111AAAA:
+grp:::::::::KEYGRIP1:
+uid:u::::1700000000::HASH::Me <me@example.com>::::::::::0:
+ssb:u:255:18:BBBB:1700000000::::::e:::+:::cv25519::
+fpr:::::::::2222BBBB:
+";
+        assert_eq!(secret_key_fingerprints(listing), vec!["1111AAAA"]);
+        assert!(secret_key_fingerprints("").is_empty());
+    }
+
+    #[test]
+    fn mount_targets_fixed_socket() {
+        assert_eq!(
+            mount(Path::new("/run/user/1000/gnupg/S.gpg-agent.extra")),
+            "source=/run/user/1000/gnupg/S.gpg-agent.extra,target=/tmp/dcw-gpg-agent.sock,type=bind"
+        );
+    }
+}
@@ -0,0 +1,245 @@
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::io::{self, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use tracing::{debug, info, warn};
+
+use crate::customizations::{self, HooksCustomizations};
+use crate::docker::{self, Execute, Protocol};
+use crate::logging::Logged;
+use crate::settings::ForwardBackend;
+use crate::workspace;
+
+/// A point in `dcw up` / `dcw down` where workspace hooks run.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Hook {
+    PreUp,
+    PostUp,
+    PreDown,
+    PostDown,
+}
+
+impl Hook {
+    /// Key of the hook in `customizations.dcw.hooks`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Hook::PreUp => "preUp",
+            Hook::PostUp => "postUp",
+            Hook::PreDown => "preDown",
+            Hook::PostDown => "postDown",
+        }
+    }
+
+    fn event(self) -> Event {
+        match self {
+            Hook::PreUp => Event::PreUp,
+            Hook::PostUp => Event::PostUp,
+            Hook::PreDown => Event::PreDown,
+            Hook::PostDown => Event::PostDown,
+        }
+    }
+
+    fn commands(self, hooks: &HooksCustomizations) -> &[String] {
+        match self {
+            Hook::PreUp => &hooks.pre_up,
+            Hook::PostUp => &hooks.post_up,
+            Hook::PreDown => &hooks.pre_down,
+            Hook::PostDown => &hooks.post_down,
+        }
+    }
+}
+
+/// Something that happened to a workspace, passed as JSON on stdin to the
+/// executable of the same name in `~/.config/dcw/hooks/`, e.g.
+/// `{"event":"post-forward","host_port":3000,...}`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum Event {
+    PreUp,
+    PostUp,
+    PreDown,
+    PostDown,
+    /// The port watcher found a new listening port in the container
+    PortDetected {
+        port: u16,
+        protocol: Protocol,
+    },
+    /// A port forward was started in the background
+    PostForward {
+        host_port: u16,
+        container_port: u16,
+        protocol: Protocol,
+        bind: String,
+        backend: ForwardBackend,
+        /// e.g. `watch` for the port watcher's forwards
+        source: Option<String>,
+        label: Option<String>,
+    },
+    /// A port forward was removed
+    PostUnforward {
+        port: u16,
+        protocol: Protocol,
+    },
+}
+
+impl Event {
+    /// File name of the event's executable in the hooks directory.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::PreUp => "pre-up",
+            Event::PostUp => "post-up",
+            Event::PreDown => "pre-down",
+            Event::PostDown => "post-down",
+            Event::PortDetected { .. } => "port-detected",
+            Event::PostForward { .. } => "post-forward",
+            Event::PostUnforward { .. } => "post-unforward",
+        }
+    }
+}
+
+/// An event with the workspace it happened to.
+#[derive(Serialize)]
+struct Payload<'a> {
+    #[serde(flatten)]
+    event: &'a Event,
+    workspace_folder: &'a str,
+    workspace_id: &'a str,
+}
+
+/// Run the workspace's commands for `hook` on the host, in order, with the
+/// workspace as working directory, then the user's executable for the
+/// event. Stops at the first failing command.
+pub fn run(hook: Hook, workspace_root: &Path) -> Result<()> {
+    let hooks = customizations::load_or_default(workspace_root).hooks;
+    let ws_id = workspace::workspace_id()?;
+    for command in hook.commands(&hooks) {
+        info!("Running {} hook: {command}", hook.name());
+        let status = Command::new("sh")
+            .args(["-c", command])
+            .current_dir(workspace_root)
+            .env("DCW_HOOK", hook.name())
+            .env("DCW_WORKSPACE_FOLDER", workspace_root)
+            .env("DCW_WORKSPACE_ID", &ws_id)
+            .execute_status()
+            .with_context(|| format!("failed to run {} hook", hook.name()))?;
+        if !status.success() {
+            bail!("{} hook `{command}` exited with {status}", hook.name());
+        }
+    }
+
+    let event = hook.event();
+    if let Some((path, mut child)) = spawn_executable(&event)? {
+        let status = child
+            .wait()
+            .with_context(|| format!("failed to run {}", path.display()))?;
+        if !status.success() {
+            bail!("{} exited with {status}", path.display());
+        }
+    }
+    Ok(())
+}
+
+/// Run the user's executable for `event`, if there is one, without waiting
+/// for it. Failures are only logged, so a broken hook can't stop dcw.
+pub fn emit(event: Event) {
+    match spawn_executable(&event) {
+        Ok(Some((path, mut child))) => {
+            // Reap it so the port watcher doesn't collect zombies
+            thread::spawn(move || match child.wait() {
+                Ok(status) if !status.success() => {
+                    warn!("{} exited with {status}", path.display())
+                }
+                Ok(_) => {}
+                Err(e) => warn!("failed to wait for {}: {e}", path.display()),
+            });
+        }
+        Ok(None) => {}
+        Err(e) => warn!("{} hook failed: {e:#}", event.name()),
+    }
+}
+
+/// `~/.config/dcw/hooks`, where users put executables named after events.
+fn hooks_dir() -> Option<PathBuf> {
+    Some(dirs::config_dir()?.join("dcw").join("hooks"))
+}
+
+/// Start the executable for `event` with its payload on stdin. Its output
+/// goes to stderr so that it can't get mixed into `--output json`.
+fn spawn_executable(event: &Event) -> Result<Option<(PathBuf, Child)>> {
+    let Some(path) = hooks_dir().map(|dir| dir.join(event.name())) else {
+        return Ok(None);
+    };
+    let executable = path
+        .metadata()
+        .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0);
+    if !executable {
+        return Ok(None);
+    }
+    if docker::dry_run() {
+        docker::print_dry_run(&format!(
+            "run {} for the {} event",
+            path.display(),
+            event.name()
+        ));
+        return Ok(None);
+    }
+
+    let folder = workspace::workspace_folder()?;
+    let ws_id = workspace::workspace_id_for(&folder)?;
+    let payload = serde_json::to_string(&Payload {
+        event,
+        workspace_folder: &folder,
+        workspace_id: &ws_id,
+    })?;
+    debug!("Running {} hook: {}", event.name(), path.display());
+    let mut child = Command::new(&path)
+        .current_dir(&folder)
+        .env("DCW_HOOK", event.name())
+        .env("DCW_WORKSPACE_FOLDER", &folder)
+        .env("DCW_WORKSPACE_ID", &ws_id)
+        .stdin(Stdio::piped())
+        .stdout(io::stderr())
+        .logged()
+        .spawn()
+        .with_context(|| format!("failed to run {}", path.display()))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // An executable that doesn't read its stdin closes the pipe early
+        let _ = stdin.write_all(payload.as_bytes());
+    }
+    Ok(Some((path, child)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payloads_are_tagged_with_the_event() {
+        let event = Event::PostForward {
+            host_port: 3001,
+            container_port: 3000,
+            protocol: Protocol::Tcp,
+            bind: "127.0.0.1".to_string(),
+            backend: ForwardBackend::Native,
+            source: Some("watch".to_string()),
+            label: None,
+        };
+        let payload = Payload {
+            event: &event,
+            workspace_folder: "/src/app",
+            workspace_id: "abc123",
+        };
+        assert_eq!(
+            serde_json::to_string(&payload).unwrap(),
+            r#"{"event":"post-forward","host_port":3001,"container_port":3000,"protocol":"tcp","bind":"127.0.0.1","backend":"native","source":"watch","label":null,"workspace_folder":"/src/app","workspace_id":"abc123"}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&Event::PreDown).unwrap(),
+            r#"{"event":"pre-down"}"#
+        );
+        assert_eq!(event.name(), "post-forward");
+    }
+}
@@ -0,0 +1,145 @@
+use std::net::IpAddr;
+use std::path::Path;
+
+use crate::customizations::DcwCustomizations;
+use crate::docker::Protocol;
+
+/// Marks the lines dcw manages in the hosts file.
+const BLOCK_START: &str = "# BEGIN dcw";
+const BLOCK_END: &str = "# END dcw";
+
+/// `<name>.localhost` for a workspace, from `customizations.dcw.hostname`
+/// or else the folder name. Browsers, and resolvers such as
+/// systemd-resolved, map every `.localhost` name to the loopback address.
+pub fn workspace_hostname(root: &Path, settings: &DcwCustomizations) -> Option<String> {
+    let name = match &settings.hostname {
+        Some(name) => name.trim_end_matches(".localhost").to_string(),
+        None => root.file_name()?.to_string_lossy().to_string(),
+    };
+    Some(format!("{}.localhost", dns_label(&name)?))
+}
+
+/// The name a forward is reached at: the workspace's hostname, or
+/// `<service>.<workspace hostname>` for a compose service. `None` for UDP
+/// and Unix socket forwards, and for ones not listening on loopback.
+pub fn forward_hostname(
+    workspace: &str,
+    service: Option<&str>,
+    protocol: Protocol,
+    bind: &str,
+    unix_socket: bool,
+) -> Option<String> {
+    if protocol != Protocol::Tcp || unix_socket {
+        return None;
+    }
+    // Listening on every address includes loopback
+    let reachable = match bind.parse::<IpAddr>() {
+        Ok(ip) => ip.is_loopback() || ip.is_unspecified(),
+        Err(_) => bind == "localhost",
+    };
+    if !reachable {
+        return None;
+    }
+    match service {
+        Some(service) => Some(format!("{}.{workspace}", dns_label(service)?)),
+        None => Some(workspace.to_string()),
+    }
+}
+
+/// Lowercase letters, digits and single hyphens, at most 63 characters.
+fn dns_label(name: &str) -> Option<String> {
+    let mut label = String::new();
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            label.push(c.to_ascii_lowercase());
+        } else if !label.is_empty() && !label.ends_with('-') {
+            label.push('-');
+        }
+    }
+    label.truncate(63);
+    let label = label.trim_end_matches('-');
+    (!label.is_empty()).then(|| label.to_string())
+}
+
+/// `contents` of a hosts file with dcw's block replaced by `lines`, or
+/// removed if there are none.
+pub fn replace_hosts_block(contents: &str, lines: &[String]) -> String {
+    let mut kept = Vec::new();
+    let mut in_block = false;
+    for line in contents.lines() {
+        match line.trim() {
+            BLOCK_START => in_block = true,
+            BLOCK_END => in_block = false,
+            _ if !in_block => kept.push(line),
+            _ => {}
+        }
+    }
+    let mut out = kept.join("\n");
+    if !lines.is_empty() {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(BLOCK_START);
+        for line in lines {
+            out.push('\n');
+            out.push_str(line);
+        }
+        out.push('\n');
+        out.push_str(BLOCK_END);
+    }
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hostnames_from_folder_or_customization() {
+        let settings = DcwCustomizations::default();
+        assert_eq!(
+            workspace_hostname(Path::new("/src/My_App.v2"), &settings).as_deref(),
+            Some("my-app-v2.localhost")
+        );
+        let settings = DcwCustomizations {
+            hostname: Some("shop.localhost".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            workspace_hostname(Path::new("/src/app"), &settings).as_deref(),
+            Some("shop.localhost")
+        );
+
+        let host = |service, protocol, bind| {
+            forward_hostname("shop.localhost", service, protocol, bind, false)
+        };
+        assert_eq!(
+            host(None, Protocol::Tcp, "127.0.0.1").as_deref(),
+            Some("shop.localhost")
+        );
+        assert_eq!(
+            host(Some("db"), Protocol::Tcp, "0.0.0.0").as_deref(),
+            Some("db.shop.localhost")
+        );
+        assert_eq!(host(None, Protocol::Udp, "127.0.0.1"), None);
+        assert_eq!(host(None, Protocol::Tcp, "192.168.1.10"), None);
+    }
+
+    #[test]
+    fn replace_hosts_block_keeps_other_entries() {
+        let hosts =
+            "127.0.0.1 localhost\n# BEGIN dcw\n127.0.0.1 old.localhost\n# END dcw\n::1 localhost\n";
+        let lines = vec!["127.0.0.1 shop.localhost db.shop.localhost".to_string()];
+        assert_eq!(
+            replace_hosts_block(hosts, &lines),
+            "127.0.0.1 localhost\n::1 localhost\n# BEGIN dcw\n127.0.0.1 shop.localhost db.shop.localhost\n# END dcw\n"
+        );
+        assert_eq!(
+            replace_hosts_block(hosts, &[]),
+            "127.0.0.1 localhost\n::1 localhost\n"
+        );
+    }
+}
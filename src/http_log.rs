@@ -0,0 +1,264 @@
+use anyhow::{Context, Result};
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::time::{FormatTime, SystemTime};
+
+/// Methods recognized at the start of a request line.
+const METHODS: [&str; 9] = [
+    "GET", "HEAD", "POST", "PUT", "DELETE", "CONNECT", "OPTIONS", "TRACE", "PATCH",
+];
+
+/// Longer lines are skipped; request and status lines are far shorter.
+const MAX_LINE: usize = 8 * 1024;
+
+/// Access log of one forward, shared by all its connections.
+pub struct AccessLog {
+    file: Mutex<File>,
+}
+
+impl AccessLog {
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context("failed to create runtime directory")?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("failed to open {}", path.display()))?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+
+    fn write(&self, entry: &str) {
+        let mut time = String::new();
+        let _ = SystemTime.format_time(&mut Writer::new(&mut time));
+        let _ = writeln!(self.file.lock().unwrap(), "{time} {entry}");
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    started: Instant,
+}
+
+/// The requests of one connection that are awaiting a response. HTTP/1.1
+/// answers requests in order, so responses are matched first in, first out.
+pub struct Exchange {
+    log: Arc<AccessLog>,
+    pending: Mutex<VecDeque<Request>>,
+    /// Set after `101 Switching Protocols`; the rest isn't HTTP
+    upgraded: AtomicBool,
+}
+
+impl Exchange {
+    pub fn new(log: Arc<AccessLog>) -> Arc<Self> {
+        Arc::new(Self {
+            log,
+            pending: Mutex::default(),
+            upgraded: AtomicBool::new(false),
+        })
+    }
+
+    /// Observer for the data clients send.
+    pub fn requests(self: &Arc<Self>) -> Observer {
+        Observer {
+            exchange: self.clone(),
+            responses: false,
+            line: Vec::new(),
+            overlong: false,
+        }
+    }
+
+    /// Observer for the data the container sends back.
+    pub fn responses(self: &Arc<Self>) -> Observer {
+        Observer {
+            responses: true,
+            ..self.requests()
+        }
+    }
+
+    fn request_line(&self, line: &str) {
+        if let Some((method, path)) = parse_request_line(line) {
+            self.pending.lock().unwrap().push_back(Request {
+                method: method.to_string(),
+                path: path.to_string(),
+                started: Instant::now(),
+            });
+        }
+    }
+
+    fn status_line(&self, line: &str) {
+        let Some(status) = parse_status_line(line) else {
+            return;
+        };
+        if status == 101 {
+            self.upgraded.store(true, Ordering::Relaxed);
+        } else if (100..200).contains(&status) {
+            // Interim response; the final one follows
+            return;
+        }
+        let request = self.pending.lock().unwrap().pop_front();
+        if let Some(request) = request {
+            self.log.write(&format_entry(
+                &request.method,
+                &request.path,
+                status,
+                request.started.elapsed(),
+            ));
+        }
+    }
+}
+
+/// Watches one direction of a connection for request or status lines.
+pub struct Observer {
+    exchange: Arc<Exchange>,
+    responses: bool,
+    /// The current line so far
+    line: Vec<u8>,
+    /// The current line exceeded [`MAX_LINE`] and is being skipped
+    overlong: bool,
+}
+
+impl Observer {
+    pub fn observe(&mut self, data: &[u8]) {
+        if self.exchange.upgraded.load(Ordering::Relaxed) {
+            return;
+        }
+        for chunk in data.split_inclusive(|&b| b == b'\n') {
+            if !self.overlong {
+                if self.line.len() + chunk.len() > MAX_LINE {
+                    self.overlong = true;
+                    self.line.clear();
+                } else {
+                    self.line.extend_from_slice(chunk);
+                }
+            }
+            if chunk.ends_with(b"\n") {
+                if !self.overlong {
+                    if let Ok(line) = std::str::from_utf8(&self.line) {
+                        let line = line.trim_end_matches(['\r', '\n']);
+                        if self.responses {
+                            self.exchange.status_line(line);
+                        } else {
+                            self.exchange.request_line(line);
+                        }
+                    }
+                }
+                self.line.clear();
+                self.overlong = false;
+            }
+        }
+    }
+}
+
+/// Method and path of an HTTP/1.x request line.
+fn parse_request_line(line: &str) -> Option<(&str, &str)> {
+    let mut parts = line.split(' ');
+    let method = parts.next()?;
+    let path = parts.next()?;
+    let version = parts.next()?;
+    (METHODS.contains(&method)
+        && !path.is_empty()
+        && version.starts_with("HTTP/1.")
+        && parts.next().is_none())
+    .then_some((method, path))
+}
+
+/// Status code of an HTTP/1.x status line.
+fn parse_status_line(line: &str) -> Option<u16> {
+    let rest = line.strip_prefix("HTTP/1.")?;
+    let code = rest.split(' ').nth(1)?;
+    if code.len() != 3 {
+        return None;
+    }
+    code.parse().ok()
+}
+
+/// One access log entry; the duration is the time until the response's
+/// status line arrived.
+fn format_entry(method: &str, path: &str, status: u16, duration: Duration) -> String {
+    format!("{method} {path} {status} {}ms", duration.as_millis())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_request_and_status_lines() {
+        assert_eq!(
+            parse_request_line("GET /api/users?page=2 HTTP/1.1"),
+            Some(("GET", "/api/users?page=2"))
+        );
+        assert_eq!(parse_request_line("FOO / HTTP/1.1"), None);
+        assert_eq!(parse_request_line("GET / HTTP/2"), None);
+        assert_eq!(parse_request_line("hello world"), None);
+
+        assert_eq!(parse_status_line("HTTP/1.1 404 Not Found"), Some(404));
+        assert_eq!(parse_status_line("HTTP/1.0 200 OK"), Some(200));
+        assert_eq!(parse_status_line("HTTP/1.1 20 OK"), None);
+        assert_eq!(parse_status_line("<html>"), None);
+    }
+
+    #[test]
+    fn format_entry_in_milliseconds() {
+        assert_eq!(
+            format_entry("POST", "/login", 302, Duration::from_micros(12_345)),
+            "POST /login 302 12ms"
+        );
+    }
+
+    #[test]
+    fn exchange_logs_pipelined_requests_in_order() {
+        let dir = std::env::temp_dir().join(format!("dcw-test-http-log-{}", std::process::id()));
+        let path = dir.join("http-3000.log");
+        let exchange = Exchange::new(Arc::new(AccessLog::open(&path).unwrap()));
+        let mut requests = exchange.requests();
+        let mut responses = exchange.responses();
+
+        // Lines split across reads and an interim 100 Continue
+        requests.observe(b"GET /a HTTP/1.1\r\nHost: x\r\n\r\nPOST /b HT");
+        requests.observe(b"TP/1.1\r\nExpect: 100-continue\r\n\r\n");
+        responses.observe(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello");
+        responses.observe(b"HTTP/1.1 100 Continue\r\n\r\n");
+        responses.observe(b"HTTP/1.1 201 Created\r\n\r\n");
+
+        let log = fs::read_to_string(&path).unwrap();
+        // Drop the timestamp and duration
+        let entries: Vec<&str> = log
+            .lines()
+            .map(|line| line.split_once(' ').unwrap().1)
+            .map(|entry| entry.rsplit_once(' ').unwrap().0)
+            .collect();
+        assert_eq!(entries, ["GET /a 200", "POST /b 201"]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn exchange_stops_after_upgrade() {
+        let dir = std::env::temp_dir().join(format!("dcw-test-http-ws-{}", std::process::id()));
+        let path = dir.join("http-3000.log");
+        let exchange = Exchange::new(Arc::new(AccessLog::open(&path).unwrap()));
+        let mut requests = exchange.requests();
+        let mut responses = exchange.responses();
+
+        requests.observe(b"GET /ws HTTP/1.1\r\nUpgrade: websocket\r\n\r\n");
+        responses.observe(b"HTTP/1.1 101 Switching Protocols\r\n\r\n");
+        requests.observe(b"GET /not-http HTTP/1.1\r\n");
+        responses.observe(b"HTTP/1.1 200 OK\r\n");
+
+        let log = fs::read_to_string(&path).unwrap();
+        assert_eq!(log.lines().count(), 1);
+        assert!(log.contains("GET /ws 101"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
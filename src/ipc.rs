@@ -0,0 +1,197 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+
+use crate::commands::port::ForwardRow;
+use crate::commands::watch::WatcherHealth;
+use crate::workspace;
+
+/// How long a client waits for the daemon's reply. Restarting a watcher
+/// waits for the old one to remove its forwards.
+pub const REPLY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long a change notification may wait for the daemon, which is busy
+/// while it stops a watcher.
+const NOTIFY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A request to the daemon, sent as one line of JSON on its socket, e.g.
+/// `{"request":"forwards","folder":"/src/app"}`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "request", rename_all = "snake_case")]
+pub enum Request {
+    /// Start watching a workspace's ports, restarting its watcher if it
+    /// has one
+    Watch {
+        folder: String,
+        container: Option<String>,
+    },
+    /// Stop a workspace's watcher
+    Unwatch { folder: String },
+    /// List the watchers
+    Status,
+    /// List a workspace's port forwards
+    Forwards { folder: String },
+    /// A workspace's port forwards were added or removed
+    Changed { folder: String },
+    /// Stop every watcher and exit
+    Shutdown,
+}
+
+/// The daemon's answer to a [`Request`], one line of JSON.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "reply", rename_all = "snake_case")]
+pub enum Reply {
+    Watching {
+        pid: u32,
+    },
+    Unwatched {
+        pid: Option<u32>,
+    },
+    Status {
+        pid: u32,
+        watchers: Vec<WatcherInfo>,
+    },
+    Forwards {
+        forwards: Vec<ForwardRow>,
+    },
+    Ok,
+    ShuttingDown,
+    Error {
+        message: String,
+    },
+}
+
+/// A workspace watcher run by the daemon.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatcherInfo {
+    pub ws_id: String,
+    pub folder: String,
+    /// `running`, or `restarting` while a crashed watch loop waits to be
+    /// restarted
+    pub state: String,
+    pub pid: Option<u32>,
+    /// Seconds since the watch loop was last started
+    pub uptime_secs: Option<u64>,
+    /// Restarts after the watch loop crashed
+    pub health: WatcherHealth,
+}
+
+/// Send one request to the daemon. `None` if no daemon is listening.
+pub fn send(request: &Request) -> Result<Option<Reply>> {
+    send_within(request, REPLY_TIMEOUT)
+}
+
+fn send_within(request: &Request, timeout: Duration) -> Result<Option<Reply>> {
+    let Ok(stream) = UnixStream::connect(workspace::daemon_socket()) else {
+        return Ok(None);
+    };
+    stream.set_read_timeout(Some(timeout))?;
+    write_message(&stream, request).context("failed to send request to dcw daemon")?;
+    let reply = read_message(&stream)
+        .context("failed to read reply from dcw daemon")?
+        .context("dcw daemon closed the connection")?;
+    match serde_json::from_str(&reply).context("invalid reply from dcw daemon")? {
+        Reply::Error { message } => bail!("{message}"),
+        reply => Ok(Some(reply)),
+    }
+}
+
+/// Write `message` as one line of JSON.
+pub fn write_message(mut stream: &UnixStream, message: &impl Serialize) -> Result<()> {
+    let mut line = serde_json::to_string(message)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+/// Read one line, or `None` at end of stream.
+pub fn read_message(stream: &UnixStream) -> Result<Option<String>> {
+    let mut line = String::new();
+    if BufReader::new(stream).read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(line))
+}
+
+/// The port forwards of the workspace in `folder` as the running daemon
+/// knows them, or `None` if no daemon is running.
+pub fn forwards(folder: &str) -> Result<Option<Vec<ForwardRow>>> {
+    let request = Request::Forwards {
+        folder: folder.to_string(),
+    };
+    match send(&request)? {
+        Some(Reply::Forwards { forwards }) => Ok(Some(forwards)),
+        Some(_) => bail!("unexpected reply from dcw daemon"),
+        None => Ok(None),
+    }
+}
+
+/// Tell the daemon, if one is running, that the forwards of the workspace
+/// in `folder` changed.
+pub fn notify_changed(folder: &str) {
+    let request = Request::Changed {
+        folder: folder.to_string(),
+    };
+    if let Err(e) = send_within(&request, NOTIFY_TIMEOUT) {
+        tracing::debug!("failed to notify dcw daemon: {e:#}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::docker::Protocol;
+    use crate::settings::ForwardBackend;
+
+    #[test]
+    fn requests_and_replies_are_tagged_json() {
+        let request = Request::Watch {
+            folder: "/src/app".to_string(),
+            container: None,
+        };
+        assert_eq!(
+            serde_json::to_string(&request).unwrap(),
+            r#"{"request":"watch","folder":"/src/app","container":null}"#
+        );
+        let request: Request =
+            serde_json::from_str(r#"{"request":"forwards","folder":"/src/app"}"#).unwrap();
+        assert!(matches!(request, Request::Forwards { folder } if folder == "/src/app"));
+        let reply: Reply = serde_json::from_str(r#"{"reply":"unwatched","pid":42}"#).unwrap();
+        assert!(matches!(reply, Reply::Unwatched { pid: Some(42) }));
+        assert_eq!(
+            serde_json::to_string(&Reply::Ok).unwrap(),
+            r#"{"reply":"ok"}"#
+        );
+    }
+
+    #[test]
+    fn forwards_round_trip() {
+        let row = ForwardRow {
+            name: String::new(),
+            backend: ForwardBackend::Native,
+            host_port: 3000,
+            container_port: Some(3000),
+            protocol: Protocol::Tcp,
+            bind: "127.0.0.1".to_string(),
+            unix_socket: None,
+            source: Some("watch".to_string()),
+            label: None,
+            service: None,
+            hostname: None,
+            stats: None,
+        };
+        let reply = Reply::Forwards {
+            forwards: vec![row.clone()],
+        };
+        let json = serde_json::to_string(&reply).unwrap();
+        assert!(
+            json.starts_with(r#"{"reply":"forwards","forwards":[{"name":"","backend":"native""#)
+        );
+        let Reply::Forwards { forwards } = serde_json::from_str(&json).unwrap() else {
+            panic!("not a forwards reply");
+        };
+        assert_eq!(forwards, [row]);
+    }
+}
@@ -0,0 +1,301 @@
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+
+/// Output of a failed lifecycle command kept for the error message; the end
+/// of it is what explains the failure.
+const MAX_OUTPUT: usize = 16 * 1024;
+
+/// One line of `devcontainer up --log-format json` output on stderr.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum LogEvent {
+    /// A log line
+    Text { text: String },
+    /// Output of a command, e.g. `docker build` or a lifecycle command
+    Raw { text: String },
+    /// Start or end of a step such as a lifecycle command
+    Progress {
+        name: String,
+        status: String,
+        #[serde(rename = "stepDetail")]
+        step_detail: Option<String>,
+    },
+    /// A command finished; timestamps are in milliseconds
+    Stop {
+        text: String,
+        timestamp: u64,
+        #[serde(rename = "startTimestamp")]
+        start_timestamp: u64,
+    },
+    /// `start` events, which only repeat what the `stop` event reports
+    #[serde(other)]
+    Other,
+}
+
+/// What to show for one line of the CLI's log.
+#[derive(Debug, PartialEq)]
+pub enum Step {
+    /// Output to pass through as it is
+    Output(String),
+    /// A lifecycle command such as `postCreateCommand` started
+    HookStarted {
+        hook: String,
+        command: Option<String>,
+    },
+    /// The lifecycle command finished
+    HookFinished {
+        hook: String,
+        success: bool,
+        elapsed: Duration,
+    },
+}
+
+/// A lifecycle command that failed, with the end of its output.
+#[derive(Debug, PartialEq)]
+pub struct FailedHook {
+    pub hook: String,
+    pub command: Option<String>,
+    pub output: String,
+}
+
+struct Running {
+    hook: String,
+    command: Option<String>,
+    started: Instant,
+    output: String,
+}
+
+/// Follows the lifecycle commands in the CLI's JSON log.
+#[derive(Default)]
+pub struct LifecycleLog {
+    running: Option<Running>,
+    failed: Option<FailedHook>,
+    /// Time spent building the image
+    build: Option<Duration>,
+    /// Time each lifecycle command took, in order
+    hooks: Vec<(String, Duration)>,
+}
+
+impl LifecycleLog {
+    /// Interpret one line of the log. Lines that aren't JSON, such as
+    /// warnings from Node, are passed through.
+    pub fn handle(&mut self, line: &str) -> Option<Step> {
+        let Ok(event) = serde_json::from_str::<LogEvent>(line) else {
+            return Some(Step::Output(format!("{line}\n")));
+        };
+        match event {
+            LogEvent::Text { text } => Some(self.output(format!("{text}\n"))),
+            LogEvent::Raw { text } => Some(self.output(text)),
+            LogEvent::Progress {
+                name,
+                status,
+                step_detail,
+            } => {
+                let hook = hook_name(&name)?;
+                match status.as_str() {
+                    "running" => {
+                        self.running = Some(Running {
+                            hook: hook.to_string(),
+                            command: step_detail.clone(),
+                            started: Instant::now(),
+                            output: String::new(),
+                        });
+                        Some(Step::HookStarted {
+                            hook: hook.to_string(),
+                            command: step_detail,
+                        })
+                    }
+                    "succeeded" | "failed" => {
+                        let running = self.running.take().filter(|r| r.hook == hook)?;
+                        let success = status == "succeeded";
+                        let elapsed = running.started.elapsed();
+                        self.hooks.push((hook.to_string(), elapsed));
+                        if !success {
+                            self.failed = Some(FailedHook {
+                                hook: running.hook,
+                                command: running.command,
+                                output: running.output,
+                            });
+                        }
+                        Some(Step::HookFinished {
+                            hook: hook.to_string(),
+                            success,
+                            elapsed,
+                        })
+                    }
+                    _ => None,
+                }
+            }
+            LogEvent::Stop {
+                text,
+                timestamp,
+                start_timestamp,
+            } => {
+                if is_build_command(&text) {
+                    let took = Duration::from_millis(timestamp.saturating_sub(start_timestamp));
+                    *self.build.get_or_insert_default() += took;
+                }
+                None
+            }
+            LogEvent::Other => None,
+        }
+    }
+
+    /// The lifecycle command that failed, if any.
+    pub fn failed(self) -> Option<FailedHook> {
+        self.failed
+    }
+
+    /// How long the image build and each lifecycle command took.
+    pub fn timings(&self) -> Vec<(String, Duration)> {
+        let build = self.build.map(|took| ("image build".to_string(), took));
+        build.into_iter().chain(self.hooks.clone()).collect()
+    }
+
+    fn output(&mut self, text: String) -> Step {
+        if let Some(running) = &mut self.running {
+            running.output.push_str(&text);
+            if running.output.len() > MAX_OUTPUT {
+                let mut cut = running.output.len() - MAX_OUTPUT;
+                while !running.output.is_char_boundary(cut) {
+                    cut += 1;
+                }
+                running.output.drain(..cut);
+            }
+        }
+        Step::Output(text)
+    }
+}
+
+/// `postCreateCommand` from a progress step named `Running postCreateCommand...`.
+fn hook_name(progress: &str) -> Option<&str> {
+    progress
+        .strip_prefix("Running ")?
+        .strip_suffix("...")
+        .filter(|hook| hook.ends_with("Command"))
+}
+
+/// Whether a `Run: ...` step builds an image, e.g. `Run: docker buildx build
+/// ...` or `Run: docker compose ... build`.
+fn is_build_command(text: &str) -> bool {
+    let Some(command) = text.strip_prefix("Run: ") else {
+        return false;
+    };
+    let mut words = command.split_whitespace();
+    let engine = words.next().unwrap_or_default();
+    (engine.ends_with("docker") || engine.ends_with("podman")) && words.any(|w| w == "build")
+}
+
+/// Error message of the result the CLI prints on stdout when `up` fails,
+/// e.g. `{"outcome":"error","message":"...","description":"..."}`.
+pub fn error_message(result: &str) -> Option<String> {
+    let result: serde_json::Value = serde_json::from_str(result.trim()).ok()?;
+    if result["outcome"] != "error" {
+        return None;
+    }
+    let message = result["message"].as_str()?;
+    match result["description"].as_str() {
+        Some(description) if description != message => {
+            Some(format!("{}: {message}", description.trim_end_matches('.')))
+        }
+        _ => Some(message.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follows_a_failing_lifecycle_command() {
+        let mut log = LifecycleLog::default();
+        assert_eq!(
+            log.handle(r#"{"type":"text","level":3,"timestamp":1,"text":"Container started"}"#),
+            Some(Step::Output("Container started\n".to_string()))
+        );
+        assert_eq!(
+            log.handle(r#"{"type":"progress","name":"Running postCreateCommand...","status":"running","stepDetail":"npm ci"}"#),
+            Some(Step::HookStarted {
+                hook: "postCreateCommand".to_string(),
+                command: Some("npm ci".to_string()),
+            })
+        );
+        log.handle(
+            r#"{"type":"raw","level":3,"timestamp":2,"text":"npm ERR! missing script\r\n"}"#,
+        );
+        assert_eq!(
+            log.handle(r#"{"type":"start","level":2,"timestamp":3,"text":"Run: docker inspect"}"#),
+            None
+        );
+        let finished = log.handle(
+            r#"{"type":"progress","name":"Running postCreateCommand...","status":"failed"}"#,
+        );
+        assert!(matches!(
+            finished,
+            Some(Step::HookFinished { success: false, .. })
+        ));
+        assert_eq!(
+            log.failed(),
+            Some(FailedHook {
+                hook: "postCreateCommand".to_string(),
+                command: Some("npm ci".to_string()),
+                output: "npm ERR! missing script\r\n".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn times_build_and_lifecycle_commands() {
+        let mut log = LifecycleLog::default();
+        log.handle(r#"{"type":"start","level":2,"timestamp":1000,"text":"Run: docker buildx build -f Dockerfile ."}"#);
+        log.handle(r#"{"type":"stop","level":2,"timestamp":31000,"text":"Run: docker buildx build -f Dockerfile .","startTimestamp":1000}"#);
+        log.handle(r#"{"type":"stop","level":2,"timestamp":32000,"text":"Run: docker inspect abc","startTimestamp":31500}"#);
+        log.handle(
+            r#"{"type":"progress","name":"Running postCreateCommand...","status":"running"}"#,
+        );
+        log.handle(
+            r#"{"type":"progress","name":"Running postCreateCommand...","status":"succeeded"}"#,
+        );
+
+        let timings = log.timings();
+        assert_eq!(
+            timings[0],
+            ("image build".to_string(), Duration::from_secs(30))
+        );
+        assert_eq!(timings[1].0, "postCreateCommand");
+        assert_eq!(timings.len(), 2);
+        assert!(log.failed().is_none());
+    }
+
+    #[test]
+    fn passes_through_other_lines() {
+        let mut log = LifecycleLog::default();
+        assert_eq!(
+            log.handle("(node:1) Warning: something"),
+            Some(Step::Output("(node:1) Warning: something\n".to_string()))
+        );
+        assert_eq!(
+            log.handle(r#"{"type":"progress","name":"Resolving features...","status":"running"}"#),
+            None
+        );
+        assert!(log.failed().is_none());
+    }
+
+    #[test]
+    fn error_message_from_result() {
+        assert_eq!(
+            error_message(
+                r#"{"outcome":"error","message":"Command failed: npm ci","description":"The postCreateCommand in the devcontainer.json failed."}"#
+            ),
+            Some(
+                "The postCreateCommand in the devcontainer.json failed: Command failed: npm ci"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            error_message(r#"{"outcome":"success","containerId":"abc"}"#),
+            None
+        );
+        assert_eq!(error_message("not json"), None);
+    }
+}
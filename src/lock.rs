@@ -0,0 +1,116 @@
+use anyhow::{Context, Result};
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use tracing::info;
+
+use crate::workspace;
+
+/// Set to the PID of the process holding the workspace lock, so dcw
+/// commands it runs (e.g. from hooks) don't wait for their own parent.
+const HOLDER_ENV: &str = "DCW_LOCK_HOLDER";
+
+/// An advisory lock on a workspace's runtime directory, serializing the
+/// dcw commands that change its devcontainer and forwards. Released when
+/// dropped or when the process exits.
+pub struct WorkspaceLock {
+    _file: File,
+}
+
+/// Take the current workspace's lock for `operation`, waiting for another
+/// dcw process that holds it. Returns `None` if a parent dcw process
+/// already holds it.
+pub fn acquire(operation: &str) -> Result<Option<WorkspaceLock>> {
+    let path = workspace::lock_file(&workspace::workspace_id()?);
+    let file = open(&path)?;
+    if !flock(&file, libc::LOCK_EX | libc::LOCK_NB)? {
+        let holder = fs::read_to_string(&path).unwrap_or_default();
+        let (pid, holder_operation) = holder.trim().split_once(' ').unwrap_or(("", ""));
+        if !pid.is_empty() && env::var(HOLDER_ENV).is_ok_and(|p| p == pid) {
+            return Ok(None);
+        }
+        if pid.is_empty() {
+            info!("Another dcw operation is in progress; waiting for it to finish...");
+        } else {
+            info!(
+                "Another dcw operation is in progress (`dcw {holder_operation}`, pid {pid}); waiting for it to finish..."
+            );
+        }
+        flock(&file, libc::LOCK_EX)?;
+    }
+    let lock = hold(file, operation)?;
+    env::set_var(HOLDER_ENV, std::process::id().to_string());
+    Ok(Some(lock))
+}
+
+/// Take the lock of workspace `ws_id` for `operation` unless another
+/// process holds it, for work that can wait until the next round, like
+/// the watcher's passes.
+pub fn try_acquire(ws_id: &str, operation: &str) -> Result<Option<WorkspaceLock>> {
+    let file = open(&workspace::lock_file(ws_id))?;
+    if !flock(&file, libc::LOCK_EX | libc::LOCK_NB)? {
+        return Ok(None);
+    }
+    hold(file, operation).map(Some)
+}
+
+fn open(path: &Path) -> Result<File> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context("failed to create runtime directory")?;
+    }
+    OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .read(true)
+        .write(true)
+        .open(path)
+        .with_context(|| format!("failed to open {}", path.display()))
+}
+
+/// Record who holds the lock, for the message others print while waiting.
+fn hold(mut file: File, operation: &str) -> Result<WorkspaceLock> {
+    file.set_len(0)
+        .and_then(|()| write!(file, "{} {operation}", std::process::id()))
+        .context("failed to write workspace lock")?;
+    Ok(WorkspaceLock { _file: file })
+}
+
+/// `flock(2)`, retried when interrupted. Returns `false` if a non-blocking
+/// attempt finds the lock taken.
+fn flock(file: &File, operation: libc::c_int) -> Result<bool> {
+    loop {
+        if unsafe { libc::flock(file.as_raw_fd(), operation) } == 0 {
+            return Ok(true);
+        }
+        let err = io::Error::last_os_error();
+        match err.kind() {
+            io::ErrorKind::Interrupted => continue,
+            io::ErrorKind::WouldBlock => return Ok(false),
+            _ => return Err(err).context("failed to lock the workspace"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn second_lock_is_refused_until_released() {
+        let ws_id = format!("dev-lock-test-{}", std::process::id());
+        let first = try_acquire(&ws_id, "up").unwrap().unwrap();
+        let path = workspace::lock_file(&ws_id);
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            format!("{} up", std::process::id())
+        );
+        // flock locks belong to the open file, so a second open conflicts
+        // even within one process
+        assert!(try_acquire(&ws_id, "port watch").unwrap().is_none());
+        drop(first);
+        assert!(try_acquire(&ws_id, "port watch").unwrap().is_some());
+        let _ = fs::remove_dir_all(workspace::runtime_dir_for(&ws_id));
+    }
+}
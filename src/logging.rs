@@ -0,0 +1,163 @@
+use anyhow::{Context, Result};
+use std::fmt;
+use std::fs::OpenOptions;
+use std::path::Path;
+use std::process::Command;
+use std::sync::Mutex;
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::fmt::format::{FormatEvent, FormatFields, Writer};
+use tracing_subscriber::fmt::writer::MakeWriterExt;
+use tracing_subscriber::fmt::FmtContext;
+use tracing_subscriber::registry::LookupSpan;
+
+/// How much dcw prints, from `-q`/`--quiet` and `-v`/`--verbose`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verbosity {
+    /// Errors only
+    Quiet,
+    /// Progress, warnings and errors
+    Normal,
+    /// Also the external commands being run
+    Verbose,
+}
+
+impl Verbosity {
+    pub fn from_flags(verbose: bool, quiet: bool) -> Self {
+        if quiet {
+            Verbosity::Quiet
+        } else if verbose {
+            Verbosity::Verbose
+        } else {
+            Verbosity::Normal
+        }
+    }
+
+    fn level(self) -> Level {
+        match self {
+            Verbosity::Quiet => Level::ERROR,
+            Verbosity::Normal => Level::INFO,
+            Verbosity::Verbose => Level::DEBUG,
+        }
+    }
+}
+
+/// Install the global logger. On the terminal, progress goes to stdout and
+/// warnings, errors and `-v` output to stderr. With a log file (used by the
+/// detached watcher), everything goes there with timestamps.
+pub fn init(verbosity: Verbosity, log_file: Option<&Path>) -> Result<()> {
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(verbosity.level())
+        .with_target(false);
+    let result = match log_file {
+        Some(path) => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("failed to open log file {}", path.display()))?;
+            tracing::subscriber::set_global_default(builder.with_writer(Mutex::new(file)).finish())
+        }
+        None => {
+            let writer = std::io::stderr.with_max_level(Level::WARN).or_else(
+                std::io::stdout
+                    .with_max_level(Level::INFO)
+                    .or_else(std::io::stderr),
+            );
+            tracing::subscriber::set_global_default(
+                builder
+                    .event_format(TerminalFormat)
+                    .with_writer(writer)
+                    .finish(),
+            )
+        }
+    };
+    result.context("failed to set up logging")
+}
+
+/// Plain messages, with the "Warning:"/"Error:" prefixes dcw has always
+/// printed.
+struct TerminalFormat;
+
+impl<S, N> FormatEvent<S, N> for TerminalFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        match *event.metadata().level() {
+            Level::ERROR => write!(writer, "Error: ")?,
+            Level::WARN => write!(writer, "Warning: ")?,
+            _ => {}
+        }
+        ctx.field_format().format_fields(writer.by_ref(), event)?;
+        writeln!(writer)
+    }
+}
+
+/// Log external commands before running them, for `-v`.
+pub trait Logged {
+    fn logged(&mut self) -> &mut Self;
+}
+
+impl Logged for Command {
+    fn logged(&mut self) -> &mut Self {
+        tracing::debug!("$ {}", command_line(self));
+        self
+    }
+}
+
+/// A command as it would be typed in a shell.
+pub fn command_line(cmd: &Command) -> String {
+    std::iter::once(cmd.get_program())
+        .chain(cmd.get_args())
+        .map(|arg| {
+            let arg = arg.to_string_lossy();
+            if !arg.is_empty()
+                && arg
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_./:=,@%+".contains(c))
+            {
+                arg.to_string()
+            } else {
+                format!("'{}'", arg.replace('\'', r"'\''"))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verbosity_from_flags() {
+        assert_eq!(Verbosity::from_flags(false, false), Verbosity::Normal);
+        assert_eq!(Verbosity::from_flags(true, false), Verbosity::Verbose);
+        assert_eq!(Verbosity::from_flags(false, true), Verbosity::Quiet);
+    }
+
+    #[test]
+    fn command_line_quotes_when_needed() {
+        let mut cmd = Command::new("docker");
+        cmd.args([
+            "exec",
+            "-u",
+            "root",
+            "abc",
+            "sh",
+            "-c",
+            "echo 'hi' $HOME",
+            "",
+        ]);
+        assert_eq!(
+            command_line(&cmd),
+            r#"docker exec -u root abc sh -c 'echo '\''hi'\'' $HOME' ''"#
+        );
+    }
+}
@@ -1,31 +1,176 @@
 mod browser;
 mod commands;
 mod config;
+mod customizations;
+mod display;
 mod docker;
+mod engine;
 mod forward_ports;
+mod forward_state;
+mod gpg;
+mod hooks;
+mod hostnames;
+mod http_log;
+mod ipc;
+mod lifecycle;
+mod lock;
+mod logging;
+mod notify;
+mod output;
 mod process;
+mod proxy;
+mod registry;
+mod secrets;
 mod settings;
+mod templates;
+mod tls;
+mod traffic;
+mod version_check;
 mod workspace;
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use clap::Parser;
 
-use commands::{browser_relay, down, exec, port, up, update};
+use commands::{browser_relay, build, code, daemon, doctor, down, env, events, exec, gc, hosts, init, list, logs, mount, port, prebuild, prune, restart, run, shell, snapshot, ssh, stats, status, stop, ui, up, update, volume, workspaces};
+use output::OutputFormat;
 
 #[derive(Parser)]
 #[command(name = "dcw", about = "Devcontainer CLI helper", version)]
-enum Cli {
+struct Cli {
+    /// Output format for `list`, `status`, `port list`, `port watch --status`, `mount list`, `volume list`, `snapshot list`, `daemon status`, `config diff`, `workspace list`, `events`, `port overview`, `stats` and `env`
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+
+    /// Shorthand for `--output json`
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Devcontainer config file to use instead of `.devcontainer/devcontainer.json`
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<std::path::PathBuf>,
+
+    /// Devcontainer to use (name or ID) when the workspace has several,
+    /// e.g. one per config under `.devcontainer/*`
+    #[arg(long, global = true, value_name = "NAME")]
+    container: Option<String>,
+
+    /// Run in a workspace registered with `dcw workspace add` (or a folder)
+    /// instead of the current directory; goes before the subcommand
+    #[arg(short, long, value_name = "NAME")]
+    workspace: Option<String>,
+
+    /// Also show the docker and devcontainer commands being run
+    #[arg(short, long, global = true, conflicts_with = "quiet")]
+    verbose: bool,
+
+    /// Print only errors
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// Print the docker and devcontainer commands that would change
+    /// something instead of running them (`up`, `stop`, `down`, `restart`,
+    /// `build`, `prune`, `gc`, `volume prune`, `port add/remove/restore`
+    /// and `snapshot create/restore/delete`)
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    /// Write log messages to this file instead of the terminal
+    #[arg(long, global = true, hide = true, value_name = "PATH")]
+    log_file: Option<std::path::PathBuf>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(clap::Subcommand)]
+enum Command {
+    /// Scaffold a devcontainer configuration
+    Init(init::InitArgs),
     /// Start the devcontainer
     Up(up::UpArgs),
-    /// Stop the devcontainer
-    Down,
+    /// Build the devcontainer image without starting it
+    Build(build::BuildArgs),
+    /// Build and push the image `dcw up` can start from instead of building
+    Prebuild(prebuild::PrebuildArgs),
+    /// Stop the devcontainer but keep it, to resume quickly with `up`
+    Stop,
+    /// Stop the devcontainer and clean up after it; with --remove, delete it
+    Down(down::DownArgs),
+    /// Restart the devcontainer and restore port forwards
+    Restart(restart::RestartArgs),
     /// Execute a command inside the devcontainer
     Exec(exec::ExecArgs),
+    /// Show logs from the devcontainer
+    Logs(logs::LogsArgs),
+    /// Stream Docker events of the devcontainer, its compose services and
+    /// port-forward sidecars
+    Events(events::EventsArgs),
+    /// Show CPU, memory, network and disk usage of the devcontainer and
+    /// its port-forward sidecars
+    Stats(stats::StatsArgs),
+    /// Show containerEnv and remoteEnv from the config next to their values
+    /// in the container
+    Env(env::EnvArgs),
+    /// Dashboard of every devcontainer, its usage and port forwards
+    Ui(ui::UiArgs),
+    /// Map workspace hostnames such as `myapp.localhost` to the loopback
+    /// address in /etc/hosts
+    Hosts(hosts::HostsArgs),
+    /// Open an interactive shell inside the devcontainer
+    Shell(shell::ShellArgs),
+    /// Run a one-off container on the devcontainer's network
+    Run(run::RunArgs),
+    /// Open VS Code attached to the devcontainer
+    Code(code::CodeArgs),
+    /// Connect to the devcontainer over SSH
+    Ssh(ssh::SshArgs),
+    /// List all workspaces managed by dcw
+    #[command(alias = "ls")]
+    List,
+    /// Show the state of the current workspace
+    Status,
     /// Manage port forwards
     Port {
         #[command(subcommand)]
         action: port::PortAction,
     },
+    /// Manage bind mounts in devcontainer.local.json
+    Mount {
+        #[command(subcommand)]
+        action: mount::MountAction,
+    },
+    /// List and prune the volumes of the workspace's devcontainer
+    Volume {
+        #[command(subcommand)]
+        action: volume::VolumeAction,
+    },
+    /// Save the devcontainer as an image and recreate it from one later
+    Snapshot {
+        #[command(subcommand)]
+        action: snapshot::SnapshotAction,
+    },
+    /// Remove orphaned sidecars and stale runtime directories
+    Prune,
+    /// Reclaim disk from dangling devcontainer images, old sidecar images
+    /// and stale runtime directories
+    Gc(gc::GcArgs),
+    /// Inspect and validate the devcontainer config
+    Config {
+        #[command(subcommand)]
+        action: commands::config::ConfigAction,
+    },
+    /// Manage named workspaces for `-w`/`--workspace`
+    Workspace {
+        #[command(subcommand)]
+        action: workspaces::WorkspaceAction,
+    },
+    /// Run the background daemon that watches ports for every workspace
+    Daemon {
+        #[command(subcommand)]
+        action: daemon::DaemonAction,
+    },
+    /// Check the environment for common problems
+    Doctor,
     /// Update dcw to the latest version
     Update(update::UpdateArgs),
     /// Internal: browser relay server
@@ -36,15 +181,131 @@ enum Cli {
     },
 }
 
+impl Command {
+    fn supports_dry_run(&self) -> bool {
+        match self {
+            Command::Up(_)
+            | Command::Stop
+            | Command::Down(_)
+            | Command::Restart(_)
+            | Command::Build(_)
+            | Command::Prune
+            | Command::Gc(_) => true,
+            Command::Port { action } => matches!(
+                action,
+                port::PortAction::Add { .. }
+                    | port::PortAction::Remove { .. }
+                    | port::PortAction::Restore
+            ),
+            Command::Volume { action } => matches!(action, volume::VolumeAction::Prune),
+            Command::Snapshot { action } => !matches!(action, snapshot::SnapshotAction::List),
+            _ => false,
+        }
+    }
+
+    /// Name of the operation for commands that change the workspace's
+    /// containers, forwards or runtime directory, which take its lock so
+    /// concurrent dcw invocations run one after another.
+    fn lock_operation(&self) -> Option<&'static str> {
+        match self {
+            Command::Up(_) => Some("up"),
+            Command::Stop => Some("stop"),
+            Command::Down(_) => Some("down"),
+            Command::Restart(_) => Some("restart"),
+            Command::Prune => Some("prune"),
+            Command::Gc(_) => Some("gc"),
+            Command::Volume { action } => match action {
+                volume::VolumeAction::Prune => Some("volume prune"),
+                volume::VolumeAction::List => None,
+            },
+            Command::Port { action } => match action {
+                port::PortAction::Add { .. } => Some("port add"),
+                port::PortAction::Remove { .. } => Some("port remove"),
+                port::PortAction::Restore => Some("port restore"),
+                _ => None,
+            },
+            Command::Mount { action } => match action {
+                mount::MountAction::Add { .. } => Some("mount add"),
+                mount::MountAction::Remove { .. } => Some("mount remove"),
+                _ => None,
+            },
+            Command::Snapshot { action } => match action {
+                snapshot::SnapshotAction::Create { .. } => Some("snapshot create"),
+                snapshot::SnapshotAction::Restore { .. } => Some("snapshot restore"),
+                snapshot::SnapshotAction::Delete { .. } => Some("snapshot delete"),
+                snapshot::SnapshotAction::List => None,
+            },
+            _ => None,
+        }
+    }
+}
+
 fn main() -> Result<()> {
     let cli = Cli::parse();
+    logging::init(
+        logging::Verbosity::from_flags(cli.verbose, cli.quiet),
+        cli.log_file.as_deref(),
+    )?;
+    let format = OutputFormat::from_flags(cli.output, cli.json);
+    if let Some(path) = &cli.config {
+        config::set_config_flag(path)?;
+    }
+    if let Some(container) = &cli.container {
+        docker::set_container_flag(container);
+    }
+    if let Some(name) = &cli.workspace {
+        registry::enter(name)?;
+    }
+    if cli.dry_run {
+        if !cli.command.supports_dry_run() {
+            bail!("--dry-run is not supported by this command");
+        }
+        docker::set_dry_run();
+    }
+    // Held until the command finishes; a dry run changes nothing
+    let _lock = match cli.command.lock_operation() {
+        Some(operation) if !cli.dry_run => lock::acquire(operation)?,
+        _ => None,
+    };
+
+    let result = match &cli.command {
+        Command::Init(args) => init::run(args),
+        Command::Up(args) => up::run(args, format),
+        Command::Build(args) => build::run(args),
+        Command::Prebuild(args) => prebuild::run(args),
+        Command::Stop => stop::run(),
+        Command::Down(args) => down::run(args),
+        Command::Restart(args) => restart::run(args),
+        Command::Exec(args) => exec::run(args),
+        Command::Logs(args) => logs::run(args),
+        Command::Events(args) => events::run(args, format),
+        Command::Stats(args) => stats::run(args, format),
+        Command::Env(args) => env::run(args, format),
+        Command::Ui(args) => ui::run(args),
+        Command::Hosts(args) => hosts::run(args),
+        Command::Shell(args) => shell::run(args),
+        Command::Run(args) => run::run(args),
+        Command::Ssh(args) => ssh::run(args),
+        Command::Code(args) => code::run(args),
+        Command::List => list::run(format),
+        Command::Status => status::run(format),
+        Command::Port { action } => port::run(action, format),
+        Command::Mount { action } => mount::run(action, format),
+        Command::Volume { action } => volume::run(action, format),
+        Command::Snapshot { action } => snapshot::run(action, format),
+        Command::Prune => prune::run(),
+        Command::Gc(args) => gc::run(args),
+        Command::Config { action } => commands::config::run(action, format),
+        Command::Workspace { action } => workspaces::run(action, format),
+        Command::Daemon { action } => daemon::run(action, format),
+        Command::Doctor => doctor::run(),
+        Command::Update(args) => update::run(args),
+        Command::BrowserRelay { action } => browser_relay::run(action),
+    };
 
-    match &cli {
-        Cli::Up(args) => up::run(args),
-        Cli::Down => down::run(),
-        Cli::Exec(args) => exec::run(args),
-        Cli::Port { action } => port::run(action),
-        Cli::Update(args) => update::run(args),
-        Cli::BrowserRelay { action } => browser_relay::run(action),
+    let internal = matches!(cli.command, Command::BrowserRelay { .. }) || cli.log_file.is_some();
+    if result.is_ok() && !internal && !matches!(cli.command, Command::Update(_)) {
+        version_check::notify();
     }
+    result
 }
@@ -0,0 +1,41 @@
+use anyhow::{bail, Context, Result};
+use std::process::Command;
+
+use crate::logging::Logged;
+
+/// Show a desktop notification.
+/// Uses `osascript` on macOS and `notify-send` on Linux.
+/// Title and body are passed as arguments (not spliced into a script) to
+/// prevent injection.
+pub fn send(title: &str, body: &str) -> Result<()> {
+    let mut cmd = if cfg!(target_os = "macos") {
+        let mut cmd = Command::new("osascript");
+        cmd.args([
+            "-e",
+            "on run argv",
+            "-e",
+            "display notification (item 2 of argv) with title (item 1 of argv)",
+            "-e",
+            "end run",
+            title,
+            body,
+        ]);
+        cmd
+    } else {
+        let mut cmd = Command::new("notify-send");
+        cmd.args(["--app-name=dcw", title, body]);
+        cmd
+    };
+
+    let output = cmd
+        .logged()
+        .output()
+        .context("failed to run notification command")?;
+    if !output.status.success() {
+        bail!(
+            "notification command failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use anyhow::Result;
+use serde::Serialize;
+
+/// Output format for commands that print tables.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable table
+    #[default]
+    Text,
+    /// Machine-readable JSON
+    Json,
+}
+
+impl OutputFormat {
+    /// Resolve the global `--output` and `--json` flags.
+    pub fn from_flags(output: OutputFormat, json: bool) -> Self {
+        if json {
+            Self::Json
+        } else {
+            output
+        }
+    }
+}
+
+/// Print `value` as pretty JSON on stdout.
+pub fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_flag_overrides_output() {
+        assert_eq!(
+            OutputFormat::from_flags(OutputFormat::Text, true),
+            OutputFormat::Json
+        );
+        assert_eq!(
+            OutputFormat::from_flags(OutputFormat::Text, false),
+            OutputFormat::Text
+        );
+    }
+}
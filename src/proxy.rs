@@ -0,0 +1,1336 @@
+use anyhow::{bail, Context, Result};
+use rustls::{ServerConfig, ServerConnection};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket};
+use std::os::fd::AsRawFd;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+use crate::docker::{self, ContainerAddresses, ForwardSpec, Protocol};
+use crate::hooks::{self, Event};
+use crate::http_log::{AccessLog, Exchange, Observer};
+use crate::logging::Logged;
+use crate::process;
+use crate::settings::ForwardBackend;
+use crate::tls;
+use crate::traffic::TrafficStats;
+use crate::workspace;
+
+/// How often listener threads check their stop flag.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How often the daemon re-reads the state file.
+const RELOAD_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Timeout for connecting to the container.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// UDP client mappings are dropped after this long without replies.
+const UDP_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A forward served by the native proxy, as recorded in the workspace's
+/// `proxy.json` state file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProxyForward {
+    pub host_port: u16,
+    pub container_port: u16,
+    #[serde(default)]
+    pub protocol: Protocol,
+    pub bind: String,
+    /// Container IP connections are forwarded to
+    pub target: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Log HTTP requests to the workspace's `http-<host_port>.log`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub log_http: bool,
+    /// Terminate TLS from clients; plain TCP reaches the container
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub tls: bool,
+    /// `target` is the container's IPv6 address
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub ipv6: bool,
+    /// Bytes per second in each direction
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<u64>,
+    /// Connections, or UDP clients, served at once; more are turned away
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_connections: Option<u32>,
+    /// Seconds without traffic after which the daemon removes the forward
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_timeout: Option<u64>,
+}
+
+impl ProxyForward {
+    fn from_spec(spec: &ForwardSpec, target: &str) -> Self {
+        Self {
+            host_port: spec.host_port,
+            container_port: spec.container_port,
+            protocol: spec.protocol,
+            bind: spec
+                .bind_address
+                .unwrap_or(docker::DEFAULT_BIND_ADDRESS)
+                .to_string(),
+            target: target.to_string(),
+            source: spec.source.map(str::to_string),
+            label: spec.label.map(str::to_string),
+            log_http: spec.log_http,
+            tls: spec.tls,
+            ipv6: spec.ipv6,
+            rate_limit: spec.rate_limit,
+            max_connections: spec.max_connections,
+            idle_timeout: spec.idle_timeout,
+        }
+    }
+
+    /// Forwards are identified by container port and protocol, like sidecars.
+    fn key(&self) -> (u16, Protocol) {
+        (self.container_port, self.protocol)
+    }
+}
+
+/// Traffic through one native forward since its listener started.
+#[derive(Debug, Default)]
+struct Counters {
+    /// Bytes from clients to the container
+    bytes_in: AtomicU64,
+    /// Bytes from the container to clients
+    bytes_out: AtomicU64,
+    /// Open TCP connections, or UDP clients with a live mapping
+    connections: AtomicU64,
+}
+
+/// A forward's counters as written to `proxy-stats.json` by the daemon.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct ForwardStats {
+    container_port: u16,
+    protocol: Protocol,
+    bytes_in: u64,
+    bytes_out: u64,
+    connections: u64,
+}
+
+impl ForwardStats {
+    fn snapshot(fwd: &ProxyForward, counters: &Counters) -> Self {
+        Self {
+            container_port: fwd.container_port,
+            protocol: fwd.protocol,
+            bytes_in: counters.bytes_in.load(Ordering::Relaxed),
+            bytes_out: counters.bytes_out.load(Ordering::Relaxed),
+            connections: counters.connections.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// When a forward last carried traffic, for `idle_timeout`.
+struct Activity {
+    bytes: u64,
+    since: Instant,
+}
+
+impl Activity {
+    fn new(now: Instant) -> Self {
+        Self {
+            bytes: 0,
+            since: now,
+        }
+    }
+
+    /// How long the forward has gone without moving bytes or holding a
+    /// connection open, as of `stats` taken at `now`.
+    fn idle_for(&mut self, stats: &ForwardStats, now: Instant) -> Duration {
+        let bytes = stats.bytes_in + stats.bytes_out;
+        if bytes != self.bytes || stats.connections > 0 {
+            self.bytes = bytes;
+            self.since = now;
+        }
+        now.duration_since(self.since)
+    }
+}
+
+/// Token bucket for one direction of a `--rate-limit` forward, shared by
+/// all its connections.
+struct RateLimit {
+    /// Bytes per second
+    rate: u64,
+    /// When the budget was last topped up, and the bytes it allows; below
+    /// zero while senders sleep off what they took in advance
+    budget: Mutex<(Instant, f64)>,
+}
+
+impl RateLimit {
+    fn new(rate: u64) -> Self {
+        let limit = Self {
+            rate,
+            budget: Mutex::new((Instant::now(), 0.0)),
+        };
+        limit.budget.lock().unwrap().1 = limit.chunk() as f64;
+        limit
+    }
+
+    /// How much to read at a time, a tenth of a second's worth, so data
+    /// trickles rather than arriving in bursts.
+    fn chunk(&self) -> usize {
+        (self.rate / 10).clamp(1, 16 * 1024) as usize
+    }
+
+    /// Wait until `n` more bytes may pass.
+    fn take(&self, n: usize) {
+        let wait = {
+            let mut budget = self.budget.lock().unwrap();
+            let (last, available) = &mut *budget;
+            let now = Instant::now();
+            let rate = self.rate as f64;
+            // An idle forward saves up no more than one chunk
+            *available = (*available + now.duration_since(*last).as_secs_f64() * rate)
+                .min(self.chunk() as f64);
+            *last = now;
+            *available -= n as f64;
+            Duration::from_secs_f64((-*available).max(0.0) / rate)
+        };
+        thread::sleep(wait);
+    }
+}
+
+/// The two directions of a `--rate-limit` forward.
+struct RateLimits {
+    /// Client to container
+    inbound: RateLimit,
+    /// Container to client
+    outbound: RateLimit,
+}
+
+impl RateLimits {
+    fn new(rate: u64) -> Arc<Self> {
+        Arc::new(Self {
+            inbound: RateLimit::new(rate),
+            outbound: RateLimit::new(rate),
+        })
+    }
+}
+
+/// Start a forward using the given backend. Returns the host port used.
+pub fn start_forward(
+    ws_id: &str,
+    container_id: &str,
+    network: &str,
+    spec: &ForwardSpec,
+    backend: ForwardBackend,
+) -> Result<u16> {
+    let endpoint = docker::docker_endpoint();
+    let remote = endpoint.is_remote();
+    // Container IPs of a remote daemon aren't routable from here
+    let backend = if remote && backend == ForwardBackend::Native {
+        warn!(
+            "Docker endpoint {} ({}) is remote, using sidecars instead of native forwarding.",
+            endpoint.context, endpoint.host
+        );
+        ForwardBackend::Sidecar
+    } else if spec.service.is_some() {
+        // Native and shared forwards all follow the devcontainer's IP
+        ForwardBackend::Sidecar
+    } else {
+        backend
+    };
+    if spec.log_http && backend != ForwardBackend::Native {
+        bail!("--log-http needs the native backend, which can't serve this forward");
+    }
+    if spec.tls && backend != ForwardBackend::Native {
+        bail!("--tls needs the native backend, which can't serve this forward");
+    }
+    if (spec.rate_limit.is_some() || spec.max_connections.is_some())
+        && backend != ForwardBackend::Native
+    {
+        bail!("--rate-limit and --max-connections need the native backend, which can't serve this forward");
+    }
+    if spec.idle_timeout.is_some() && backend != ForwardBackend::Native {
+        bail!("--idle-timeout needs the native backend, which can't serve this forward");
+    }
+
+    let host_port = match backend {
+        ForwardBackend::Sidecar => docker::start_port_forward(ws_id, container_id, network, spec)?,
+        ForwardBackend::Native => start_native_forward(container_id, network, spec)?,
+        ForwardBackend::Shared => docker::start_shared_forward(ws_id, container_id, network, spec)?,
+    };
+    if remote {
+        info!(
+            "Note: port {host_port} is published on the remote Docker host; {}.",
+            endpoint.tunnel_hint(host_port)
+        );
+    }
+    // A forward in the foreground has already stopped by now
+    if spec.detach {
+        hooks::emit(Event::PostForward {
+            host_port,
+            container_port: spec.container_port,
+            protocol: spec.protocol,
+            bind: spec
+                .bind_address
+                .unwrap_or(docker::DEFAULT_BIND_ADDRESS)
+                .to_string(),
+            backend,
+            source: spec.source.map(str::to_string),
+            label: spec.label.map(str::to_string),
+        });
+    }
+    Ok(host_port)
+}
+
+/// Remove the forward for `port` from whichever backend serves it.
+pub fn remove_forward(ws_id: &str, port: u16, protocol: Protocol) -> Result<()> {
+    let removed = remove_native_forward(port, protocol)?
+        || docker::remove_shared_forwards(ws_id, |f| {
+            (f.container_port, f.protocol) == (port, protocol)
+        })?;
+    if !removed {
+        docker::remove_port_forward(ws_id, port, protocol)?;
+    }
+    hooks::emit(Event::PostUnforward { port, protocol });
+    Ok(())
+}
+
+/// Forward a port through the native proxy. Detached forwards are handed to
+/// the workspace's proxy daemon (started if needed); otherwise the forward is
+/// served in the foreground until the process is interrupted.
+fn start_native_forward(container_id: &str, network: &str, spec: &ForwardSpec) -> Result<u16> {
+    let addresses = docker::get_container_addresses(container_id, network)?;
+    let state_file = workspace::proxy_state_file()?;
+    let mut forwards = load_state(&state_file);
+
+    let mut fwd = ProxyForward::from_spec(spec, addresses.target(spec.ipv6));
+    let existing = forwards.iter().position(|f| f.key() == fwd.key());
+    // Our own listener for this forward may hold the port, so only look for
+    // another one if something else does
+    let held_by_us = existing.is_some_and(|i| forwards[i].host_port == spec.host_port);
+    if spec.fallback && !held_by_us {
+        let port = docker::find_free_host_port(&fwd.bind, spec.host_port, spec.protocol)
+            .with_context(|| format!("no free host port found from {}", spec.host_port))?;
+        if port != spec.host_port {
+            info!(
+                "Host port {} is in use, using {port} instead.",
+                spec.host_port
+            );
+        }
+        fwd.host_port = port;
+    }
+    if docker::dry_run() {
+        docker::print_dry_run(&format!(
+            "forward {}:{} -> {}:{}/{} in the native proxy",
+            fwd.bind,
+            fwd.host_port,
+            fwd.target,
+            fwd.container_port,
+            fwd.protocol.as_str()
+        ));
+        return Ok(fwd.host_port);
+    }
+    if fwd.log_http {
+        info!(
+            "Logging HTTP requests to {}",
+            workspace::http_log_file(fwd.host_port)?.display()
+        );
+    }
+    if fwd.tls {
+        tls::ensure_certificate()?;
+        info!("Serving HTTPS on https://localhost:{}/", fwd.host_port);
+    }
+
+    if !spec.detach {
+        if let Some(i) = existing {
+            forwards.remove(i);
+            save_state(&state_file, &forwards)?;
+            // Give the daemon a chance to release the port
+            thread::sleep(RELOAD_INTERVAL * 2);
+        }
+        let stop = Arc::new(AtomicBool::new(false));
+        for handle in spawn_listener(&fwd, stop, Arc::default())? {
+            let _ = handle.join();
+        }
+        return Ok(fwd.host_port);
+    }
+
+    let host_port = fwd.host_port;
+    match existing {
+        Some(i) => forwards[i] = fwd,
+        None => forwards.push(fwd),
+    }
+    save_state(&state_file, &forwards)?;
+    ensure_daemon()?;
+    Ok(host_port)
+}
+
+/// Remove a native forward. Returns whether one was found.
+fn remove_native_forward(port: u16, protocol: Protocol) -> Result<bool> {
+    let state_file = workspace::proxy_state_file()?;
+    let mut forwards = load_state(&state_file);
+    let before = forwards.len();
+    forwards.retain(|f| f.key() != (port, protocol));
+    if forwards.len() == before {
+        return Ok(false);
+    }
+    if docker::dry_run() {
+        docker::print_dry_run(&format!(
+            "remove native forward for port {port}/{}",
+            protocol.as_str()
+        ));
+        return Ok(true);
+    }
+    save_state(&state_file, &forwards)?;
+    Ok(true)
+}
+
+/// Remove all native forwards carrying the given `source`.
+pub fn remove_forwards_by_source(source: &str) -> Result<()> {
+    let state_file = workspace::proxy_state_file()?;
+    let mut forwards = load_state(&state_file);
+    let before = forwards.len();
+    forwards.retain(|f| f.source.as_deref() != Some(source));
+    if forwards.len() != before {
+        save_state(&state_file, &forwards)?;
+    }
+    Ok(())
+}
+
+/// Native forwards recorded in another runtime directory.
+pub fn forwards_in(runtime_dir: &Path) -> Vec<ProxyForward> {
+    load_state(&runtime_dir.join("proxy.json"))
+}
+
+/// Native forwards of the current workspace.
+pub fn list_forwards() -> Result<Vec<ProxyForward>> {
+    Ok(load_state(&workspace::proxy_state_file()?))
+}
+
+/// Traffic of the native forwards, keyed by container port and protocol, as
+/// last written by the daemon.
+pub fn traffic() -> Result<HashMap<(u16, Protocol), TrafficStats>> {
+    let stats: Vec<ForwardStats> = fs::read_to_string(workspace::proxy_stats_file()?)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+    Ok(stats
+        .into_iter()
+        .map(|s| {
+            let traffic = TrafficStats {
+                bytes_in: Some(s.bytes_in),
+                bytes_out: Some(s.bytes_out),
+                bytes_total: Some(s.bytes_in + s.bytes_out),
+                connections: Some(s.connections),
+            };
+            ((s.container_port, s.protocol), traffic)
+        })
+        .collect())
+}
+
+/// Drop all native forwards and stop the proxy daemon.
+pub fn stop() -> Result<()> {
+    if docker::dry_run() {
+        if !list_forwards()?.is_empty() {
+            docker::print_dry_run("stop the native proxy and remove its forwards");
+        }
+        return Ok(());
+    }
+    let _ = fs::remove_file(workspace::proxy_state_file()?);
+    let _ = fs::remove_file(workspace::proxy_stats_file()?);
+    let pid_file = workspace::proxy_pid_file()?;
+    if let Some(pid) = read_pid(&pid_file) {
+        process::kill_dcw_process(pid);
+    }
+    let _ = fs::remove_file(pid_file);
+    Ok(())
+}
+
+/// Point all native forwards at a new container IP (e.g. after a restart),
+/// dropping watcher-managed forwards, which the watcher recreates.
+pub fn retarget(addresses: &ContainerAddresses) -> Result<()> {
+    let state_file = workspace::proxy_state_file()?;
+    let mut forwards = load_state(&state_file);
+    if forwards.is_empty() {
+        return Ok(());
+    }
+    if docker::dry_run() {
+        docker::print_dry_run(&format!("point native forwards at {}", addresses.ipv4));
+        return Ok(());
+    }
+    forwards.retain(|f| f.source.as_deref() != Some("watch"));
+    for fwd in &mut forwards {
+        fwd.target = addresses.target(fwd.ipv6).to_string();
+    }
+    save_state(&state_file, &forwards)
+}
+
+/// Keep native forwards healthy: point them at the container's current
+/// addresses if they changed and restart the daemon if it died. Returns
+/// whether the targets were refreshed.
+pub fn supervise(addresses: &ContainerAddresses) -> Result<bool> {
+    let state_file = workspace::proxy_state_file()?;
+    let mut forwards = load_state(&state_file);
+    if forwards.is_empty() {
+        return Ok(false);
+    }
+    let stale = forwards
+        .iter()
+        .any(|f| f.target != addresses.target(f.ipv6));
+    if stale {
+        for fwd in &mut forwards {
+            fwd.target = addresses.target(fwd.ipv6).to_string();
+        }
+        save_state(&state_file, &forwards)?;
+    }
+    ensure_daemon()?;
+    Ok(stale)
+}
+
+fn read_pid(path: &Path) -> Option<i32> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|s| s.trim().parse().ok())
+}
+
+fn load_state(path: &Path) -> Vec<ProxyForward> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_state(path: &Path, forwards: &[ProxyForward]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context("failed to create runtime directory")?;
+    }
+    // Write atomically so the daemon never reads a partial file
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_string_pretty(forwards)?)
+        .context("failed to write proxy state")?;
+    fs::rename(&tmp, path).context("failed to write proxy state")?;
+    Ok(())
+}
+
+/// Spawn `dcw port proxy` in the background unless it is already running.
+fn ensure_daemon() -> Result<()> {
+    let pid_file = workspace::proxy_pid_file()?;
+    if read_pid(&pid_file).is_some_and(process::is_dcw_process) {
+        return Ok(());
+    }
+
+    let exe = std::env::current_exe().context("failed to get current executable path")?;
+    let child = Command::new(exe)
+        .args(["port", "proxy"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .logged()
+        .spawn()
+        .context("failed to spawn port proxy")?;
+
+    fs::write(&pid_file, child.id().to_string()).context("failed to write proxy PID file")?;
+    Ok(())
+}
+
+/// A served forward with its listener's stop flag and traffic counters.
+type Listener = (ProxyForward, Arc<AtomicBool>, Arc<Counters>);
+
+/// Run the proxy daemon: serve every forward in the workspace's state file,
+/// picking up changes as they are made, and exit once no forwards remain.
+pub fn run_daemon() -> Result<()> {
+    let state_file = workspace::proxy_state_file()?;
+    let pid_file = workspace::proxy_pid_file()?;
+    let stats_file = workspace::proxy_stats_file()?;
+    let mut running: HashMap<(u16, Protocol), Listener> = HashMap::new();
+    let mut failed: Vec<ProxyForward> = Vec::new();
+    let mut activity: HashMap<(u16, Protocol), Activity> = HashMap::new();
+
+    loop {
+        let desired = load_state(&state_file);
+        if desired.is_empty() {
+            for (_, stop, _) in running.values() {
+                stop.store(true, Ordering::Relaxed);
+            }
+            let _ = fs::remove_file(&stats_file);
+            let _ = fs::remove_file(&pid_file);
+            return Ok(());
+        }
+
+        let mut stopped_any = false;
+        running.retain(|_, (fwd, stop, _)| {
+            let keep = desired.contains(fwd);
+            if !keep {
+                stop.store(true, Ordering::Relaxed);
+                stopped_any = true;
+            }
+            keep
+        });
+        failed.retain(|fwd| desired.contains(fwd));
+        activity.retain(|key, _| running.contains_key(key));
+        if stopped_any {
+            // Let stopped listeners release their ports before rebinding
+            thread::sleep(POLL_INTERVAL * 3);
+        }
+
+        for fwd in desired {
+            if running.contains_key(&fwd.key()) || failed.contains(&fwd) {
+                continue;
+            }
+            let stop = Arc::new(AtomicBool::new(false));
+            let counters = Arc::new(Counters::default());
+            match spawn_listener(&fwd, stop.clone(), counters.clone()) {
+                Ok(_) => {
+                    activity.insert(fwd.key(), Activity::new(Instant::now()));
+                    running.insert(fwd.key(), (fwd, stop, counters));
+                }
+                Err(e) => {
+                    warn!("failed to forward port {}: {e}", fwd.host_port);
+                    failed.push(fwd);
+                }
+            }
+        }
+
+        let stats: Vec<ForwardStats> = running
+            .values()
+            .map(|(fwd, _, counters)| ForwardStats::snapshot(fwd, counters))
+            .collect();
+        if let Err(e) = save_stats(&stats_file, &stats) {
+            warn!("{e:#}");
+        }
+
+        let now = Instant::now();
+        let idle: Vec<(u16, Protocol)> = stats
+            .iter()
+            .filter_map(|s| {
+                let key = (s.container_port, s.protocol);
+                let timeout = running.get(&key)?.0.idle_timeout?;
+                let idle_for = activity.get_mut(&key)?.idle_for(s, now);
+                (idle_for >= Duration::from_secs(timeout)).then_some(key)
+            })
+            .collect();
+        if !idle.is_empty() {
+            // The next pass stops their listeners like any removed forward
+            for (port, protocol) in &idle {
+                info!(
+                    "Port {port}/{} saw no traffic for its idle timeout, removing its forward.",
+                    protocol.as_str()
+                );
+            }
+            let mut forwards = load_state(&state_file);
+            forwards.retain(|f| !idle.contains(&f.key()));
+            if let Err(e) = save_state(&state_file, &forwards) {
+                warn!("{e:#}");
+            }
+        }
+
+        thread::sleep(RELOAD_INTERVAL);
+    }
+}
+
+fn save_stats(path: &Path, stats: &[ForwardStats]) -> Result<()> {
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_string(stats)?).context("failed to write proxy stats")?;
+    fs::rename(&tmp, path).context("failed to write proxy stats")?;
+    Ok(())
+}
+
+/// Bind the host side of a forward and serve it on background threads, one
+/// per bind address, until `stop` is set.
+fn spawn_listener(
+    fwd: &ProxyForward,
+    stop: Arc<AtomicBool>,
+    counters: Arc<Counters>,
+) -> Result<Vec<thread::JoinHandle<()>>> {
+    let target = resolve(&fwd.target, fwd.container_port)?;
+    match fwd.protocol {
+        Protocol::Tcp => {
+            let mut options = TcpOptions::default();
+            if fwd.log_http {
+                let path = workspace::http_log_file(fwd.host_port)?;
+                options.log = Some(Arc::new(AccessLog::open(&path)?));
+            }
+            if fwd.tls {
+                options.tls = Some(tls::server_config(&tls::cert_dir()?)?);
+            }
+            options.rate = fwd.rate_limit.map(RateLimits::new);
+            options.max_connections = fwd.max_connections;
+            // Bind every address before serving any, so a failure leaves
+            // nothing behind
+            let listeners = docker::bind_addresses(&fwd.bind)
+                .into_iter()
+                .map(|bind| {
+                    let listener = TcpListener::bind((bind, fwd.host_port))
+                        .with_context(|| format!("failed to bind {bind}:{}", fwd.host_port))?;
+                    listener.set_nonblocking(true)?;
+                    Ok(listener)
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Ok(listeners
+                .into_iter()
+                .map(|listener| {
+                    let (stop, counters, options) =
+                        (stop.clone(), counters.clone(), options.clone());
+                    thread::spawn(move || serve_tcp(listener, target, &stop, counters, options))
+                })
+                .collect())
+        }
+        Protocol::Udp => {
+            let sockets = docker::bind_addresses(&fwd.bind)
+                .into_iter()
+                .map(|bind| {
+                    let socket = UdpSocket::bind((bind, fwd.host_port))
+                        .with_context(|| format!("failed to bind {bind}:{}/udp", fwd.host_port))?;
+                    socket.set_read_timeout(Some(POLL_INTERVAL))?;
+                    Ok(socket)
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let rate = fwd.rate_limit.map(RateLimits::new);
+            let max_clients = fwd.max_connections.unwrap_or(u32::MAX);
+            Ok(sockets
+                .into_iter()
+                .map(|socket| {
+                    let (stop, counters, rate) = (stop.clone(), counters.clone(), rate.clone());
+                    thread::spawn(move || {
+                        serve_udp(socket, target, &stop, counters, rate, max_clients)
+                    })
+                })
+                .collect())
+        }
+    }
+}
+
+fn resolve(host: &str, port: u16) -> Result<SocketAddr> {
+    (host, port)
+        .to_socket_addrs()?
+        .next()
+        .with_context(|| format!("could not resolve {host}"))
+}
+
+/// What a TCP listener does besides relaying its connections.
+#[derive(Clone, Default)]
+struct TcpOptions {
+    /// Access log of a `--log-http` forward
+    log: Option<Arc<AccessLog>>,
+    /// Certificate of a `--tls` forward, whose clients connect with TLS
+    tls: Option<Arc<ServerConfig>>,
+    rate: Option<Arc<RateLimits>>,
+    max_connections: Option<u32>,
+    /// Connections being served, across the forward's listeners
+    open: Arc<AtomicU32>,
+}
+
+fn serve_tcp(
+    listener: TcpListener,
+    target: SocketAddr,
+    stop: &AtomicBool,
+    counters: Arc<Counters>,
+    options: TcpOptions,
+) {
+    while !stop.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((client, peer)) => {
+                let max = options.max_connections.unwrap_or(u32::MAX);
+                let admitted = options
+                    .open
+                    .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| {
+                        (n < max).then_some(n + 1)
+                    })
+                    .is_ok();
+                if !admitted {
+                    debug!("turning away {peer}: {max} connection(s) already open");
+                    continue;
+                }
+                let counters = counters.clone();
+                let exchange = options.log.clone().map(Exchange::new);
+                let tls = options.tls.clone();
+                let rate = options.rate.clone();
+                let open = options.open.clone();
+                thread::spawn(move || {
+                    let result = match tls {
+                        Some(config) => pipe_tls(client, target, &counters, exchange, config, rate),
+                        None => pipe_tcp(client, target, &counters, exchange, rate),
+                    };
+                    if let Err(e) = result {
+                        warn!("connection to {target} failed: {e}");
+                    }
+                    open.fetch_sub(1, Ordering::Relaxed);
+                });
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+            Err(e) => {
+                warn!("accept failed: {e}");
+                thread::sleep(POLL_INTERVAL);
+            }
+        }
+    }
+}
+
+/// Copy data in both directions between a client and the container,
+/// logging the HTTP requests seen if `exchange` is given.
+fn pipe_tcp(
+    client: TcpStream,
+    target: SocketAddr,
+    counters: &Arc<Counters>,
+    exchange: Option<Arc<Exchange>>,
+    rate: Option<Arc<RateLimits>>,
+) -> io::Result<()> {
+    client.set_nonblocking(false)?;
+    let upstream = TcpStream::connect_timeout(&target, CONNECT_TIMEOUT)?;
+    counters.connections.fetch_add(1, Ordering::Relaxed);
+
+    let mut client_read = client.try_clone()?;
+    let mut upstream_write = upstream.try_clone()?;
+    let inbound = counters.clone();
+    let mut requests = exchange.as_ref().map(Exchange::requests);
+    let inbound_rate = rate.clone();
+    let outbound = thread::spawn(move || {
+        let _ = copy_counted(
+            &mut client_read,
+            &mut upstream_write,
+            &inbound.bytes_in,
+            requests.as_mut(),
+            inbound_rate.as_ref().map(|r| &r.inbound),
+        );
+        let _ = upstream_write.shutdown(Shutdown::Write);
+    });
+
+    let (mut upstream_read, mut client_write) = (upstream, client);
+    let mut responses = exchange.as_ref().map(Exchange::responses);
+    let _ = copy_counted(
+        &mut upstream_read,
+        &mut client_write,
+        &counters.bytes_out,
+        responses.as_mut(),
+        rate.as_ref().map(|r| &r.outbound),
+    );
+    let _ = client_write.shutdown(Shutdown::Write);
+    let _ = outbound.join();
+    counters.connections.fetch_sub(1, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Like [`pipe_tcp`], but the client speaks TLS, which is terminated here.
+/// A TLS connection can't be split between threads, so one thread serves
+/// both directions.
+fn pipe_tls(
+    mut client: TcpStream,
+    target: SocketAddr,
+    counters: &Counters,
+    exchange: Option<Arc<Exchange>>,
+    config: Arc<ServerConfig>,
+    rate: Option<Arc<RateLimits>>,
+) -> io::Result<()> {
+    client.set_nonblocking(false)?;
+    let mut tls = ServerConnection::new(config).map_err(io::Error::other)?;
+    // Browsers routinely abort handshakes with certificates they don't
+    // trust, so that's not worth a warning
+    if let Err(e) = tls.complete_io(&mut client) {
+        debug!("TLS handshake failed: {e}");
+        return Ok(());
+    }
+    let mut upstream = TcpStream::connect_timeout(&target, CONNECT_TIMEOUT)?;
+    counters.connections.fetch_add(1, Ordering::Relaxed);
+    let result = relay_tls(
+        &mut client,
+        &mut upstream,
+        &mut tls,
+        counters,
+        exchange,
+        rate.as_deref(),
+    );
+    counters.connections.fetch_sub(1, Ordering::Relaxed);
+    result
+}
+
+/// Relay between a TLS client and the plain upstream until the upstream
+/// closes.
+fn relay_tls(
+    client: &mut TcpStream,
+    upstream: &mut TcpStream,
+    tls: &mut ServerConnection,
+    counters: &Counters,
+    exchange: Option<Arc<Exchange>>,
+    rate: Option<&RateLimits>,
+) -> io::Result<()> {
+    let mut requests = exchange.as_ref().map(Exchange::requests);
+    let mut responses = exchange.as_ref().map(Exchange::responses);
+    let mut buf = [0u8; 16 * 1024];
+    let mut client_open = true;
+
+    loop {
+        // Plaintext may already be buffered, e.g. sent along with the end
+        // of the handshake
+        let state = tls.process_new_packets().map_err(io::Error::other)?;
+        let mut pending = state.plaintext_bytes_to_read();
+        let closed = state.peer_has_closed();
+        while pending > 0 {
+            let chunk = rate.map_or(buf.len(), |r| r.inbound.chunk());
+            let len = pending.min(chunk);
+            let n = tls.reader().read(&mut buf[..len])?;
+            if let Some(requests) = requests.as_mut() {
+                requests.observe(&buf[..n]);
+            }
+            if let Some(rate) = rate {
+                rate.inbound.take(n);
+            }
+            upstream.write_all(&buf[..n])?;
+            counters.bytes_in.fetch_add(n as u64, Ordering::Relaxed);
+            pending -= n;
+        }
+        if client_open && closed {
+            client_open = false;
+            let _ = upstream.shutdown(Shutdown::Write);
+        }
+        while tls.wants_write() {
+            tls.write_tls(client)?;
+        }
+        let events = |open: bool| if open { libc::POLLIN } else { 0 };
+        let mut fds = [
+            libc::pollfd {
+                fd: client.as_raw_fd(),
+                events: events(client_open),
+                revents: 0,
+            },
+            libc::pollfd {
+                fd: upstream.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            },
+        ];
+        if unsafe { libc::poll(fds.as_mut_ptr(), 2, -1) } < 0 {
+            let e = io::Error::last_os_error();
+            if e.kind() == ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(e);
+        }
+        let ready =
+            |fd: &libc::pollfd| fd.revents & (libc::POLLIN | libc::POLLHUP | libc::POLLERR) != 0;
+
+        // Decrypted at the top of the next pass
+        if client_open && ready(&fds[0]) && tls.read_tls(client)? == 0 {
+            client_open = false;
+            let _ = upstream.shutdown(Shutdown::Write);
+        }
+
+        if ready(&fds[1]) {
+            let chunk = rate.map_or(buf.len(), |r| r.outbound.chunk());
+            let n = match upstream.read(&mut buf[..chunk]) {
+                Ok(n) => n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            if n == 0 {
+                tls.send_close_notify();
+                while tls.wants_write() {
+                    tls.write_tls(client)?;
+                }
+                let _ = client.shutdown(Shutdown::Write);
+                return Ok(());
+            }
+            if let Some(responses) = responses.as_mut() {
+                responses.observe(&buf[..n]);
+            }
+            if let Some(rate) = rate {
+                rate.outbound.take(n);
+            }
+            // rustls buffers a limited amount of plaintext, so flush as we go
+            let mut data = &buf[..n];
+            while !data.is_empty() {
+                let written = tls.writer().write(data)?;
+                data = &data[written..];
+                while tls.wants_write() {
+                    tls.write_tls(client)?;
+                }
+            }
+            counters.bytes_out.fetch_add(n as u64, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Like `io::copy`, adding each chunk written to `count`, showing it to
+/// `observer` and holding it back to `rate`.
+fn copy_counted(
+    reader: &mut impl Read,
+    writer: &mut impl Write,
+    count: &AtomicU64,
+    mut observer: Option<&mut Observer>,
+    rate: Option<&RateLimit>,
+) -> io::Result<()> {
+    let mut buf = [0u8; 16 * 1024];
+    let chunk = rate.map_or(buf.len(), RateLimit::chunk);
+    loop {
+        let n = match reader.read(&mut buf[..chunk]) {
+            Ok(0) => return Ok(()),
+            Ok(n) => n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        if let Some(observer) = observer.as_mut() {
+            observer.observe(&buf[..n]);
+        }
+        if let Some(rate) = rate {
+            rate.take(n);
+        }
+        writer.write_all(&buf[..n])?;
+        count.fetch_add(n as u64, Ordering::Relaxed);
+    }
+}
+
+/// Relay UDP datagrams. Each client address gets its own upstream socket so
+/// replies can be routed back to it; datagrams from clients beyond
+/// `max_clients` are dropped.
+fn serve_udp(
+    socket: UdpSocket,
+    target: SocketAddr,
+    stop: &AtomicBool,
+    counters: Arc<Counters>,
+    rate: Option<Arc<RateLimits>>,
+    max_clients: u32,
+) {
+    let clients: Arc<Mutex<HashMap<SocketAddr, UdpSocket>>> = Arc::default();
+    let mut buf = [0u8; 65535];
+
+    while !stop.load(Ordering::Relaxed) {
+        let (n, client) = match socket.recv_from(&mut buf) {
+            Ok(v) => v,
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => continue,
+            Err(e) => {
+                warn!("UDP receive failed: {e}");
+                continue;
+            }
+        };
+
+        if let Some(rate) = &rate {
+            rate.inbound.take(n);
+        }
+        let mut map = clients.lock().unwrap();
+        let upstream = match map.entry(client) {
+            Entry::Occupied(e) => e.into_mut(),
+            // Counted across the forward's sockets
+            Entry::Vacant(_)
+                if counters.connections.load(Ordering::Relaxed) >= u64::from(max_clients) =>
+            {
+                debug!("dropping UDP from {client}: {max_clients} client(s) already served");
+                continue;
+            }
+            Entry::Vacant(e) => {
+                let relay = UdpRelay {
+                    clients: clients.clone(),
+                    counters: counters.clone(),
+                    rate: rate.clone(),
+                };
+                match udp_upstream(&socket, client, target, relay) {
+                    Ok(upstream) => {
+                        counters.connections.fetch_add(1, Ordering::Relaxed);
+                        e.insert(upstream)
+                    }
+                    Err(e) => {
+                        warn!("failed to relay UDP for {client}: {e}");
+                        continue;
+                    }
+                }
+            }
+        };
+        match upstream.send(&buf[..n]) {
+            Ok(_) => {
+                counters.bytes_in.fetch_add(n as u64, Ordering::Relaxed);
+            }
+            Err(e) => warn!("failed to send UDP to {target}: {e}"),
+        }
+    }
+}
+
+/// What a UDP reply thread shares with its listener.
+struct UdpRelay {
+    clients: Arc<Mutex<HashMap<SocketAddr, UdpSocket>>>,
+    counters: Arc<Counters>,
+    rate: Option<Arc<RateLimits>>,
+}
+
+/// Create the upstream socket for a UDP client and a thread relaying replies
+/// back to it until it goes idle.
+fn udp_upstream(
+    socket: &UdpSocket,
+    client: SocketAddr,
+    target: SocketAddr,
+    relay: UdpRelay,
+) -> io::Result<UdpSocket> {
+    let local: SocketAddr = if target.is_ipv4() {
+        ([0, 0, 0, 0], 0).into()
+    } else {
+        ([0u16; 8], 0).into()
+    };
+    let upstream = UdpSocket::bind(local)?;
+    upstream.connect(target)?;
+    upstream.set_read_timeout(Some(UDP_IDLE_TIMEOUT))?;
+
+    let replies = upstream.try_clone()?;
+    let downstream = socket.try_clone()?;
+    thread::spawn(move || {
+        let UdpRelay {
+            clients,
+            counters,
+            rate,
+        } = relay;
+        let mut buf = [0u8; 65535];
+        while let Ok(n) = replies.recv(&mut buf) {
+            if let Some(rate) = &rate {
+                rate.outbound.take(n);
+            }
+            if downstream.send_to(&buf[..n], client).is_err() {
+                break;
+            }
+            counters.bytes_out.fetch_add(n as u64, Ordering::Relaxed);
+        }
+        if clients.lock().unwrap().remove(&client).is_some() {
+            counters.connections.fetch_sub(1, Ordering::Relaxed);
+        }
+    });
+    Ok(upstream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn forward(container_port: u16, protocol: Protocol) -> ProxyForward {
+        ProxyForward {
+            host_port: container_port,
+            container_port,
+            protocol,
+            bind: "127.0.0.1".to_string(),
+            target: "127.0.0.1".to_string(),
+            source: None,
+            label: None,
+            log_http: false,
+            tls: false,
+            ipv6: false,
+            rate_limit: None,
+            max_connections: None,
+            idle_timeout: None,
+        }
+    }
+
+    #[test]
+    fn state_round_trips() {
+        let dir = std::env::temp_dir().join(format!("dcw-proxy-test-{}", std::process::id()));
+        let path = dir.join("proxy.json");
+        let forwards = vec![forward(3000, Protocol::Tcp), forward(53, Protocol::Udp)];
+        save_state(&path, &forwards).unwrap();
+        assert_eq!(load_state(&path), forwards);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn missing_state_is_empty() {
+        assert!(load_state(Path::new("/nonexistent/proxy.json")).is_empty());
+    }
+
+    #[test]
+    fn key_distinguishes_protocols() {
+        assert_ne!(
+            forward(53, Protocol::Tcp).key(),
+            forward(53, Protocol::Udp).key()
+        );
+    }
+
+    #[test]
+    fn tcp_forward_relays_data() {
+        let echo = TcpListener::bind("127.0.0.1:0").unwrap();
+        let echo_port = echo.local_addr().unwrap().port();
+        thread::spawn(move || {
+            let (mut conn, _) = echo.accept().unwrap();
+            let mut buf = [0u8; 5];
+            conn.read_exact(&mut buf).unwrap();
+            conn.write_all(&buf).unwrap();
+        });
+
+        let host_port = docker::find_free_host_port("127.0.0.1", 20000, Protocol::Tcp).unwrap();
+        let fwd = ProxyForward {
+            host_port,
+            ..forward(echo_port, Protocol::Tcp)
+        };
+        let stop = Arc::new(AtomicBool::new(false));
+        let counters = Arc::new(Counters::default());
+        let handles = spawn_listener(&fwd, stop.clone(), counters.clone()).unwrap();
+
+        let mut client = TcpStream::connect(("127.0.0.1", host_port)).unwrap();
+        client.write_all(b"hello").unwrap();
+        let mut buf = [0u8; 5];
+        client.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"hello");
+
+        drop(client);
+        for _ in 0..50 {
+            if counters.connections.load(Ordering::Relaxed) == 0 {
+                break;
+            }
+            thread::sleep(POLL_INTERVAL / 10);
+        }
+        let stats = ForwardStats::snapshot(&fwd, &counters);
+        assert_eq!(
+            (stats.bytes_in, stats.bytes_out, stats.connections),
+            (5, 5, 0)
+        );
+
+        stop.store(true, Ordering::Relaxed);
+        for handle in handles {
+            handle.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn activity_tracks_idle_time() {
+        let start = Instant::now();
+        let mut activity = Activity::new(start);
+        let stats = |bytes_in, connections| ForwardStats {
+            container_port: 3000,
+            protocol: Protocol::Tcp,
+            bytes_in,
+            bytes_out: 0,
+            connections,
+        };
+        let at = |secs| start + Duration::from_secs(secs);
+        assert_eq!(
+            activity.idle_for(&stats(0, 0), at(5)),
+            Duration::from_secs(5)
+        );
+        // An open connection counts as activity even without data
+        assert_eq!(activity.idle_for(&stats(0, 1), at(10)), Duration::ZERO);
+        assert_eq!(
+            activity.idle_for(&stats(0, 0), at(12)),
+            Duration::from_secs(2)
+        );
+        assert_eq!(activity.idle_for(&stats(100, 0), at(20)), Duration::ZERO);
+        assert_eq!(
+            activity.idle_for(&stats(100, 0), at(50)),
+            Duration::from_secs(30)
+        );
+    }
+
+    #[test]
+    fn rate_limit_paces_chunks() {
+        let limit = RateLimit::new(1000);
+        assert_eq!(limit.chunk(), 100);
+        let start = Instant::now();
+        for _ in 0..4 {
+            limit.take(100);
+        }
+        // The first chunk is saved up, the other three take 0.1s each
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(280), "{elapsed:?}");
+        assert!(elapsed < Duration::from_secs(2), "{elapsed:?}");
+    }
+
+    #[test]
+    fn max_connections_turns_away_extra_clients() {
+        let echo = TcpListener::bind("127.0.0.1:0").unwrap();
+        let echo_port = echo.local_addr().unwrap().port();
+        thread::spawn(move || {
+            for conn in echo.incoming() {
+                let mut conn = conn.unwrap();
+                thread::spawn(move || {
+                    let mut buf = [0u8; 5];
+                    while conn.read_exact(&mut buf).is_ok() {
+                        conn.write_all(&buf).unwrap();
+                    }
+                });
+            }
+        });
+
+        let host_port = docker::find_free_host_port("127.0.0.1", 21000, Protocol::Tcp).unwrap();
+        let fwd = ProxyForward {
+            host_port,
+            max_connections: Some(1),
+            ..forward(echo_port, Protocol::Tcp)
+        };
+        let stop = Arc::new(AtomicBool::new(false));
+        let handles = spawn_listener(&fwd, stop.clone(), Arc::default()).unwrap();
+
+        let mut buf = [0u8; 5];
+        let mut first = TcpStream::connect(("127.0.0.1", host_port)).unwrap();
+        first.write_all(b"hello").unwrap();
+        first.read_exact(&mut buf).unwrap();
+
+        let mut second = TcpStream::connect(("127.0.0.1", host_port)).unwrap();
+        second
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let _ = second.write_all(b"hello");
+        assert!(matches!(second.read(&mut buf), Ok(0) | Err(_)));
+
+        first.write_all(b"again").unwrap();
+        first.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"again");
+
+        stop.store(true, Ordering::Relaxed);
+        for handle in handles {
+            handle.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn tls_forward_terminates_tls() {
+        use rustls::pki_types::pem::PemObject;
+        use rustls::pki_types::CertificateDer;
+
+        let dir = std::env::temp_dir().join(format!("dcw-proxy-tls-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        tls::generate_self_signed(&dir).unwrap();
+
+        let echo = TcpListener::bind("127.0.0.1:0").unwrap();
+        let target = echo.local_addr().unwrap();
+        thread::spawn(move || {
+            let (mut conn, _) = echo.accept().unwrap();
+            let mut buf = [0u8; 5];
+            conn.read_exact(&mut buf).unwrap();
+            conn.write_all(&buf).unwrap();
+        });
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        listener.set_nonblocking(true).unwrap();
+        let options = TcpOptions {
+            tls: Some(tls::server_config(&dir).unwrap()),
+            ..Default::default()
+        };
+        let stop = Arc::new(AtomicBool::new(false));
+        let counters = Arc::new(Counters::default());
+        let handle = {
+            let (stop, counters) = (stop.clone(), counters.clone());
+            thread::spawn(move || serve_tcp(listener, target, &stop, counters, options))
+        };
+
+        let mut roots = rustls::RootCertStore::empty();
+        for cert in CertificateDer::pem_file_iter(dir.join("cert.pem")).unwrap() {
+            roots.add(cert.unwrap()).unwrap();
+        }
+        let config = rustls::ClientConfig::builder_with_provider(Arc::new(
+            rustls::crypto::ring::default_provider(),
+        ))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+        let conn = rustls::ClientConnection::new(Arc::new(config), "localhost".try_into().unwrap())
+            .unwrap();
+        let mut client =
+            rustls::StreamOwned::new(conn, TcpStream::connect(("127.0.0.1", port)).unwrap());
+        client.write_all(b"hello").unwrap();
+        let mut buf = [0u8; 5];
+        client.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"hello");
+        assert_eq!(counters.bytes_in.load(Ordering::Relaxed), 5);
+
+        stop.store(true, Ordering::Relaxed);
+        handle.join().unwrap();
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
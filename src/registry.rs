@@ -0,0 +1,98 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Named workspaces registered with `dcw workspace add`, kept in
+/// `workspaces.toml` next to config.toml.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Registry {
+    #[serde(default)]
+    pub workspaces: BTreeMap<String, PathBuf>,
+}
+
+fn registry_path() -> Result<PathBuf> {
+    Ok(dirs::config_dir()
+        .context("failed to determine config directory")?
+        .join("dcw")
+        .join("workspaces.toml"))
+}
+
+impl Registry {
+    pub fn load() -> Result<Registry> {
+        Self::load_from(&registry_path()?)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        self.save_to(&registry_path()?)
+    }
+
+    fn load_from(path: &Path) -> Result<Registry> {
+        let Ok(contents) = fs::read_to_string(path) else {
+            return Ok(Registry::default());
+        };
+        toml::from_str(&contents).with_context(|| format!("failed to parse {}", path.display()))
+    }
+
+    fn save_to(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context("failed to create config directory")?;
+        }
+        fs::write(path, toml::to_string(self)?)
+            .with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    /// The folder for `-w`/`--workspace`: a registered name, else a path to
+    /// a directory.
+    pub fn resolve(&self, name_or_path: &str) -> Result<PathBuf> {
+        if let Some(path) = self.workspaces.get(name_or_path) {
+            return Ok(path.clone());
+        }
+        let path = Path::new(name_or_path);
+        if path.is_dir() {
+            return std::path::absolute(path).context("failed to resolve workspace path");
+        }
+        bail!("unknown workspace '{name_or_path}' (see `dcw workspace list`)");
+    }
+}
+
+/// Switch to the workspace named by `-w`/`--workspace`, so that everything
+/// keyed off the current directory applies to it.
+pub fn enter(name_or_path: &str) -> Result<()> {
+    let folder = Registry::load()?.resolve(name_or_path)?;
+    std::env::set_current_dir(&folder)
+        .with_context(|| format!("failed to enter workspace {}", folder.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registry_round_trip_and_resolve() {
+        let dir = std::env::temp_dir().join(format!("dcw-test-registry-{}", std::process::id()));
+        let path = dir.join("workspaces.toml");
+        assert_eq!(Registry::load_from(&path).unwrap(), Registry::default());
+
+        let mut registry = Registry::default();
+        registry
+            .workspaces
+            .insert("api".to_string(), PathBuf::from("/home/me/src/api"));
+        registry.save_to(&path).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "[workspaces]\napi = \"/home/me/src/api\"\n"
+        );
+        let loaded = Registry::load_from(&path).unwrap();
+        assert_eq!(loaded, registry);
+
+        assert_eq!(
+            loaded.resolve("api").unwrap(),
+            PathBuf::from("/home/me/src/api")
+        );
+        assert_eq!(loaded.resolve(dir.to_str().unwrap()).unwrap(), dir);
+        assert!(loaded.resolve("web").is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,138 @@
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+use std::env;
+use std::process::Command;
+
+use crate::logging::Logged;
+
+/// A secret kept out of the config, referred to by a `remoteEnv` value in
+/// `devcontainer.local.json`.
+#[derive(Debug, PartialEq)]
+pub enum SecretRef<'a> {
+    /// `op://vault/item/field`, read with the 1Password CLI
+    OnePassword(&'a str),
+    /// `pass:path/to/entry`, the first line of a password-store entry
+    Pass(&'a str),
+    /// `env:NAME`, a variable in dcw's own environment
+    Env(&'a str),
+}
+
+impl SecretRef<'_> {
+    /// The reference in `value`, if it is one.
+    pub fn parse(value: &str) -> Option<SecretRef<'_>> {
+        let reference = if value.starts_with("op://") {
+            SecretRef::OnePassword(value)
+        } else if let Some(path) = value.strip_prefix("pass:") {
+            SecretRef::Pass(path)
+        } else if let Some(name) = value.strip_prefix("env:") {
+            SecretRef::Env(name)
+        } else {
+            return None;
+        };
+        let target = match reference {
+            SecretRef::OnePassword(r) => r.trim_start_matches("op://"),
+            SecretRef::Pass(r) | SecretRef::Env(r) => r,
+        };
+        (!target.is_empty()).then_some(reference)
+    }
+
+    /// Look the secret up with its provider.
+    pub fn resolve(&self) -> Result<String> {
+        match self {
+            SecretRef::OnePassword(reference) => {
+                let mut command = Command::new("op");
+                command.args(["read", "--no-newline", reference]);
+                run_provider(&mut command, "1Password CLI (op)")
+            }
+            SecretRef::Pass(path) => {
+                let mut command = Command::new("pass");
+                command.args(["show", path]);
+                let entry = run_provider(&mut command, "pass")?;
+                // The password is the first line; the rest is metadata
+                Ok(entry.lines().next().unwrap_or_default().to_string())
+            }
+            SecretRef::Env(name) => {
+                env::var(name).with_context(|| format!("environment variable {name} is not set"))
+            }
+        }
+    }
+}
+
+fn run_provider(command: &mut Command, provider: &str) -> Result<String> {
+    let output = command
+        .logged()
+        .output()
+        .with_context(|| format!("failed to run {provider} — is it installed?"))?;
+    if !output.status.success() {
+        bail!(
+            "{provider} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    String::from_utf8(output.stdout).with_context(|| format!("{provider} returned invalid UTF-8"))
+}
+
+/// Replace secret references among the `remoteEnv` values of `config` by
+/// the secrets. Returns whether there were any.
+pub fn resolve_remote_env(config: &mut Value) -> Result<bool> {
+    let Some(env) = config.get_mut("remoteEnv").and_then(Value::as_object_mut) else {
+        return Ok(false);
+    };
+    let mut resolved = false;
+    for (name, value) in env.iter_mut() {
+        let Some(reference) = value.as_str().and_then(SecretRef::parse) else {
+            continue;
+        };
+        let secret = reference
+            .resolve()
+            .with_context(|| format!("failed to resolve remoteEnv.{name}"))?;
+        *value = Value::String(secret);
+        resolved = true;
+    }
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parse_secret_references() {
+        assert_eq!(
+            SecretRef::parse("op://dev/db/password"),
+            Some(SecretRef::OnePassword("op://dev/db/password"))
+        );
+        assert_eq!(
+            SecretRef::parse("pass:work/npm"),
+            Some(SecretRef::Pass("work/npm"))
+        );
+        assert_eq!(
+            SecretRef::parse("env:NPM_TOKEN"),
+            Some(SecretRef::Env("NPM_TOKEN"))
+        );
+        assert_eq!(SecretRef::parse("env:"), None);
+        assert_eq!(SecretRef::parse("op://"), None);
+        assert_eq!(SecretRef::parse("postgres://localhost"), None);
+    }
+
+    #[test]
+    fn remote_env_references_are_replaced() {
+        let mut config = json!({
+            "remoteEnv": {
+                "HOME_DIR": "env:HOME",
+                "EDITOR": "vim",
+                "UNSET": null
+            }
+        });
+        assert!(resolve_remote_env(&mut config).unwrap());
+        assert_eq!(config["remoteEnv"]["HOME_DIR"], env::var("HOME").unwrap());
+        assert_eq!(config["remoteEnv"]["EDITOR"], "vim");
+        assert_eq!(config["remoteEnv"]["UNSET"], Value::Null);
+
+        let mut config = json!({"remoteEnv": {"TOKEN": "env:DCW_TEST_SURELY_UNSET"}});
+        let err = resolve_remote_env(&mut config).unwrap_err();
+        assert!(format!("{err:#}").contains("remoteEnv.TOKEN"));
+        assert!(!resolve_remote_env(&mut json!({"remoteEnv": {"A": "b"}})).unwrap());
+    }
+}
@@ -1,14 +1,18 @@
 use std::sync::OnceLock;
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
 
 static SETTINGS: OnceLock<Settings> = OnceLock::new();
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 #[serde(default)]
 pub struct Settings {
     pub docker: DockerSettings,
     pub relay: RelaySettings,
+    pub forward: ForwardSettings,
+    pub watch: WatchSettings,
+    pub up: UpSettings,
 }
 
 #[derive(Debug, Deserialize)]
@@ -16,6 +20,20 @@ pub struct Settings {
 pub struct DockerSettings {
     pub path: String,
     pub compose_path: String,
+    /// Query the Engine API over its unix socket instead of spawning the CLI
+    /// for inspect/list calls
+    pub api: bool,
+    /// Container runtime whose executables `path` and `compose_path` default to
+    pub runtime: Runtime,
+}
+
+/// Preferred container runtime.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Runtime {
+    #[default]
+    Docker,
+    Podman,
 }
 
 #[derive(Debug, Deserialize)]
@@ -31,11 +49,80 @@ pub struct RelayFeature {
     pub enabled: bool,
 }
 
-impl Default for Settings {
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ForwardSettings {
+    pub backend: ForwardBackend,
+    /// Default host address port forwards bind to, unless the workspace sets
+    /// `customizations.dcw.bindAddress`
+    pub bind_address: Option<String>,
+    /// Image for socat sidecars instead of `alpine/socat`, e.g. from a
+    /// private registry
+    pub sidecar_image: Option<String>,
+    /// Also listen on `::1` for forwards bound to `127.0.0.1`
+    pub dual_stack: bool,
+}
+
+/// User defaults for `dcw port watch`; `customizations.dcw.watch` and
+/// command-line flags take precedence.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct WatchSettings {
+    /// Show a desktop notification when the watcher adds or removes a forward
+    pub notify: bool,
+    /// Seconds between checks of the container
+    pub interval: Option<u64>,
+    /// Minimum port number to forward
+    pub min_port: Option<u16>,
+    /// Ports never to forward, in addition to the workspace's
+    pub exclude: Vec<u16>,
+    /// Seconds a port must stay closed before its forward is removed
+    pub remove_delay: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct UpSettings {
+    /// Forward `forwardPorts` after `dcw up` unless `--auto-forward` says otherwise
+    pub auto_forward: bool,
+    /// Forward the host gpg-agent into the container unless `--gpg-agent`
+    /// says otherwise
+    pub gpg_agent: bool,
+    /// Map `host.docker.internal` to the host unless `--host-gateway` says
+    /// otherwise
+    pub host_gateway: bool,
+}
+
+/// How port forwards are implemented.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ForwardBackend {
+    /// In-process proxy connecting straight to the container IP
+    Native,
+    /// One `alpine/socat` container per forward
+    Sidecar,
+    /// One `alpine/socat` container per workspace serving every forward
+    Shared,
+}
+
+impl ForwardBackend {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ForwardBackend::Native => "native",
+            ForwardBackend::Sidecar => "sidecar",
+            ForwardBackend::Shared => "shared",
+        }
+    }
+}
+
+impl Default for ForwardBackend {
+    /// Container IPs are only routable from the host on Linux; Docker Desktop
+    /// and similar VMs need the sidecar backend.
     fn default() -> Self {
-        Self {
-            docker: DockerSettings::default(),
-            relay: RelaySettings::default(),
+        if cfg!(target_os = "linux") {
+            Self::Native
+        } else {
+            Self::Sidecar
         }
     }
 }
@@ -45,6 +132,18 @@ impl Default for DockerSettings {
         Self {
             path: "docker".to_string(),
             compose_path: "docker-compose".to_string(),
+            api: true,
+            runtime: Runtime::Docker,
+        }
+    }
+}
+
+impl Default for UpSettings {
+    fn default() -> Self {
+        Self {
+            auto_forward: true,
+            gpg_agent: false,
+            host_gateway: false,
         }
     }
 }
@@ -67,12 +166,13 @@ impl Default for RelayFeature {
 impl Settings {
     /// Get the global settings instance. Loads from config file on first access.
     pub fn get() -> &'static Settings {
-        SETTINGS.get_or_init(|| Self::load())
+        SETTINGS.get_or_init(Self::load)
     }
 
     /// Load settings from config.toml with environment variable overrides.
     fn load() -> Settings {
         let mut settings = Self::load_from_file();
+        Self::apply_runtime(&mut settings);
         Self::apply_env_overrides(&mut settings);
         settings
     }
@@ -91,12 +191,25 @@ impl Settings {
         match toml::from_str(&contents) {
             Ok(settings) => settings,
             Err(e) => {
-                eprintln!("Warning: failed to parse {}: {e}", config_path.display());
+                warn!("failed to parse {}: {e}", config_path.display());
                 Settings::default()
             }
         }
     }
 
+    /// Point executables left at their docker defaults at the preferred runtime.
+    fn apply_runtime(settings: &mut Settings) {
+        let docker = &mut settings.docker;
+        if docker.runtime == Runtime::Podman {
+            if docker.path == "docker" {
+                docker.path = "podman".to_string();
+            }
+            if docker.compose_path == "docker-compose" {
+                docker.compose_path = "podman-compose".to_string();
+            }
+        }
+    }
+
     /// Apply environment variable overrides (highest priority).
     fn apply_env_overrides(settings: &mut Settings) {
         if let Ok(val) = std::env::var("DCW_DOCKER_PATH") {
@@ -105,6 +218,9 @@ impl Settings {
         if let Ok(val) = std::env::var("DCW_DOCKER_COMPOSE_PATH") {
             settings.docker.compose_path = val;
         }
+        if let Ok(val) = std::env::var("DCW_SIDECAR_IMAGE") {
+            settings.forward.sidecar_image = Some(val);
+        }
     }
 
     /// Parse settings from a TOML string. For testing.
@@ -167,6 +283,80 @@ enabled = false
         assert!(!s.relay.cmux.enabled);
     }
 
+    #[test]
+    fn parse_forward_backend() {
+        let toml = r#"
+[forward]
+backend = "sidecar"
+"#;
+        let s = Settings::from_toml(toml).unwrap();
+        assert_eq!(s.forward.backend, ForwardBackend::Sidecar);
+
+        let s = Settings::from_toml("[forward]\nbackend = \"shared\"").unwrap();
+        assert_eq!(s.forward.backend, ForwardBackend::Shared);
+    }
+
+    #[test]
+    fn parse_watch_notify() {
+        assert!(!Settings::default().watch.notify);
+        let s = Settings::from_toml("[watch]\nnotify = true").unwrap();
+        assert!(s.watch.notify);
+    }
+
+    #[test]
+    fn parse_user_defaults() {
+        let s = Settings::default();
+        assert!(s.up.auto_forward);
+        assert!(!s.up.gpg_agent);
+        assert!(!s.up.host_gateway);
+        assert!(s.forward.bind_address.is_none());
+        assert!(s.forward.sidecar_image.is_none());
+        assert!(!s.forward.dual_stack);
+
+        let toml = r#"
+[forward]
+bind_address = "0.0.0.0"
+sidecar_image = "registry.internal/mirror/socat:1.8"
+dual_stack = true
+
+[watch]
+interval = 5
+exclude = [5432, 6379]
+
+[up]
+auto_forward = false
+gpg_agent = true
+host_gateway = true
+"#;
+        let s = Settings::from_toml(toml).unwrap();
+        assert_eq!(s.forward.bind_address.as_deref(), Some("0.0.0.0"));
+        assert_eq!(
+            s.forward.sidecar_image.as_deref(),
+            Some("registry.internal/mirror/socat:1.8")
+        );
+        assert!(s.forward.dual_stack);
+        assert_eq!(s.watch.interval, Some(5));
+        assert_eq!(s.watch.exclude, vec![5432, 6379]);
+        assert!(!s.up.auto_forward);
+        assert!(s.up.gpg_agent);
+        assert!(s.up.host_gateway);
+    }
+
+    #[test]
+    fn runtime_podman_sets_default_paths() {
+        let mut s = Settings::from_toml("[docker]\nruntime = \"podman\"").unwrap();
+        Settings::apply_runtime(&mut s);
+        assert_eq!(s.docker.path, "podman");
+        assert_eq!(s.docker.compose_path, "podman-compose");
+
+        // Explicit paths win
+        let toml = "[docker]\nruntime = \"podman\"\npath = \"/opt/podman\"";
+        let mut s = Settings::from_toml(toml).unwrap();
+        Settings::apply_runtime(&mut s);
+        assert_eq!(s.docker.path, "/opt/podman");
+        assert_eq!(s.docker.compose_path, "podman-compose");
+    }
+
     #[test]
     fn parse_partial_settings() {
         let toml = r#"
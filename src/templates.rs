@@ -0,0 +1,428 @@
+use anyhow::{bail, Context, Result};
+use reqwest::blocking::{Client, Response};
+use reqwest::header::{ACCEPT, WWW_AUTHENTICATE};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+
+use crate::commands::update::CURRENT_VERSION;
+
+/// Where a template given by ID alone, e.g. `go`, is looked up.
+pub const OFFICIAL_COLLECTION: &str = "ghcr.io/devcontainers/templates";
+
+const MANIFEST_MEDIA_TYPE: &str = "application/vnd.oci.image.manifest.v1+json";
+
+/// Files describing a template that aren't copied into the workspace.
+const METADATA_FILES: [&str; 3] = ["devcontainer-template.json", "README.md", "NOTES.md"];
+
+/// A template as described by its `devcontainer-template.json`, or listed
+/// in a collection's `devcontainer-collection.json`.
+#[derive(Debug, Deserialize)]
+pub struct TemplateInfo {
+    pub id: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub options: BTreeMap<String, TemplateOption>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TemplateOption {
+    /// `string` or `boolean`
+    #[serde(rename = "type", default)]
+    pub kind: String,
+    #[serde(default)]
+    pub description: String,
+    /// Suggested values; others are allowed
+    #[serde(default)]
+    pub proposals: Vec<String>,
+    /// The only values allowed, if limited
+    #[serde(rename = "enum")]
+    pub allowed: Option<Vec<String>>,
+    /// A string, or a bool for a boolean option
+    pub default: Option<serde_json::Value>,
+}
+
+impl TemplateOption {
+    /// The default as it is substituted into the template's files.
+    pub fn default_value(&self) -> String {
+        match &self.default {
+            Some(serde_json::Value::String(value)) => value.clone(),
+            Some(value) => value.to_string(),
+            None => String::new(),
+        }
+    }
+
+    /// Values to offer when asking for this option.
+    pub fn choices(&self) -> Vec<String> {
+        if self.kind == "boolean" {
+            return vec!["true".to_string(), "false".to_string()];
+        }
+        self.allowed
+            .clone()
+            .unwrap_or_else(|| self.proposals.clone())
+    }
+
+    pub fn check(&self, name: &str, value: &str) -> Result<()> {
+        if self.kind == "boolean" && value != "true" && value != "false" {
+            bail!("option {name} must be true or false");
+        }
+        if let Some(allowed) = &self.allowed {
+            if !allowed.iter().any(|a| a == value) {
+                bail!("option {name} must be one of {}", allowed.join(", "));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Where an OCI artifact is published, e.g.
+/// `ghcr.io/devcontainers/templates/go:latest`.
+#[derive(Debug, PartialEq)]
+pub struct Reference {
+    pub registry: String,
+    pub repository: String,
+    /// A tag, or a `sha256:` digest
+    pub tag: String,
+}
+
+impl Reference {
+    /// A template ID from the official collection (`go`), or a full
+    /// reference (`ghcr.io/owner/templates/app:1`, tag defaulting to
+    /// `latest`).
+    pub fn parse(s: &str) -> Result<Reference> {
+        let full = if s.contains('/') {
+            s.to_string()
+        } else {
+            format!("{OFFICIAL_COLLECTION}/{s}")
+        };
+        let (registry, rest) = full
+            .split_once('/')
+            .with_context(|| format!("invalid template reference: {s}"))?;
+        let (repository, tag) = match rest.split_once('@') {
+            Some((repository, digest)) => (repository, digest),
+            None => match rest.rsplit_once(':') {
+                Some((repository, tag)) if !tag.contains('/') => (repository, tag),
+                _ => (rest, "latest"),
+            },
+        };
+        if registry.is_empty() || repository.is_empty() || tag.is_empty() {
+            bail!("invalid template reference: {s}");
+        }
+        Ok(Reference {
+            registry: registry.to_string(),
+            repository: repository.to_string(),
+            tag: tag.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Reference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let separator = if self.tag.starts_with("sha256:") {
+            '@'
+        } else {
+            ':'
+        };
+        write!(
+            f,
+            "{}/{}{separator}{}",
+            self.registry, self.repository, self.tag
+        )
+    }
+}
+
+/// A downloaded template.
+pub struct Template {
+    pub info: TemplateInfo,
+    /// Paths relative to the workspace folder, and their contents
+    files: Vec<(PathBuf, Vec<u8>)>,
+}
+
+impl Template {
+    /// The template's files with every `${templateOption:<name>}` replaced
+    /// by the option's value.
+    pub fn render(&self, values: &HashMap<String, String>) -> Vec<(PathBuf, Vec<u8>)> {
+        self.files
+            .iter()
+            .map(|(path, contents)| {
+                let contents = match std::str::from_utf8(contents) {
+                    Ok(text) => substitute(text, values).into_bytes(),
+                    Err(_) => contents.clone(),
+                };
+                (path.clone(), contents)
+            })
+            .collect()
+    }
+}
+
+fn substitute(text: &str, values: &HashMap<String, String>) -> String {
+    let mut text = text.to_string();
+    for (name, value) in values {
+        text = text.replace(&format!("${{templateOption:{name}}}"), value);
+    }
+    text
+}
+
+/// The templates in the official collection.
+pub fn list_official() -> Result<Vec<TemplateInfo>> {
+    #[derive(Deserialize)]
+    struct Collection {
+        templates: Vec<TemplateInfo>,
+    }
+
+    let reference = Reference::parse(&format!("{OFFICIAL_COLLECTION}:latest"))?;
+    let blob = fetch_layer(&http_client()?, &reference)?;
+    let collection: Collection =
+        serde_json::from_slice(&blob).context("invalid devcontainer-collection.json")?;
+    Ok(collection.templates)
+}
+
+/// Download the template published at `reference`.
+pub fn fetch(reference: &Reference) -> Result<Template> {
+    let blob = fetch_layer(&http_client()?, reference)
+        .with_context(|| format!("failed to fetch template {reference}"))?;
+    unpack(&blob)
+}
+
+fn http_client() -> Result<Client> {
+    Client::builder()
+        .user_agent(format!("dcw/{CURRENT_VERSION}"))
+        .build()
+        .context("failed to create HTTP client")
+}
+
+/// The only layer of an artifact: a template's tarball, or a collection's
+/// metadata.
+fn fetch_layer(client: &Client, reference: &Reference) -> Result<Vec<u8>> {
+    #[derive(Deserialize)]
+    struct Manifest {
+        layers: Vec<Layer>,
+    }
+    #[derive(Deserialize)]
+    struct Layer {
+        digest: String,
+    }
+
+    let manifest = get(
+        client,
+        reference,
+        &format!("manifests/{}", reference.tag),
+        MANIFEST_MEDIA_TYPE,
+    )?
+    .text()?;
+    let manifest: Manifest = serde_json::from_str(&manifest).context("invalid OCI manifest")?;
+    let layer = manifest
+        .layers
+        .first()
+        .with_context(|| format!("{reference} has no layers"))?;
+
+    let mut blob = Vec::new();
+    get(client, reference, &format!("blobs/{}", layer.digest), "*/*")?
+        .read_to_end(&mut blob)
+        .context("failed to download template")?;
+    if let Some(expected) = layer.digest.strip_prefix("sha256:") {
+        let actual = format!("{:x}", Sha256::digest(&blob));
+        if actual != expected {
+            bail!("digest mismatch for {reference} (expected {expected}, got {actual})");
+        }
+    }
+    Ok(blob)
+}
+
+/// GET `/v2/<repository>/<path>`, fetching an anonymous token if the
+/// registry asks for one, as ghcr.io does even for public packages.
+fn get(client: &Client, reference: &Reference, path: &str, accept: &str) -> Result<Response> {
+    let url = format!(
+        "https://{}/v2/{}/{path}",
+        reference.registry, reference.repository
+    );
+    let send = |token: Option<&str>| {
+        let mut request = client.get(&url).header(ACCEPT, accept);
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        request
+            .send()
+            .with_context(|| format!("request failed: {url}"))
+    };
+
+    let mut response = send(None)?;
+    if response.status() == StatusCode::UNAUTHORIZED {
+        let challenge = response
+            .headers()
+            .get(WWW_AUTHENTICATE)
+            .and_then(|v| v.to_str().ok())
+            .map(parse_challenge)
+            .with_context(|| format!("{} requires authentication", reference.registry))?;
+        let token = fetch_token(client, &challenge)?;
+        response = send(Some(&token))?;
+    }
+    response
+        .error_for_status()
+        .with_context(|| format!("request failed: {url}"))
+}
+
+fn fetch_token(client: &Client, challenge: &HashMap<String, String>) -> Result<String> {
+    #[derive(Deserialize)]
+    struct TokenResponse {
+        #[serde(alias = "access_token")]
+        token: String,
+    }
+
+    let realm = challenge
+        .get("realm")
+        .context("registry sent no token realm")?;
+    let params: Vec<(&str, &str)> = ["service", "scope"]
+        .into_iter()
+        .filter_map(|key| Some((key, challenge.get(key)?.as_str())))
+        .collect();
+    let body = client
+        .get(realm)
+        .query(&params)
+        .send()
+        .and_then(|r| r.error_for_status())
+        .and_then(|r| r.text())
+        .with_context(|| format!("failed to get a registry token from {realm}"))?;
+    let response: TokenResponse =
+        serde_json::from_str(&body).context("invalid registry token response")?;
+    Ok(response.token)
+}
+
+/// Parameters of a `WWW-Authenticate: Bearer realm="...",scope="..."`
+/// header. Quoted values may contain commas.
+fn parse_challenge(header: &str) -> HashMap<String, String> {
+    let params = header
+        .trim()
+        .strip_prefix("Bearer")
+        .unwrap_or(header)
+        .trim_start();
+    let mut result = HashMap::new();
+    let mut rest = params;
+    while let Some((key, after)) = rest.split_once('=') {
+        let key = key.trim().trim_start_matches(',').trim().to_string();
+        let (value, remainder) = match after.strip_prefix('"') {
+            Some(quoted) => match quoted.split_once('"') {
+                Some((value, remainder)) => (value, remainder),
+                None => (quoted, ""),
+            },
+            None => match after.split_once(',') {
+                Some((value, remainder)) => (value, remainder),
+                None => (after, ""),
+            },
+        };
+        result.insert(key, value.to_string());
+        rest = remainder;
+    }
+    result
+}
+
+/// Read a template's tarball.
+fn unpack(tarball: &[u8]) -> Result<Template> {
+    let mut info = None;
+    let mut files = Vec::new();
+    let mut archive = tar::Archive::new(tarball);
+    for entry in archive.entries().context("invalid template archive")? {
+        let mut entry = entry.context("invalid template archive")?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path: PathBuf = entry
+            .path()?
+            .components()
+            .filter(|c| *c != Component::CurDir)
+            .collect();
+        if !path.components().all(|c| matches!(c, Component::Normal(_))) {
+            bail!("template contains an unsafe path: {}", path.display());
+        }
+        let mut contents = Vec::new();
+        entry.read_to_end(&mut contents)?;
+        if path == Path::new("devcontainer-template.json") {
+            info = Some(
+                serde_json::from_slice(&contents).context("invalid devcontainer-template.json")?,
+            );
+        }
+        if !METADATA_FILES.iter().any(|f| path == Path::new(f)) {
+            files.push((path, contents));
+        }
+    }
+    Ok(Template {
+        info: info.context("template has no devcontainer-template.json")?,
+        files,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn references_default_to_the_official_collection() {
+        let reference = Reference::parse("go").unwrap();
+        assert_eq!(reference.registry, "ghcr.io");
+        assert_eq!(reference.repository, "devcontainers/templates/go");
+        assert_eq!(reference.tag, "latest");
+
+        let reference = Reference::parse("localhost:5000/me/templates/app:1.2").unwrap();
+        assert_eq!(reference.registry, "localhost:5000");
+        assert_eq!(reference.repository, "me/templates/app");
+        assert_eq!(reference.tag, "1.2");
+        assert_eq!(reference.to_string(), "localhost:5000/me/templates/app:1.2");
+
+        let reference = Reference::parse("ghcr.io/me/app@sha256:abc").unwrap();
+        assert_eq!(reference.tag, "sha256:abc");
+        assert_eq!(reference.to_string(), "ghcr.io/me/app@sha256:abc");
+    }
+
+    #[test]
+    fn bearer_challenge_parameters() {
+        let challenge = parse_challenge(
+            r#"Bearer realm="https://ghcr.io/token",service="ghcr.io",scope="repository:me/app:pull,push""#,
+        );
+        assert_eq!(challenge["realm"], "https://ghcr.io/token");
+        assert_eq!(challenge["service"], "ghcr.io");
+        assert_eq!(challenge["scope"], "repository:me/app:pull,push");
+    }
+
+    #[test]
+    fn unpacked_templates_substitute_options() {
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut add = |path: &str, contents: &str| {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, path, contents.as_bytes())
+                .unwrap();
+        };
+        add(
+            "./devcontainer-template.json",
+            r#"{"id": "go", "options": {"imageVariant": {"type": "string", "proposals": ["1.22", "1.21"], "default": "1.22"}}}"#,
+        );
+        add("./NOTES.md", "notes");
+        add(
+            "./.devcontainer/devcontainer.json",
+            r#"{"image": "mcr.microsoft.com/devcontainers/go:${templateOption:imageVariant}"}"#,
+        );
+        let template = unpack(&builder.into_inner().unwrap()).unwrap();
+        assert_eq!(template.info.id, "go");
+        assert_eq!(
+            template.info.options["imageVariant"].default_value(),
+            "1.22"
+        );
+
+        let values = HashMap::from([("imageVariant".to_string(), "1.21".to_string())]);
+        let files = template.render(&values);
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].0, Path::new(".devcontainer/devcontainer.json"));
+        assert_eq!(
+            String::from_utf8_lossy(&files[0].1),
+            r#"{"image": "mcr.microsoft.com/devcontainers/go:1.21"}"#
+        );
+    }
+}
@@ -0,0 +1,120 @@
+use anyhow::{bail, Context, Result};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::ServerConfig;
+use std::fs;
+use std::io::ErrorKind;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+use tracing::info;
+
+use crate::logging::Logged;
+
+/// Names the certificate is valid for.
+const HOSTS: [&str; 3] = ["localhost", "127.0.0.1", "::1"];
+
+/// Directory holding the certificate `--tls` forwards present, shared by
+/// all workspaces.
+pub fn cert_dir() -> Result<PathBuf> {
+    Ok(dirs::config_dir()
+        .context("failed to determine config directory")?
+        .join("dcw")
+        .join("tls"))
+}
+
+fn cert_file(dir: &Path) -> PathBuf {
+    dir.join("cert.pem")
+}
+
+fn key_file(dir: &Path) -> PathBuf {
+    dir.join("key.pem")
+}
+
+/// Create the certificate on first use: with mkcert if it is installed, so
+/// browsers trust it once `mkcert -install` has been run, else self-signed.
+pub fn ensure_certificate() -> Result<()> {
+    let dir = cert_dir()?;
+    if cert_file(&dir).exists() && key_file(&dir).exists() {
+        return Ok(());
+    }
+    fs::create_dir_all(&dir).context("failed to create dcw TLS directory")?;
+    if generate_with_mkcert(&dir)? {
+        info!("Created a certificate with mkcert in {}.", dir.display());
+    } else {
+        generate_self_signed(&dir)?;
+        info!(
+            "Created a self-signed certificate in {} (install mkcert for one browsers trust).",
+            dir.display()
+        );
+    }
+    Ok(())
+}
+
+/// Returns `false` if mkcert isn't installed.
+fn generate_with_mkcert(dir: &Path) -> Result<bool> {
+    let output = Command::new("mkcert")
+        .arg("-cert-file")
+        .arg(cert_file(dir))
+        .arg("-key-file")
+        .arg(key_file(dir))
+        .args(HOSTS)
+        .logged()
+        .output();
+    match output {
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e).context("failed to run mkcert"),
+        Ok(output) if !output.status.success() => bail!(
+            "mkcert failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+        Ok(_) => Ok(true),
+    }
+}
+
+/// Write a self-signed certificate and its key to `dir`.
+pub fn generate_self_signed(dir: &Path) -> Result<()> {
+    let hosts = HOSTS.map(str::to_string).to_vec();
+    let certified =
+        rcgen::generate_simple_self_signed(hosts).context("failed to generate certificate")?;
+    fs::write(cert_file(dir), certified.cert.pem()).context("failed to write certificate")?;
+    fs::write(key_file(dir), certified.key_pair.serialize_pem())
+        .context("failed to write private key")?;
+    fs::set_permissions(key_file(dir), fs::Permissions::from_mode(0o600))
+        .context("failed to restrict private key permissions")?;
+    Ok(())
+}
+
+/// Server configuration presenting the certificate in `dir`. Only HTTP/1.1
+/// is offered, since plain HTTP is what reaches the container.
+pub fn server_config(dir: &Path) -> Result<Arc<ServerConfig>> {
+    let certs = CertificateDer::pem_file_iter(cert_file(dir))
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("failed to read {}", cert_file(dir).display()))?;
+    let key = PrivateKeyDer::from_pem_file(key_file(dir))
+        .with_context(|| format!("failed to read {}", key_file(dir).display()))?;
+    let mut config =
+        ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .context("invalid TLS certificate")?;
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(Arc::new(config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn self_signed_certificate_loads() {
+        let dir = std::env::temp_dir().join(format!("dcw-test-tls-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        generate_self_signed(&dir).unwrap();
+        let config = server_config(&dir).unwrap();
+        assert_eq!(config.alpn_protocols, [b"http/1.1".to_vec()]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,257 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::docker::{self, Protocol};
+
+/// Traffic through a forward. Fields are absent where the backend can't
+/// measure them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TrafficStats {
+    /// Bytes from clients to the container
+    pub bytes_in: Option<u64>,
+    /// Bytes from the container to clients
+    pub bytes_out: Option<u64>,
+    /// Bytes in both directions; sidecars only report this
+    pub bytes_total: Option<u64>,
+    /// Open TCP connections, or UDP clients with a live mapping
+    pub connections: Option<u64>,
+}
+
+impl TrafficStats {
+    /// Traffic since `baseline` was recorded. Counters that went backwards
+    /// were restarted since, so they are reported as they are. Connections
+    /// are a gauge and are never offset.
+    pub fn since(&self, baseline: &TrafficStats) -> TrafficStats {
+        let delta = |current: Option<u64>, base: Option<u64>| match (current, base) {
+            (Some(c), Some(b)) if c >= b => Some(c - b),
+            _ => current,
+        };
+        TrafficStats {
+            bytes_in: delta(self.bytes_in, baseline.bytes_in),
+            bytes_out: delta(self.bytes_out, baseline.bytes_out),
+            bytes_total: delta(self.bytes_total, baseline.bytes_total),
+            connections: self.connections,
+        }
+    }
+}
+
+/// Read a sidecar's network counters and socket table.
+pub fn read_sidecar(name: &str) -> Result<String> {
+    docker::exec_in_container(
+        name,
+        &[
+            "sh",
+            "-c",
+            "cat /proc/net/dev /proc/net/tcp /proc/net/tcp6 2>/dev/null; true",
+        ],
+    )
+    .with_context(|| format!("failed to read traffic of {name}"))
+}
+
+/// Traffic of one forward from the output of [`read_sidecar`]. Every byte a
+/// sidecar relays is received once on its network interface, so the received
+/// bytes approximate the total in both directions. `shared` sidecars serve
+/// several forwards, so their bytes can't be attributed to one.
+pub fn parse_sidecar(
+    output: &str,
+    host_port: u16,
+    protocol: Protocol,
+    shared: bool,
+) -> TrafficStats {
+    enum Section {
+        None,
+        Dev,
+        Tcp,
+    }
+    let mut section = Section::None;
+    let mut received = 0u64;
+    let mut connections = 0u64;
+    for line in output.lines() {
+        if line.contains('|') {
+            section = Section::Dev;
+            continue;
+        }
+        if line.trim_start().starts_with("sl ") {
+            section = Section::Tcp;
+            continue;
+        }
+        match section {
+            Section::None => {}
+            Section::Dev => {
+                let Some((iface, counters)) = line.split_once(':') else {
+                    continue;
+                };
+                if iface.trim() == "lo" {
+                    continue;
+                }
+                received += counters
+                    .split_whitespace()
+                    .next()
+                    .and_then(|n| n.parse::<u64>().ok())
+                    .unwrap_or(0);
+            }
+            Section::Tcp => {
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                // Connections accepted by socat have the listening port as
+                // their local port; 01 is ESTABLISHED
+                let local_port = fields
+                    .get(1)
+                    .and_then(|addr| addr.rsplit_once(':'))
+                    .and_then(|(_, port)| u16::from_str_radix(port, 16).ok());
+                if local_port == Some(host_port) && fields.get(3) == Some(&"01") {
+                    connections += 1;
+                }
+            }
+        }
+    }
+    TrafficStats {
+        bytes_in: None,
+        bytes_out: None,
+        bytes_total: (!shared).then_some(received),
+        connections: (protocol == Protocol::Tcp).then_some(connections),
+    }
+}
+
+/// Counters recorded by the last reset, keyed by forward.
+pub fn load_baseline(path: &Path) -> HashMap<String, TrafficStats> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_baseline(path: &Path, baseline: &HashMap<String, TrafficStats>) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context("failed to create runtime directory")?;
+    }
+    fs::write(path, serde_json::to_string_pretty(baseline)?)
+        .context("failed to write traffic baseline")
+}
+
+/// Format a byte count for tables, e.g. `1.5 MiB`; `-` when unknown.
+pub fn format_bytes(bytes: Option<u64>) -> String {
+    let Some(bytes) = bytes else {
+        return "-".to_string();
+    };
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+/// Parse `--rate-limit` into bytes per second: an amount with a unit of
+/// `bit`, `kbit`, `mbit` or `gbit`, or bytes as `b`, `kb`, `mb` or `gb`,
+/// all in powers of 1000 as for `tc`.
+pub fn parse_rate(value: &str) -> Result<u64, String> {
+    let value = value.trim().to_ascii_lowercase();
+    let amount = value.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let invalid = || format!("invalid rate {value:?} (e.g. 1mbit, 512kbit or 100kb)");
+    let bits_per_unit = match &value[amount.len()..] {
+        "bit" => 1.0,
+        "kbit" => 1e3,
+        "mbit" => 1e6,
+        "gbit" => 1e9,
+        "b" => 8.0,
+        "kb" => 8e3,
+        "mb" => 8e6,
+        "gb" => 8e9,
+        _ => return Err(invalid()),
+    };
+    let amount: f64 = amount.parse().map_err(|_| invalid())?;
+    let bytes = (amount * bits_per_unit / 8.0) as u64;
+    if bytes == 0 {
+        return Err(format!("rate {value:?} is below one byte per second"));
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIDECAR_OUTPUT: &str = "\
+Inter-|   Receive                                                |  Transmit
+ face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed
+    lo:     120       2    0    0    0     0          0         0      120       2    0    0    0     0       0          0
+  eth0:    5000      40    0    0    0     0          0         0     4800      38    0    0    0     0       0          0
+  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000:0BB8 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 1 1
+   1: 020012AC:0BB8 010012AC:D431 01 00000000:00000000 00:00000000 00000000     0        0 2 1
+   2: 020012AC:8F2A 030012AC:0BB8 01 00000000:00000000 00:00000000 00000000     0        0 3 1
+   3: 020012AC:0BB8 010012AC:D432 06 00000000:00000000 00:00000000 00000000     0        0 4 1
+  sl  local_address                         remote_address                        st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 0000000000000000FFFF0000020012AC:0BB8 0000000000000000FFFF0000010012AC:D433 01 00000000:00000000 00:00000000 00000000     0        0 5 1
+";
+
+    #[test]
+    fn parse_sidecar_counts_bytes_and_connections() {
+        let stats = parse_sidecar(SIDECAR_OUTPUT, 3000, Protocol::Tcp, false);
+        assert_eq!(stats.bytes_total, Some(5000));
+        assert_eq!(stats.connections, Some(2));
+        assert_eq!(stats.bytes_in, None);
+    }
+
+    #[test]
+    fn parse_sidecar_shared_or_udp_omits_what_it_cannot_tell() {
+        let shared = parse_sidecar(SIDECAR_OUTPUT, 3000, Protocol::Tcp, true);
+        assert_eq!(shared.bytes_total, None);
+        assert_eq!(shared.connections, Some(2));
+        let udp = parse_sidecar(SIDECAR_OUTPUT, 3000, Protocol::Udp, false);
+        assert_eq!(udp.connections, None);
+    }
+
+    #[test]
+    fn since_subtracts_baseline_unless_restarted() {
+        let current = TrafficStats {
+            bytes_in: Some(100),
+            bytes_out: Some(50),
+            bytes_total: Some(150),
+            connections: Some(3),
+        };
+        let baseline = TrafficStats {
+            bytes_in: Some(40),
+            bytes_out: Some(80),
+            bytes_total: Some(120),
+            connections: Some(1),
+        };
+        assert_eq!(
+            current.since(&baseline),
+            TrafficStats {
+                bytes_in: Some(60),
+                bytes_out: Some(50),
+                bytes_total: Some(30),
+                connections: Some(3),
+            }
+        );
+        assert_eq!(current.since(&TrafficStats::default()), current);
+    }
+
+    #[test]
+    fn format_bytes_picks_unit() {
+        assert_eq!(format_bytes(None), "-");
+        assert_eq!(format_bytes(Some(512)), "512 B");
+        assert_eq!(format_bytes(Some(1536)), "1.5 KiB");
+        assert_eq!(format_bytes(Some(5 * 1024 * 1024)), "5.0 MiB");
+    }
+
+    #[test]
+    fn parse_rate_units() {
+        assert_eq!(parse_rate("1mbit"), Ok(125_000));
+        assert_eq!(parse_rate("512Kbit"), Ok(64_000));
+        assert_eq!(parse_rate("1.5mb"), Ok(1_500_000));
+        assert_eq!(parse_rate("100b"), Ok(100));
+        assert!(parse_rate("100").is_err());
+        assert!(parse_rate("1mbps").is_err());
+        assert!(parse_rate("1bit").is_err());
+    }
+}
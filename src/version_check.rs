@@ -0,0 +1,162 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::commands::update;
+
+/// How often the latest release is looked up in the background.
+const CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Set to disable the background check and the hint.
+const DISABLE_ENV: &str = "DCW_NO_UPDATE_CHECK";
+
+/// What the last release lookup found, kept in the XDG cache directory.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct Cache {
+    /// Unix time of the last lookup, successful or not
+    checked_at: u64,
+    /// Latest stable release tag, if a lookup has succeeded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    latest: Option<String>,
+}
+
+fn cache_path() -> Result<PathBuf> {
+    Ok(dirs::cache_dir()
+        .context("failed to determine cache directory")?
+        .join("dcw")
+        .join("version-check.json"))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Record the latest stable release, as found by `dcw update`.
+pub fn record_latest(tag: &str) -> Result<()> {
+    save_to(
+        &cache_path()?,
+        &Cache {
+            checked_at: now(),
+            latest: Some(tag.to_string()),
+        },
+    )
+}
+
+/// After a command: print a one-line hint if the cached lookup found a newer
+/// release, and start a background lookup if the cache is a day old. Only
+/// on a terminal, and never with `-q`.
+pub fn notify() {
+    if std::env::var_os(DISABLE_ENV).is_some()
+        || !tracing::enabled!(tracing::Level::INFO)
+        || !std::io::stderr().is_terminal()
+    {
+        return;
+    }
+    let Ok(path) = cache_path() else {
+        return;
+    };
+    let mut cache = load_from(&path);
+    if let Some(hint) = hint(&cache, update::CURRENT_VERSION) {
+        eprintln!("{hint}");
+    }
+    if is_due(cache.checked_at, now()) {
+        // Stamp the attempt first so an offline host doesn't retry on every
+        // command
+        cache.checked_at = now();
+        if save_to(&path, &cache).is_ok() {
+            spawn_check();
+        }
+    }
+}
+
+fn is_due(checked_at: u64, now: u64) -> bool {
+    now.saturating_sub(checked_at) >= CHECK_INTERVAL.as_secs()
+}
+
+fn hint(cache: &Cache, current: &str) -> Option<String> {
+    let latest = cache.latest.as_deref()?;
+    update::is_newer(latest, current).then(|| {
+        format!(
+            "A new version of dcw is available: v{} → {latest} (run `dcw update`)",
+            current.trim_start_matches('v')
+        )
+    })
+}
+
+/// Run `dcw update --check` detached; it records what it finds.
+fn spawn_check() {
+    let Ok(exe) = std::env::current_exe() else {
+        return;
+    };
+    let _ = Command::new(exe)
+        .args(["update", "--check", "--quiet"])
+        .env(DISABLE_ENV, "1")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+}
+
+fn load_from(path: &Path) -> Cache {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_to(path: &Path, cache: &Cache) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context("failed to create cache directory")?;
+    }
+    fs::write(path, serde_json::to_string(cache)?).context("failed to write version check cache")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_round_trip() {
+        let dir = std::env::temp_dir().join(format!("dcw-test-version-{}", std::process::id()));
+        let path = dir.join("version-check.json");
+        assert_eq!(load_from(&path), Cache::default());
+
+        let cache = Cache {
+            checked_at: 1_700_000_000,
+            latest: Some("v0.6.0".to_string()),
+        };
+        save_to(&path, &cache).unwrap();
+        assert_eq!(load_from(&path), cache);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn is_due_after_a_day() {
+        let day = CHECK_INTERVAL.as_secs();
+        assert!(is_due(0, 1_700_000_000));
+        assert!(!is_due(1_700_000_000, 1_700_000_000 + day - 1));
+        assert!(is_due(1_700_000_000, 1_700_000_000 + day));
+    }
+
+    #[test]
+    fn hint_only_for_newer_release() {
+        let cache = |latest: &str| Cache {
+            checked_at: 0,
+            latest: Some(latest.to_string()),
+        };
+        assert_eq!(
+            hint(&cache("v0.6.0"), "0.5.1").as_deref(),
+            Some("A new version of dcw is available: v0.5.1 → v0.6.0 (run `dcw update`)")
+        );
+        assert_eq!(hint(&cache("v0.5.1"), "0.5.1"), None);
+        assert_eq!(hint(&cache("v0.5.0"), "0.5.1"), None);
+        assert_eq!(hint(&Cache::default(), "0.5.1"), None);
+    }
+}
@@ -1,19 +1,44 @@
 use anyhow::{Context, Result};
+use std::collections::BTreeMap;
 use std::env;
+use std::fs;
 use std::hash::{Hash, Hasher};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::docker;
 
 /// Returns a workspace identifier derived from the current directory.
 /// Format: `dev-<basename>-<hash8>` where hash is based on the full path
 /// to avoid collisions between directories with the same basename.
 pub fn workspace_id() -> Result<String> {
-    let folder = workspace_folder()?;
-    let basename = PathBuf::from(&folder)
+    workspace_id_for(&workspace_folder()?)
+}
+
+/// Returns the workspace identifier for an arbitrary workspace folder.
+/// The hash is of the canonical path, so symlinked or `..` spellings of the
+/// same folder share an ID.
+pub fn workspace_id_for(folder: &str) -> Result<String> {
+    let canonical = fs::canonicalize(folder)
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|_| folder.to_string());
+    id_for_path(&canonical)
+}
+
+/// The ID the current workspace had when IDs were derived from the path as
+/// given rather than the canonical one, if that differs.
+pub fn legacy_workspace_id() -> Result<Option<String>> {
+    let legacy = id_for_path(&workspace_folder()?)?;
+    Ok((legacy != workspace_id()?).then_some(legacy))
+}
+
+fn id_for_path(folder: &str) -> Result<String> {
+    let basename = PathBuf::from(folder)
         .file_name()
         .context("workspace folder has no basename")?
         .to_string_lossy()
         .to_string();
-    let hash = path_hash(&folder);
+    let hash = path_hash(folder);
     Ok(format!("dev-{basename}-{hash}"))
 }
 
@@ -23,26 +48,198 @@ fn path_hash(path: &str) -> String {
     format!("{:08x}", hasher.finish() & 0xFFFF_FFFF)
 }
 
-/// Returns the absolute path of the current working directory.
+/// Returns the absolute path of the workspace containing the current
+/// working directory (see [`find_workspace_root`]).
 pub fn workspace_folder() -> Result<String> {
     let cwd = env::current_dir().context("failed to get current directory")?;
-    Ok(cwd.to_string_lossy().to_string())
+    Ok(find_workspace_root(&cwd).to_string_lossy().to_string())
+}
+
+/// The nearest directory at or above `dir` holding a `.devcontainer`
+/// folder, else the nearest holding `.git`, else `dir` itself.
+/// `.devcontainer` is looked for first so that a project with its own
+/// devcontainer inside a larger repository is its own workspace.
+pub fn find_workspace_root(dir: &Path) -> PathBuf {
+    dir.ancestors()
+        .find(|d| d.join(".devcontainer").is_dir())
+        .or_else(|| dir.ancestors().find(|d| d.join(".git").exists()))
+        .unwrap_or(dir)
+        .to_path_buf()
 }
 
 /// Returns the XDG runtime directory for this workspace.
 /// Uses `$XDG_RUNTIME_DIR/dcw/<ws_id>/`, falling back to `/tmp/dcw-<uid>/<ws_id>/`.
 pub fn runtime_dir() -> Result<PathBuf> {
-    let ws_id = workspace_id()?;
-    let base = match env::var("XDG_RUNTIME_DIR") {
-        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
-        _ => PathBuf::from(format!("/tmp/dcw-{}", unsafe { libc::getuid() })),
-    };
-    Ok(base.join("dcw").join(ws_id))
+    Ok(runtime_dir_for(&workspace_id()?))
+}
+
+/// Returns the runtime directory of the workspace with the given ID.
+pub fn runtime_dir_for(ws_id: &str) -> PathBuf {
+    shared_runtime_dir().join(ws_id)
+}
+
+/// Returns the path of the log file of a workspace's port watcher.
+pub fn watcher_log_file(ws_id: &str) -> PathBuf {
+    runtime_dir_for(ws_id).join("watch.log")
+}
+
+/// Returns the path of the lock file serializing dcw operations on a workspace.
+pub fn lock_file(ws_id: &str) -> PathBuf {
+    runtime_dir_for(ws_id).join("lock")
+}
+
+/// Returns the path of the PID file for the native port proxy daemon.
+pub fn proxy_pid_file() -> Result<PathBuf> {
+    Ok(runtime_dir()?.join("proxy.pid"))
+}
+
+/// Returns the path of the state file listing the native proxy's forwards.
+pub fn proxy_state_file() -> Result<PathBuf> {
+    Ok(runtime_dir()?.join("proxy.json"))
+}
+
+/// Returns the path of the file the native proxy daemon writes its traffic
+/// counters to.
+pub fn proxy_stats_file() -> Result<PathBuf> {
+    Ok(runtime_dir()?.join("proxy-stats.json"))
+}
+
+/// Returns the path of the HTTP access log of a native forward started with
+/// `--log-http`.
+pub fn http_log_file(host_port: u16) -> Result<PathBuf> {
+    Ok(runtime_dir()?.join(format!("http-{host_port}.log")))
+}
+
+/// Returns the path of the traffic counters recorded by `port list --reset`.
+pub fn traffic_baseline_file() -> Result<PathBuf> {
+    Ok(runtime_dir()?.join("traffic-baseline.json"))
+}
+
+/// Returns the path of the phase timings recorded by `dcw up --timings`.
+pub fn timings_file() -> Result<PathBuf> {
+    Ok(runtime_dir()?.join("timings.json"))
+}
+
+/// Returns the path of the file listing forwards to restore on `dcw up`.
+pub fn forwards_file() -> Result<PathBuf> {
+    Ok(runtime_dir()?.join("forwards.json"))
+}
+
+/// Returns the persistent state directory for this workspace, which unlike
+/// the runtime directory survives reboots: `$XDG_STATE_HOME/dcw/<ws_id>/`.
+pub fn state_dir() -> Result<PathBuf> {
+    let base = dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .context("failed to determine state directory")?;
+    Ok(base.join("dcw").join(workspace_id()?))
+}
+
+/// Returns the path of the file listing the snapshots made with `dcw snapshot`.
+pub fn snapshots_file() -> Result<PathBuf> {
+    Ok(state_dir()?.join("snapshots.json"))
+}
+
+/// Returns the path of the file recording the workspace folder for this runtime dir.
+pub fn folder_file() -> Result<PathBuf> {
+    Ok(runtime_dir()?.join("workspace_folder"))
+}
+
+/// Record the current workspace folder in the runtime directory so that
+/// commands scanning all runtime dirs can map a workspace ID back to its folder.
+pub fn record_workspace_folder() -> Result<()> {
+    let path = folder_file()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context("failed to create runtime directory")?;
+    }
+    fs::write(&path, workspace_folder()?).context("failed to write workspace folder file")
 }
 
-/// Returns the path of the PID file for the port watcher process.
-pub fn watcher_pid_file() -> Result<PathBuf> {
-    Ok(runtime_dir()?.join("watch.pid"))
+/// Returns the path of the file recording the profile selected by `dcw up --profile`.
+pub fn profile_file() -> Result<PathBuf> {
+    Ok(runtime_dir()?.join("profile"))
+}
+
+/// Returns the path of the file recording the config selected by `dcw up --config-name`.
+pub fn config_name_file() -> Result<PathBuf> {
+    Ok(runtime_dir()?.join("config_name"))
+}
+
+/// Returns the path of the file recording the config given to `dcw up --config`.
+pub fn config_path_file() -> Result<PathBuf> {
+    Ok(runtime_dir()?.join("config_path"))
+}
+
+/// Record the profile selected by `dcw up`, or clear it when `None`, so that
+/// later commands resolve the same config.
+pub fn record_profile(profile: Option<&str>) -> Result<()> {
+    record_selection(&profile_file()?, profile)
+}
+
+/// The profile selected by the last `dcw up`, if any.
+pub fn active_profile() -> Option<String> {
+    read_selection(&profile_file().ok()?)
+}
+
+/// Record the config selected by `dcw up --config-name`, or clear it when `None`.
+pub fn record_config_name(name: Option<&str>) -> Result<()> {
+    record_selection(&config_name_file()?, name)
+}
+
+/// The config name selected by the last `dcw up`, if any.
+pub fn active_config_name() -> Option<String> {
+    read_selection(&config_name_file().ok()?)
+}
+
+/// Record the config file given to `dcw up --config`, or clear it when `None`.
+pub fn record_config_path(path: Option<&Path>) -> Result<()> {
+    let path_str = path.map(|p| p.to_string_lossy());
+    record_selection(&config_path_file()?, path_str.as_deref())
+}
+
+/// The config file given to the last `dcw up --config`, if any.
+pub fn active_config_path() -> Option<PathBuf> {
+    read_selection(&config_path_file().ok()?).map(PathBuf::from)
+}
+
+/// Selections a dry run would have recorded, so the rest of the run sees
+/// them while the files stay as they are.
+static DRY_RUN_SELECTIONS: Mutex<BTreeMap<PathBuf, Option<String>>> = Mutex::new(BTreeMap::new());
+
+fn record_selection(path: &Path, value: Option<&str>) -> Result<()> {
+    if docker::dry_run() {
+        if read_selection(path).as_deref() != value {
+            docker::print_dry_run(&format!("update {}", path.display()));
+        }
+        DRY_RUN_SELECTIONS
+            .lock()
+            .unwrap()
+            .insert(path.to_path_buf(), value.map(str::to_string));
+        return Ok(());
+    }
+    match value {
+        Some(value) => {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).context("failed to create runtime directory")?;
+            }
+            fs::write(path, value)
+                .with_context(|| format!("failed to write {}", path.display()))
+        }
+        None => match fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(e).with_context(|| format!("failed to remove {}", path.display()))
+            }
+            _ => Ok(()),
+        },
+    }
+}
+
+fn read_selection(path: &Path) -> Option<String> {
+    if let Some(value) = DRY_RUN_SELECTIONS.lock().unwrap().get(path) {
+        return value.clone();
+    }
+    let value = fs::read_to_string(path).ok()?;
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
 }
 
 /// Returns the shared dcw runtime directory (not workspace-specific).
@@ -55,6 +252,22 @@ pub fn shared_runtime_dir() -> PathBuf {
     base.join("dcw")
 }
 
+/// Path to the control socket of `dcw daemon`.
+pub fn daemon_socket() -> PathBuf {
+    shared_runtime_dir().join("daemon.sock")
+}
+
+/// Path to the log file of `dcw daemon`.
+pub fn daemon_log_file() -> PathBuf {
+    shared_runtime_dir().join("daemon.log")
+}
+
+/// Path to the file listing the workspaces `dcw daemon` watches, so a
+/// restarted daemon picks them up again.
+pub fn daemon_state_file() -> PathBuf {
+    shared_runtime_dir().join("daemon.json")
+}
+
 /// Path to the browser relay PID file.
 pub fn relay_pid_file() -> PathBuf {
     shared_runtime_dir().join("browser-relay.pid")
@@ -82,6 +295,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn workspace_id_for_uses_folder_basename() {
+        let id = workspace_id_for("/home/user/src/api").unwrap();
+        assert!(id.starts_with("dev-api-"), "got: {id}");
+        assert_eq!(id, workspace_id_for("/home/user/src/api").unwrap());
+    }
+
+    #[test]
+    fn workspace_id_for_follows_symlinks() {
+        let dir = env::temp_dir().join(format!("dcw-test-id-{}", std::process::id()));
+        let real = dir.join("api");
+        let link = dir.join("link");
+        fs::create_dir_all(&real).unwrap();
+        std::os::unix::fs::symlink(&real, &link).unwrap();
+
+        let real_id = workspace_id_for(real.to_str().unwrap()).unwrap();
+        assert_eq!(workspace_id_for(link.to_str().unwrap()).unwrap(), real_id);
+        assert_ne!(id_for_path(link.to_str().unwrap()).unwrap(), real_id);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
     #[test]
     fn path_hash_is_deterministic() {
         let h1 = path_hash("/foo/bar");
@@ -96,6 +330,24 @@ mod tests {
         assert_ne!(h1, h2);
     }
 
+    #[test]
+    fn find_workspace_root_walks_up() {
+        let root = env::temp_dir().join(format!("dcw-test-root-{}", std::process::id()));
+        let project = root.join("services").join("api");
+        let deep = project.join("src").join("handlers");
+        fs::create_dir_all(&deep).unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+
+        assert_eq!(find_workspace_root(&deep), root);
+        fs::create_dir_all(project.join(".devcontainer")).unwrap();
+        assert_eq!(find_workspace_root(&deep), project);
+        assert_eq!(find_workspace_root(&project), project);
+
+        let _ = fs::remove_dir_all(&root);
+        let lone = env::temp_dir();
+        assert_eq!(find_workspace_root(&lone), lone);
+    }
+
     #[test]
     fn workspace_folder_is_absolute() {
         let folder = workspace_folder().unwrap();